@@ -0,0 +1,325 @@
+use async_trait::async_trait;
+use log::warn;
+use serde::Serialize;
+use tbf_parser::parse::{parse_tbf_header, parse_tbf_header_lengths};
+use tbf_parser::types::{InitialTbfParseError, TbfHeader};
+
+use crate::errors::TockloaderError;
+use crate::inspect::Integrity;
+use crate::interfaces::traits::BytesReader;
+
+/// Where apps start in flash when the board does not say otherwise.
+pub const DEFAULT_APP_ADDRESS: u32 = 0x30000;
+
+/// A TBF found in flash.
+#[derive(Clone, Debug)]
+pub struct AppLocation {
+    pub address: u32,
+    pub header: TbfHeader,
+    pub total_size: u32,
+}
+
+/// How many TBFs a [`TbfIterator`] reads before giving up, unless set
+/// otherwise.
+pub const DEFAULT_MAX_TBFS: usize = 128;
+
+/// Walks the linked list of TBFs in flash.
+///
+/// Each TBF starts right after the previous one ends, and the list ends at the
+/// first address which does not hold a TBF header (usually erased flash), or
+/// at a TBF with a size of 0. Padding TBFs are part of the list and are
+/// returned as well.
+///
+/// Corrupted flash could make the list go on for ever, so the walk fails with
+/// [`TockloaderError::TbfListTooLong`] after [`DEFAULT_MAX_TBFS`] TBFs or when
+/// it leaves the app region.
+pub struct TbfIterator {
+    address: u32,
+    max_tbfs: usize,
+    region_end: Option<u32>,
+    count: usize,
+}
+
+impl TbfIterator {
+    /// Start walking at `address`, which should be the start of the app
+    /// region.
+    pub fn new(address: u32) -> Self {
+        TbfIterator {
+            address,
+            max_tbfs: DEFAULT_MAX_TBFS,
+            region_end: None,
+            count: 0,
+        }
+    }
+
+    /// Give up after reading `max_tbfs` TBFs instead of [`DEFAULT_MAX_TBFS`].
+    pub fn max_tbfs(mut self, max_tbfs: usize) -> Self {
+        self.max_tbfs = max_tbfs;
+        self
+    }
+
+    /// Stop at `region_end`, the end of the app region, and fail if a TBF
+    /// reaches past it.
+    pub fn region_end(mut self, region_end: u32) -> Self {
+        self.region_end = Some(region_end);
+        self
+    }
+
+    /// The address the next TBF will be read from. Once the end of the list
+    /// is reached, this is where a new app can be appended.
+    pub fn address(&self) -> u32 {
+        self.address
+    }
+
+    /// Read the next TBF from the board, or `None` at the end of the list.
+    ///
+    /// TBFs whose header can not be parsed but whose length is known are
+    /// skipped.
+    pub async fn next<B>(&mut self, board: &mut B) -> Result<Option<AppLocation>, TockloaderError>
+    where
+        B: BytesReader + Send + ?Sized,
+    {
+        while let Some(tbf) = self.next_raw(board).await? {
+            match tbf.header {
+                Ok(header) => {
+                    return Ok(Some(AppLocation {
+                        address: tbf.address,
+                        header,
+                        total_size: tbf.total_size,
+                    }))
+                }
+                Err(error) => warn!("Skipping TBF at {:#x}: {error}", tbf.address),
+            }
+        }
+        Ok(None)
+    }
+
+    /// Read the next TBF from the board like [`next()`](Self::next), but
+    /// also return the TBFs whose header does not parse.
+    pub async fn next_raw<B>(&mut self, board: &mut B) -> Result<Option<RawTbf>, TockloaderError>
+    where
+        B: BytesReader + Send + ?Sized,
+    {
+        let address = self.address;
+        if let Some(region_end) = self.region_end {
+            if address == region_end {
+                return Ok(None);
+            }
+            if address > region_end {
+                return Err(TockloaderError::TbfListTooLong(format!(
+                    "the last TBF ends at {address:#x}, past the end of the app region at {region_end:#x}"
+                )));
+            }
+        }
+        if self.count == self.max_tbfs {
+            return Err(TockloaderError::TbfListTooLong(format!(
+                "gave up after {} TBFs, at {address:#x}",
+                self.max_tbfs
+            )));
+        }
+        self.count += 1;
+
+        let lengths: [u8; 8] = board
+            .read_range(address as usize, 8)
+            .await?
+            .try_into()
+            .map_err(|_| TockloaderError::MalformedResponse("short read of a TBF header".into()))?;
+
+        let (version, header_size, total_size) = match parse_tbf_header_lengths(&lengths) {
+            Ok(lengths) => lengths,
+            Err(InitialTbfParseError::UnableToParse) => return Ok(None),
+            // Skipping it would not move on to another TBF.
+            Err(InitialTbfParseError::InvalidHeader(0)) => return Ok(None),
+            Err(InitialTbfParseError::InvalidHeader(total_size)) => {
+                self.address = Self::skip(address, total_size)?;
+                return Ok(Some(RawTbf {
+                    address,
+                    total_size,
+                    header: Err("invalid header length".to_string()),
+                }));
+            }
+        };
+        self.address = Self::skip(address, total_size)?;
+
+        let header_bytes = board
+            .read_range(address as usize, header_size as usize)
+            .await?;
+        Ok(Some(RawTbf {
+            address,
+            total_size,
+            header: parse_tbf_header(&header_bytes, version).map_err(|error| error.to_string()),
+        }))
+    }
+
+    /// The address after the TBF of `total_size` bytes at `address`. A
+    /// corrupt size can claim more than the address space has left.
+    fn skip(address: u32, total_size: u32) -> Result<u32, TockloaderError> {
+        address.checked_add(total_size).ok_or_else(|| {
+            TockloaderError::TbfListTooLong(format!(
+                "the TBF at {address:#x} claims {total_size:#x} bytes, past the end of the address space"
+            ))
+        })
+    }
+}
+
+/// A TBF found in flash, whose header may be broken. The length of a TBF is
+/// read before its header is checked, so the list can go on past it.
+#[derive(Clone, Debug)]
+pub struct RawTbf {
+    pub address: u32,
+    pub total_size: u32,
+    /// The parsed header, or why it could not be parsed.
+    pub header: Result<TbfHeader, String>,
+}
+
+impl AppLocation {
+    /// The version of the app binary, or `None` for apps without a Program
+    /// Header, which can not carry one.
+    pub fn binary_version(&self) -> Option<u32> {
+        self.header
+            .has_program_header()
+            .then(|| self.header.get_binary_version())
+    }
+}
+
+/// What is known about an installed app from its header, for machine-readable
+/// output.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct AppInfo {
+    pub name: Option<String>,
+    pub enabled: bool,
+    pub sticky: bool,
+    /// `None` for apps without a Program Header.
+    pub binary_version: Option<u32>,
+    pub address: u32,
+    pub size: u32,
+    /// Where in flash the app was built to be placed, if it must be.
+    pub fixed_flash_address: Option<u32>,
+    /// Where in RAM the app was built to be placed, if it must be.
+    pub fixed_ram_address: Option<u32>,
+    pub minimum_ram_size: u32,
+    pub writeable_flash_regions: Vec<FlashRegion>,
+    /// The oldest kernel the app runs on, as `major.minor`.
+    pub kernel_version: Option<String>,
+    /// Whether the credentials in the footers match the app, only given once
+    /// they were checked.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub integrity: Option<Integrity>,
+}
+
+/// A range of flash, as an absolute address and a size in bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct FlashRegion {
+    pub start: u32,
+    pub size: u32,
+}
+
+impl From<&AppLocation> for AppInfo {
+    fn from(app: &AppLocation) -> Self {
+        let header = &app.header;
+        AppInfo {
+            name: header.get_package_name().map(String::from),
+            enabled: header.enabled(),
+            sticky: header.sticky(),
+            binary_version: app.binary_version(),
+            address: app.address,
+            size: app.total_size,
+            fixed_flash_address: header.get_fixed_address_flash(),
+            fixed_ram_address: header.get_fixed_address_ram(),
+            minimum_ram_size: header.get_minimum_app_ram_size(),
+            writeable_flash_regions: header
+                .get_writeable_flash_regions()
+                .into_iter()
+                .map(|(offset, size)| FlashRegion {
+                    start: app.address.saturating_add(offset),
+                    size,
+                })
+                .collect(),
+            kernel_version: header
+                .get_kernel_version()
+                .map(|(major, minor)| format!("{major}.{minor}")),
+            integrity: None,
+        }
+    }
+}
+
+/// Operations on the apps installed on a board.
+#[async_trait]
+pub trait AppReader: BytesReader + Send {
+    /// List the apps in the app region starting at `app_address`, in flash
+    /// order. Padding is left out.
+    async fn list_apps(&mut self, app_address: u32) -> Result<Vec<AppLocation>, TockloaderError> {
+        let mut iterator = TbfIterator::new(app_address);
+        let mut apps = Vec::new();
+        while let Some(app) = iterator.next(self).await? {
+            if app.header.is_app() {
+                apps.push(app);
+            }
+        }
+        Ok(apps)
+    }
+
+    /// Find where the apps in the app region starting at `app_address` end,
+    /// which is where the next app can be installed.
+    async fn apps_end(&mut self, app_address: u32) -> Result<u32, TockloaderError> {
+        let mut iterator = TbfIterator::new(app_address);
+        while iterator.next(self).await?.is_some() {}
+        Ok(iterator.address())
+    }
+
+    /// Find every app named `name` in the app region starting at
+    /// `app_address`. Several apps may share a name, so all matches are
+    /// returned, in flash order.
+    async fn find_app(
+        &mut self,
+        app_address: u32,
+        name: &str,
+    ) -> Result<Vec<AppLocation>, TockloaderError> {
+        let apps = self.list_apps(app_address).await?;
+        Ok(apps
+            .into_iter()
+            .filter(|app| app.header.get_package_name() == Some(name))
+            .collect())
+    }
+
+    /// Read the app whose TBF starts at `address`, returning its parsed
+    /// header and all of its bytes, footers included. `None` if there is
+    /// padding or no TBF at all there.
+    ///
+    /// Only the app itself is read, rather than walking the region to it.
+    async fn read_app_at(
+        &mut self,
+        address: u32,
+    ) -> Result<Option<(TbfHeader, Vec<u8>)>, TockloaderError> {
+        let lengths: [u8; 8] = self
+            .read_range(address as usize, 8)
+            .await?
+            .try_into()
+            .map_err(|_| TockloaderError::MalformedResponse("short read of a TBF header".into()))?;
+        let (version, header_size, total_size) = match parse_tbf_header_lengths(&lengths) {
+            Ok(lengths) => lengths,
+            Err(InitialTbfParseError::UnableToParse | InitialTbfParseError::InvalidHeader(0)) => {
+                return Ok(None)
+            }
+            Err(InitialTbfParseError::InvalidHeader(_)) => {
+                return Err(TockloaderError::InvalidTbf(format!(
+                    "invalid header length at {address:#x}"
+                )))
+            }
+        };
+
+        let header_bytes = self
+            .read_range(address as usize, header_size as usize)
+            .await?;
+        let header = parse_tbf_header(&header_bytes, version)?;
+        if !header.is_app() {
+            return Ok(None);
+        }
+        let bytes = self
+            .read_range(address as usize, total_size as usize)
+            .await?;
+        Ok(Some((header, bytes)))
+    }
+}
+
+impl<T: BytesReader + Send + ?Sized> AppReader for T {}
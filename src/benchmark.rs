@@ -0,0 +1,91 @@
+//! Measuring how fast a board can be read and written.
+
+use std::time::{Duration, Instant};
+
+use crate::errors::TockloaderError;
+use crate::interfaces::traits::{BytesReader, FlashWriter};
+
+/// Timings of one kind of command.
+#[derive(Clone, Debug, Default)]
+pub struct OperationStats {
+    pub bytes: usize,
+    /// How long each command took, in the order they were issued.
+    pub latencies: Vec<Duration>,
+}
+
+impl OperationStats {
+    fn record(&mut self, bytes: usize, latency: Duration) {
+        self.bytes += bytes;
+        self.latencies.push(latency);
+    }
+
+    pub fn total(&self) -> Duration {
+        self.latencies.iter().sum()
+    }
+
+    /// Throughput in kilobytes (1024 bytes) per second.
+    pub fn kib_per_second(&self) -> f64 {
+        let seconds = self.total().as_secs_f64();
+        if seconds == 0.0 {
+            return 0.0;
+        }
+        self.bytes as f64 / 1024.0 / seconds
+    }
+
+    /// The latency that `percent` percent of the commands stayed within,
+    /// using the nearest rank. `None` if no command was timed.
+    pub fn percentile(&self, percent: u32) -> Option<Duration> {
+        let mut sorted = self.latencies.clone();
+        sorted.sort();
+        let rank = (sorted.len() * percent.min(100) as usize).div_ceil(100);
+        sorted.get(rank.max(1) - 1).copied()
+    }
+}
+
+/// The timings of a [`benchmark`] run.
+#[derive(Clone, Debug, Default)]
+pub struct BenchmarkReport {
+    pub writes: OperationStats,
+    pub reads: OperationStats,
+}
+
+/// Write `size` bytes of flash at `scratch_address` a page at a time, then
+/// read them back a page at a time, timing every command.
+///
+/// Whatever was stored there is overwritten, so `scratch_address` must point
+/// at flash that is not in use. It has to be page aligned, and `size` is
+/// rounded up to whole pages.
+pub async fn benchmark<B>(
+    board: &mut B,
+    scratch_address: u32,
+    size: usize,
+) -> Result<BenchmarkReport, TockloaderError>
+where
+    B: BytesReader + FlashWriter + Send + ?Sized,
+{
+    let page_size = board.page_size();
+    if !(scratch_address as usize).is_multiple_of(page_size) {
+        return Err(TockloaderError::InvalidScratchAddress {
+            address: scratch_address,
+            page_size,
+        });
+    }
+
+    let pages = size.div_ceil(page_size);
+    let mut report = BenchmarkReport::default();
+    for page in 0..pages {
+        let address = scratch_address as usize + page * page_size;
+        // Varying data, so that nothing can be skipped for being erased.
+        let data: Vec<u8> = (0..page_size).map(|i| (i + page) as u8).collect();
+        let start = Instant::now();
+        board.write_page(address as u32, &data).await?;
+        report.writes.record(page_size, start.elapsed());
+    }
+    for page in 0..pages {
+        let address = scratch_address as usize + page * page_size;
+        let start = Instant::now();
+        board.read_range(address, page_size).await?;
+        report.reads.record(page_size, start.elapsed());
+    }
+    Ok(report)
+}
@@ -0,0 +1,303 @@
+//! Settings that depend on the board: its architecture, flash page size and
+//! where apps go.
+//!
+//! Boards with a serial bootloader describe themselves through attributes,
+//! other boards (e.g. flashed with JLink) have to be named with `--board` or
+//! `--arch`.
+
+use log::warn;
+
+use crate::apps::DEFAULT_APP_ADDRESS;
+use crate::bootloader::attribute::Attribute;
+use crate::errors::TockloaderError;
+
+/// Architectures that apps can be compiled for.
+pub const KNOWN_ARCHS: &[&str] = &[
+    "cortex-m0",
+    "cortex-m3",
+    "cortex-m4",
+    "cortex-m7",
+    "rv32i",
+    "rv32imc",
+    "rv32imac",
+];
+
+/// Flash page size used when neither the board nor the user gives one.
+pub const DEFAULT_PAGE_SIZE: usize = 512;
+
+/// Settings typical for chips of one architecture.
+pub struct ArchDefaults {
+    pub arch: &'static str,
+    pub page_size: usize,
+    pub app_address: u32,
+}
+
+/// Fallbacks for when only the architecture is known, e.g. when flashing
+/// with JLink a board that is not in [`KNOWN_BOARDS`].
+///
+/// These are guesses based on common chips, and are wrong for some boards:
+/// they are only used when the user, the board's attributes and its profile
+/// all leave a setting open.
+pub const KNOWN_ARCH_DEFAULTS: &[ArchDefaults] = &[
+    ArchDefaults {
+        arch: "cortex-m0",
+        page_size: 1024,
+        app_address: 0x20000,
+    },
+    ArchDefaults {
+        arch: "cortex-m3",
+        page_size: 512,
+        app_address: 0x30000,
+    },
+    ArchDefaults {
+        arch: "cortex-m4",
+        page_size: 512,
+        app_address: 0x30000,
+    },
+    ArchDefaults {
+        arch: "cortex-m7",
+        page_size: 4096,
+        app_address: 0x40000,
+    },
+    ArchDefaults {
+        arch: "rv32i",
+        page_size: 512,
+        app_address: 0x20030000,
+    },
+    ArchDefaults {
+        arch: "rv32imc",
+        page_size: 512,
+        app_address: 0x20030000,
+    },
+    ArchDefaults {
+        arch: "rv32imac",
+        page_size: 4096,
+        app_address: 0x20040000,
+    },
+];
+
+/// Look up the defaults for an architecture.
+pub fn find_arch_defaults(arch: &str) -> Option<&'static ArchDefaults> {
+    KNOWN_ARCH_DEFAULTS
+        .iter()
+        .find(|defaults| defaults.arch == arch)
+}
+
+/// What is known about a board without asking it.
+pub struct BoardProfile {
+    pub name: &'static str,
+    pub arch: &'static str,
+    pub page_size: usize,
+    pub app_address: u32,
+    /// Where the app region ends, which is the end of flash on every board
+    /// listed here.
+    pub app_region_end: u32,
+    /// How to start the bootloader by hand, if known.
+    pub bootloader_entry: Option<&'static str>,
+    /// The name JLinkExe knows the chip of the board by.
+    pub jlink_device: Option<&'static str>,
+}
+
+pub const KNOWN_BOARDS: &[BoardProfile] = &[
+    BoardProfile {
+        name: "hail",
+        arch: "cortex-m4",
+        page_size: 512,
+        app_address: 0x30000,
+        app_region_end: 0x80000,
+        bootloader_entry: Some("Hold the user button while pressing reset"),
+        jlink_device: Some("ATSAM4LC8C"),
+    },
+    BoardProfile {
+        name: "imix",
+        arch: "cortex-m4",
+        page_size: 512,
+        app_address: 0x40000,
+        app_region_end: 0x80000,
+        bootloader_entry: Some("Hold the user button while pressing reset"),
+        jlink_device: Some("ATSAM4LC8C"),
+    },
+    BoardProfile {
+        name: "nrf52dk",
+        arch: "cortex-m4",
+        page_size: 4096,
+        app_address: 0x30000,
+        app_region_end: 0x80000,
+        bootloader_entry: None,
+        jlink_device: Some("nrf52832_xxaa"),
+    },
+    BoardProfile {
+        name: "nrf52840dk",
+        arch: "cortex-m4",
+        page_size: 4096,
+        app_address: 0x40000,
+        app_region_end: 0x100000,
+        bootloader_entry: None,
+        jlink_device: Some("nrf52840_xxaa"),
+    },
+    BoardProfile {
+        name: "microbit_v2",
+        arch: "cortex-m4",
+        page_size: 4096,
+        app_address: 0x40000,
+        app_region_end: 0x80000,
+        bootloader_entry: None,
+        jlink_device: Some("nrf52833_xxaa"),
+    },
+];
+
+/// Look up a board by name.
+pub fn find_board(name: &str) -> Option<&'static BoardProfile> {
+    KNOWN_BOARDS.iter().find(|board| board.name == name)
+}
+
+/// How to start the bootloader by hand when no board profile says.
+pub const MANUAL_BOOTLOADER_ENTRY: &str =
+    "Hold the bootloader button of the board, if it has one, while pressing reset";
+
+/// How to start the bootloader of `board` by hand.
+pub fn bootloader_entry_instructions(board: Option<&str>) -> &'static str {
+    board
+        .and_then(find_board)
+        .and_then(|profile| profile.bootloader_entry)
+        .unwrap_or(MANUAL_BOOTLOADER_ENTRY)
+}
+
+/// What a board says about itself through its attributes.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BoardAttributes {
+    /// The `board` attribute.
+    pub board: Option<String>,
+    /// The `arch` attribute.
+    pub arch: Option<String>,
+    /// The `appaddr` attribute, where the apps of the board start.
+    pub app_address: Option<u32>,
+}
+
+impl BoardAttributes {
+    /// Pick the attributes describing the board out of the slots read from
+    /// it. An `appaddr` which is not an address is left out, with a warning.
+    pub fn from_attributes(attributes: &[Option<Attribute>]) -> Self {
+        let find = |key: &str| {
+            attributes
+                .iter()
+                .flatten()
+                .find(|attribute| attribute.key == key)
+                .map(|attribute| attribute.value.clone())
+        };
+        let app_address = find("appaddr").and_then(|value| {
+            let address = match value.strip_prefix("0x").or(value.strip_prefix("0X")) {
+                Some(hex) => u32::from_str_radix(hex, 16),
+                None => value.parse(),
+            };
+            address
+                .inspect_err(|_| warn!("Ignoring the appaddr attribute '{value}', not an address"))
+                .ok()
+        });
+        BoardAttributes {
+            board: find("board"),
+            arch: find("arch"),
+            app_address,
+        }
+    }
+
+    /// How the attributes disagree with the board named `board` and its
+    /// profile, if it is known. Missing attributes agree with anything.
+    pub fn differences_from(&self, board: &str) -> Vec<String> {
+        let mut differences = Vec::new();
+        if let Some(name) = self.board.as_deref().filter(|&name| name != board) {
+            differences.push(format!("it says it is '{name}'"));
+        }
+        if let Some(profile) = find_board(board) {
+            if let Some(arch) = self.arch.as_deref().filter(|&arch| arch != profile.arch) {
+                differences.push(format!("its arch is {arch}, not {}", profile.arch));
+            }
+            if let Some(address) = self
+                .app_address
+                .filter(|&address| address != profile.app_address)
+            {
+                differences.push(format!(
+                    "its apps start at {address:#x}, not {:#x}",
+                    profile.app_address
+                ));
+            }
+        }
+        differences
+    }
+}
+
+/// The settings used when talking to a board.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BoardSettings {
+    pub board: Option<String>,
+    pub arch: Option<String>,
+    pub page_size: usize,
+    pub app_address: u32,
+    /// The end of the app region, if known. Nothing is written at or past it.
+    pub app_region_end: Option<u32>,
+}
+
+impl BoardSettings {
+    /// Combine what the user passed explicitly with what is known about the
+    /// board. Explicit values win, then the profile of a known board, then
+    /// the defaults of its architecture, then the global defaults.
+    pub fn resolve(
+        board: Option<&str>,
+        arch: Option<&str>,
+        page_size: Option<usize>,
+        app_address: Option<u32>,
+        app_region_end: Option<u32>,
+    ) -> Result<BoardSettings, TockloaderError> {
+        if let Some(arch) = arch {
+            if !KNOWN_ARCHS.contains(&arch) {
+                return Err(TockloaderError::InvalidArch(arch.to_string()));
+            }
+        }
+        let profile = board.and_then(find_board);
+        let arch = arch.or(profile.map(|profile| profile.arch));
+        let arch_defaults = arch.and_then(find_arch_defaults);
+
+        let settings = BoardSettings {
+            board: board.map(String::from),
+            arch: arch.map(String::from),
+            page_size: page_size
+                .or(profile.map(|profile| profile.page_size))
+                .or(arch_defaults.map(|defaults| defaults.page_size))
+                .unwrap_or(DEFAULT_PAGE_SIZE),
+            app_address: app_address
+                .or(profile.map(|profile| profile.app_address))
+                .or(arch_defaults.map(|defaults| defaults.app_address))
+                .unwrap_or(DEFAULT_APP_ADDRESS),
+            app_region_end: app_region_end.or(profile.map(|profile| profile.app_region_end)),
+        };
+        if let Some(end) = settings.app_region_end {
+            if settings.app_address >= end {
+                return Err(TockloaderError::InvalidAppRegion {
+                    start: settings.app_address,
+                    end,
+                });
+            }
+        }
+        Ok(settings)
+    }
+
+    /// Check that the bootloader can be told to start the kernel at
+    /// `address`: it must be on a page boundary, and inside of flash when its
+    /// end is known.
+    pub fn check_start_address(&self, address: u32) -> Result<(), TockloaderError> {
+        if !(address as usize).is_multiple_of(self.page_size) {
+            return Err(TockloaderError::InvalidStartAddress(format!(
+                "{address:#x} is not a multiple of the {} byte page size",
+                self.page_size
+            )));
+        }
+        if let Some(end) = self.app_region_end {
+            if address >= end {
+                return Err(TockloaderError::InvalidStartAddress(format!(
+                    "{address:#x} is past the end of flash at {end:#x}"
+                )));
+            }
+        }
+        Ok(())
+    }
+}
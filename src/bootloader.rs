@@ -0,0 +1,5 @@
+pub mod attribute;
+pub mod commands;
+pub mod crc;
+pub mod flash;
+pub mod info;
@@ -0,0 +1,119 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::errors::TockloaderError;
+
+/// Size of an attribute slot in the bootloader, and of its raw encoding.
+pub const ATTRIBUTE_LEN: usize = 64;
+
+// Layout of a slot, as stored by the bootloader: the key padded with nulls,
+// then one byte for the length of the value, then the value. Whatever follows
+// the value is left over and not part of it.
+const KEY_OFFSET: usize = 0;
+const VALUE_LEN_OFFSET: usize = KEY_OFFSET + MAX_KEY_LEN;
+const VALUE_OFFSET: usize = VALUE_LEN_OFFSET + 1;
+
+/// The longest key an attribute can have, in bytes.
+pub const MAX_KEY_LEN: usize = 8;
+/// The longest value an attribute can have, in bytes.
+pub const MAX_VALUE_LEN: usize = ATTRIBUTE_LEN - VALUE_OFFSET;
+
+/// One of the key-value attributes stored by the bootloader, such as the
+/// board name or its architecture.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Attribute {
+    pub key: String,
+    pub value: String,
+}
+
+impl Attribute {
+    /// Create an attribute, checking that it fits in a bootloader slot.
+    pub fn new(key: &str, value: &str) -> Result<Attribute, TockloaderError> {
+        if key.is_empty() || key.len() > MAX_KEY_LEN || key.contains('\0') {
+            return Err(TockloaderError::InvalidAttribute(format!(
+                "key '{key}' must be 1 to {MAX_KEY_LEN} bytes long"
+            )));
+        }
+        if value.is_empty() || value.len() > MAX_VALUE_LEN {
+            return Err(TockloaderError::InvalidAttribute(format!(
+                "value of '{key}' must be 1 to {MAX_VALUE_LEN} bytes long"
+            )));
+        }
+        Ok(Attribute {
+            key: key.to_string(),
+            value: value.to_string(),
+        })
+    }
+
+    /// Encode the attribute like [`parse_raw()`](Attribute::parse_raw)
+    /// expects it, for `COMMAND_SET_ATTRIBUTE`.
+    pub fn to_raw(&self) -> [u8; ATTRIBUTE_LEN] {
+        let mut raw = [0u8; ATTRIBUTE_LEN];
+        raw[KEY_OFFSET..KEY_OFFSET + self.key.len()].copy_from_slice(self.key.as_bytes());
+        raw[VALUE_LEN_OFFSET] = self.value.len() as u8;
+        raw[VALUE_OFFSET..VALUE_OFFSET + self.value.len()].copy_from_slice(self.value.as_bytes());
+        raw
+    }
+
+    /// Parse the data of a `RESPONSE_GET_ATTRIBUTE` response.
+    ///
+    /// The response is [`ATTRIBUTE_LEN`] bytes long: a [`MAX_KEY_LEN`] byte
+    /// null padded key, one byte for the length of the value, and up to
+    /// [`MAX_VALUE_LEN`] bytes for the value itself. Returns `None` for unused
+    /// attribute slots, which have no value.
+    ///
+    /// Only the padding after the key is trimmed, the value is taken as long
+    /// as its length says. Bytes which are not UTF-8 are replaced rather than
+    /// dropping the attribute, so that the slot is not mistaken for unused.
+    pub fn parse_raw(bytes: &[u8]) -> Option<Attribute> {
+        let key = String::from_utf8_lossy(bytes.get(KEY_OFFSET..VALUE_LEN_OFFSET)?)
+            .trim_end_matches('\0')
+            .to_string();
+        let vlen = *bytes.get(VALUE_LEN_OFFSET)? as usize;
+        if vlen == 0 || vlen > MAX_VALUE_LEN {
+            return None;
+        }
+        let value =
+            String::from_utf8_lossy(bytes.get(VALUE_OFFSET..VALUE_OFFSET + vlen)?).to_string();
+        Some(Attribute { key, value })
+    }
+}
+
+/// Parse a `key=value` assignment, as given on the command line.
+pub fn parse_assignment(assignment: &str) -> Result<Attribute, TockloaderError> {
+    let (key, value) = assignment.split_once('=').ok_or_else(|| {
+        TockloaderError::InvalidAttribute(format!("'{assignment}' is not of the form key=value"))
+    })?;
+    Attribute::new(key, value)
+}
+
+/// Read attributes from a file holding a flat table of strings, as JSON if
+/// its extension is `.json` and as TOML otherwise:
+///
+/// ```toml
+/// board = "hail"
+/// arch = "cortex-m4"
+/// appaddr = "0x30000"
+/// ```
+///
+/// Values must be strings, so that e.g. addresses keep the format the kernel
+/// expects.
+pub fn read_attribute_file(path: &Path) -> Result<Vec<Attribute>, TockloaderError> {
+    let contents = std::fs::read_to_string(path)?;
+    let invalid =
+        |reason: String| TockloaderError::InvalidAttribute(format!("{}: {reason}", path.display()));
+    let table: BTreeMap<String, String> = if path
+        .extension()
+        .is_some_and(|extension| extension == "json")
+    {
+        serde_json::from_str(&contents).map_err(|error| invalid(error.to_string()))?
+    } else {
+        toml::from_str(&contents).map_err(|error| invalid(error.to_string()))?
+    };
+    table
+        .iter()
+        .map(|(key, value)| Attribute::new(key, value))
+        .collect()
+}
@@ -0,0 +1,85 @@
+//! Constants and framing for the Tock serial bootloader protocol.
+//!
+//! Commands are sent as their (escaped) arguments followed by
+//! `ESCAPE_CHAR, COMMAND_*`. Responses start with `ESCAPE_CHAR, RESPONSE_*`,
+//! followed by the (escaped) response data. Any `ESCAPE_CHAR` inside of the
+//! data is sent twice.
+//!
+//! Reference: https://github.com/tock/tock-bootloader/blob/master/doc/protocol.md
+
+pub const ESCAPE_CHAR: u8 = 0xFC;
+
+pub const COMMAND_PING: u8 = 0x01;
+pub const COMMAND_INFO: u8 = 0x03;
+pub const COMMAND_ID: u8 = 0x04;
+pub const COMMAND_RESET: u8 = 0x05;
+pub const COMMAND_ERASE_PAGE: u8 = 0x06;
+pub const COMMAND_WRITE_PAGE: u8 = 0x07;
+pub const COMMAND_XEBLOCK: u8 = 0x08;
+pub const COMMAND_XWPAGE: u8 = 0x09;
+pub const COMMAND_CRCRX: u8 = 0x10;
+pub const COMMAND_READ_RANGE: u8 = 0x11;
+pub const COMMAND_XRRANGE: u8 = 0x12;
+pub const COMMAND_SET_ATTRIBUTE: u8 = 0x13;
+pub const COMMAND_GET_ATTRIBUTE: u8 = 0x14;
+pub const COMMAND_CRC_INTERNAL_FLASH: u8 = 0x15;
+pub const COMMAND_CRCEF: u8 = 0x16;
+pub const COMMAND_XEPAGE: u8 = 0x17;
+pub const COMMAND_XFINIT: u8 = 0x18;
+pub const COMMAND_CLKOUT: u8 = 0x19;
+pub const COMMAND_WUSER: u8 = 0x20;
+pub const COMMAND_CHANGE_BAUD_RATE: u8 = 0x21;
+pub const COMMAND_EXIT: u8 = 0x22;
+pub const COMMAND_SET_START_ADDRESS: u8 = 0x23;
+
+/// First argument of `COMMAND_CHANGE_BAUD_RATE`: switch to the new rate once
+/// the response has been sent.
+pub const CHANGE_BAUD_RATE_SET: u8 = 0x01;
+/// First argument of `COMMAND_CHANGE_BAUD_RATE`: confirm the new rate works.
+/// Without it the bootloader goes back to the previous rate.
+pub const CHANGE_BAUD_RATE_CONFIRM: u8 = 0x02;
+
+pub const RESPONSE_OVERFLOW: u8 = 0x10;
+pub const RESPONSE_PONG: u8 = 0x11;
+pub const RESPONSE_BADADDR: u8 = 0x12;
+pub const RESPONSE_INTERROR: u8 = 0x13;
+pub const RESPONSE_BADARGS: u8 = 0x14;
+pub const RESPONSE_OK: u8 = 0x15;
+pub const RESPONSE_UNKNOWN: u8 = 0x16;
+pub const RESPONSE_XFTIMEOUT: u8 = 0x17;
+pub const RESPONSE_XFEPE: u8 = 0x18;
+pub const RESPONSE_CRCRX: u8 = 0x19;
+pub const RESPONSE_READ_RANGE: u8 = 0x20;
+pub const RESPONSE_XRRANGE: u8 = 0x21;
+pub const RESPONSE_GET_ATTRIBUTE: u8 = 0x22;
+pub const RESPONSE_CRC_INTERNAL_FLASH: u8 = 0x23;
+pub const RESPONSE_CRCXF: u8 = 0x24;
+pub const RESPONSE_INFO: u8 = 0x25;
+pub const RESPONSE_CHANGE_BAUD_FAIL: u8 = 0x26;
+
+/// Escape the arguments of a command, doubling every `ESCAPE_CHAR`.
+pub fn escape(data: &[u8]) -> Vec<u8> {
+    let mut escaped = Vec::with_capacity(data.len());
+    for &byte in data {
+        escaped.push(byte);
+        if byte == ESCAPE_CHAR {
+            escaped.push(ESCAPE_CHAR);
+        }
+    }
+    escaped
+}
+
+/// Undo [`escape`], collapsing every doubled `ESCAPE_CHAR`.
+pub fn deescape(data: &[u8]) -> Vec<u8> {
+    let mut deescaped = Vec::with_capacity(data.len());
+    let mut previous_was_escape = false;
+    for &byte in data {
+        if byte == ESCAPE_CHAR && !previous_was_escape {
+            previous_was_escape = true;
+            continue;
+        }
+        previous_was_escape = false;
+        deescaped.push(byte);
+    }
+    deescaped
+}
@@ -0,0 +1,45 @@
+//! The CRC used by the bootloader's `COMMAND_CRC_INTERNAL_FLASH`.
+//!
+//! The bootloader computes the standard CRC-32 (CRC-32/ISO-HDLC, the one used
+//! by zlib and Ethernet), which is also what the Python tockloader checks the
+//! response against:
+//!
+//! - polynomial `0x04C11DB7`, processed bit-reflected as `0xEDB88320`
+//! - initial value `0xFFFFFFFF`
+//! - input and output reflected
+//! - final XOR `0xFFFFFFFF`
+//!
+//! Reference: https://github.com/tock/tock-bootloader/blob/master/doc/protocol.md
+
+const POLYNOMIAL_REFLECTED: u32 = 0xEDB8_8320;
+
+const TABLE: [u32; 256] = make_table();
+
+const fn make_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut index = 0;
+    while index < 256 {
+        let mut crc = index as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ POLYNOMIAL_REFLECTED
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[index] = crc;
+        index += 1;
+    }
+    table
+}
+
+/// Compute the CRC of `data` the same way the bootloader does, so that it can
+/// be compared with the response to `COMMAND_CRC_INTERNAL_FLASH`.
+pub fn tock_crc32(data: &[u8]) -> u32 {
+    let crc = data.iter().fold(0xFFFF_FFFF, |crc: u32, &byte| {
+        (crc >> 8) ^ TABLE[((crc ^ byte as u32) & 0xFF) as usize]
+    });
+    crc ^ 0xFFFF_FFFF
+}
@@ -0,0 +1,41 @@
+//! What the bootloader keeps in flash, for backends which read flash directly
+//! rather than asking the bootloader.
+
+use crate::bootloader::attribute::{Attribute, ATTRIBUTE_LEN};
+use crate::errors::TockloaderError;
+use crate::interfaces::traits::{BytesReader, ATTRIBUTE_COUNT, ERASED_BYTE};
+
+/// Address of the first attribute slot. The slots follow each other.
+pub const ATTRIBUTES_ADDRESS: usize = 0x600;
+
+/// Address of the version string of the bootloader.
+pub const VERSION_ADDRESS: usize = 0x40E;
+/// The longest version string, in bytes.
+pub const VERSION_LEN: usize = 8;
+
+/// Read every attribute slot. Unused slots are `None`.
+pub async fn read_attributes<R>(board: &mut R) -> Result<Vec<Option<Attribute>>, TockloaderError>
+where
+    R: BytesReader + Send + ?Sized,
+{
+    let slots = board
+        .read_range(ATTRIBUTES_ADDRESS, ATTRIBUTE_COUNT as usize * ATTRIBUTE_LEN)
+        .await?;
+    Ok(slots
+        .chunks(ATTRIBUTE_LEN)
+        .map(Attribute::parse_raw)
+        .collect())
+}
+
+/// Read the version of the bootloader, or `None` if there is no bootloader.
+pub async fn read_version<R>(board: &mut R) -> Result<Option<String>, TockloaderError>
+where
+    R: BytesReader + Send + ?Sized,
+{
+    let bytes = board.read_range(VERSION_ADDRESS, VERSION_LEN).await?;
+    if bytes.iter().all(|&byte| byte == ERASED_BYTE) {
+        return Ok(None);
+    }
+    let version = String::from_utf8_lossy(&bytes);
+    Ok(Some(version.trim_end_matches('\0').to_string()))
+}
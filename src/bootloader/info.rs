@@ -0,0 +1,167 @@
+//! The information the bootloader gives about itself.
+//!
+//! `COMMAND_INFO` is answered with `RESPONSE_INFO` and 193 bytes of data: the
+//! length of the information string in the first byte, then the string,
+//! padded with zeros. The string is a JSON object such as
+//! `{"version":"1.1.3","name":"Tock Bootloader","start_address":"0x10000"}`.
+//! Every bootloader reports its `version`, older ones nothing else. Boards
+//! whose bootloader does not answer `COMMAND_INFO` may keep the version in
+//! the [`VERSION_ATTRIBUTE`] attribute instead.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde::{Serialize, Serializer};
+
+use crate::bootloader::attribute::Attribute;
+use crate::errors::TockloaderError;
+
+/// Length of the data of `RESPONSE_INFO`, length byte included.
+pub const INFO_LEN: usize = 193;
+
+/// Key of the attribute holding the version of the bootloader, on boards
+/// which keep it there.
+pub const VERSION_ATTRIBUTE: &str = "bootver";
+
+/// What the bootloader says about itself.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct BootloaderInfo {
+    pub version: Option<String>,
+    pub name: Option<String>,
+    /// The address the bootloader starts the kernel at.
+    pub start_address: Option<u32>,
+    /// Every other key, with its value as given, or as JSON if it is not a
+    /// string.
+    #[serde(flatten)]
+    pub other: BTreeMap<String, String>,
+}
+
+impl BootloaderInfo {
+    /// Parse the information string, a JSON object.
+    pub fn parse(info: &str) -> Result<Self, TockloaderError> {
+        let malformed = |reason: String| {
+            TockloaderError::MalformedResponse(format!("bootloader information: {reason}"))
+        };
+        let object: BTreeMap<String, serde_json::Value> =
+            serde_json::from_str(info).map_err(|error| malformed(error.to_string()))?;
+
+        let mut info = BootloaderInfo::default();
+        for (key, value) in object {
+            match (key.as_str(), value) {
+                ("version", serde_json::Value::String(version)) => info.version = Some(version),
+                ("name", serde_json::Value::String(name)) => info.name = Some(name),
+                ("start_address", address) => info.start_address = parse_address(&address),
+                (_, serde_json::Value::String(value)) => {
+                    info.other.insert(key, value);
+                }
+                (_, value) => {
+                    info.other.insert(key, value.to_string());
+                }
+            }
+        }
+        Ok(info)
+    }
+
+    /// The version reported, if it can be parsed.
+    pub fn parsed_version(&self) -> Option<BootloaderVersion> {
+        self.version.as_deref().and_then(BootloaderVersion::parse)
+    }
+}
+
+/// The version of a bootloader, such as `1.1.3`, which tells which commands
+/// it understands. Versions compare in order of their numbers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BootloaderVersion {
+    pub major: u16,
+    pub minor: u16,
+    pub patch: u16,
+}
+
+impl BootloaderVersion {
+    pub const fn new(major: u16, minor: u16, patch: u16) -> Self {
+        BootloaderVersion {
+            major,
+            minor,
+            patch,
+        }
+    }
+
+    /// Parse a version such as `1.1.3`, `v1.1` or `1.2.0-dev`. Missing
+    /// numbers are 0, and anything after the numbers is ignored.
+    pub fn parse(version: &str) -> Option<Self> {
+        let version = version.trim();
+        let version = version.strip_prefix('v').unwrap_or(version);
+        let end = version
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(version.len());
+        let mut numbers = version[..end].split('.').map(str::parse::<u16>);
+        let major = numbers.next()?.ok()?;
+        let minor = numbers.next().transpose().ok()?.unwrap_or(0);
+        let patch = numbers.next().transpose().ok()?.unwrap_or(0);
+        if numbers.next().is_some() {
+            return None;
+        }
+        Some(BootloaderVersion::new(major, minor, patch))
+    }
+
+    /// Find the version of the bootloader in its information, or else in
+    /// the [`VERSION_ATTRIBUTE`] attribute if a slot holds it.
+    pub fn find(info: Option<&BootloaderInfo>, attributes: &[Option<Attribute>]) -> Option<Self> {
+        info.and_then(BootloaderInfo::parsed_version).or_else(|| {
+            attributes
+                .iter()
+                .flatten()
+                .find(|attribute| attribute.key == VERSION_ATTRIBUTE)
+                .and_then(|attribute| BootloaderVersion::parse(&attribute.value))
+        })
+    }
+}
+
+impl fmt::Display for BootloaderVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+impl Serialize for BootloaderVersion {
+    /// As a string, e.g. `"1.1.3"`.
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// An address given as a number, or as a string in decimal or in hex with a
+/// `0x` prefix.
+fn parse_address(address: &serde_json::Value) -> Option<u32> {
+    match address {
+        serde_json::Value::Number(number) => number
+            .as_u64()
+            .and_then(|address| u32::try_from(address).ok()),
+        serde_json::Value::String(address) => match address.strip_prefix("0x") {
+            Some(hex) => u32::from_str_radix(hex, 16).ok(),
+            None => address.parse().ok(),
+        },
+        _ => None,
+    }
+}
+
+impl fmt::Display for BootloaderInfo {
+    /// One line, e.g. `Tock Bootloader version 1.1.3, starts the kernel at
+    /// 0x10000`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(name) = &self.name {
+            write!(f, "{name} ")?;
+        }
+        match &self.version {
+            Some(version) => write!(f, "version {version}")?,
+            None => write!(f, "(unknown version)")?,
+        }
+        if let Some(address) = self.start_address {
+            write!(f, ", starts the kernel at {address:#x}")?;
+        }
+        for (key, value) in &self.other {
+            write!(f, ", {key}: {value}")?;
+        }
+        Ok(())
+    }
+}
@@ -1,4 +1,7 @@
 use clap::{arg, crate_version, Command};
+use tockloader::interfaces::serial::console_protocol::ProtocolKind;
+use tockloader::interfaces::serial::virtual_terminal::{CtrlC, LocalEcho, Substitution};
+use tockloader::style::OutputFormat;
 
 /// Create the [command](clap::Command) object which will handle all of the command line arguments.
 pub fn make_cli() -> Command {
@@ -8,7 +11,22 @@ pub fn make_cli() -> Command {
         .arg_required_else_help(true)
         .subcommands(get_subcommands())
         .args([
-            arg!(--debug "Print additional debugging information").action(clap::ArgAction::SetTrue)
+            arg!(--debug "Print additional debugging information")
+                .action(clap::ArgAction::SetTrue)
+                .global(true),
+            arg!(--"dry-run" "Print what would be written to the board without modifying it")
+                .action(clap::ArgAction::SetTrue)
+                .global(true),
+            arg!(--json "Same as --format json")
+                .action(clap::ArgAction::SetTrue)
+                .global(true),
+            arg!(--"no-color" "Do not color the output, as when it is not a terminal or NO_COLOR is set")
+                .action(clap::ArgAction::SetTrue)
+                .global(true),
+            arg!(--format <FORMAT> "Print results, and errors on stderr, as text or json")
+                .value_parser(clap::value_parser!(OutputFormat))
+                .default_value("text")
+                .global(true),
         ])
     // Note: arg_require_else_help will trigger the help command if no argument/subcommand is given.
     // This means that the --debug flag will not trigger the help menu, even if alone it does nothing.
@@ -16,57 +34,352 @@ pub fn make_cli() -> Command {
 
 /// Generate all of the [subcommands](clap::Command) used by the program.
 fn get_subcommands() -> Vec<Command> {
-    vec![Command::new("listen")
-        .about("Open a terminal to receive UART data")
-        .args(get_app_args())
-        .args(get_channel_args())
-        .arg_required_else_help(true)]
+    vec![
+        Command::new("listen")
+            .about("Open a terminal to receive UART data")
+            .args(get_app_args())
+            .args(get_interface_args())
+            .args(get_terminal_args()),
+        Command::new("reset")
+            .about("Reset the board out of the bootloader, so that the kernel starts")
+            .args(get_interface_args())
+            .arg(
+                arg!(--listen "Then open a terminal to receive UART data, as with listen")
+                    .action(clap::ArgAction::SetTrue)
+                    .conflicts_with_all(["jlink", "openocd", "flash-file"]),
+            )
+            .args(get_terminal_args()),
+        Command::new("install")
+            .about("Install apps on the board")
+            .args(get_app_args())
+            .args(get_interface_args())
+            .args([
+                arg!(<PATH> "Tabs or TBF files to install, or directories whose tabs are all installed")
+                    .num_args(1..),
+                arg!(--alignment <BYTES> "Start apps on multiples of this many bytes instead of the page size")
+                    .value_parser(clap::value_parser!(u32)),
+                arg!(--"slot-size" <BYTES> "Give each app a fixed slot of this many bytes, so apps start at multiples of it")
+                    .value_parser(clap::value_parser!(u32).range(1..))
+                    .conflicts_with("alignment"),
+                arg!(--"op-timeout" <SECONDS> "Abort if flashing takes longer than this, e.g. because the board stopped responding")
+                    .value_parser(clap::value_parser!(u64))
+                    .default_value("60"),
+                arg!(--erase "Replace the installed apps instead of adding to them, keeping sticky apps unless --force is given")
+                    .action(clap::ArgAction::SetTrue),
+                arg!(--"update-app-address" "Store the app address in the board's appaddr attribute, so that it matches where the apps are")
+                    .action(clap::ArgAction::SetTrue)
+                    .conflicts_with("flash-file"),
+                arg!(--"no-fast" "Flash at the usual baud rate, even if the bootloader can go faster")
+                    .action(clap::ArgAction::SetTrue),
+                arg!(--"fast-baud" <RATE> "The baud rate to flash at, going back to the usual one when done")
+                    .value_parser(clap::value_parser!(u32))
+                    .default_value("921600"),
+                get_keep_alive_arg(),
+            ])
+            .args(get_hook_args()),
+        Command::new("erase-apps")
+            .about("Erase the apps on the board, keeping sticky apps unless --force is given")
+            .long_about(
+                "Erase the apps on the board, keeping sticky apps unless --force is given. \
+                 Every page of the app region is erased, up to its end if it is known and \
+                 else up to the end of the apps. No padding is written: erased flash already \
+                 ends the list of apps, and padding would leave no room for new ones.",
+            )
+            .args(get_app_args())
+            .args(get_interface_args())
+            .arg(get_keep_alive_arg())
+            .args(get_hook_args()),
+        Command::new("uninstall")
+            .about("Remove the apps with a given name from the board")
+            .args(get_app_args())
+            .args(get_interface_args())
+            .args([
+                arg!(<NAME> "The package name of the apps to remove"),
+                arg!(--first "Only remove the first app with this name, in flash order")
+                    .action(clap::ArgAction::SetTrue),
+                get_keep_alive_arg(),
+            ])
+            .args(get_hook_args()),
+        Command::new("list-ports")
+            .about("List the serial ports and debug probes attached to this machine"),
+        Command::new("flash-kernel")
+            .about("Write a kernel binary to the board, refusing to overwrite apps")
+            .args(get_app_args())
+            .args(get_interface_args())
+            .args([
+                arg!(<FILE> "The kernel binary"),
+                arg!(--address <ADDRESS> "Where to write the kernel")
+                    .value_parser(parse_address)
+                    .required(true),
+                get_keep_alive_arg(),
+            ])
+            .args(get_hook_args()),
+        Command::new("info")
+            .about("Show what is on the board: attributes, bootloader and apps")
+            .args(get_app_args())
+            .args(get_interface_args())
+            .arg(
+                arg!(--"attributes-only" "Only list the attributes stored in the bootloader")
+                    .action(clap::ArgAction::SetTrue),
+            ),
+        Command::new("set-attributes")
+            .about("Store attributes in the bootloader, e.g. when commissioning a board")
+            .args(get_interface_args())
+            .args([
+                arg!([ATTRIBUTES] ... "Attributes to set, as key=value"),
+                arg!(--file <FILE> "Also set the attributes in this TOML or JSON file"),
+            ])
+            .group(
+                clap::ArgGroup::new("attributes")
+                    .args(["ATTRIBUTES", "file"])
+                    .multiple(true)
+                    .required(true),
+            ),
+        Command::new("get-start-address")
+            .about("Show the address the bootloader starts the kernel at")
+            .args(get_interface_args()),
+        Command::new("set-start-address")
+            .about("Change the address the bootloader starts the kernel at")
+            .args(get_app_args())
+            .args(get_interface_args())
+            .arg(arg!(<ADDRESS> "The new start address").value_parser(parse_address)),
+        Command::new("list")
+            .about("List the apps installed on the board")
+            .args(get_app_args())
+            .args(get_interface_args())
+            .arg(
+                arg!(--"min-binary-version" <VERSION> "Only list apps with at least this binary version")
+                    .value_parser(clap::value_parser!(u32)),
+            )
+            .args([
+                arg!(--"fixed-only" "Only list apps built for fixed addresses in flash or RAM")
+                    .action(clap::ArgAction::SetTrue),
+                arg!(--"pic-only" "Only list position independent apps")
+                    .action(clap::ArgAction::SetTrue)
+                    .conflicts_with("fixed-only"),
+                arg!(-v --verbose "Also list the writeable flash regions and the kernel version each app needs")
+                    .action(clap::ArgAction::SetTrue),
+                arg!(--verify "Check the hashes and signatures in the footers of each app")
+                    .action(clap::ArgAction::SetTrue),
+            ]),
+        Command::new("read")
+            .about("Read a range of flash, as a hex dump or into a file")
+            .args(get_interface_args())
+            .args([
+                arg!(<ADDRESS> "Where to start reading").value_parser(parse_address),
+                arg!(<LENGTH> "How many bytes to read").value_parser(parse_address),
+                arg!(-o --output <FILE> "Write the raw bytes to this file instead of printing them"),
+            ]),
+        // For maintainers, to measure changes to the protocol code.
+        Command::new("benchmark")
+            .about("Time reads and writes of a scratch area of flash, overwriting it")
+            .hide(true)
+            .args(get_interface_args())
+            .args([
+                arg!(--"scratch-address" <ADDRESS> "Start of flash that is not in use and may be overwritten")
+                    .value_parser(parse_address)
+                    .required(true),
+                arg!(--size <BYTES> "How many bytes to write and read back")
+                    .value_parser(parse_address)
+                    .default_value("4096"),
+            ]),
+        Command::new("verify-region")
+            .about("Check the header, size and credentials of every TBF on the board")
+            .args(get_app_args())
+            .args(get_interface_args()),
+        Command::new("doctor")
+            .about("Check the serial ports, permissions, tools and board, with hints to fix problems")
+            .args(get_interface_args()),
+        Command::new("version")
+            .about("Show the version of tockloader")
+            .arg(
+                arg!(-v --verbose "Also show supported formats, backends and the git commit")
+                    .action(clap::ArgAction::SetTrue),
+            ),
+        Command::new("tab-extract")
+            .about("Write the TBF of one architecture out of a tab file, without a board")
+            .args([
+                arg!(<TAB> "The tab file"),
+                arg!(--arch <ARCH> "The architecture to extract, the available ones are listed if not given")
+                    .requires("output"),
+                arg!(-o --output <FILE> "Where to write the TBF").requires("arch"),
+            ]),
+        Command::new("inspect")
+            .about("Show the contents of a TBF or tab file, without a board")
+            .args([
+                arg!(<FILE> "The TBF or tab file to inspect"),
+                arg!(--hash "Hash the app and compare with the hashes in its credentials")
+                    .action(clap::ArgAction::SetTrue),
+                arg!(--raw "List the fields and TLVs of the header with their offsets and bytes")
+                    .action(clap::ArgAction::SetTrue)
+                    .conflicts_with("hash"),
+            ]),
+    ]
 }
 
 /// Generate all of the [arguments](clap::Arg) that are required by subcommands which work with apps.
 fn get_app_args() -> Vec<clap::Arg> {
     vec![
-        arg!(-a --"app-address" <ADDRESS> "Address where apps are located"),
+        arg!(-a --"app-address" <ADDRESS> "Address where apps are located, in decimal or in hex with a 0x prefix")
+            .value_parser(parse_address),
+        arg!(--"kernel-end" <ADDRESS> "Where the kernel ends, and so where apps start")
+            .value_parser(parse_address)
+            .conflicts_with("app-address"),
+        arg!(--"app-region-end" <ADDRESS> "End of the app region, nothing is written past it")
+            .value_parser(parse_address),
+        arg!(--"app-region" <RANGE> "Start and end of the app region, as START:END")
+            .value_parser(parse_region)
+            .conflicts_with_all(["app-address", "kernel-end", "app-region-end"]),
         arg!(--force "Allow apps on boards that are not listed as compatible")
             .action(clap::ArgAction::SetTrue),
         arg!(--"bundle-apps" "Concatenate apps and flash all together, re-flashing apps as needed")
             .action(clap::ArgAction::SetTrue),
+        arg!(--"force-arch" <ARCH> "Install the binary for this architecture instead of the one reported by the board"),
     ]
     // Note: the .action(clap::ArgAction::SetTrue) doesn't seem to be necessary, though in clap documentation it is used.
 }
 
-/// Generate all of the [arguments](clap::Arg) that are required by subcommands which work
-/// with channels and computer-board communication.
-fn get_channel_args() -> Vec<clap::Arg> {
+/// Generate the [arguments](clap::Arg) of subcommands which open a terminal to the
+/// board.
+fn get_terminal_args() -> Vec<clap::Arg> {
+    vec![
+        arg!(--log <FILE> "Also append everything received to this file, byte for byte")
+            .visible_alias("output"),
+        arg!(--"strip-ansi" "Remove ANSI escape sequences from the log")
+            .action(clap::ArgAction::SetTrue)
+            .requires("log"),
+        arg!(--timestamp "Start each line received with the seconds since the terminal was opened")
+            .action(clap::ArgAction::SetTrue),
+        arg!(--"local-echo" <MODE> "Print typed keys locally: on, off, or auto to rely on the board")
+            .value_parser(clap::value_parser!(LocalEcho))
+            .default_value("auto"),
+        arg!(--"ctrl-c" <MODE> "What Ctrl+C does: quit, or forward it to the board and quit on a second one within 500 ms")
+            .value_parser(clap::value_parser!(CtrlC))
+            .default_value("quit"),
+        arg!(--protocol <PROTOCOL> "How the console talks: raw text, or packets tagged with the process they are for")
+            .value_parser(clap::value_parser!(ProtocolKind))
+            .default_value("raw"),
+        arg!(--substitute <SUBSTITUTIONS> "For older kernels, print newlines as \\r\\n (newline) and null bytes as \u{2400} (null), comma separated")
+            .value_parser(clap::value_parser!(Substitution))
+            .value_delimiter(','),
+        arg!(--raw "Print what the board sends byte for byte, even bytes that are not valid UTF-8")
+            .action(clap::ArgAction::SetTrue)
+            .conflicts_with_all(["substitute", "timestamp"]),
+        arg!(--"strict-utf8" "Stop when the board sends bytes that are not valid UTF-8, rather than showing them as \u{FFFD}")
+            .action(clap::ArgAction::SetTrue)
+            .conflicts_with("raw"),
+    ]
+}
+
+/// Generate the [argument](clap::Arg) of subcommands which flash the board, to keep its
+/// bootloader from timing out while tockloader is busy elsewhere.
+fn get_keep_alive_arg() -> clap::Arg {
+    arg!(--"keep-bootloader-alive" "Ping the bootloader whenever it is idle for a few seconds, for bootloaders that start the kernel when left alone")
+        .action(clap::ArgAction::SetTrue)
+}
+
+/// Generate the [arguments](clap::Arg) of subcommands which flash the board, to run shell
+/// commands around it. These run with the user's rights, see [`tockloader::hooks`].
+fn get_hook_args() -> Vec<clap::Arg> {
+    vec![
+        arg!(--before <CMD> "Shell command to run before connecting, e.g. to power cycle the board. Flashing is aborted if it fails"),
+        arg!(--after <CMD> "Shell command to run when done. TOCKLOADER_RESULT and TOCKLOADER_EXIT_CODE tell it how flashing went"),
+    ]
+}
+
+/// Generate all of the [arguments](clap::Arg) that are required by subcommands which talk
+/// to a board, selecting the [interface](tockloader::interfaces) to it.
+fn get_interface_args() -> Vec<clap::Arg> {
     vec![
-        arg!(-p --port "The serial port or device name to use"),
+        arg!(-p --port <PORT> "The serial port or device name to use"),
+        arg!(--"no-interactive" "Fail instead of asking which serial port to use when several are found")
+            .action(clap::ArgAction::SetTrue),
         arg!(--serial "Use the serial bootloader to flash")
             .action(clap::ArgAction::SetTrue),
         arg!(--jlink "Use JLinkExe to flash")
             .action(clap::ArgAction::SetTrue),
         arg!(--openocd "Use OpenOCD to flash")
-            .action(clap::ArgAction::SetTrue),
+            .action(clap::ArgAction::SetTrue)
+            .requires("openocd-board"),
         arg!(--"jlink-device" <DEVICE> "The device type to pass to JLinkExe. Useful for initial commissioning.")
             .default_value("cortex-m0"),
         arg!(--"jlink-cmd" <CMD> "The JLinkExe binary to invoke"),
-        arg!(--"jlink-speed" <SPEED> "The JLink speed to pass to JLinkExe"),
+        arg!(--"jlink-speed" <SPEED> "The JLink speed to pass to JLinkExe, in kHz")
+            .value_parser(clap::value_parser!(u32)),
         arg!(--"jlink-if" <INTERFACE> "The interface type to pass to JLinkExe"),
         arg!(--"openocd-board" <CFG_FILE> "The cfg file in OpenOCD `board` folder"),
         arg!(--"openocd-cmd" <CMD> "The openocd binary to invoke")
             .default_value("openocd"),
         // These may not work out of the box
         arg!(--"openocd-options" <OPTIONS> "Tockloader-specific flags to direct how Tockloader uses OpenOCD"),
-        arg!(--"openocd-commands" <CMDS> "Directly specify which OpenOCD commands to use for \"program\", \"read\", or \"erase\" actions"),
+        arg!(--"openocd-commands" <CMDS> "Directly specify which OpenOCD commands to use for \"program\", \"read\", or \"erase\" actions, as ACTION=COMMANDS. May be given once per action")
+            .value_parser(parse_openocd_commands)
+            .action(clap::ArgAction::Append),
         // -----
-        arg!(--"flash-file" "Operate on a binary flash file instead of a proper board")
-            .action(clap::ArgAction::SetTrue),
+        arg!(--"flash-file" <FILE> "Operate on a binary flash file instead of a proper board")
+            .conflicts_with_all(["jlink", "openocd"]),
+        arg!(--"flash-file-size" <SIZE> "How many bytes of erased flash to create the flash file with, if it does not exist")
+            .value_parser(parse_address)
+            .default_value("0x100000"),
         arg!(--board <BOARD> "Explicitly specify the board that is being targeted"),
         arg!(--arch <ARCH> "Explicitly specify the architecture of the board that is being targeted"),
         arg!(--"page-size" <SIZE> "Explicitly specify how many bytes in a flash page")
+            .value_parser(clap::value_parser!(usize))
             .default_value("0"),
         arg!(--"baud-rate" <RATE> "If using serial, set the target baud rate")
+            .value_parser(clap::value_parser!(u32))
             .default_value("115200"),
+        arg!(--retries <COUNT> "How many times to retry a command after a malformed response")
+            .value_parser(clap::value_parser!(u32))
+            .default_value("3"),
+        arg!(--timeout <MS> "How long to wait for each response of the bootloader, in milliseconds")
+            .value_parser(clap::value_parser!(u64).range(1..))
+            .default_value("1000"),
         arg!(--"no-bootloader-entry" "Tell Tockloader to assume the bootloader is already active")
             .action(clap::ArgAction::SetTrue),
+        arg!(--"dtr-rts-wait" <MS> "How long the bootloader takes to start after a reset with DTR and RTS, in milliseconds")
+            .value_parser(clap::value_parser!(u64))
+            .default_value("500"),
+        arg!(--"baud-touch-wait" <MS> "How long the port takes to come back after a reset by touching it at 1200 baud, in milliseconds")
+            .value_parser(clap::value_parser!(u64))
+            .default_value("1000"),
     ]
 }
+
+/// Parse an address given in decimal, or in hex with a `0x` prefix.
+fn parse_address(value: &str) -> Result<u32, String> {
+    let result = match value.strip_prefix("0x").or(value.strip_prefix("0X")) {
+        Some(hex) => u32::from_str_radix(hex, 16),
+        None => value.parse(),
+    };
+    result.map_err(|error| format!("invalid address '{value}': {error}"))
+}
+
+/// Parse an address range given as `START:END`, with the end exclusive.
+fn parse_region(value: &str) -> Result<(u32, u32), String> {
+    let (start, end) = value
+        .split_once(':')
+        .ok_or(format!("invalid region '{value}': expected START:END"))?;
+    let (start, end) = (parse_address(start)?, parse_address(end)?);
+    if start >= end {
+        return Err(format!(
+            "invalid region '{value}': the start must be before the end"
+        ));
+    }
+    Ok((start, end))
+}
+
+/// Parse an `--openocd-commands` value, `ACTION=COMMANDS`.
+fn parse_openocd_commands(value: &str) -> Result<(String, String), String> {
+    const ACTIONS: [&str; 3] = ["program", "read", "erase"];
+    let (action, commands) = value.split_once('=').ok_or(format!(
+        "invalid commands '{value}': expected ACTION=COMMANDS"
+    ))?;
+    if !ACTIONS.contains(&action) {
+        return Err(format!(
+            "invalid action '{action}': expected one of {}",
+            ACTIONS.join(", ")
+        ));
+    }
+    Ok((action.to_string(), commands.to_string()))
+}
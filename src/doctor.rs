@@ -0,0 +1,119 @@
+//! Checking the environment tockloader runs in, for `tockloader doctor`.
+//!
+//! Each check reports what it found, and when it fails, what to do about it.
+//! Most hints come from the errors themselves, which already explain how to
+//! fix e.g. missing permissions.
+
+use std::path::{Path, PathBuf};
+
+#[cfg(feature = "serial")]
+use tokio_serial::SerialPortInfo;
+
+#[cfg(feature = "serial")]
+use crate::interfaces::serial::{available_ports, open_port};
+use crate::interfaces::traits::BootloaderInterface;
+
+/// What one check found.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Check {
+    pub name: String,
+    pub passed: bool,
+    /// What was found, or what went wrong and how to fix it.
+    pub detail: String,
+}
+
+impl Check {
+    pub fn pass(name: impl Into<String>, detail: impl Into<String>) -> Self {
+        Check {
+            name: name.into(),
+            passed: true,
+            detail: detail.into(),
+        }
+    }
+
+    pub fn fail(name: impl Into<String>, detail: impl Into<String>) -> Self {
+        Check {
+            name: name.into(),
+            passed: false,
+            detail: detail.into(),
+        }
+    }
+}
+
+/// Check that serial ports can be listed and that there is at least one, and
+/// return the ports found.
+#[cfg(feature = "serial")]
+pub fn check_ports() -> (Check, Vec<SerialPortInfo>) {
+    let name = "Serial ports";
+    match available_ports() {
+        Ok(ports) if ports.is_empty() => (
+            Check::fail(
+                name,
+                "No serial port found. Check that the board is plugged in with a cable that \
+                 carries data, not only power.",
+            ),
+            ports,
+        ),
+        Ok(ports) => (Check::pass(name, format!("{} found", ports.len())), ports),
+        Err(error) => (Check::fail(name, error.to_string()), Vec::new()),
+    }
+}
+
+/// Check that the serial port at `path` can be opened.
+#[cfg(feature = "serial")]
+pub fn check_port_access(path: &str, baud_rate: u32) -> Check {
+    let name = format!("Access to {path}");
+    match open_port(path, baud_rate) {
+        Ok(_) => Check::pass(name, "can be opened"),
+        Err(error) => Check::fail(name, error.to_string()),
+    }
+}
+
+/// Check that `program`, which the `tool` backend runs, can be found.
+pub fn check_tool(tool: &str, program: &str) -> Check {
+    let name = format!("{tool} backend");
+    match find_program(program) {
+        Some(path) => Check::pass(name, format!("{program} is {}", path.display())),
+        None => Check::fail(
+            name,
+            format!("{program} is not on the PATH. Install it, or add its directory to the PATH."),
+        ),
+    }
+}
+
+/// Check the programs run by the debug probe backends built into this
+/// tockloader.
+pub fn check_tools() -> Vec<Check> {
+    let tools: &[(&str, &str)] = &[
+        #[cfg(feature = "openocd")]
+        ("openocd", "openocd"),
+        #[cfg(feature = "jlink")]
+        ("jlink", crate::interfaces::jlink::JLINK_COMMAND),
+    ];
+    tools
+        .iter()
+        .map(|(tool, program)| check_tool(tool, program))
+        .collect()
+}
+
+/// Check that the bootloader of `board` answers a ping.
+pub async fn check_ping<B: BootloaderInterface>(board: &mut B, port: &str) -> Check {
+    let name = format!("Bootloader on {port}");
+    match board.ping().await {
+        Ok(()) => Check::pass(name, "answered a ping"),
+        Err(error) => Check::fail(name, error.to_string()),
+    }
+}
+
+/// Find where `program` would be run from: itself if it is a path, or else
+/// the first match in the directories of the `PATH`.
+pub fn find_program(program: &str) -> Option<PathBuf> {
+    let is_file =
+        |path: &Path| path.is_file() || (cfg!(windows) && path.with_extension("exe").is_file());
+    if Path::new(program).components().count() > 1 {
+        return is_file(Path::new(program)).then(|| PathBuf::from(program));
+    }
+    std::env::split_paths(&std::env::var_os("PATH")?)
+        .map(|directory| directory.join(program))
+        .find(|path| is_file(path))
+}
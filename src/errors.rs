@@ -0,0 +1,646 @@
+use std::fmt;
+use std::io;
+use std::time::Duration;
+
+use tbf_parser::types::TbfParseError;
+
+use crate::bootloader::commands::{
+    RESPONSE_BADADDR, RESPONSE_BADARGS, RESPONSE_CHANGE_BAUD_FAIL, RESPONSE_UNKNOWN,
+};
+
+/// All of the errors that can be produced by tockloader.
+#[derive(Debug)]
+pub enum TockloaderError {
+    IOError(io::Error),
+    /// A `--before` or `--after` command failed. `code` is `None` if it was
+    /// killed by a signal.
+    HookFailed {
+        command: String,
+        code: Option<i32>,
+    },
+    #[cfg(feature = "serial")]
+    TokioSerialError(tokio_serial::Error),
+    NoPortAvailable,
+    /// Several serial ports were found and none was chosen, as asking was
+    /// not allowed. Each port is described on one line.
+    MultiplePortsAvailable {
+        ports: Vec<String>,
+    },
+    /// A backend was selected which this build can not use. `compiled` tells
+    /// whether its Cargo feature was enabled.
+    UnsupportedBackend {
+        backend: &'static str,
+        compiled: bool,
+    },
+    /// The program of a debug probe backend, run as `command`, is not
+    /// installed. `hint` says how to install it or point tockloader to it.
+    ToolNotFound {
+        tool: &'static str,
+        command: String,
+        hint: &'static str,
+    },
+    /// The program of a debug probe backend could not attach to the target
+    /// and halt it. `output` is what it printed.
+    TargetNotResponding {
+        tool: &'static str,
+        output: String,
+    },
+    /// The program of a debug probe backend attached to the board, but could
+    /// not carry out a command. `output` is what it printed.
+    ToolCommandFailed {
+        tool: &'static str,
+        output: String,
+    },
+    /// The program of a debug probe backend does not know the microcontroller
+    /// `device` it was asked to connect to.
+    UnknownDevice {
+        tool: &'static str,
+        device: String,
+    },
+    /// `failed` of the `total` checks of `tockloader doctor` did not pass.
+    DoctorChecksFailed {
+        failed: usize,
+        total: usize,
+    },
+    /// The serial ports at these paths exist, but the user is not allowed to
+    /// open them.
+    PermissionDenied {
+        paths: Vec<String>,
+    },
+    /// The board did not answer in time.
+    Timeout,
+    /// A whole operation took longer than its deadline, most likely because
+    /// the board stopped responding. Flash may have been partially written.
+    OperationTimeout {
+        timeout: Duration,
+        pages_written: usize,
+    },
+    /// The board did not print its console prompt in time.
+    PromptNotFound {
+        prompt: String,
+        timeout: Duration,
+    },
+    /// The serial stream could not be put back together after the terminal
+    /// used it, so the connection is lost.
+    StreamClosed,
+    /// The bootloader does not answer. If `tried`, the board was reset into
+    /// it to no avail, otherwise the port can not reset the board.
+    /// `instructions` say how to start it by hand.
+    BootloaderNotEntered {
+        tried: bool,
+        instructions: &'static str,
+    },
+    /// A bootloader command was issued before the serial stream was opened.
+    BootloaderNotOpen,
+    /// The bootloader refused `command` with the error `response`, such as
+    /// `RESPONSE_BADADDR` for an address outside of its flash.
+    CommandRejected {
+        command: u8,
+        response: u8,
+    },
+    /// The bootloader answered with something other than what the protocol
+    /// allows. The string describes what was received.
+    MalformedResponse(String),
+    TbfParseError(TbfParseError),
+    /// The header of a TBF declares `expected` bytes, but only `actual` are
+    /// there, e.g. because a file was truncated.
+    NotEnoughFlash {
+        expected: usize,
+        actual: usize,
+    },
+    /// An app read back after being written is not what was written. The
+    /// string explains the difference.
+    InstalledAppMismatch {
+        address: u32,
+        reason: String,
+    },
+    /// The TBF parsed correctly, but its contents can not be placed in flash
+    /// as described. The string explains what is wrong.
+    InvalidTbf(String),
+    /// The tab does not contain a TBF for the architecture of the board.
+    NoBinaryForArch {
+        arch: String,
+        available: Vec<String>,
+    },
+    /// The tab's `metadata.toml` could not be parsed.
+    InvalidTabMetadata(String),
+    /// The tab declares that it is only for other boards. Installing anyway
+    /// needs `--force`.
+    IncompatibleBoard {
+        board: String,
+        supported: Vec<String>,
+    },
+    /// The attributes of the board disagree with the board given with
+    /// `--board`. Going ahead with its settings anyway needs `--force`.
+    BoardMismatch {
+        board: String,
+        differences: Vec<String>,
+    },
+    /// The architecture given with `--arch` is not one apps are built for.
+    InvalidArch(String),
+    /// The app region would start at or after its end.
+    InvalidAppRegion {
+        start: u32,
+        end: u32,
+    },
+    /// An attribute does not fit in a bootloader slot. The string explains
+    /// why.
+    InvalidAttribute(String),
+    /// Every attribute slot of the bootloader is in use.
+    NoAttributeSlot {
+        key: String,
+    },
+    /// `failed` of the `total` attributes could not be set.
+    AttributesNotSet {
+        failed: usize,
+        total: usize,
+    },
+    /// The kernel can not start at the given address. The string explains
+    /// why.
+    InvalidStartAddress(String),
+    /// The bootloader reports another start address than the one just set.
+    StartAddressNotSet {
+        requested: u32,
+        reported: u32,
+    },
+    /// Flash is written and erased a page at a time, but `address` is not the
+    /// start of a page of `page_size` bytes.
+    UnalignedPage {
+        address: u32,
+        page_size: usize,
+    },
+    /// Flash is written a whole page at a time, but `length` bytes were given
+    /// for the page at `address` instead of `page_size`.
+    PartialPageWrite {
+        address: u32,
+        length: usize,
+        page_size: usize,
+    },
+    /// The bootloader refused to erase the page at `address`, although it is
+    /// aligned to `page_size`: it is outside of flash, or the pages of the
+    /// board have another size.
+    ErasePageRefused {
+        address: u32,
+        page_size: usize,
+    },
+    /// The scratch area of a benchmark must start on a page boundary.
+    InvalidScratchAddress {
+        address: u32,
+        page_size: usize,
+    },
+    /// A range of flash to read is empty, or runs past the end of the 32-bit
+    /// address space.
+    InvalidReadRange {
+        address: u32,
+        length: u32,
+    },
+    /// The architecture of the board is not known, and was not given with
+    /// `--force-arch`.
+    UnknownArch,
+    /// The apps being installed need `needed` bytes, but only `available`
+    /// are left in the app region.
+    AppRegionFull {
+        needed: u32,
+        available: u32,
+    },
+    /// The app `name` of `size` bytes does not fit in a slot of `slot_size`
+    /// bytes, leaving room for the padding that fills the rest of the slot.
+    AppTooLargeForSlot {
+        name: String,
+        size: u32,
+        slot_size: u32,
+    },
+    /// Writing the kernel, which would end at `kernel_end`, would destroy the
+    /// app `name` installed at `address`.
+    KernelOverlapsApp {
+        name: String,
+        address: u32,
+        kernel_end: u32,
+    },
+    /// No app named `name` is installed. `installed` names the apps that are.
+    AppNotFound {
+        name: String,
+        installed: Vec<String>,
+    },
+    /// The list of TBFs in flash does not end where it should, most likely
+    /// because flash is corrupted. The string says where the walk stopped.
+    TbfListTooLong(String),
+    /// `failed` of the `checked` TBFs in the app region did not pass
+    /// verification.
+    RegionCheckFailed {
+        failed: usize,
+        checked: usize,
+    },
+}
+
+impl TockloaderError {
+    /// The name of the variant, for machine-readable output.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            TockloaderError::IOError(_) => "IOError",
+            TockloaderError::HookFailed { .. } => "HookFailed",
+            #[cfg(feature = "serial")]
+            TockloaderError::TokioSerialError(_) => "TokioSerialError",
+            TockloaderError::NoPortAvailable => "NoPortAvailable",
+            TockloaderError::MultiplePortsAvailable { .. } => "MultiplePortsAvailable",
+            TockloaderError::UnsupportedBackend { .. } => "UnsupportedBackend",
+            TockloaderError::ToolNotFound { .. } => "ToolNotFound",
+            TockloaderError::TargetNotResponding { .. } => "TargetNotResponding",
+            TockloaderError::ToolCommandFailed { .. } => "ToolCommandFailed",
+            TockloaderError::UnknownDevice { .. } => "UnknownDevice",
+            TockloaderError::DoctorChecksFailed { .. } => "DoctorChecksFailed",
+            TockloaderError::PermissionDenied { .. } => "PermissionDenied",
+            TockloaderError::Timeout => "Timeout",
+            TockloaderError::OperationTimeout { .. } => "OperationTimeout",
+            TockloaderError::PromptNotFound { .. } => "PromptNotFound",
+            TockloaderError::StreamClosed => "StreamClosed",
+            TockloaderError::BootloaderNotEntered { .. } => "BootloaderNotEntered",
+            TockloaderError::BootloaderNotOpen => "BootloaderNotOpen",
+            TockloaderError::CommandRejected { .. } => "CommandRejected",
+            TockloaderError::MalformedResponse(_) => "MalformedResponse",
+            TockloaderError::TbfParseError(_) => "TbfParseError",
+            TockloaderError::NotEnoughFlash { .. } => "NotEnoughFlash",
+            TockloaderError::InstalledAppMismatch { .. } => "InstalledAppMismatch",
+            TockloaderError::InvalidTbf(_) => "InvalidTbf",
+            TockloaderError::NoBinaryForArch { .. } => "NoBinaryForArch",
+            TockloaderError::InvalidTabMetadata(_) => "InvalidTabMetadata",
+            TockloaderError::IncompatibleBoard { .. } => "IncompatibleBoard",
+            TockloaderError::BoardMismatch { .. } => "BoardMismatch",
+            TockloaderError::InvalidArch(_) => "InvalidArch",
+            TockloaderError::InvalidAppRegion { .. } => "InvalidAppRegion",
+            TockloaderError::InvalidAttribute(_) => "InvalidAttribute",
+            TockloaderError::NoAttributeSlot { .. } => "NoAttributeSlot",
+            TockloaderError::AttributesNotSet { .. } => "AttributesNotSet",
+            TockloaderError::InvalidStartAddress(_) => "InvalidStartAddress",
+            TockloaderError::StartAddressNotSet { .. } => "StartAddressNotSet",
+            TockloaderError::UnalignedPage { .. } => "UnalignedPage",
+            TockloaderError::PartialPageWrite { .. } => "PartialPageWrite",
+            TockloaderError::ErasePageRefused { .. } => "ErasePageRefused",
+            TockloaderError::InvalidScratchAddress { .. } => "InvalidScratchAddress",
+            TockloaderError::InvalidReadRange { .. } => "InvalidReadRange",
+            TockloaderError::UnknownArch => "UnknownArch",
+            TockloaderError::AppRegionFull { .. } => "AppRegionFull",
+            TockloaderError::AppTooLargeForSlot { .. } => "AppTooLargeForSlot",
+            TockloaderError::KernelOverlapsApp { .. } => "KernelOverlapsApp",
+            TockloaderError::AppNotFound { .. } => "AppNotFound",
+            TockloaderError::TbfListTooLong(_) => "TbfListTooLong",
+            TockloaderError::RegionCheckFailed { .. } => "RegionCheckFailed",
+        }
+    }
+
+    /// The code the process exits with when failing with this error.
+    ///
+    /// Errors are grouped by what went wrong, so that scripts can tell them
+    /// apart. 2 is left out, clap uses it for usage errors.
+    ///
+    /// - 1: local I/O, or a `--before`/`--after` command failed
+    /// - 3: the serial port or debug probe tool could not be used
+    /// - 4: the board or its bootloader misbehaved
+    /// - 5: the app or tab is unusable
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            TockloaderError::IOError(_) | TockloaderError::HookFailed { .. } => 1,
+            #[cfg(feature = "serial")]
+            TockloaderError::TokioSerialError(_) => 3,
+            TockloaderError::NoPortAvailable
+            | TockloaderError::MultiplePortsAvailable { .. }
+            | TockloaderError::UnsupportedBackend { .. }
+            | TockloaderError::ToolNotFound { .. }
+            | TockloaderError::ToolCommandFailed { .. }
+            | TockloaderError::UnknownDevice { .. }
+            | TockloaderError::DoctorChecksFailed { .. }
+            | TockloaderError::PermissionDenied { .. } => 3,
+            TockloaderError::Timeout
+            | TockloaderError::TargetNotResponding { .. }
+            | TockloaderError::OperationTimeout { .. }
+            | TockloaderError::PromptNotFound { .. }
+            | TockloaderError::NoAttributeSlot { .. }
+            | TockloaderError::InstalledAppMismatch { .. }
+            | TockloaderError::StartAddressNotSet { .. }
+            | TockloaderError::TbfListTooLong(_)
+            | TockloaderError::AttributesNotSet { .. }
+            | TockloaderError::StreamClosed
+            | TockloaderError::BootloaderNotEntered { .. }
+            | TockloaderError::BootloaderNotOpen
+            | TockloaderError::CommandRejected { .. }
+            | TockloaderError::MalformedResponse(_) => 4,
+            TockloaderError::TbfParseError(_)
+            | TockloaderError::NotEnoughFlash { .. }
+            | TockloaderError::InvalidTbf(_)
+            | TockloaderError::NoBinaryForArch { .. }
+            | TockloaderError::InvalidTabMetadata(_)
+            | TockloaderError::IncompatibleBoard { .. }
+            | TockloaderError::BoardMismatch { .. }
+            | TockloaderError::InvalidArch(_)
+            | TockloaderError::InvalidAppRegion { .. }
+            | TockloaderError::InvalidAttribute(_)
+            | TockloaderError::InvalidStartAddress(_)
+            | TockloaderError::InvalidScratchAddress { .. }
+            | TockloaderError::InvalidReadRange { .. }
+            | TockloaderError::UnalignedPage { .. }
+            | TockloaderError::PartialPageWrite { .. }
+            | TockloaderError::ErasePageRefused { .. }
+            | TockloaderError::UnknownArch
+            | TockloaderError::AppRegionFull { .. }
+            | TockloaderError::AppTooLargeForSlot { .. }
+            | TockloaderError::KernelOverlapsApp { .. }
+            | TockloaderError::AppNotFound { .. }
+            | TockloaderError::RegionCheckFailed { .. } => 5,
+        }
+    }
+}
+
+impl fmt::Display for TockloaderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TockloaderError::IOError(inner) => write!(f, "I/O error: {inner}"),
+            TockloaderError::HookFailed {
+                command,
+                code: Some(code),
+            } => write!(f, "The command `{command}` failed with exit code {code}"),
+            TockloaderError::HookFailed {
+                command,
+                code: None,
+            } => write!(f, "The command `{command}` was killed by a signal"),
+            #[cfg(feature = "serial")]
+            TockloaderError::TokioSerialError(inner) => write!(f, "Serial port error: {inner}"),
+            TockloaderError::NoPortAvailable => write!(f, "No serial port is available"),
+            TockloaderError::MultiplePortsAvailable { ports } => {
+                write!(f, "Several serial ports were found, choose one with --port:")?;
+                for port in ports {
+                    write!(f, "\n  {port}")?;
+                }
+                Ok(())
+            }
+            TockloaderError::UnsupportedBackend {
+                backend,
+                compiled: false,
+            } => write!(
+                f,
+                "This tockloader was built without the {backend} backend, \
+                 rebuild it with `--features {backend}`"
+            ),
+            TockloaderError::UnsupportedBackend {
+                backend,
+                compiled: true,
+            } => write!(f, "The {backend} backend is not implemented yet"),
+            TockloaderError::ToolNotFound {
+                tool,
+                command,
+                hint,
+            } => write!(f, "{tool} not found: could not run `{command}`.\n{hint}"),
+            TockloaderError::TargetNotResponding { tool, output } => {
+                write!(f, "{tool} could not attach to the board and halt it")?;
+                if !output.is_empty() {
+                    write!(f, ":\n{output}")?;
+                }
+                Ok(())
+            }
+            TockloaderError::ToolCommandFailed { tool, output } => {
+                write!(f, "{tool} could not carry out the command")?;
+                if !output.is_empty() {
+                    write!(f, ":\n{output}")?;
+                }
+                Ok(())
+            }
+            TockloaderError::UnknownDevice { tool, device } => write!(
+                f,
+                "{tool} does not know the device '{device}'. Check the name given with `--jlink-device`."
+            ),
+            TockloaderError::DoctorChecksFailed { failed, total } => {
+                write!(f, "{failed} of {total} checks failed")
+            }
+            TockloaderError::PermissionDenied { paths } => {
+                write!(f, "Permission denied for {}", paths.join(", "))?;
+                if cfg!(target_os = "linux") {
+                    write!(
+                        f,
+                        "\nAdd your user to the 'dialout' group (`sudo usermod -aG dialout $USER`, \
+                         then log in again) and check that your udev rules allow access to the device."
+                    )?;
+                }
+                Ok(())
+            }
+            TockloaderError::Timeout => write!(
+                f,
+                "Timed out waiting for the board. Check that it is connected and in bootloader mode, \
+                 or give a slow link more time with --timeout."
+            ),
+            TockloaderError::OperationTimeout {
+                timeout,
+                pages_written,
+            } => write!(
+                f,
+                "The board stopped responding after {pages_written} pages were written \
+                 (gave up after {}s). Flash was only partially written, run the command again \
+                 once the board is back in bootloader mode.",
+                timeout.as_secs()
+            ),
+            TockloaderError::PromptNotFound { prompt, timeout } => write!(
+                f,
+                "The board did not print the prompt {prompt:?} within {}s. \
+                 Check that it is running a kernel with the process console.",
+                timeout.as_secs()
+            ),
+            TockloaderError::StreamClosed => write!(
+                f,
+                "The connection to the board was lost, reconnect to use it again"
+            ),
+            TockloaderError::BootloaderNotEntered {
+                tried,
+                instructions,
+            } => {
+                if *tried {
+                    write!(f, "The board was reset into its bootloader, but it does not answer.")?;
+                } else {
+                    write!(
+                        f,
+                        "The bootloader does not answer, and this port can not reset the board into it."
+                    )?;
+                }
+                write!(
+                    f,
+                    "\n{instructions} to start it by hand, then run the command again with \
+                     --no-bootloader-entry."
+                )
+            }
+            TockloaderError::BootloaderNotOpen => write!(
+                f,
+                "The connection to the bootloader was used before it was opened"
+            ),
+            TockloaderError::CommandRejected { command, response } => {
+                let reason = match *response {
+                    RESPONSE_BADADDR => "the address is outside of its flash or not aligned",
+                    RESPONSE_BADARGS => "its arguments are not valid, e.g. not a whole page",
+                    RESPONSE_UNKNOWN => "it does not know the command, it may be too old",
+                    RESPONSE_CHANGE_BAUD_FAIL => "it can not use that baud rate",
+                    _ => "unknown error",
+                };
+                write!(
+                    f,
+                    "The bootloader rejected command {command:#04x} ({response:#04x}): {reason}"
+                )
+            }
+            TockloaderError::MalformedResponse(reason) => {
+                write!(
+                    f,
+                    "Unexpected response from the bootloader: {reason}. \
+                     Check that no other program is using the serial port."
+                )
+            }
+            TockloaderError::TbfParseError(inner) => write!(f, "Invalid TBF header: {inner}"),
+            TockloaderError::NotEnoughFlash { expected, actual } => write!(
+                f,
+                "The TBF is truncated: its header declares {expected} bytes, but only {actual} are present"
+            ),
+            TockloaderError::InstalledAppMismatch { address, reason } => write!(
+                f,
+                "The app at {address:#x} was not written correctly: {reason}. \
+                 Flash was only partially written, install it again."
+            ),
+            TockloaderError::InvalidTbf(reason) => write!(f, "Invalid TBF: {reason}"),
+            TockloaderError::NoBinaryForArch { arch, available } => write!(
+                f,
+                "The tab has no binary for architecture '{arch}'. Available architectures: {}",
+                available.join(", ")
+            ),
+            TockloaderError::InvalidTabMetadata(reason) => {
+                write!(f, "Invalid metadata.toml in tab: {reason}")
+            }
+            TockloaderError::IncompatibleBoard { board, supported } => write!(
+                f,
+                "The app is only for boards {}, not '{board}'. Use --force to install it anyway.",
+                supported.join(", ")
+            ),
+            TockloaderError::BoardMismatch { board, differences } => write!(
+                f,
+                "The board does not look like a '{board}': {}. Use --force to go ahead with \
+                 the settings of '{board}' anyway.",
+                differences.join(", ")
+            ),
+            TockloaderError::InvalidArch(arch) => write!(
+                f,
+                "Unknown architecture '{arch}'. Known architectures: {}",
+                crate::board::KNOWN_ARCHS.join(", ")
+            ),
+            TockloaderError::InvalidAppRegion { start, end } => write!(
+                f,
+                "The app region would start at {start:#x}, which is not before its end at {end:#x}"
+            ),
+            TockloaderError::InvalidAttribute(reason) => write!(f, "Invalid attribute: {reason}"),
+            TockloaderError::NoAttributeSlot { key } => write!(
+                f,
+                "Can not store attribute '{key}', every attribute slot of the bootloader is in use"
+            ),
+            TockloaderError::AttributesNotSet { failed, total } => {
+                write!(f, "{failed} of {total} attributes could not be set")
+            }
+            TockloaderError::InvalidStartAddress(reason) => {
+                write!(f, "Invalid start address: {reason}")
+            }
+            TockloaderError::StartAddressNotSet {
+                requested,
+                reported,
+            } => write!(
+                f,
+                "The start address was set to {requested:#x}, but the bootloader reports {reported:#x}"
+            ),
+            TockloaderError::UnalignedPage { address, page_size } => write!(
+                f,
+                "Flash is written and erased a page at a time, but {address:#x} is not the start \
+                 of a page: it must be a multiple of {page_size} bytes. Check the page size of \
+                 the board, or give it with --page-size"
+            ),
+            TockloaderError::PartialPageWrite {
+                address,
+                length,
+                page_size,
+            } => write!(
+                f,
+                "Flash is written a whole page at a time, but {length} bytes were given for the \
+                 page at {address:#x} instead of {page_size}"
+            ),
+            TockloaderError::ErasePageRefused { address, page_size } => write!(
+                f,
+                "The bootloader refused to erase the page at {address:#x}: it is outside of \
+                 flash, or the pages of the board are not {page_size} bytes. Check the app \
+                 address, or give the page size with --page-size"
+            ),
+            TockloaderError::InvalidScratchAddress { address, page_size } => write!(
+                f,
+                "The scratch address {address:#x} is not a multiple of the {page_size} byte page size"
+            ),
+            TockloaderError::InvalidReadRange { address, length } if *length == 0 => {
+                write!(f, "Nothing to read at {address:#x}, the length is 0")
+            }
+            TockloaderError::InvalidReadRange { address, length } => write!(
+                f,
+                "Reading {length:#x} bytes from {address:#x} would go past the end of the \
+                 address space at 0xffffffff"
+            ),
+            TockloaderError::UnknownArch => write!(
+                f,
+                "Could not determine the architecture of the board, use --force-arch to choose one"
+            ),
+            TockloaderError::AppRegionFull { needed, available } => write!(
+                f,
+                "The apps need {needed} bytes of flash, but only {available} bytes are left in the app region"
+            ),
+            TockloaderError::AppTooLargeForSlot {
+                name,
+                size,
+                slot_size,
+            } => write!(
+                f,
+                "{name} takes {size} bytes, which does not fit in a slot of {slot_size} bytes"
+            ),
+            TockloaderError::KernelOverlapsApp {
+                name,
+                address,
+                kernel_end,
+            } => write!(
+                f,
+                "The kernel would end at {kernel_end:#x} and overwrite the app '{name}' at {address:#x}. \
+                 Use --force to flash it anyway."
+            ),
+            TockloaderError::AppNotFound { name, installed } if installed.is_empty() => {
+                write!(f, "No app named '{name}' is installed, there are no apps")
+            }
+            TockloaderError::AppNotFound { name, installed } => write!(
+                f,
+                "No app named '{name}' is installed. Installed apps: {}",
+                installed.join(", ")
+            ),
+            TockloaderError::TbfListTooLong(reason) => write!(
+                f,
+                "The apps in flash do not form a proper list ({reason}). \
+                 Run verify-region to look for corruption."
+            ),
+            TockloaderError::RegionCheckFailed { failed, checked } => write!(
+                f,
+                "{failed} of {checked} TBFs in the app region failed verification"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for TockloaderError {}
+
+impl From<io::Error> for TockloaderError {
+    fn from(value: io::Error) -> Self {
+        TockloaderError::IOError(value)
+    }
+}
+
+#[cfg(feature = "serial")]
+impl From<tokio_serial::Error> for TockloaderError {
+    fn from(value: tokio_serial::Error) -> Self {
+        TockloaderError::TokioSerialError(value)
+    }
+}
+
+impl From<TbfParseError> for TockloaderError {
+    fn from(value: TbfParseError) -> Self {
+        TockloaderError::TbfParseError(value)
+    }
+}
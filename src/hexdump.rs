@@ -0,0 +1,112 @@
+//! Printing flash contents as a hex dump.
+
+use std::io::{self, Write};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use tokio::io::AsyncWrite;
+
+/// How many bytes are shown on each line.
+const LINE_LEN: usize = 16;
+
+/// Formats the bytes written to it as a hex dump, one line of 16 bytes at a
+/// time, with the address of the first byte and the printable characters:
+///
+/// ```text
+/// 00030000  02 00 34 00 00 08 00 00  01 00 00 00 0c 2b 0b 00  |..4..........+..|
+/// ```
+///
+/// Lines are printed as soon as they are complete. The last, partial line is
+/// printed by [`finish()`](HexDump::finish), or on shutdown.
+pub struct HexDump<W: Write> {
+    output: W,
+    address: usize,
+    line: Vec<u8>,
+}
+
+impl<W: Write> HexDump<W> {
+    /// Dump to `output`, labelling the first byte with `address`.
+    pub fn new(output: W, address: usize) -> Self {
+        HexDump {
+            output,
+            address,
+            line: Vec::with_capacity(LINE_LEN),
+        }
+    }
+
+    /// Print the last line, if it is partial, and return the output.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.flush_line()?;
+        Ok(self.output)
+    }
+
+    fn flush_line(&mut self) -> io::Result<()> {
+        if self.line.is_empty() {
+            return Ok(());
+        }
+
+        let mut hex = String::new();
+        for index in 0..LINE_LEN {
+            match self.line.get(index) {
+                Some(byte) => hex.push_str(&format!("{byte:02x} ")),
+                None => hex.push_str("   "),
+            }
+            if index == LINE_LEN / 2 - 1 {
+                hex.push(' ');
+            }
+        }
+        let ascii: String = self
+            .line
+            .iter()
+            .map(|&byte| {
+                if byte.is_ascii_graphic() || byte == b' ' {
+                    byte as char
+                } else {
+                    '.'
+                }
+            })
+            .collect();
+        writeln!(self.output, "{:08x}  {hex} |{ascii}|", self.address)?;
+
+        self.address += self.line.len();
+        self.line.clear();
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for HexDump<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for &byte in buf {
+            self.line.push(byte);
+            if self.line.len() == LINE_LEN {
+                self.flush_line()?;
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.output.flush()
+    }
+}
+
+// The output is a local writer such as stdout, so it is written to directly
+// rather than through the runtime.
+impl<W: Write + Unpin> AsyncWrite for HexDump<W> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Poll::Ready(self.get_mut().write(buf))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(self.get_mut().flush())
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        Poll::Ready(this.flush_line().and_then(|()| this.output.flush()))
+    }
+}
@@ -0,0 +1,66 @@
+//! Shell commands run around flashing, given with `--before` and `--after`,
+//! e.g. to power cycle a board or to start a test runner.
+//!
+//! The commands are passed to the shell as they are, and run with all the
+//! rights of the user running tockloader. Only use commands you would run
+//! yourself: taking them from an untrusted build script or CI configuration
+//! lets its author run anything on this machine.
+
+use tokio::process::Command;
+
+use crate::errors::TockloaderError;
+
+/// Tells an `--after` command how the operation went: `success` or
+/// `failure`.
+pub const RESULT_VAR: &str = "TOCKLOADER_RESULT";
+
+/// The code tockloader is going to exit with, 0 on success.
+pub const EXIT_CODE_VAR: &str = "TOCKLOADER_EXIT_CODE";
+
+/// Run the `--before` command. The operation must not go ahead if this fails.
+pub async fn run_before(command: &str) -> Result<(), TockloaderError> {
+    run(command, &[]).await
+}
+
+/// Run the `--after` command, telling it the `result` of the operation
+/// through [`RESULT_VAR`] and [`EXIT_CODE_VAR`].
+pub async fn run_after<T>(
+    command: &str,
+    result: &Result<T, TockloaderError>,
+) -> Result<(), TockloaderError> {
+    let (outcome, exit_code) = match result {
+        Ok(_) => ("success", 0),
+        Err(error) => ("failure", error.exit_code()),
+    };
+    let exit_code = exit_code.to_string();
+    run(
+        command,
+        &[(RESULT_VAR, outcome), (EXIT_CODE_VAR, &exit_code)],
+    )
+    .await
+}
+
+/// Run `command` through the shell, with its output going straight to ours.
+async fn run(command: &str, env: &[(&str, &str)]) -> Result<(), TockloaderError> {
+    let mut shell = if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C");
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+    let status = shell
+        .arg(command)
+        .envs(env.iter().copied())
+        .status()
+        .await?;
+    if !status.success() {
+        return Err(TockloaderError::HookFailed {
+            command: command.to_string(),
+            code: status.code(),
+        });
+    }
+    Ok(())
+}
@@ -0,0 +1,345 @@
+//! Looking into TBFs on the host, without a board.
+
+use std::fmt;
+
+use serde::Serialize;
+use sha2::{Digest, Sha256, Sha384, Sha512};
+use tbf_parser::parse::{
+    header_tlvs, parse_tbf_footer, parse_tbf_header, parse_tbf_header_lengths,
+};
+use tbf_parser::types::{TbfFooterV2CredentialsType, TbfHeader, TbfHeaderTypes, TbfParseError};
+
+use crate::errors::TockloaderError;
+
+/// The hashes that a Credentials Footer can hold.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HashAlgorithm {
+    Sha256,
+    Sha384,
+    Sha512,
+}
+
+impl HashAlgorithm {
+    pub const ALL: [HashAlgorithm; 3] = [
+        HashAlgorithm::Sha256,
+        HashAlgorithm::Sha384,
+        HashAlgorithm::Sha512,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            HashAlgorithm::Sha256 => "SHA256",
+            HashAlgorithm::Sha384 => "SHA384",
+            HashAlgorithm::Sha512 => "SHA512",
+        }
+    }
+
+    pub fn compute(&self, data: &[u8]) -> Vec<u8> {
+        match self {
+            HashAlgorithm::Sha256 => Sha256::digest(data).to_vec(),
+            HashAlgorithm::Sha384 => Sha384::digest(data).to_vec(),
+            HashAlgorithm::Sha512 => Sha512::digest(data).to_vec(),
+        }
+    }
+
+    fn from_credentials(format: TbfFooterV2CredentialsType) -> Option<HashAlgorithm> {
+        match format {
+            TbfFooterV2CredentialsType::SHA256 => Some(HashAlgorithm::Sha256),
+            TbfFooterV2CredentialsType::SHA384 => Some(HashAlgorithm::Sha384),
+            TbfFooterV2CredentialsType::SHA512 => Some(HashAlgorithm::Sha512),
+            _ => None,
+        }
+    }
+}
+
+/// A hash found in a Credentials Footer, with the one computed over the app.
+#[derive(Clone, Debug)]
+pub struct StoredHash {
+    pub algorithm: HashAlgorithm,
+    pub stored: Vec<u8>,
+    /// Only for display, whether the hashes match is decided by
+    /// [`TbfFooterV2Credentials::verify()`](tbf_parser::types::TbfFooterV2Credentials::verify).
+    pub computed: Vec<u8>,
+    pub valid: bool,
+}
+
+impl StoredHash {
+    pub fn matches(&self) -> bool {
+        self.valid
+    }
+}
+
+/// A signature found in a Credentials Footer, checked with the public key
+/// stored next to it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StoredSignature {
+    pub format: TbfFooterV2CredentialsType,
+    /// Whether the signature matches the app. Credentials which are not a key
+    /// and a signature of the expected sizes are not valid either.
+    pub valid: bool,
+}
+
+/// Whether the credentials of an app match it, all of them taken together.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Integrity {
+    Ok,
+    /// A hash or a signature does not match, or the footers are corrupted.
+    Failed,
+    /// The app has no hash or signature that could be checked.
+    Unchecked,
+}
+
+impl fmt::Display for Integrity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Integrity::Ok => write!(f, "OK"),
+            Integrity::Failed => write!(f, "FAILED"),
+            Integrity::Unchecked => write!(f, "unchecked"),
+        }
+    }
+}
+
+/// The hashes of an app's integrity region: its header, protected trailer and
+/// binary, up to [`get_binary_end()`](TbfHeader::get_binary_end). This is
+/// what the kernel hashes when checking SHA credentials, and what RSA
+/// credentials sign.
+#[derive(Clone, Debug)]
+pub struct HashReport {
+    pub header: TbfHeader,
+    pub computed: Vec<(HashAlgorithm, Vec<u8>)>,
+    pub stored: Vec<StoredHash>,
+    pub signatures: Vec<StoredSignature>,
+    /// Credentials which can not be checked, such as ECDSA signatures whose
+    /// key is not stored with them.
+    pub unchecked: Vec<TbfFooterV2CredentialsType>,
+}
+
+impl HashReport {
+    pub fn integrity(&self) -> Integrity {
+        if self.stored.is_empty() && self.signatures.is_empty() {
+            Integrity::Unchecked
+        } else if self.stored.iter().all(StoredHash::matches)
+            && self.signatures.iter().all(|signature| signature.valid)
+        {
+            Integrity::Ok
+        } else {
+            Integrity::Failed
+        }
+    }
+}
+
+/// Hash a TBF with every supported algorithm, compare the result with the
+/// hashes stored in its footers, and check the RSA signatures in them.
+pub fn hash_tbf(tbf: &[u8]) -> Result<HashReport, TockloaderError> {
+    let lengths: &[u8; 8] = tbf
+        .get(0..8)
+        .ok_or(TbfParseError::NotEnoughFlash)?
+        .try_into()
+        .map_err(|_| TbfParseError::InternalError)?;
+    let (version, header_size, total_size) = parse_tbf_header_lengths(lengths)
+        .map_err(|_| TockloaderError::InvalidTbf("Could not read the TBF lengths".into()))?;
+    let header = parse_tbf_header(
+        tbf.get(0..header_size as usize)
+            .ok_or(TbfParseError::NotEnoughFlash)?,
+        version,
+    )?;
+
+    let binary_end = header.get_binary_end() as usize;
+    let total_size = total_size as usize;
+    if tbf.len() < total_size {
+        return Err(TockloaderError::NotEnoughFlash {
+            expected: total_size,
+            actual: tbf.len(),
+        });
+    }
+    if binary_end > total_size {
+        return Err(TbfParseError::NotEnoughFlash.into());
+    }
+    let integrity_region = &tbf[..binary_end];
+
+    let computed: Vec<(HashAlgorithm, Vec<u8>)> = HashAlgorithm::ALL
+        .iter()
+        .map(|algorithm| (*algorithm, algorithm.compute(integrity_region)))
+        .collect();
+
+    let mut stored = Vec::new();
+    let mut signatures = Vec::new();
+    let mut unchecked = Vec::new();
+    let mut offset = binary_end;
+    while offset < total_size {
+        let (footer, footer_size) = parse_tbf_footer(&tbf[offset..total_size])?;
+        offset += footer_size as usize;
+        let format = footer.get_format();
+        // Reserved footers only hold space for credentials added later.
+        if format == TbfFooterV2CredentialsType::Reserved {
+            continue;
+        }
+        let valid = match footer.verify(integrity_region) {
+            Ok(valid) => valid,
+            Err(TbfParseError::UnsupportedCredentials(format)) => {
+                unchecked.push(format);
+                continue;
+            }
+            Err(_) => false,
+        };
+        match HashAlgorithm::from_credentials(format) {
+            Some(algorithm) => stored.push(StoredHash {
+                algorithm,
+                stored: footer.get_credentials().to_vec(),
+                computed: computed
+                    .iter()
+                    .find(|(computed, _)| *computed == algorithm)
+                    .map(|(_, hash)| hash.clone())
+                    .unwrap_or_default(),
+                valid,
+            }),
+            None => signatures.push(StoredSignature { format, valid }),
+        }
+    }
+
+    Ok(HashReport {
+        header,
+        computed,
+        stored,
+        signatures,
+        unchecked,
+    })
+}
+
+/// Format bytes as lowercase hex, without separators.
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// One field of a TBF header, as laid out in its bytes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HeaderField<'a> {
+    /// Where the field starts, from the start of the header.
+    pub offset: usize,
+    /// What the field is: the name of a base header field, or the type of a
+    /// TLV.
+    pub name: String,
+    /// The bytes of the field, only the value for a TLV.
+    pub bytes: &'a [u8],
+    /// Why the field is malformed, if it is.
+    pub problem: Option<String>,
+}
+
+/// The fixed fields of a v2 base header, with their offsets and lengths.
+const BASE_FIELDS: [(&str, usize, usize); 5] = [
+    ("version", 0, 2),
+    ("header_size", 2, 2),
+    ("total_size", 4, 4),
+    ("flags", 8, 4),
+    ("checksum", 12, 4),
+];
+
+/// The words of a v1 header, which has nothing but fixed fields.
+const V1_FIELDS: [&str; 19] = [
+    "version",
+    "total_size",
+    "entry_offset",
+    "rel_data_offset",
+    "rel_data_size",
+    "text_offset",
+    "text_size",
+    "got_offset",
+    "got_size",
+    "data_offset",
+    "data_size",
+    "bss_mem_offset",
+    "bss_size",
+    "min_stack_len",
+    "min_app_heap_len",
+    "min_kernel_heap_len",
+    "package_name_offset",
+    "package_name_size",
+    "checksum",
+];
+
+/// Split the header at the start of `tbf` into the fields of its base header
+/// and its TLVs, in order and without interpreting them, for looking at
+/// headers which do not parse.
+///
+/// A TLV whose length does not suit its type, or which runs past the end of
+/// the header, has a `problem`. Nothing after such a TLV can be found.
+pub fn header_fields(tbf: &[u8]) -> Result<Vec<HeaderField<'_>>, TockloaderError> {
+    let (version, header) = header_bytes(tbf)?;
+
+    if version == 1 {
+        return Ok(V1_FIELDS
+            .iter()
+            .enumerate()
+            .map(|(index, name)| HeaderField {
+                offset: index * 4,
+                name: name.to_string(),
+                bytes: &header[index * 4..index * 4 + 4],
+                problem: None,
+            })
+            .collect());
+    }
+
+    let mut fields: Vec<HeaderField> = BASE_FIELDS
+        .iter()
+        .map(|&(name, offset, len)| HeaderField {
+            offset,
+            name: name.to_string(),
+            bytes: &header[offset..offset + len],
+            problem: None,
+        })
+        .collect();
+    let mut tlvs = header_tlvs(header);
+    loop {
+        let offset = tlvs.offset();
+        let field = match tlvs.next() {
+            None => break,
+            Some(Ok(tlv)) => {
+                let tipe = TbfHeaderTypes::from(tlv.tipe);
+                HeaderField {
+                    offset,
+                    name: format!("{tipe:?} ({})", tlv.tipe),
+                    bytes: tlv.value,
+                    problem: tipe
+                        .fixed_len()
+                        .filter(|&len| len != tlv.value.len())
+                        .map(|len| format!("should be {len} bytes long")),
+                }
+            }
+            Some(Err(error)) => HeaderField {
+                offset,
+                name: "TLV".to_string(),
+                bytes: &header[offset..],
+                problem: Some(error.to_string()),
+            },
+        };
+        fields.push(field);
+    }
+    Ok(fields)
+}
+
+/// Parse the header at the start of `tbf` as the kernel would, to tell
+/// whether the fields listed by [`header_fields`] make a valid header.
+pub fn parse_header(tbf: &[u8]) -> Result<TbfHeader, TockloaderError> {
+    let (version, header) = header_bytes(tbf)?;
+    Ok(parse_tbf_header(header, version)?)
+}
+
+/// The version of the header at the start of `tbf`, and its bytes. The size
+/// of the header depends on its version: v1 headers have no size field.
+fn header_bytes(tbf: &[u8]) -> Result<(u16, &[u8]), TockloaderError> {
+    let lengths: &[u8; 8] = tbf
+        .get(0..8)
+        .ok_or(TbfParseError::NotEnoughFlash)?
+        .try_into()
+        .map_err(|_| TbfParseError::InternalError)?;
+    let (version, header_size, _) = parse_tbf_header_lengths(lengths)
+        .map_err(|_| TockloaderError::InvalidTbf("Could not read the TBF lengths".into()))?;
+    let header = tbf
+        .get(0..header_size as usize)
+        .ok_or(TockloaderError::NotEnoughFlash {
+            expected: header_size as usize,
+            actual: tbf.len(),
+        })?;
+    Ok((version, header))
+}
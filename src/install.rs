@@ -0,0 +1,604 @@
+use tbf_parser::build::TbfHeaderBuilder;
+use tbf_parser::parse::{parse_tbf_header, parse_tbf_header_lengths};
+use tbf_parser::types::{TbfHeader, TbfParseError};
+
+use crate::apps::{AppLocation, AppReader, TbfIterator};
+use crate::bootloader::crc::tock_crc32;
+use crate::errors::TockloaderError;
+use crate::interfaces::traits::{BytesReader, FlashWriter, ERASED_BYTE};
+
+/// An app laid out exactly as it has to appear in flash.
+#[derive(Clone)]
+pub struct AppImage {
+    header: TbfHeader,
+    bytes: Vec<u8>,
+}
+
+impl AppImage {
+    /// Parse a TBF and assemble the image that will be written to flash.
+    ///
+    /// The image is built from its three regions, in order: the TBF header,
+    /// the protected trailer, and the binary starting at
+    /// [`get_app_start_offset()`](TbfHeader::get_app_start_offset) (followed
+    /// by any footers). The kernel computes the entry point relative to the
+    /// start of the app, so the binary must land at exactly that offset.
+    pub fn from_tbf(tbf: &[u8]) -> Result<AppImage, TockloaderError> {
+        let lengths: &[u8; 8] = tbf
+            .get(0..8)
+            .ok_or(TbfParseError::NotEnoughFlash)?
+            .try_into()
+            .map_err(|_| TbfParseError::InternalError)?;
+        let (version, header_size, total_size) = parse_tbf_header_lengths(lengths)
+            .map_err(|_| TockloaderError::InvalidTbf("Could not read the TBF lengths".into()))?;
+
+        let header_size = header_size as usize;
+        let total_size = total_size as usize;
+        let header_bytes = tbf
+            .get(0..header_size)
+            .ok_or(TbfParseError::NotEnoughFlash)?;
+        let header = parse_tbf_header(header_bytes, version)?;
+
+        if !header.is_app() {
+            return Err(TockloaderError::InvalidTbf(
+                "The TBF is padding, not an app".into(),
+            ));
+        }
+        if tbf.len() < total_size {
+            return Err(TockloaderError::NotEnoughFlash {
+                expected: total_size,
+                actual: tbf.len(),
+            });
+        }
+
+        let protected_size = header.get_protected_size() as usize;
+        let app_start = header.get_app_start_offset() as usize;
+        // Only a Main or Program header says where the code is.
+        if protected_size < header_size {
+            return Err(TockloaderError::InvalidTbf(
+                "The TBF has neither a Main nor a Program header".into(),
+            ));
+        }
+        if protected_size > total_size {
+            return Err(TockloaderError::InvalidTbf(format!(
+                "Protected region ({protected_size} bytes) is larger than the app ({total_size} bytes)"
+            )));
+        }
+        let binary_end = header.get_binary_end() as usize;
+        if binary_end > total_size {
+            return Err(TockloaderError::InvalidTbf(format!(
+                "Binary end offset {binary_end:#x} is past the end of the app ({total_size:#x})"
+            )));
+        }
+        let init_fn_offset = header.get_init_function_offset() as usize;
+        if init_fn_offset < app_start || init_fn_offset >= binary_end {
+            return Err(TockloaderError::InvalidTbf(format!(
+                "Init function offset {init_fn_offset:#x} is outside of the app binary ({app_start:#x}..{binary_end:#x})"
+            )));
+        }
+
+        let mut bytes = Vec::with_capacity(total_size);
+        bytes.extend_from_slice(&tbf[..header_size]);
+        // The protected trailer is preserved as built, the kernel does not
+        // give it any meaning but the app may.
+        bytes.extend_from_slice(&tbf[header_size..protected_size]);
+        bytes.extend_from_slice(&tbf[app_start..total_size]);
+
+        Ok(AppImage { header, bytes })
+    }
+
+    pub fn header(&self) -> &TbfHeader {
+        &self.header
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+/// Size of a padding TBF, which is only a base header.
+pub const PADDING_HEADER_SIZE: u32 = 16;
+
+/// Build the header of a padding TBF which spans `total_size` bytes.
+///
+/// Only the header is returned, the kernel skips the rest of the padding
+/// without looking at it.
+pub fn create_padding_tbf(total_size: u32) -> Vec<u8> {
+    TbfHeaderBuilder::padding(total_size)
+        .build()
+        .expect("a base header always fits")
+}
+
+/// Write an app to flash, starting at `address`, and return the address at
+/// which the next app may start.
+///
+/// The app takes up a slot of its `total_size` rounded up to `alignment`
+/// (no rounding if `None`). The kernel walks the apps one after the other, so
+/// any room left at the end of the slot is filled with a padding TBF, unless
+/// that padding is already there. A slot is grown by another `alignment` if
+/// the room left is too small for a padding header.
+///
+/// Pages which the app only partially covers keep their other contents.
+/// Nothing is written if the slot would run past the end of the 32-bit
+/// address space.
+pub async fn install_app<B>(
+    board: &mut B,
+    address: u32,
+    app: &AppImage,
+    alignment: Option<u32>,
+) -> Result<u32, TockloaderError>
+where
+    B: BytesReader + FlashWriter + Send,
+{
+    let app_end = u64::from(address) + app.as_bytes().len() as u64;
+    let slot_end = check_address_space(address, slot_end(app_end, alignment))?;
+    // The slot ends after the app, so the app ends in the address space too.
+    let app_end = app_end as u32;
+
+    write_bytes(board, address, app.as_bytes()).await?;
+
+    let padding_size = slot_end - app_end;
+    if padding_size > 0 && !is_padded(board, app_end, padding_size).await? {
+        write_bytes(board, app_end, &create_padding_tbf(padding_size)).await?;
+    }
+
+    Ok(slot_end)
+}
+
+/// Install several apps one after the other, starting at the first address
+/// from `address` that is a multiple of `alignment`, and return the address
+/// of each.
+///
+/// Apps have to start on a page boundary for the kernel to be able to protect
+/// them and for erasing to work, so callers should pass at least the page
+/// size as `alignment`. The room skipped before the first app is filled with
+/// padding, so the kernel can still walk from `address` to the apps.
+///
+/// The whole layout is computed before anything is written, so that apps
+/// which do not fit before `region_end` are refused without touching flash.
+pub async fn install_apps<B>(
+    board: &mut B,
+    address: u32,
+    apps: &[AppImage],
+    alignment: Option<u32>,
+    region_end: Option<u32>,
+) -> Result<Vec<u32>, TockloaderError>
+where
+    B: BytesReader + FlashWriter + Send,
+{
+    let (addresses, end) = plan_layout(address, apps, alignment)?;
+    check_fits(address, end, region_end)?;
+    board.plan_writes(address, end).await;
+
+    if let Some(&start) = addresses.first() {
+        if start > address {
+            write_bytes(board, address, &create_padding_tbf(start - address)).await?;
+        }
+    }
+    for (app, &app_address) in apps.iter().zip(&addresses) {
+        install_app(board, app_address, app, alignment).await?;
+        verify_installed(board, app_address, app).await?;
+    }
+    Ok(addresses)
+}
+
+/// Replace the apps starting at `address` with `apps`, so that the layout
+/// does not depend on what was installed before, and return the sticky apps
+/// that were kept along with the address of every app installed.
+///
+/// Sticky apps are installed again ahead of `apps`, unless `force` is set, in
+/// which case they are erased like the others. The new layout is written over
+/// the old one, and whatever is left of the old apps past its end is erased.
+pub async fn replace_apps<B>(
+    board: &mut B,
+    address: u32,
+    apps: &[AppImage],
+    alignment: Option<u32>,
+    region_end: Option<u32>,
+    force: bool,
+) -> Result<(Vec<AppImage>, Vec<u32>), TockloaderError>
+where
+    B: BytesReader + FlashWriter + Send,
+{
+    let (kept, old_end) = read_installed(board, address, region_end, |header| {
+        header.sticky() && !force
+    })
+    .await?;
+
+    let all: Vec<AppImage> = kept.iter().chain(apps).cloned().collect();
+    let addresses = install_apps(board, address, &all, alignment, region_end).await?;
+    // With no apps, not even the padding up to the first slot is written.
+    let new_end = if all.is_empty() {
+        address
+    } else {
+        plan_layout(address, &all, alignment)?.1
+    };
+    erase_range(board, new_end, old_end).await?;
+    Ok((kept, addresses))
+}
+
+/// Erase the apps starting at `address`, and every page after them up to
+/// `region_end` if it is known, and return the sticky apps that were kept.
+///
+/// Sticky apps are installed again at `address` unless `force` is set, as in
+/// [`replace_apps`]. No padding is written after them: erased flash already
+/// ends the list for the kernel, and padding up to `region_end` would leave
+/// no room to install apps after it.
+pub async fn erase_apps<B>(
+    board: &mut B,
+    address: u32,
+    alignment: Option<u32>,
+    region_end: Option<u32>,
+    force: bool,
+) -> Result<Vec<AppImage>, TockloaderError>
+where
+    B: BytesReader + FlashWriter + Send,
+{
+    let (kept, old_end) = read_installed(board, address, region_end, |header| {
+        header.sticky() && !force
+    })
+    .await?;
+    install_apps(board, address, &kept, alignment, region_end).await?;
+    let new_end = if kept.is_empty() {
+        address
+    } else {
+        plan_layout(address, &kept, alignment)?.1
+    };
+    erase_range(board, new_end, region_end.unwrap_or(old_end).max(old_end)).await?;
+    Ok(kept)
+}
+
+/// Install `apps` after the apps starting at `address` by writing all of them
+/// again as one image, in a single pass over the app region, and return the
+/// apps that were already installed along with the address of every app.
+///
+/// The installed apps are read back and laid out again ahead of `apps`,
+/// without the padding between them, so the region ends up packed. With
+/// `replace` given, only the sticky apps are kept as in [`replace_apps`]
+/// (none if it is `Some(true)`). Nothing is written if the apps do not fit
+/// before `region_end`, and an interrupted write leaves no half-moved apps
+/// behind the ones already written.
+pub async fn bundle_apps<B>(
+    board: &mut B,
+    address: u32,
+    apps: &[AppImage],
+    alignment: Option<u32>,
+    region_end: Option<u32>,
+    replace: Option<bool>,
+) -> Result<(Vec<AppImage>, Vec<u32>), TockloaderError>
+where
+    B: BytesReader + FlashWriter + Send,
+{
+    let (kept, old_end) = read_installed(board, address, region_end, |header| match replace {
+        Some(force) => header.sticky() && !force,
+        None => header.is_app(),
+    })
+    .await?;
+
+    let all: Vec<AppImage> = kept.iter().chain(apps).cloned().collect();
+    if all.is_empty() {
+        erase_range(board, address, old_end).await?;
+        return Ok((kept, Vec::new()));
+    }
+    let (addresses, new_end) = plan_layout(address, &all, alignment)?;
+    check_fits(address, new_end, region_end)?;
+    board.plan_writes(address, new_end).await;
+
+    // Every gap, before the first app and at the end of each slot, is padding.
+    let mut image = vec![ERASED_BYTE; (new_end - address) as usize];
+    let mut next = address;
+    for (app, &app_address) in all.iter().zip(&addresses) {
+        if app_address > next {
+            let offset = (next - address) as usize;
+            let padding = create_padding_tbf(app_address - next);
+            image[offset..offset + padding.len()].copy_from_slice(&padding);
+        }
+        let offset = (app_address - address) as usize;
+        image[offset..offset + app.as_bytes().len()].copy_from_slice(app.as_bytes());
+        next = app_address + app.as_bytes().len() as u32;
+    }
+    if new_end > next {
+        let offset = (next - address) as usize;
+        let padding = create_padding_tbf(new_end - next);
+        image[offset..offset + padding.len()].copy_from_slice(&padding);
+    }
+
+    write_bytes(board, address, &image).await?;
+    erase_range(board, new_end, old_end).await?;
+    for (app, &app_address) in all.iter().zip(&addresses) {
+        verify_installed(board, app_address, app).await?;
+    }
+    Ok((kept, addresses))
+}
+
+/// Read the TBFs starting at `address` for which `keep` holds, and return
+/// them along with where the TBFs end.
+async fn read_installed<B>(
+    board: &mut B,
+    address: u32,
+    region_end: Option<u32>,
+    keep: impl Fn(&TbfHeader) -> bool,
+) -> Result<(Vec<AppImage>, u32), TockloaderError>
+where
+    B: BytesReader + Send,
+{
+    let mut iterator = TbfIterator::new(address);
+    if let Some(region_end) = region_end {
+        iterator = iterator.region_end(region_end);
+    }
+    let mut kept = Vec::new();
+    while let Some(tbf) = iterator.next(board).await? {
+        if keep(&tbf.header) {
+            let bytes = board
+                .read_range(tbf.address as usize, tbf.total_size as usize)
+                .await?;
+            kept.push(AppImage::from_tbf(&bytes)?);
+        }
+    }
+    Ok((kept, iterator.address()))
+}
+
+/// Check that apps laid out from `address` up to `end` fit before
+/// `region_end`.
+fn check_fits(address: u32, end: u32, region_end: Option<u32>) -> Result<(), TockloaderError> {
+    match region_end {
+        Some(region_end) if end > region_end => Err(TockloaderError::AppRegionFull {
+            needed: end - address,
+            available: region_end.saturating_sub(address),
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// Remove the apps named `name` from the app region starting at `address`,
+/// or only the first of them if `first_only` is set, and return the apps
+/// removed along with the sticky apps that were kept.
+///
+/// Each app is turned into padding of its own size by overwriting its header,
+/// so the apps after it stay where they are. Sticky apps are kept unless
+/// `force` is set.
+pub async fn uninstall_apps<B>(
+    board: &mut B,
+    address: u32,
+    name: &str,
+    first_only: bool,
+    force: bool,
+) -> Result<(Vec<AppLocation>, Vec<AppLocation>), TockloaderError>
+where
+    B: BytesReader + FlashWriter + Send,
+{
+    let mut matches = board.find_app(address, name).await?;
+    if matches.is_empty() {
+        let installed = board.list_apps(address).await?;
+        return Err(TockloaderError::AppNotFound {
+            name: name.to_string(),
+            installed: installed
+                .iter()
+                .filter_map(|app| app.header.get_package_name())
+                .map(String::from)
+                .collect(),
+        });
+    }
+    if first_only {
+        matches.truncate(1);
+    }
+
+    let (kept, removed): (Vec<_>, Vec<_>) = matches
+        .into_iter()
+        .partition(|app| app.header.sticky() && !force);
+    for app in &removed {
+        write_bytes(board, app.address, &create_padding_tbf(app.total_size)).await?;
+    }
+    Ok((removed, kept))
+}
+
+/// Read back the app installed at `address` and check that it is complete:
+/// its header must declare the size of `app`, and the CRC of all of it must
+/// match. This catches transfers that stopped part way through, and pages
+/// corrupted on the way.
+///
+/// Boards which compute the CRC themselves do not need the app read back,
+/// unless it does not match, in which case the first byte that differs is
+/// looked for.
+pub async fn verify_installed<B>(
+    board: &mut B,
+    address: u32,
+    app: &AppImage,
+) -> Result<(), TockloaderError>
+where
+    B: BytesReader + Send,
+{
+    let expected = app.as_bytes();
+    let mismatch = |reason: String| TockloaderError::InstalledAppMismatch { address, reason };
+
+    let lengths: [u8; 8] = board
+        .read_range(address as usize, 8)
+        .await?
+        .try_into()
+        .map_err(|_| mismatch("the header could not be read back".into()))?;
+    match parse_tbf_header_lengths(&lengths) {
+        Ok((_, _, total_size)) if total_size as usize == expected.len() => {}
+        Ok((_, _, total_size)) => {
+            return Err(mismatch(format!(
+                "its header declares {total_size} bytes instead of {}",
+                expected.len()
+            )))
+        }
+        Err(_) => return Err(mismatch("no valid header was found".into())),
+    }
+
+    let expected_crc = tock_crc32(expected);
+    let crc = board.crc_range(address as usize, expected.len()).await?;
+    if crc == expected_crc {
+        return Ok(());
+    }
+
+    let written = board.read_range(address as usize, expected.len()).await?;
+    if written.len() != expected.len() {
+        return Err(mismatch(format!(
+            "only {} of {} bytes could be read back",
+            written.len(),
+            expected.len()
+        )));
+    }
+    if let Some(offset) = written.iter().zip(expected).position(|(a, b)| a != b) {
+        return Err(mismatch(format!(
+            "the contents differ from byte {offset} of {}",
+            expected.len()
+        )));
+    }
+    // The same bytes read back, so the board got the CRC wrong, or the
+    // flash changed in between.
+    Err(mismatch(format!(
+        "its CRC is {crc:#010x} instead of {expected_crc:#010x}"
+    )))
+}
+
+/// Lay out `apps` contiguously from the first multiple of `alignment` from
+/// `address` as [`install_apps`] would, and return where each one starts
+/// along with the end of the last one's slot.
+///
+/// Fails with `AppRegionFull` if the apps run past the end of the 32-bit
+/// address space.
+pub fn plan_layout(
+    address: u32,
+    apps: &[AppImage],
+    alignment: Option<u32>,
+) -> Result<(Vec<u32>, u32), TockloaderError> {
+    let mut addresses = Vec::with_capacity(apps.len());
+    let mut next = slot_end(u64::from(address), alignment);
+    for app in apps {
+        addresses.push(check_address_space(address, next)?);
+        next = slot_end(next + app.as_bytes().len() as u64, alignment);
+    }
+    Ok((addresses, check_address_space(address, next)?))
+}
+
+/// Check that each of `apps` fits in a fixed slot of `slot_size` bytes, with
+/// room for the padding that fills the rest of the slot.
+///
+/// Installed with `slot_size` as the alignment, each app then takes exactly
+/// one slot, so every app starts at a multiple of `slot_size`.
+pub fn check_slot_size(apps: &[AppImage], slot_size: u32) -> Result<(), TockloaderError> {
+    for app in apps {
+        let size = app.as_bytes().len() as u32;
+        if size != slot_size && size.saturating_add(PADDING_HEADER_SIZE) > slot_size {
+            return Err(TockloaderError::AppTooLargeForSlot {
+                name: app
+                    .header()
+                    .get_package_name()
+                    .unwrap_or("unnamed app")
+                    .to_string(),
+                size,
+                slot_size,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// The end of the slot of an app ending at `app_end`, which is also where the
+/// next app may start: the next multiple of `alignment`, leaving either no
+/// room or enough for a padding header.
+///
+/// Computed in 64 bits, so that a slot past the end of the address space can
+/// be caught with [`check_address_space`] rather than wrap around.
+fn slot_end(app_end: u64, alignment: Option<u32>) -> u64 {
+    let alignment = u64::from(alignment.unwrap_or(1).max(1));
+    let padding_size = u64::from(PADDING_HEADER_SIZE);
+    let slot_end = app_end.next_multiple_of(alignment);
+    if slot_end != app_end && slot_end - app_end < padding_size {
+        (app_end + padding_size).next_multiple_of(alignment)
+    } else {
+        slot_end
+    }
+}
+
+/// Check that apps laid out from `address` up to `end` stay within the 32-bit
+/// address space, and return `end` as an address.
+fn check_address_space(address: u32, end: u64) -> Result<u32, TockloaderError> {
+    u32::try_from(end).map_err(|_| TockloaderError::AppRegionFull {
+        needed: u32::try_from(end - u64::from(address)).unwrap_or(u32::MAX),
+        available: u32::MAX - address,
+    })
+}
+
+/// Whether a TBF of exactly `size` bytes already starts at `address`.
+async fn is_padded<B>(board: &mut B, address: u32, size: u32) -> Result<bool, TockloaderError>
+where
+    B: BytesReader + Send,
+{
+    let lengths: [u8; 8] = match board.read_range(address as usize, 8).await?.try_into() {
+        Ok(lengths) => lengths,
+        Err(_) => return Ok(false),
+    };
+    Ok(matches!(
+        parse_tbf_header_lengths(&lengths),
+        Ok((_, _, total_size)) if total_size == size
+    ))
+}
+
+/// Erase flash from `start` up to `end`. Pages wholly in the range are erased,
+/// and the pages at either end that are only partly in it have that part
+/// written with erased bytes.
+pub async fn erase_range<B>(board: &mut B, start: u32, end: u32) -> Result<(), TockloaderError>
+where
+    B: BytesReader + FlashWriter + Send,
+{
+    if start >= end {
+        return Ok(());
+    }
+    let page_size = board.page_size() as u32;
+    let first_page = start.next_multiple_of(page_size);
+    let last_page = end - end % page_size;
+    if first_page >= last_page {
+        let erased = vec![ERASED_BYTE; (end - start) as usize];
+        return write_bytes(board, start, &erased).await;
+    }
+
+    if start < first_page {
+        let erased = vec![ERASED_BYTE; (first_page - start) as usize];
+        write_bytes(board, start, &erased).await?;
+    }
+    for page in (first_page..last_page).step_by(page_size as usize) {
+        board.erase_page(page).await?;
+    }
+    if last_page < end {
+        let erased = vec![ERASED_BYTE; (end - last_page) as usize];
+        write_bytes(board, last_page, &erased).await?;
+    }
+    Ok(())
+}
+
+/// Write `data` to flash at `address`, keeping the rest of the pages it only
+/// partially covers.
+pub(crate) async fn write_bytes<B>(
+    board: &mut B,
+    address: u32,
+    data: &[u8],
+) -> Result<(), TockloaderError>
+where
+    B: BytesReader + FlashWriter + Send,
+{
+    let page_size = board.page_size();
+    let start = address as usize;
+    let end = start + data.len();
+    let first_page = start - start % page_size;
+
+    for page_address in (first_page..end).step_by(page_size) {
+        let page_end = page_address + page_size;
+        let mut page = if page_address < start || page_end > end {
+            board.read_range(page_address, page_size).await?
+        } else {
+            vec![0; page_size]
+        };
+
+        let copy_start = page_address.max(start);
+        let copy_end = page_end.min(end);
+        page[copy_start - page_address..copy_end - page_address]
+            .copy_from_slice(&data[copy_start - start..copy_end - start]);
+
+        board.write_page(page_address as u32, &page).await?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,31 @@
+pub mod dry_run;
+pub mod flash_file;
+#[cfg(feature = "jlink")]
+pub mod jlink;
+pub mod keep_alive;
+#[cfg(feature = "openocd")]
+pub mod openocd;
+pub mod progress;
+#[cfg(feature = "serial")]
+pub mod serial;
+#[cfg(any(feature = "jlink", feature = "openocd"))]
+mod tool;
+pub mod traits;
+pub mod watchdog;
+
+/// The ways of talking to a board built into this tockloader, as selected
+/// with Cargo features. The `probe-rs` feature is only reserved, there is no
+/// backend behind it.
+pub fn compiled_backends() -> Vec<&'static str> {
+    let mut backends = Vec::new();
+    if cfg!(feature = "serial") {
+        backends.push("serial");
+    }
+    if cfg!(feature = "jlink") {
+        backends.push("jlink");
+    }
+    if cfg!(feature = "openocd") {
+        backends.push("openocd");
+    }
+    backends
+}
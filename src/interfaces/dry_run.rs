@@ -0,0 +1,116 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use async_trait::async_trait;
+
+use crate::errors::TockloaderError;
+use crate::interfaces::traits::{BytesReader, FlashWriter, ERASED_BYTE};
+
+/// A change to flash that a command would make.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FlashOperation {
+    WritePage { address: u32, len: usize },
+    ErasePage { address: u32 },
+}
+
+impl fmt::Display for FlashOperation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FlashOperation::WritePage { address, len } => {
+                write!(f, "write {len} bytes at {address:#010x}")
+            }
+            FlashOperation::ErasePage { address } => write!(f, "erase page at {address:#010x}"),
+        }
+    }
+}
+
+/// Wraps a board so that commands run against it without modifying it.
+///
+/// Commands run exactly as they would on the real board, so the plan is
+/// faithful. Every write is recorded instead of being sent, and kept in
+/// memory so that reads later in the same command see it.
+pub struct DryRunInterface<B> {
+    board: B,
+    pages: BTreeMap<u32, Vec<u8>>,
+    operations: Vec<FlashOperation>,
+}
+
+impl<B> DryRunInterface<B> {
+    pub fn new(board: B) -> Self {
+        DryRunInterface {
+            board,
+            pages: BTreeMap::new(),
+            operations: Vec::new(),
+        }
+    }
+
+    /// Everything that would have been changed on the board, in order.
+    pub fn operations(&self) -> &[FlashOperation] {
+        &self.operations
+    }
+
+    /// Print the plan, one operation per line.
+    pub fn print_plan(&self) {
+        if self.operations.is_empty() {
+            println!("Dry run: the board would not be modified.");
+            return;
+        }
+        println!("Dry run: the board would be modified as follows:");
+        for operation in &self.operations {
+            println!("  {operation}");
+        }
+    }
+
+    pub fn into_inner(self) -> B {
+        self.board
+    }
+}
+
+#[async_trait]
+impl<B: BytesReader + Send> BytesReader for DryRunInterface<B> {
+    async fn read_range(&mut self, start: usize, len: usize) -> Result<Vec<u8>, TockloaderError> {
+        let mut data = self.board.read_range(start, len).await?;
+
+        // Replace whatever overlaps a page that would have been written.
+        let end = start + len;
+        for (&address, page) in &self.pages {
+            let page_start = address as usize;
+            let page_end = page_start + page.len();
+            if page_end <= start || page_start >= end {
+                continue;
+            }
+            let copy_start = page_start.max(start);
+            let copy_end = page_end.min(end);
+            data[copy_start - start..copy_end - start]
+                .copy_from_slice(&page[copy_start - page_start..copy_end - page_start]);
+        }
+
+        Ok(data)
+    }
+
+    // `crc_range()` is left to read through `read_range()`, as the board
+    // knows nothing of the pages that would have been written.
+}
+
+#[async_trait]
+impl<B: FlashWriter + Send> FlashWriter for DryRunInterface<B> {
+    fn page_size(&self) -> usize {
+        self.board.page_size()
+    }
+
+    async fn write_page(&mut self, address: u32, data: &[u8]) -> Result<(), TockloaderError> {
+        self.operations.push(FlashOperation::WritePage {
+            address,
+            len: data.len(),
+        });
+        self.pages.insert(address, data.to_vec());
+        Ok(())
+    }
+
+    async fn erase_page(&mut self, address: u32) -> Result<(), TockloaderError> {
+        self.operations.push(FlashOperation::ErasePage { address });
+        self.pages
+            .insert(address, vec![ERASED_BYTE; self.board.page_size()]);
+        Ok(())
+    }
+}
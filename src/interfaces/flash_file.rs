@@ -0,0 +1,87 @@
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use async_trait::async_trait;
+
+use crate::errors::TockloaderError;
+use crate::interfaces::traits::{check_page_write, BytesReader, FlashWriter, ERASED_BYTE};
+
+/// A local binary file standing in for the flash of a board.
+///
+/// Offsets in the file are used directly as flash addresses. Any part of the
+/// "flash" past the end of the file reads as erased.
+pub struct FlashFileInterface {
+    file: File,
+    page_size: usize,
+}
+
+impl FlashFileInterface {
+    /// Open the flash file at `path`, creating it if it does not exist.
+    pub fn new(path: &Path, page_size: usize) -> Result<Self, TockloaderError> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+        Ok(FlashFileInterface { file, page_size })
+    }
+
+    /// Open the flash file at `path` or, if it does not exist, create it
+    /// `size` bytes long and erased, as the flash of a board would be.
+    pub fn with_size(path: &Path, page_size: usize, size: u64) -> Result<Self, TockloaderError> {
+        let created = !path.exists();
+        let mut interface = FlashFileInterface::new(path, page_size)?;
+        if created {
+            interface
+                .file
+                .write_all(&vec![ERASED_BYTE; size as usize])?;
+            interface.file.flush()?;
+        }
+        Ok(interface)
+    }
+
+    /// Use pages of `page_size` bytes, e.g. once the board the file stands
+    /// in for is known.
+    pub fn set_page_size(&mut self, page_size: usize) {
+        self.page_size = page_size;
+    }
+}
+
+#[async_trait]
+impl BytesReader for FlashFileInterface {
+    async fn read_range(&mut self, start: usize, len: usize) -> Result<Vec<u8>, TockloaderError> {
+        let mut buffer = Vec::with_capacity(len);
+        self.file.seek(SeekFrom::Start(start as u64))?;
+        (&mut self.file).take(len as u64).read_to_end(&mut buffer)?;
+        // Whatever is past the end of the file has never been written.
+        buffer.resize(len, ERASED_BYTE);
+        Ok(buffer)
+    }
+}
+
+#[async_trait]
+impl FlashWriter for FlashFileInterface {
+    fn page_size(&self) -> usize {
+        self.page_size
+    }
+
+    async fn write_page(&mut self, address: u32, data: &[u8]) -> Result<(), TockloaderError> {
+        check_page_write(address, data, self.page_size)?;
+
+        // Writing past the end of the file would fill the gap with zeros, but
+        // untouched flash should read as erased.
+        let file_len = self.file.metadata()?.len();
+        if (address as u64) > file_len {
+            self.file.seek(SeekFrom::End(0))?;
+            let gap = vec![ERASED_BYTE; (address as u64 - file_len) as usize];
+            self.file.write_all(&gap)?;
+        }
+
+        self.file.seek(SeekFrom::Start(address as u64))?;
+        self.file.write_all(data)?;
+        self.file.flush()?;
+        Ok(())
+    }
+}
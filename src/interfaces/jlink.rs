@@ -0,0 +1,149 @@
+use std::process::{Command, Output};
+
+use async_trait::async_trait;
+
+use crate::errors::TockloaderError;
+use crate::interfaces::tool::{printed, run_tool, DumpFile};
+use crate::interfaces::traits::{BoardInterface, BytesReader};
+
+/// The J-Link Commander, as it is named on this platform.
+pub const JLINK_COMMAND: &str = if cfg!(windows) {
+    "JLink.exe"
+} else {
+    "JLinkExe"
+};
+
+/// Connection to a board through a SEGGER J-Link probe.
+///
+/// J-Link Commander is run once per operation, reading its commands from
+/// standard input.
+pub struct JLinkInterface {
+    command: String,
+    device: String,
+    interface: String,
+    speed: u32,
+    connected: bool,
+}
+
+impl JLinkInterface {
+    /// Create an interface for the microcontroller `device`, as named by
+    /// SEGGER (e.g. `nRF52840_xxAA`), connected over SWD at 1200 kHz. Nothing
+    /// is run yet.
+    pub fn new(device: &str) -> Self {
+        JLinkInterface {
+            command: JLINK_COMMAND.to_string(),
+            device: device.to_string(),
+            interface: "SWD".to_string(),
+            speed: 1200,
+            connected: false,
+        }
+    }
+
+    /// Run `command` instead of the J-Link Commander found on the PATH.
+    pub fn set_command(&mut self, command: &str) {
+        self.command = command.to_string();
+    }
+
+    /// Connect with another debug interface than SWD, such as `JTAG`.
+    pub fn set_interface(&mut self, interface: &str) {
+        self.interface = interface.to_string();
+    }
+
+    /// Connect at `speed` kHz.
+    pub fn set_speed(&mut self, speed: u32) {
+        self.speed = speed;
+    }
+
+    /// Whether [`open()`](BoardInterface::open) attached to the board.
+    pub fn is_connected(&self) -> bool {
+        self.connected
+    }
+
+    /// Reset the board and let it run, which starts the kernel.
+    pub fn reset(&mut self) -> Result<(), TockloaderError> {
+        let output = self.run("r\ng")?;
+        if !output.status.success() {
+            return Err(TockloaderError::ToolCommandFailed {
+                tool: "JLinkExe",
+                output: printed(&output),
+            });
+        }
+        Ok(())
+    }
+
+    /// Run J-Link Commander, connected to the board, with `commands`.
+    ///
+    /// A device J-Link does not know is reported as
+    /// [`TockloaderError::UnknownDevice`], whatever else happened.
+    fn run(&self, commands: &str) -> Result<Output, TockloaderError> {
+        let speed = self.speed.to_string();
+        let output = run_tool(
+            "JLinkExe",
+            Command::new(&self.command).args([
+                "-device",
+                &self.device,
+                "-if",
+                &self.interface,
+                "-speed",
+                &speed,
+                "-autoconnect",
+                "1",
+                "-nogui",
+                "1",
+                "-exitonerror",
+                "1",
+            ]),
+            format!("{commands}\nexit\n").as_bytes(),
+        )?;
+        if printed(&output).to_lowercase().contains("unknown device") {
+            return Err(TockloaderError::UnknownDevice {
+                tool: "JLinkExe",
+                device: self.device.clone(),
+            });
+        }
+        Ok(output)
+    }
+}
+
+impl BoardInterface for JLinkInterface {
+    /// Attach to the board and halt it, checking that the probe and the
+    /// target both respond.
+    fn open(&mut self) -> Result<(), TockloaderError> {
+        self.connected = false;
+        let output = self.run("halt")?;
+        let printed = printed(&output);
+        // Older versions do not exit with an error when the target is
+        // missing, only say so.
+        if !output.status.success() || printed.contains("Cannot connect to target") {
+            return Err(TockloaderError::TargetNotResponding {
+                tool: "JLinkExe",
+                output: printed,
+            });
+        }
+        self.connected = true;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl BytesReader for JLinkInterface {
+    /// Save the range to a temporary file with J-Link Commander, then read
+    /// the file.
+    async fn read_range(&mut self, start: usize, len: usize) -> Result<Vec<u8>, TockloaderError> {
+        let binary = DumpFile::new("jlink");
+        let output = self.run(&format!(
+            "halt\nsavebin {}, {start:#x}, {len:#x}",
+            binary.path().display()
+        ))?;
+
+        // J-Link Commander only stops at errors with `-exitonerror`, which
+        // older versions ignore, so only the file tells whether it worked.
+        match std::fs::read(binary.path()) {
+            Ok(bytes) if output.status.success() && bytes.len() == len => Ok(bytes),
+            _ => Err(TockloaderError::ToolCommandFailed {
+                tool: "JLinkExe",
+                output: printed(&output),
+            }),
+        }
+    }
+}
@@ -0,0 +1,134 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use log::warn;
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+use crate::errors::TockloaderError;
+use crate::interfaces::traits::{BootloaderInterface, BytesReader, FlashWriter};
+
+/// How long the bootloader may go without a command before it is pinged.
+pub const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(3);
+
+/// Wraps a board so that its bootloader is pinged whenever it has been idle
+/// for a while, for bootloaders that give up and start the kernel when left
+/// alone too long during a multi-step operation.
+///
+/// The pings are sent from a background task. It takes turns with the
+/// commands sent through the wrapper, so a ping never lands in the middle of
+/// another command.
+pub struct KeepAliveInterface<B> {
+    shared: Arc<Mutex<Shared<B>>>,
+    page_size: usize,
+    task: Option<JoinHandle<()>>,
+}
+
+struct Shared<B> {
+    board: B,
+    last_used: Instant,
+}
+
+impl<B> KeepAliveInterface<B>
+where
+    B: BootloaderInterface + FlashWriter + Send + 'static,
+{
+    /// Ping the bootloader after every `interval` without a command, or never
+    /// if it is `None`, in which case commands are simply passed on.
+    pub fn new(board: B, interval: Option<Duration>) -> Self {
+        let page_size = board.page_size();
+        let shared = Arc::new(Mutex::new(Shared {
+            board,
+            last_used: Instant::now(),
+        }));
+        let task = interval.map(|interval| tokio::spawn(keep_alive(shared.clone(), interval)));
+        KeepAliveInterface {
+            shared,
+            page_size,
+            task,
+        }
+    }
+
+    /// Stop pinging and give back the board.
+    pub async fn into_inner(mut self) -> B {
+        if let Some(task) = self.task.take() {
+            task.abort();
+            // Only a cancelled task is expected, and nothing is lost with it.
+            let _ = task.await;
+        }
+        let shared = self.shared.clone();
+        drop(self);
+        match Arc::into_inner(shared) {
+            Some(shared) => shared.into_inner().board,
+            None => unreachable!("the keep-alive task has ended"),
+        }
+    }
+}
+
+impl<B> Drop for KeepAliveInterface<B> {
+    fn drop(&mut self) {
+        if let Some(task) = &self.task {
+            task.abort();
+        }
+    }
+}
+
+/// Ping the bootloader whenever nothing was sent to it for `interval`.
+async fn keep_alive<B: BootloaderInterface>(shared: Arc<Mutex<Shared<B>>>, interval: Duration) {
+    let mut next_check = Instant::now() + interval;
+    loop {
+        tokio::time::sleep_until(next_check).await;
+        let mut shared = shared.lock().await;
+        if shared.last_used.elapsed() >= interval {
+            if let Err(error) = shared.board.ping().await {
+                warn!("Keep-alive ping failed: {error}");
+            }
+            shared.last_used = Instant::now();
+        }
+        next_check = shared.last_used + interval;
+    }
+}
+
+#[async_trait]
+impl<B: BytesReader + Send> BytesReader for KeepAliveInterface<B> {
+    async fn read_range(&mut self, start: usize, len: usize) -> Result<Vec<u8>, TockloaderError> {
+        let mut shared = self.shared.lock().await;
+        let result = shared.board.read_range(start, len).await;
+        shared.last_used = Instant::now();
+        result
+    }
+
+    async fn crc_range(&mut self, start: usize, len: usize) -> Result<u32, TockloaderError> {
+        let mut shared = self.shared.lock().await;
+        let result = shared.board.crc_range(start, len).await;
+        shared.last_used = Instant::now();
+        result
+    }
+}
+
+#[async_trait]
+impl<B: FlashWriter + Send> FlashWriter for KeepAliveInterface<B> {
+    fn page_size(&self) -> usize {
+        self.page_size
+    }
+
+    async fn write_page(&mut self, address: u32, data: &[u8]) -> Result<(), TockloaderError> {
+        let mut shared = self.shared.lock().await;
+        let result = shared.board.write_page(address, data).await;
+        shared.last_used = Instant::now();
+        result
+    }
+
+    async fn erase_page(&mut self, address: u32) -> Result<(), TockloaderError> {
+        let mut shared = self.shared.lock().await;
+        let result = shared.board.erase_page(address).await;
+        shared.last_used = Instant::now();
+        result
+    }
+
+    async fn plan_writes(&mut self, start: u32, end: u32) {
+        self.shared.lock().await.board.plan_writes(start, end).await
+    }
+}
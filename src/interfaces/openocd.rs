@@ -0,0 +1,125 @@
+use std::collections::BTreeMap;
+use std::process::Command;
+
+use async_trait::async_trait;
+
+use crate::errors::TockloaderError;
+use crate::interfaces::tool::{printed, run_tool, DumpFile};
+use crate::interfaces::traits::{BoardInterface, BytesReader};
+
+/// The OpenOCD commands reading flash into the file `{binary}`, unless
+/// replaced with [`set_action_command()`](OpenOcdInterface::set_action_command).
+pub const DEFAULT_READ_COMMAND: &str = "dump_image {binary} {address} {length}";
+
+/// Connection to a board through OpenOCD.
+///
+/// OpenOCD is run once per operation, with the board's configuration file
+/// from its `board/` directory, rather than kept running as a server.
+pub struct OpenOcdInterface {
+    command: String,
+    board: String,
+    /// The commands replacing the default ones, by action.
+    action_commands: BTreeMap<String, String>,
+    connected: bool,
+}
+
+impl OpenOcdInterface {
+    /// Create an interface for the board described by `board`, a file in the
+    /// `board/` directory of OpenOCD such as `nordic_nrf52_dk.cfg`. Nothing is
+    /// run yet.
+    pub fn new(board: &str) -> Self {
+        OpenOcdInterface {
+            command: "openocd".to_string(),
+            board: board.to_string(),
+            action_commands: BTreeMap::new(),
+            connected: false,
+        }
+    }
+
+    /// Run `command` instead of the `openocd` found on the PATH.
+    pub fn set_command(&mut self, command: &str) {
+        self.command = command.to_string();
+    }
+
+    /// Use `commands` for `action`, one of `program`, `read` or `erase`,
+    /// instead of the default ones. `{address}`, `{length}` and `{binary}` in
+    /// them are replaced with the flash address, the number of bytes and the
+    /// file holding them.
+    pub fn set_action_command(&mut self, action: &str, commands: &str) {
+        self.action_commands
+            .insert(action.to_string(), commands.to_string());
+    }
+
+    /// Whether [`open()`](BoardInterface::open) attached to the board.
+    pub fn is_connected(&self) -> bool {
+        self.connected
+    }
+
+    /// Reset the board and let it run, which starts the kernel.
+    pub fn reset(&mut self) -> Result<(), TockloaderError> {
+        let output = self.run("reset run")?;
+        if !output.status.success() {
+            return Err(TockloaderError::ToolCommandFailed {
+                tool: "openocd",
+                output: printed(&output),
+            });
+        }
+        Ok(())
+    }
+
+    /// Run OpenOCD with the board's configuration, then `commands`.
+    fn run(&self, commands: &str) -> Result<std::process::Output, TockloaderError> {
+        let script = format!("source [find board/{}]; init; {commands}; exit", self.board);
+        run_tool(
+            "openocd",
+            Command::new(&self.command).args(["-c", &script]),
+            &[],
+        )
+    }
+}
+
+impl BoardInterface for OpenOcdInterface {
+    /// Attach to the board and halt it, checking that the probe and the
+    /// target both respond.
+    fn open(&mut self) -> Result<(), TockloaderError> {
+        self.connected = false;
+        let output = self.run("halt; echo \"tockloader: [[target current] curstate]\"")?;
+        // OpenOCD prints its log, and so the echoed state, on stderr.
+        let printed = printed(&output);
+        if !output.status.success() || !printed.contains("tockloader: halted") {
+            return Err(TockloaderError::TargetNotResponding {
+                tool: "openocd",
+                output: printed,
+            });
+        }
+        self.connected = true;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl BytesReader for OpenOcdInterface {
+    /// Dump the range to a temporary file with OpenOCD, then read the file.
+    async fn read_range(&mut self, start: usize, len: usize) -> Result<Vec<u8>, TockloaderError> {
+        let binary = DumpFile::new("openocd");
+        let template = self
+            .action_commands
+            .get("read")
+            .map_or(DEFAULT_READ_COMMAND, String::as_str);
+        let commands = template
+            .replace("{address}", &format!("{start:#x}"))
+            .replace("{length}", &len.to_string())
+            .replace("{binary}", &binary.path().display().to_string());
+        let output = self.run(&format!("halt; {commands}"))?;
+
+        // OpenOCD may exit successfully after a failed command, so only the
+        // file tells whether the read worked.
+        match std::fs::read(binary.path()) {
+            Ok(bytes) if output.status.success() && bytes.len() == len => Ok(bytes),
+            _ => Err(TockloaderError::ToolCommandFailed {
+                tool: "openocd",
+                output: printed(&output),
+            }),
+        }
+    }
+}
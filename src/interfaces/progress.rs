@@ -0,0 +1,98 @@
+use async_trait::async_trait;
+
+use crate::errors::TockloaderError;
+use crate::interfaces::traits::{BytesReader, FlashWriter};
+
+/// Wraps a board so that the progress of a long write, such as an install,
+/// is reported as it goes.
+///
+/// Operations say which part of flash they are about to write with
+/// [`FlashWriter::plan_writes()`]. Flash is written from the lowest address
+/// up, so `progress` is called with how far into that part the writes have
+/// got and how long it is, both in bytes, after every page that gets
+/// further.
+pub struct ProgressInterface<B, F> {
+    board: B,
+    progress: F,
+    start: u32,
+    total: usize,
+    written: usize,
+}
+
+impl<B, F> ProgressInterface<B, F>
+where
+    F: FnMut(usize, usize),
+{
+    pub fn new(board: B, progress: F) -> Self {
+        ProgressInterface {
+            board,
+            progress,
+            start: 0,
+            total: 0,
+            written: 0,
+        }
+    }
+
+    /// Report the planned writes as done. Pages that already held what was
+    /// to be written are skipped, so the writes may stop short of the end.
+    pub fn finish(&mut self) {
+        if self.written < self.total {
+            self.written = self.total;
+            (self.progress)(self.written, self.total);
+        }
+    }
+
+    pub fn into_inner(self) -> B {
+        self.board
+    }
+
+    fn advance(&mut self, address: u32, len: usize) {
+        let end = (address as usize + len).saturating_sub(self.start as usize);
+        let written = end.min(self.total);
+        if written > self.written {
+            self.written = written;
+            (self.progress)(self.written, self.total);
+        }
+    }
+}
+
+#[async_trait]
+impl<B: BytesReader + Send, F: Send> BytesReader for ProgressInterface<B, F> {
+    async fn read_range(&mut self, start: usize, len: usize) -> Result<Vec<u8>, TockloaderError> {
+        self.board.read_range(start, len).await
+    }
+
+    async fn crc_range(&mut self, start: usize, len: usize) -> Result<u32, TockloaderError> {
+        self.board.crc_range(start, len).await
+    }
+}
+
+#[async_trait]
+impl<B, F> FlashWriter for ProgressInterface<B, F>
+where
+    B: FlashWriter + Send,
+    F: FnMut(usize, usize) + Send,
+{
+    fn page_size(&self) -> usize {
+        self.board.page_size()
+    }
+
+    async fn write_page(&mut self, address: u32, data: &[u8]) -> Result<(), TockloaderError> {
+        self.board.write_page(address, data).await?;
+        self.advance(address, data.len());
+        Ok(())
+    }
+
+    async fn erase_page(&mut self, address: u32) -> Result<(), TockloaderError> {
+        self.board.erase_page(address).await?;
+        self.advance(address, self.board.page_size());
+        Ok(())
+    }
+
+    async fn plan_writes(&mut self, start: u32, end: u32) {
+        self.finish();
+        self.start = start;
+        self.total = end.saturating_sub(start) as usize;
+        self.written = 0;
+    }
+}
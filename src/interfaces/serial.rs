@@ -0,0 +1,305 @@
+pub mod board_interface;
+pub mod bootloader_interface;
+pub mod bytes_reader;
+pub mod console_protocol;
+pub mod flash_writer;
+pub mod keys;
+pub mod virtual_terminal;
+
+use std::io::{self, BufRead, Write};
+use std::time::Duration;
+
+use log::warn;
+use tokio::runtime::{Handle, RuntimeFlavor};
+use tokio_serial::{SerialPortBuilderExt, SerialPortInfo, SerialPortType, SerialStream};
+
+use crate::board::DEFAULT_PAGE_SIZE;
+use crate::errors::TockloaderError;
+use crate::interfaces::serial::virtual_terminal::{ConsoleOutput, InvalidUtf8};
+use crate::interfaces::traits::DEFAULT_RESPONSE_TIMEOUT;
+
+/// How many times a command is retried after a malformed response, unless
+/// set otherwise.
+pub const DEFAULT_RETRIES: u32 = 3;
+
+/// How long to give the board to start its bootloader after each way of
+/// resetting it into it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EntryTimeouts {
+    /// After releasing reset with the DTR and RTS lines.
+    pub dtr_rts: Duration,
+    /// After touching the port at 1200 baud, for the port to come back.
+    pub baud_touch: Duration,
+}
+
+impl Default for EntryTimeouts {
+    fn default() -> Self {
+        EntryTimeouts {
+            dtr_rts: Duration::from_millis(500),
+            baud_touch: Duration::from_millis(1000),
+        }
+    }
+}
+
+/// What came of [`enter_bootloader()`](SerialInterface::enter_bootloader).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BootloaderEntry {
+    /// The bootloader answers, whether it was running already or was started.
+    Entered,
+    /// The board was reset into its bootloader, but nothing answers.
+    NotEntered,
+    /// The port can not reset the board, so it was not tried.
+    Unsupported,
+}
+
+/// Connection to a board over a serial port, usually to its bootloader.
+pub struct SerialInterface {
+    port: Option<String>,
+    baud_rate: u32,
+    stream: Option<SerialStream>,
+    page_size: usize,
+    retries: u32,
+    /// How long to wait for each part of a response.
+    timeout: Duration,
+    /// Whether the bootloader must be synced before the next command.
+    sync_pending: bool,
+    /// The baud rate to go back to after
+    /// [`use_fast_baud_rate()`](SerialInterface::use_fast_baud_rate).
+    original_baud_rate: Option<u32>,
+    /// How the terminal prints what the board sends.
+    console_output: ConsoleOutput,
+    invalid_utf8: InvalidUtf8,
+}
+
+impl SerialInterface {
+    /// Create an interface for the serial port at `port`, or for the first
+    /// available one if `None`. No port is opened yet.
+    pub fn new(port: Option<String>, baud_rate: u32) -> Self {
+        SerialInterface {
+            port,
+            baud_rate,
+            stream: None,
+            page_size: DEFAULT_PAGE_SIZE,
+            retries: DEFAULT_RETRIES,
+            timeout: DEFAULT_RESPONSE_TIMEOUT,
+            sync_pending: true,
+            original_baud_rate: None,
+            console_output: ConsoleOutput::default(),
+            invalid_utf8: InvalidUtf8::default(),
+        }
+    }
+
+    /// Create an interface around an already open serial stream, such as one
+    /// end of a pseudo terminal pair.
+    pub fn with_stream(stream: SerialStream, baud_rate: u32) -> Self {
+        SerialInterface {
+            port: None,
+            baud_rate,
+            stream: Some(stream),
+            page_size: DEFAULT_PAGE_SIZE,
+            retries: DEFAULT_RETRIES,
+            timeout: DEFAULT_RESPONSE_TIMEOUT,
+            sync_pending: true,
+            original_baud_rate: None,
+            console_output: ConsoleOutput::default(),
+            invalid_utf8: InvalidUtf8::default(),
+        }
+    }
+
+    pub fn port(&self) -> Option<&str> {
+        self.port.as_deref()
+    }
+
+    pub fn baud_rate(&self) -> u32 {
+        self.baud_rate
+    }
+
+    /// Retry commands up to `retries` times after a malformed response.
+    pub fn set_retries(&mut self, retries: u32) {
+        self.retries = retries;
+    }
+
+    /// Wait up to `timeout` for each part of a response, rather than
+    /// [`DEFAULT_RESPONSE_TIMEOUT`].
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// Use a page size other than [`DEFAULT_PAGE_SIZE`] for writes.
+    pub fn set_page_size(&mut self, page_size: usize) {
+        self.page_size = page_size;
+    }
+
+    /// Print what the board sends in the terminal as `output` says, rather
+    /// than as text without any substitution.
+    pub fn set_console_output(&mut self, output: ConsoleOutput) {
+        self.console_output = output;
+    }
+
+    /// Handle bytes from the board that are not valid UTF-8 in the terminal
+    /// as `invalid_utf8` says, rather than replacing them.
+    pub fn set_invalid_utf8(&mut self, invalid_utf8: InvalidUtf8) {
+        self.invalid_utf8 = invalid_utf8;
+    }
+}
+
+/// Leaving the board at a fast baud rate would garble whatever talks to it
+/// next, so a fast baud rate is restored even when an operation fails half
+/// way and the interface is dropped without
+/// [`restore_baud_rate()`](SerialInterface::restore_baud_rate).
+impl Drop for SerialInterface {
+    fn drop(&mut self) {
+        let Some(original) = self.original_baud_rate else {
+            return;
+        };
+        // Restoring takes commands, which can only be waited for here on a
+        // runtime with other threads to run them.
+        let error = match Handle::try_current() {
+            Ok(handle) if handle.runtime_flavor() == RuntimeFlavor::MultiThread => {
+                match tokio::task::block_in_place(|| handle.block_on(self.restore_baud_rate())) {
+                    Ok(()) => return,
+                    Err(error) => error.to_string(),
+                }
+            }
+            _ => "no runtime to send commands with".to_string(),
+        };
+        warn!(
+            "Could not restore the board to {original} baud, it stays at {} baud until reset: {error}",
+            self.baud_rate
+        );
+    }
+}
+
+/// List the serial ports attached to this machine, with their paths cleaned
+/// up by [`clean_port_path`].
+pub fn available_ports() -> Result<Vec<SerialPortInfo>, TockloaderError> {
+    let ports = tokio_serial::available_ports().map_err(|error| {
+        // Without libudev the ports are found by scanning sysfs, so that is
+        // where a lack of permissions would show up.
+        map_serial_error("/sys/class/tty", error)
+    })?;
+    Ok(ports
+        .into_iter()
+        .map(|port| SerialPortInfo {
+            port_name: clean_port_path(port.port_name),
+            port_type: port.port_type,
+        })
+        .collect())
+}
+
+/// Describe a port on one line: its path, which can be passed to `--port`,
+/// then how it is connected. USB ports show their vendor and product IDs,
+/// manufacturer, product and serial number, as far as they are known.
+pub fn describe_port(port: &SerialPortInfo) -> String {
+    let details = match &port.port_type {
+        SerialPortType::UsbPort(usb) => {
+            let mut details = format!("USB {:04x}:{:04x}", usb.vid, usb.pid);
+            for name in [&usb.manufacturer, &usb.product].into_iter().flatten() {
+                details.push(' ');
+                details.push_str(name);
+            }
+            if let Some(serial_number) = &usb.serial_number {
+                details.push_str(&format!(" (serial number {serial_number})"));
+            }
+            details
+        }
+        SerialPortType::PciPort => "PCI".to_string(),
+        SerialPortType::BluetoothPort => "Bluetooth".to_string(),
+        SerialPortType::Unknown => "unknown type".to_string(),
+    };
+    format!("{}  {details}", port.port_name)
+}
+
+/// Ask which of `ports` to use, listing them numbered on `output` and reading
+/// the number from `input`. An empty answer, or the end of `input`, picks the
+/// first port; anything that is not one of the numbers asks again.
+pub fn prompt_for_port(
+    ports: &[SerialPortInfo],
+    input: &mut impl BufRead,
+    output: &mut impl Write,
+) -> Result<String, TockloaderError> {
+    if ports.is_empty() {
+        return Err(TockloaderError::NoPortAvailable);
+    }
+    writeln!(output, "Several serial ports were found:")?;
+    for (index, port) in ports.iter().enumerate() {
+        writeln!(output, "  [{}] {}", index + 1, describe_port(port))?;
+    }
+    loop {
+        write!(output, "Which one should be used? [1] ")?;
+        output.flush()?;
+        let mut answer = String::new();
+        if input.read_line(&mut answer)? == 0 {
+            writeln!(output)?;
+            return Ok(ports[0].port_name.clone());
+        }
+        let answer = answer.trim();
+        if answer.is_empty() {
+            return Ok(ports[0].port_name.clone());
+        }
+        match answer.parse::<usize>() {
+            Ok(number) if (1..=ports.len()).contains(&number) => {
+                return Ok(ports[number - 1].port_name.clone());
+            }
+            _ => writeln!(output, "Enter a number from 1 to {}", ports.len())?,
+        }
+    }
+}
+
+/// Turn a port path as reported by enumeration into one that can be opened.
+///
+/// On Linux systems without libudev ports are enumerated from
+/// `/sys/class/tty`, and may be reported by their sysfs path rather than the
+/// device node. Paths on other platforms (e.g. Windows `COM` ports) are
+/// returned unchanged.
+pub fn clean_port_path(port: String) -> String {
+    if cfg!(target_os = "linux") {
+        if let Some(name) = port.strip_prefix("/sys/class/tty/") {
+            return format!("/dev/{name}");
+        }
+    }
+    port
+}
+
+/// Open the serial port at `path`.
+pub fn open_port(path: &str, baud_rate: u32) -> Result<SerialStream, TockloaderError> {
+    tokio_serial::new(path, baud_rate)
+        .open_native_async()
+        .map_err(|error| map_serial_error(path, error))
+}
+
+/// Open the first serial port that can be opened.
+///
+/// If no port could be opened because of missing permissions, the error names
+/// every port that was refused.
+pub fn open_first_available_port(baud_rate: u32) -> Result<SerialStream, TockloaderError> {
+    let ports = available_ports()?;
+    let mut denied = Vec::new();
+
+    for port in &ports {
+        match open_port(&port.port_name, baud_rate) {
+            Ok(stream) => return Ok(stream),
+            Err(TockloaderError::PermissionDenied { paths }) => denied.extend(paths),
+            Err(_) => continue,
+        }
+    }
+
+    if denied.is_empty() {
+        Err(TockloaderError::NoPortAvailable)
+    } else {
+        Err(TockloaderError::PermissionDenied { paths: denied })
+    }
+}
+
+/// Give permission errors for `path` their own error, so the user can be told
+/// how to fix them.
+fn map_serial_error(path: &str, error: tokio_serial::Error) -> TockloaderError {
+    match error.kind() {
+        tokio_serial::ErrorKind::Io(io::ErrorKind::PermissionDenied) => {
+            TockloaderError::PermissionDenied {
+                paths: vec![path.to_string()],
+            }
+        }
+        _ => TockloaderError::TokioSerialError(error),
+    }
+}
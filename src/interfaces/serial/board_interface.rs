@@ -0,0 +1,17 @@
+use crate::errors::TockloaderError;
+use crate::interfaces::serial::{open_first_available_port, open_port, SerialInterface};
+use crate::interfaces::traits::BoardInterface;
+
+impl BoardInterface for SerialInterface {
+    /// Open the port given when creating the interface, or the first available
+    /// one. A freshly opened bootloader has to be synced again.
+    fn open(&mut self) -> Result<(), TockloaderError> {
+        let stream = match &self.port {
+            Some(port) => open_port(port, self.baud_rate)?,
+            None => open_first_available_port(self.baud_rate)?,
+        };
+        self.stream = Some(stream);
+        self.sync_pending = true;
+        Ok(())
+    }
+}
@@ -0,0 +1,397 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use log::{info, warn};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio_serial::{ClearBuffer, SerialPort};
+
+use crate::bootloader::attribute::{Attribute, ATTRIBUTE_LEN};
+use crate::bootloader::commands::*;
+use crate::bootloader::info::INFO_LEN;
+use crate::errors::TockloaderError;
+use crate::interfaces::serial::{open_port, BootloaderEntry, EntryTimeouts, SerialInterface};
+use crate::interfaces::traits::{timeout, BootloaderInterface, ATTRIBUTE_COUNT};
+
+impl SerialInterface {
+    /// Send `command` with its arguments, and wait for a response of type
+    /// `response_code` carrying `response_len` bytes of data.
+    ///
+    /// The bootloader is synced first if needed. Any failure leaves the
+    /// bootloader in an unknown state, so the next command will sync again.
+    /// A malformed response is most likely noise on the line, so the command
+    /// is retried (after a sync) up to the configured number of times.
+    pub(super) async fn issue_command(
+        &mut self,
+        command: u8,
+        message: &[u8],
+        response_code: u8,
+        response_len: usize,
+    ) -> Result<Vec<u8>, TockloaderError> {
+        let mut attempt = 0;
+        loop {
+            if self.sync_pending {
+                self.sync().await?;
+            }
+
+            let result = self
+                .send_and_receive(command, message, response_code, response_len)
+                .await;
+            match result {
+                Ok(response) => return Ok(response),
+                Err(TockloaderError::MalformedResponse(reason)) if attempt < self.retries => {
+                    attempt += 1;
+                    warn!("Retrying command {command:#04x} after a malformed response: {reason}");
+                    self.discard_input()?;
+                    self.sync_pending = true;
+                }
+                Err(error) => {
+                    self.sync_pending = true;
+                    return Err(error);
+                }
+            }
+        }
+    }
+
+    /// Make sure the bootloader is running, resetting the board into it if it
+    /// does not answer.
+    ///
+    /// The board is first reset with the DTR and RTS lines, the first
+    /// selecting the bootloader and the second driving reset, as on boards
+    /// with a USB to serial chip. If that does not start it, the port is
+    /// touched at 1200 baud, which resets boards whose USB is handled by the
+    /// chip itself. Each is given the time in `timeouts` before the
+    /// bootloader is pinged again.
+    ///
+    /// A way of resetting the board that the port does not support is
+    /// skipped: ports without modem lines can not be reset with them, and
+    /// only a port opened by path can be touched, as it has to be opened
+    /// again.
+    pub async fn enter_bootloader(
+        &mut self,
+        timeouts: EntryTimeouts,
+    ) -> Result<BootloaderEntry, TockloaderError> {
+        if self.ping().await.is_ok() {
+            return Ok(BootloaderEntry::Entered);
+        }
+
+        let mut tried = false;
+        match self.reset_with_dtr_rts(timeouts.dtr_rts).await {
+            Ok(()) => {
+                tried = true;
+                if self.ping().await.is_ok() {
+                    return Ok(BootloaderEntry::Entered);
+                }
+            }
+            Err(error) => info!("Could not reset the board with DTR and RTS: {error}"),
+        }
+        if let Some(port) = self.port.clone() {
+            self.touch_at_1200_baud(&port, timeouts.baud_touch).await?;
+            tried = true;
+            if self.ping().await.is_ok() {
+                return Ok(BootloaderEntry::Entered);
+            }
+        }
+        Ok(if tried {
+            BootloaderEntry::NotEntered
+        } else {
+            BootloaderEntry::Unsupported
+        })
+    }
+
+    /// Hold the board in reset with RTS while selecting the bootloader with
+    /// DTR, then release reset and give the bootloader `wait` to start.
+    async fn reset_with_dtr_rts(&mut self, wait: Duration) -> Result<(), TockloaderError> {
+        let stream = self
+            .stream
+            .as_mut()
+            .ok_or(TockloaderError::BootloaderNotOpen)?;
+        stream.write_data_terminal_ready(true)?;
+        stream.write_request_to_send(true)?;
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        stream.write_request_to_send(false)?;
+        tokio::time::sleep(wait).await;
+        stream.write_data_terminal_ready(false)?;
+        self.discard_input()?;
+        self.sync_pending = true;
+        Ok(())
+    }
+
+    /// Leave the bootloader and start the kernel.
+    ///
+    /// The bootloader is sent `COMMAND_EXIT`, which it does not respond to.
+    /// Bootloaders too old to know it are left by resetting the board with
+    /// RTS, this time without selecting the bootloader with DTR. Ports
+    /// without modem lines skip that reset.
+    pub async fn exit_bootloader(&mut self) -> Result<(), TockloaderError> {
+        if self.needs_sync() {
+            self.sync().await?;
+        }
+        let stream = self
+            .stream
+            .as_mut()
+            .ok_or(TockloaderError::BootloaderNotOpen)?;
+        stream.write_all(&[ESCAPE_CHAR, COMMAND_EXIT]).await?;
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let reset = stream
+            .write_data_terminal_ready(false)
+            .and_then(|()| stream.write_request_to_send(true));
+        match reset {
+            Ok(()) => {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                stream.write_request_to_send(false)?;
+            }
+            Err(error) => info!("Could not reset the board with RTS: {error}"),
+        }
+        // Whatever was sent next is for the kernel, not the bootloader.
+        self.discard_input()?;
+        self.sync_pending = true;
+        Ok(())
+    }
+
+    /// Close the port after switching it to 1200 baud, which tells boards
+    /// with native USB to reset into their bootloader, then give the port
+    /// `wait` to come back and open it again.
+    async fn touch_at_1200_baud(
+        &mut self,
+        port: &str,
+        wait: Duration,
+    ) -> Result<(), TockloaderError> {
+        if let Some(mut stream) = self.stream.take() {
+            stream.set_baud_rate(1200)?;
+        }
+        tokio::time::sleep(wait).await;
+        self.stream = Some(open_port(port, self.baud_rate)?);
+        self.sync_pending = true;
+        Ok(())
+    }
+
+    /// Switch both the bootloader and the port to `baud_rate`.
+    ///
+    /// The bootloader only keeps the new rate once it is confirmed at that
+    /// rate. If that fails the port goes back to the previous rate too, so
+    /// both still agree.
+    pub async fn change_baud_rate(&mut self, baud_rate: u32) -> Result<(), TockloaderError> {
+        let mut message = vec![CHANGE_BAUD_RATE_SET];
+        message.extend_from_slice(&baud_rate.to_le_bytes());
+        self.issue_command(COMMAND_CHANGE_BAUD_RATE, &message, RESPONSE_OK, 0)
+            .await?;
+
+        let previous = self.baud_rate;
+        self.set_port_baud_rate(baud_rate)?;
+        message[0] = CHANGE_BAUD_RATE_CONFIRM;
+        let confirmed = self
+            .issue_command(COMMAND_CHANGE_BAUD_RATE, &message, RESPONSE_OK, 0)
+            .await;
+        if confirmed.is_err() {
+            self.set_port_baud_rate(previous)?;
+        }
+        confirmed.map(|_| ())
+    }
+
+    /// Switch to the faster `baud_rate` until
+    /// [`restore_baud_rate()`](SerialInterface::restore_baud_rate), or until
+    /// the interface is dropped.
+    ///
+    /// Returns whether the rate was changed. Bootloaders which do not support
+    /// `baud_rate`, or changing it at all, refuse, and the current rate is
+    /// kept.
+    pub async fn use_fast_baud_rate(&mut self, baud_rate: u32) -> Result<bool, TockloaderError> {
+        let original = self.baud_rate;
+        match self.change_baud_rate(baud_rate).await {
+            Ok(()) => {}
+            Err(TockloaderError::CommandRejected {
+                command: COMMAND_CHANGE_BAUD_RATE,
+                response: RESPONSE_UNKNOWN | RESPONSE_CHANGE_BAUD_FAIL,
+            }) => return Ok(false),
+            Err(error) => return Err(error),
+        }
+        self.original_baud_rate.get_or_insert(original);
+        Ok(true)
+    }
+
+    /// Go back to the baud rate used before
+    /// [`use_fast_baud_rate()`](SerialInterface::use_fast_baud_rate), if it
+    /// was used.
+    pub async fn restore_baud_rate(&mut self) -> Result<(), TockloaderError> {
+        if let Some(original) = self.original_baud_rate {
+            self.change_baud_rate(original).await?;
+            self.original_baud_rate = None;
+        }
+        Ok(())
+    }
+
+    fn set_port_baud_rate(&mut self, baud_rate: u32) -> Result<(), TockloaderError> {
+        if let Some(stream) = &mut self.stream {
+            stream.set_baud_rate(baud_rate)?;
+        }
+        self.baud_rate = baud_rate;
+        Ok(())
+    }
+
+    /// Drop whatever is left of a bad response, so it is not mistaken for
+    /// the response to the next command.
+    fn discard_input(&mut self) -> Result<(), TockloaderError> {
+        if let Some(stream) = &self.stream {
+            stream.clear(ClearBuffer::Input)?;
+        }
+        Ok(())
+    }
+
+    async fn send_and_receive(
+        &mut self,
+        command: u8,
+        message: &[u8],
+        response_code: u8,
+        response_len: usize,
+    ) -> Result<Vec<u8>, TockloaderError> {
+        let response_timeout = self.timeout;
+        let stream = self
+            .stream
+            .as_mut()
+            .ok_or(TockloaderError::BootloaderNotOpen)?;
+
+        let mut packet = escape(message);
+        packet.extend_from_slice(&[ESCAPE_CHAR, command]);
+        stream.write_all(&packet).await?;
+
+        let mut header = [0u8; 2];
+        timeout(response_timeout, stream.read_exact(&mut header)).await??;
+        if header[0] != ESCAPE_CHAR {
+            return Err(TockloaderError::MalformedResponse(format!(
+                "expected the escape character, got {:#04x}",
+                header[0]
+            )));
+        }
+        // A clear refusal, which retrying would not change.
+        let refusals = [
+            RESPONSE_BADADDR,
+            RESPONSE_BADARGS,
+            RESPONSE_UNKNOWN,
+            RESPONSE_CHANGE_BAUD_FAIL,
+        ];
+        if header[1] != response_code && refusals.contains(&header[1]) {
+            return Err(TockloaderError::CommandRejected {
+                command,
+                response: header[1],
+            });
+        }
+        if header[1] != response_code {
+            return Err(TockloaderError::MalformedResponse(format!(
+                "expected response {response_code:#04x}, got {:#04x}",
+                header[1]
+            )));
+        }
+
+        // The data is escaped, so read it one byte at a time until enough
+        // de-escaped bytes have arrived.
+        let mut response = Vec::with_capacity(response_len);
+        let mut previous_was_escape = false;
+        while response.len() < response_len {
+            let byte = timeout(response_timeout, stream.read_u8()).await??;
+            if byte == ESCAPE_CHAR && !previous_was_escape {
+                previous_was_escape = true;
+                continue;
+            }
+            previous_was_escape = false;
+            response.push(byte);
+        }
+
+        Ok(response)
+    }
+}
+
+#[async_trait]
+impl BootloaderInterface for SerialInterface {
+    async fn sync(&mut self) -> Result<(), TockloaderError> {
+        let stream = self
+            .stream
+            .as_mut()
+            .ok_or(TockloaderError::BootloaderNotOpen)?;
+
+        // The leading null byte completes any escape sequence that the
+        // bootloader may be waiting on, so that the reset is seen as a
+        // command.
+        stream
+            .write_all(&[0x00, ESCAPE_CHAR, COMMAND_RESET])
+            .await?;
+        self.sync_pending = false;
+        Ok(())
+    }
+
+    fn needs_sync(&self) -> bool {
+        self.sync_pending
+    }
+
+    async fn ping(&mut self) -> Result<(), TockloaderError> {
+        self.issue_command(COMMAND_PING, &[], RESPONSE_PONG, 0)
+            .await
+            .map(|_| ())
+    }
+
+    async fn get_attribute(&mut self, index: u8) -> Result<Option<Attribute>, TockloaderError> {
+        let response = self
+            .issue_command(
+                COMMAND_GET_ATTRIBUTE,
+                &[index],
+                RESPONSE_GET_ATTRIBUTE,
+                ATTRIBUTE_LEN,
+            )
+            .await?;
+        Ok(Attribute::parse_raw(&response))
+    }
+
+    async fn set_attribute(
+        &mut self,
+        index: u8,
+        attribute: &Attribute,
+    ) -> Result<(), TockloaderError> {
+        // Checked here, so that a bad slot is reported as such rather than as
+        // whatever the bootloader makes of it.
+        if index >= ATTRIBUTE_COUNT {
+            return Err(TockloaderError::InvalidAttribute(format!(
+                "slot {index} does not exist, there are {ATTRIBUTE_COUNT}"
+            )));
+        }
+        let mut message = vec![index];
+        message.extend_from_slice(&attribute.to_raw());
+        self.issue_command(COMMAND_SET_ATTRIBUTE, &message, RESPONSE_OK, 0)
+            .await
+            .map(|_| ())
+    }
+
+    async fn set_start_address(&mut self, address: u32) -> Result<(), TockloaderError> {
+        self.issue_command(
+            COMMAND_SET_START_ADDRESS,
+            &address.to_le_bytes(),
+            RESPONSE_OK,
+            0,
+        )
+        .await
+        .map(|_| ())
+    }
+
+    async fn crc_internal_flash(&mut self, address: u32, len: u32) -> Result<u32, TockloaderError> {
+        let mut message = address.to_le_bytes().to_vec();
+        message.extend_from_slice(&len.to_le_bytes());
+        let response = self
+            .issue_command(
+                COMMAND_CRC_INTERNAL_FLASH,
+                &message,
+                RESPONSE_CRC_INTERNAL_FLASH,
+                4,
+            )
+            .await?;
+        Ok(u32::from_le_bytes(response[0..4].try_into().unwrap()))
+    }
+
+    async fn get_bootloader_info(&mut self) -> Result<String, TockloaderError> {
+        // The first byte is the length of the string that follows, the rest
+        // of the response is padding.
+        let response = self
+            .issue_command(COMMAND_INFO, &[], RESPONSE_INFO, INFO_LEN)
+            .await?;
+        let len = (response[0] as usize).min(response.len() - 1);
+        Ok(String::from_utf8_lossy(&response[1..1 + len]).into_owned())
+    }
+}
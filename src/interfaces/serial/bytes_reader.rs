@@ -0,0 +1,58 @@
+use async_trait::async_trait;
+
+use crate::bootloader::commands::*;
+use crate::errors::TockloaderError;
+use crate::interfaces::serial::SerialInterface;
+use crate::interfaces::traits::{BootloaderInterface, BytesReader};
+
+/// The most bytes requested with a single `COMMAND_READ_RANGE`, so that the
+/// response fits in the bootloader's buffer.
+const READ_RANGE_MAX_LEN: usize = 512;
+
+#[async_trait]
+impl BytesReader for SerialInterface {
+    /// Read with `COMMAND_READ_RANGE`, as many times as needed for `len`.
+    async fn read_range(&mut self, start: usize, len: usize) -> Result<Vec<u8>, TockloaderError> {
+        check_address_space(start, len)?;
+
+        let mut data = Vec::with_capacity(len);
+        while data.len() < len {
+            let address = (start + data.len()) as u32;
+            let chunk_len = (len - data.len()).min(READ_RANGE_MAX_LEN) as u16;
+
+            let mut message = address.to_le_bytes().to_vec();
+            message.extend_from_slice(&chunk_len.to_le_bytes());
+            let chunk = self
+                .issue_command(
+                    COMMAND_READ_RANGE,
+                    &message,
+                    RESPONSE_READ_RANGE,
+                    chunk_len as usize,
+                )
+                .await?;
+            data.extend_from_slice(&chunk);
+        }
+        Ok(data)
+    }
+
+    /// Let the bootloader compute the CRC with `COMMAND_CRC_INTERNAL_FLASH`,
+    /// so that nothing has to be read back.
+    async fn crc_range(&mut self, start: usize, len: usize) -> Result<u32, TockloaderError> {
+        check_address_space(start, len)?;
+        self.crc_internal_flash(start as u32, len as u32).await
+    }
+}
+
+/// Check that `len` bytes from `start` can be addressed by the bootloader,
+/// which takes 32 bit addresses that must not wrap around.
+fn check_address_space(start: usize, len: usize) -> Result<(), TockloaderError> {
+    // The length is passed as 32 bits too, so all of flash can not be covered
+    // in one go.
+    if start as u64 + len as u64 > 1 << 32 || len > u32::MAX as usize {
+        return Err(TockloaderError::InvalidReadRange {
+            address: u32::try_from(start).unwrap_or(u32::MAX),
+            length: u32::try_from(len).unwrap_or(u32::MAX),
+        });
+    }
+    Ok(())
+}
@@ -0,0 +1,171 @@
+//! How the text of the console is exchanged with the board.
+//!
+//! The Tock process console simply sends and receives raw text. A console
+//! shared between several processes instead wraps everything in packets that
+//! say which process it belongs to.
+
+use std::collections::BTreeMap;
+use std::io;
+
+use bytes::{Buf, BytesMut};
+use tokio_util::codec::Decoder;
+
+use crate::interfaces::serial::virtual_terminal::TerminalCodec;
+
+/// Text received from the board.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConsoleMessage {
+    /// The virtual process that sent the text, when the protocol tells.
+    pub process: Option<u8>,
+    pub text: String,
+    /// The bytes the text was decoded from, which only differ from it where
+    /// they are not valid UTF-8.
+    pub bytes: Vec<u8>,
+}
+
+/// A protocol spoken with the console of the board: decoding turns what the
+/// board sends into [`ConsoleMessage`]s, and [`encode_input()`] frames what
+/// the user types.
+///
+/// The protocol is cloned before use, so that one copy decodes while the
+/// other encodes.
+///
+/// [`encode_input()`]: ConsoleProtocol::encode_input
+pub trait ConsoleProtocol:
+    Decoder<Item = ConsoleMessage, Error = io::Error> + Clone + Send + Unpin
+{
+    /// The bytes to send to the board for `input`, the bytes of a key.
+    fn encode_input(&self, input: &[u8]) -> Vec<u8>;
+}
+
+/// Raw text in both directions, as used by the Tock process console.
+#[derive(Clone, Debug, Default)]
+pub struct RawProtocol;
+
+impl Decoder for RawProtocol {
+    type Item = ConsoleMessage;
+    type Error = io::Error;
+
+    fn decode(&mut self, source: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        Ok(TerminalCodec
+            .decode_bytes(source, false)
+            .map(|(text, bytes)| ConsoleMessage {
+                process: None,
+                text,
+                bytes,
+            }))
+    }
+
+    fn decode_eof(&mut self, source: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        Ok(TerminalCodec
+            .decode_bytes(source, true)
+            .map(|(text, bytes)| ConsoleMessage {
+                process: None,
+                text,
+                bytes,
+            }))
+    }
+}
+
+impl ConsoleProtocol for RawProtocol {
+    fn encode_input(&self, input: &[u8]) -> Vec<u8> {
+        input.to_vec()
+    }
+}
+
+/// Size of the header of a [`PacketProtocol`] packet.
+pub const PACKET_HEADER_LEN: usize = 3;
+
+/// Text of several virtual processes, in packets of:
+///
+/// | Bytes | Field                                  |
+/// |-------|----------------------------------------|
+/// | 0     | the virtual process                    |
+/// | 1-2   | length of the payload, little endian   |
+/// | 3-    | the payload                            |
+///
+/// Each process has its own stream of text, so a character split across
+/// two of its packets is still decoded correctly. What the user types is
+/// sent to `input_process`.
+#[derive(Clone, Debug, Default)]
+pub struct PacketProtocol {
+    input_process: u8,
+    /// Bytes of each process which do not form a whole character yet.
+    pending: BTreeMap<u8, BytesMut>,
+}
+
+impl PacketProtocol {
+    /// Send what the user types to `input_process`.
+    pub fn new(input_process: u8) -> Self {
+        PacketProtocol {
+            input_process,
+            pending: BTreeMap::new(),
+        }
+    }
+}
+
+impl Decoder for PacketProtocol {
+    type Item = ConsoleMessage;
+    type Error = io::Error;
+
+    fn decode(&mut self, source: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        loop {
+            let Some(header) = source.get(..PACKET_HEADER_LEN) else {
+                return Ok(None);
+            };
+            let process = header[0];
+            let len = u16::from_le_bytes([header[1], header[2]]) as usize;
+            if source.len() < PACKET_HEADER_LEN + len {
+                source.reserve(PACKET_HEADER_LEN + len - source.len());
+                return Ok(None);
+            }
+            source.advance(PACKET_HEADER_LEN);
+            let payload = source.split_to(len);
+
+            let pending = self.pending.entry(process).or_default();
+            pending.extend_from_slice(&payload);
+            if let Some((text, bytes)) = TerminalCodec.decode_bytes(pending, false) {
+                return Ok(Some(ConsoleMessage {
+                    process: Some(process),
+                    text,
+                    bytes,
+                }));
+            }
+            // Only part of a character so far, the rest is in a later packet.
+        }
+    }
+}
+
+impl ConsoleProtocol for PacketProtocol {
+    fn encode_input(&self, input: &[u8]) -> Vec<u8> {
+        input
+            .chunks(u16::MAX as usize)
+            .flat_map(|chunk| {
+                let mut packet = vec![self.input_process];
+                packet.extend_from_slice(&(chunk.len() as u16).to_le_bytes());
+                packet.extend_from_slice(chunk);
+                packet
+            })
+            .collect()
+    }
+}
+
+/// The protocols that can be chosen with `listen --protocol`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ProtocolKind {
+    #[default]
+    Raw,
+    Packet,
+}
+
+impl std::str::FromStr for ProtocolKind {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "raw" => Ok(ProtocolKind::Raw),
+            "packet" => Ok(ProtocolKind::Packet),
+            _ => Err(format!("'{value}' is not one of raw or packet")),
+        }
+    }
+}
@@ -0,0 +1,52 @@
+use async_trait::async_trait;
+
+use crate::bootloader::commands::*;
+use crate::errors::TockloaderError;
+use crate::interfaces::serial::SerialInterface;
+use crate::interfaces::traits::{check_page_write, FlashWriter};
+
+#[async_trait]
+impl FlashWriter for SerialInterface {
+    fn page_size(&self) -> usize {
+        self.page_size
+    }
+
+    /// Write a page with `COMMAND_WRITE_PAGE`. The page is checked against the
+    /// page size of the board first, rather than sending a write the
+    /// bootloader would refuse.
+    async fn write_page(&mut self, address: u32, data: &[u8]) -> Result<(), TockloaderError> {
+        check_page_write(address, data, self.page_size)?;
+        let mut message = address.to_le_bytes().to_vec();
+        message.extend_from_slice(data);
+        self.issue_command(COMMAND_WRITE_PAGE, &message, RESPONSE_OK, 0)
+            .await
+            .map(|_| ())
+    }
+
+    /// Erase a page with `COMMAND_ERASE_PAGE`. A page which is not aligned to
+    /// the page size of the board is refused before anything is sent.
+    async fn erase_page(&mut self, address: u32) -> Result<(), TockloaderError> {
+        if !(address as usize).is_multiple_of(self.page_size) {
+            return Err(TockloaderError::UnalignedPage {
+                address,
+                page_size: self.page_size,
+            });
+        }
+        match self
+            .issue_command(COMMAND_ERASE_PAGE, &address.to_le_bytes(), RESPONSE_OK, 0)
+            .await
+        {
+            Ok(_) => Ok(()),
+            // The address is aligned, so it is out of flash or the bootloader
+            // has other pages than the ones assumed here.
+            Err(TockloaderError::CommandRejected {
+                response: RESPONSE_BADADDR,
+                ..
+            }) => Err(TockloaderError::ErasePageRefused {
+                address,
+                page_size: self.page_size,
+            }),
+            Err(error) => Err(error),
+        }
+    }
+}
@@ -0,0 +1,40 @@
+use console::Key;
+use log::trace;
+
+/// Convert a key press into the bytes a terminal would send for it.
+///
+/// This is the only mapping from keys to bytes, every terminal sends keys
+/// through it. Keys without a terminal representation are dropped.
+///
+/// `Del` is sent as the VT100 "delete" sequence `ESC [ 3 ~`, not as `0x7F`:
+/// the kernel's process console treats `0x7F` like backspace (deleting
+/// before the cursor), and recognizes `ESC [ 3 ~` as deleting the character
+/// under the cursor.
+pub fn key_to_bytes(key: Key) -> Option<String> {
+    let bytes = match key {
+        Key::Char(c) => c.to_string(),
+        Key::Enter => "\n".into(),
+        Key::Backspace => "\u{8}".into(),
+        Key::Tab => "\t".into(),
+        Key::BackTab => "\u{1B}[Z".into(),
+        Key::Escape => "\u{1B}".into(),
+        // Only reaches the board when Ctrl+C is forwarded rather than used
+        // to quit.
+        Key::CtrlC => "\u{3}".into(),
+        Key::ArrowUp => "\u{1B}[A".into(),
+        Key::ArrowDown => "\u{1B}[B".into(),
+        Key::ArrowRight => "\u{1B}[C".into(),
+        Key::ArrowLeft => "\u{1B}[D".into(),
+        Key::Home => "\u{1B}[H".into(),
+        Key::End => "\u{1B}[F".into(),
+        Key::Insert => "\u{1B}[2~".into(),
+        Key::Del => "\u{1B}[3~".into(),
+        Key::PageUp => "\u{1B}[5~".into(),
+        Key::PageDown => "\u{1B}[6~".into(),
+        other => {
+            trace!("Ignoring key without a terminal representation: {other:?}");
+            return None;
+        }
+    };
+    Some(bytes)
+}
@@ -0,0 +1,587 @@
+use std::fs::File;
+use std::future::Future;
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+use bytes::BytesMut;
+use console::{Key, Term};
+use futures::StreamExt;
+use tokio::io::{AsyncReadExt, AsyncWriteExt, ReadHalf, WriteHalf};
+use tokio::sync::mpsc;
+use tokio_serial::SerialStream;
+use tokio_util::codec::{Decoder, FramedRead};
+
+use crate::errors::TockloaderError;
+use crate::interfaces::serial::console_protocol::{ConsoleMessage, ConsoleProtocol};
+use crate::interfaces::serial::keys::key_to_bytes;
+use crate::interfaces::serial::SerialInterface;
+
+/// The prompt printed by the Tock process console.
+pub const DEFAULT_PROMPT: &str = "tock$ ";
+
+/// How soon a second Ctrl+C has to follow the first one to quit, when
+/// Ctrl+C is [forwarded](CtrlC::Forward) to the board.
+pub const DOUBLE_CTRL_C: Duration = Duration::from_millis(500);
+
+impl SerialInterface {
+    /// Wait until the board prints `prompt`, so that it is ready for input.
+    ///
+    /// The prompt is not followed by a newline, so it is looked for in the
+    /// raw bytes as they arrive rather than line by line. Everything received
+    /// up to and including the prompt is discarded.
+    pub async fn wait_for_prompt(
+        &mut self,
+        prompt: &str,
+        timeout: Duration,
+    ) -> Result<(), TockloaderError> {
+        let stream = self
+            .stream
+            .as_mut()
+            .ok_or(TockloaderError::BootloaderNotOpen)?;
+        let prompt = prompt.as_bytes();
+        if prompt.is_empty() {
+            return Ok(());
+        }
+
+        let search = async {
+            // Only the end of what was received can hold the start of a
+            // prompt that is split across reads.
+            let mut received = Vec::new();
+            let mut buffer = [0; 256];
+            loop {
+                let count = stream.read(&mut buffer).await?;
+                if count == 0 {
+                    return Err(io::Error::from(io::ErrorKind::UnexpectedEof));
+                }
+                received.extend_from_slice(&buffer[..count]);
+                if received
+                    .windows(prompt.len())
+                    .any(|window| window == prompt)
+                {
+                    return Ok(());
+                }
+                let keep = received.len().min(prompt.len().saturating_sub(1));
+                received.drain(..received.len() - keep);
+            }
+        };
+
+        tokio::time::timeout(timeout, search).await.map_err(|_| {
+            TockloaderError::PromptNotFound {
+                prompt: String::from_utf8_lossy(prompt).into_owned(),
+                timeout,
+            }
+        })??;
+        Ok(())
+    }
+
+    /// Open an interactive terminal with the board: everything it sends is
+    /// printed, and every key pressed is sent to it. Returns once the board
+    /// disconnects, or Ctrl+C is pressed to quit as `ctrl_c` says.
+    ///
+    /// If `log` is given, everything the board sends is also written to it.
+    /// With `timestamps`, each line from the board starts with when it did,
+    /// in the log too. With `local_echo`, keys are printed as they are typed,
+    /// for kernels whose console does not echo them back. Text is exchanged
+    /// as `protocol` says.
+    pub async fn run_terminal<P: ConsoleProtocol>(
+        &mut self,
+        log: Option<TerminalLog>,
+        timestamps: Option<LineTimestamps>,
+        local_echo: LocalEcho,
+        ctrl_c: CtrlC,
+        protocol: P,
+    ) -> Result<(), TockloaderError> {
+        self.run_terminal_until(
+            log,
+            timestamps,
+            local_echo,
+            ctrl_c,
+            protocol,
+            std::future::pending(),
+        )
+        .await
+    }
+
+    /// Like [`run_terminal()`](SerialInterface::run_terminal), but also
+    /// return as soon as `shutdown` completes, e.g. with
+    /// `notify.notified()` on a [`tokio::sync::Notify`]. Either way the
+    /// stream is put back, so the interface can be used again.
+    pub async fn run_terminal_until<P: ConsoleProtocol>(
+        &mut self,
+        log: Option<TerminalLog>,
+        timestamps: Option<LineTimestamps>,
+        local_echo: LocalEcho,
+        ctrl_c: CtrlC,
+        protocol: P,
+        shutdown: impl Future<Output = ()> + Send,
+    ) -> Result<(), TockloaderError> {
+        let stream = self
+            .stream
+            .take()
+            .ok_or(TockloaderError::BootloaderNotOpen)?;
+        let (mut reader, mut writer) = tokio::io::split(stream);
+        let keys = spawn_key_reader(ctrl_c);
+
+        let output = self.console_output.clone();
+        let invalid_utf8 = self.invalid_utf8;
+        let result = tokio::select! {
+            result = read_from_serial(&mut reader, log, timestamps, protocol.clone(), &output, invalid_utf8) => result,
+            result = write_to_serial(&mut writer, keys, local_echo.enabled(), &protocol) => result,
+            () = shutdown => Ok(()),
+        };
+
+        // Both halves come from the split above and are only borrowed by the
+        // tasks, so they always belong together. `unsplit` would panic if
+        // they did not, so check rather than take that for granted.
+        if !reader.is_pair_of(&writer) {
+            return Err(TockloaderError::StreamClosed);
+        }
+        self.stream = Some(reader.unsplit(writer));
+        result
+    }
+}
+
+/// Print everything received from the board as `output` says. When the text
+/// of another process starts, a line saying which one is printed first.
+async fn read_from_serial<P: ConsoleProtocol>(
+    reader: &mut ReadHalf<SerialStream>,
+    mut log: Option<TerminalLog>,
+    mut timestamps: Option<LineTimestamps>,
+    protocol: P,
+    output: &ConsoleOutput,
+    invalid_utf8: InvalidUtf8,
+) -> Result<(), TockloaderError> {
+    let mut frames = FramedRead::new(reader, protocol);
+    let mut last_process = None;
+    while let Some(message) = frames.next().await {
+        let ConsoleMessage {
+            process,
+            text,
+            bytes,
+        } = message?;
+        invalid_utf8.check(&text, &bytes)?;
+        if let Some(id) = process.filter(|_| process != last_process) {
+            print!("\r\n[process {id}]\r\n");
+            if let Some(timestamps) = &mut timestamps {
+                timestamps.line_ended();
+            }
+        }
+        last_process = process;
+        let shown = output.substitute(&text);
+        let stamped = timestamps
+            .as_mut()
+            .map(|timestamps| timestamps.stamp(&shown, Instant::now()));
+        match (output, &stamped) {
+            (ConsoleOutput::Raw, None) => io::stdout().write_all(&bytes)?,
+            _ => print!("{}", stamped.as_deref().unwrap_or(&shown)),
+        }
+        // The prompt is not followed by a newline, so it would not show up
+        // without flushing.
+        io::stdout().flush()?;
+        if let Some(log) = &mut log {
+            match &stamped {
+                Some(stamped) => log.write(stamped, stamped.as_bytes())?,
+                None => log.write(&text, &bytes)?,
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Send every key pressed to the board, until the user quits.
+async fn write_to_serial<P: ConsoleProtocol>(
+    writer: &mut WriteHalf<SerialStream>,
+    mut keys: mpsc::UnboundedReceiver<Input>,
+    local_echo: bool,
+    protocol: &P,
+) -> Result<(), TockloaderError> {
+    while let Some(input) = keys.recv().await {
+        let Input::Key(key) = input else {
+            return Ok(());
+        };
+        if let Some(bytes) = key_to_bytes(key) {
+            writer
+                .write_all(&protocol.encode_input(bytes.as_bytes()))
+                .await?;
+            if local_echo {
+                print!("{}", echo_text(&bytes));
+                io::stdout().flush()?;
+            }
+        }
+    }
+
+    // There is no keyboard to read from (e.g. stdin is not a terminal), keep
+    // printing what the board sends.
+    std::future::pending().await
+}
+
+/// A change made to the text received from the board before it is printed.
+///
+/// Older kernels sent bare newlines, which only move the cursor down in a raw
+/// terminal, and null bytes, which terminals do not show. Newer kernels send
+/// text as it should be printed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Substitution {
+    /// Print `\n` as `\r\n`.
+    Newline,
+    /// Print null bytes as U+2400, the symbol for null.
+    Null,
+}
+
+impl Substitution {
+    pub fn apply(&self, text: &str) -> String {
+        match self {
+            Substitution::Newline => text.replace('\n', "\r\n"),
+            Substitution::Null => text.replace('\0', "\u{2400}"),
+        }
+    }
+}
+
+impl std::str::FromStr for Substitution {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "newline" => Ok(Substitution::Newline),
+            "null" => Ok(Substitution::Null),
+            _ => Err(format!("'{value}' is not one of newline or null")),
+        }
+    }
+}
+
+/// How the terminal prints what the board sends.
+///
+/// Either way, the log gets the bytes as received.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConsoleOutput {
+    /// As text, with bytes that are not valid UTF-8 shown as U+FFFD, after
+    /// the substitutions.
+    Text(Vec<Substitution>),
+    /// Byte for byte, as received. Lines are still stamped as text when
+    /// asked for.
+    Raw,
+}
+
+impl Default for ConsoleOutput {
+    fn default() -> Self {
+        ConsoleOutput::Text(Vec::new())
+    }
+}
+
+impl ConsoleOutput {
+    /// The text to print for `text`, received from the board.
+    pub fn substitute(&self, text: &str) -> String {
+        match self {
+            ConsoleOutput::Text(substitutions) => substitutions
+                .iter()
+                .fold(text.to_string(), |text, substitution| {
+                    substitution.apply(&text)
+                }),
+            ConsoleOutput::Raw => text.to_string(),
+        }
+    }
+}
+
+/// What the terminal does with bytes from the board that are not valid
+/// UTF-8, such as a binary dump or noise at the wrong baud rate.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum InvalidUtf8 {
+    /// Show them as U+FFFD and keep going.
+    #[default]
+    Replace,
+    /// End the terminal with an `InvalidData` error.
+    Fail,
+}
+
+impl InvalidUtf8 {
+    /// Check `text`, decoded from `bytes` by a [`TerminalCodec`], which only
+    /// differs from them where they are not valid UTF-8.
+    pub fn check(&self, text: &str, bytes: &[u8]) -> io::Result<()> {
+        if *self == InvalidUtf8::Fail && text.as_bytes() != bytes {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("the board sent bytes that are not valid UTF-8: {bytes:02x?}"),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Whether typed keys are printed locally, besides being sent to the board.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LocalEcho {
+    On,
+    Off,
+    /// Rely on the board, as the Tock process console echoes what it
+    /// receives. Same as `Off` for now.
+    #[default]
+    Auto,
+}
+
+impl LocalEcho {
+    pub fn enabled(&self) -> bool {
+        match self {
+            LocalEcho::On => true,
+            LocalEcho::Off | LocalEcho::Auto => false,
+        }
+    }
+}
+
+/// What pressing Ctrl+C in the terminal does.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CtrlC {
+    /// Leave the terminal.
+    #[default]
+    Quit,
+    /// Send it to the board, to interrupt what runs there. Pressing it twice
+    /// within [`DOUBLE_CTRL_C`] leaves the terminal instead.
+    Forward,
+}
+
+impl CtrlC {
+    /// Whether a Ctrl+C pressed at `now` leaves the terminal, `previous`
+    /// being when the last one that did not was pressed.
+    pub fn quits(&self, previous: Option<Instant>, now: Instant) -> bool {
+        match self {
+            CtrlC::Quit => true,
+            CtrlC::Forward => {
+                previous.is_some_and(|previous| now.duration_since(previous) < DOUBLE_CTRL_C)
+            }
+        }
+    }
+}
+
+impl std::str::FromStr for CtrlC {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "quit" => Ok(CtrlC::Quit),
+            "forward" => Ok(CtrlC::Forward),
+            _ => Err(format!("'{value}' is not one of quit or forward")),
+        }
+    }
+}
+
+impl std::str::FromStr for LocalEcho {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "on" => Ok(LocalEcho::On),
+            "off" => Ok(LocalEcho::Off),
+            "auto" => Ok(LocalEcho::Auto),
+            _ => Err(format!("'{value}' is not one of on, off or auto")),
+        }
+    }
+}
+
+/// What to print locally for the bytes sent for a key. A terminal only moves
+/// the cursor for a backspace, so the character is also blanked out, and a
+/// newline has to return to the start of the line.
+pub fn echo_text(bytes: &str) -> String {
+    match bytes {
+        "\u{8}" => "\u{8} \u{8}".to_string(),
+        "\n" => "\r\n".to_string(),
+        _ => bytes.to_string(),
+    }
+}
+
+/// What the key reader passes on to the terminal.
+enum Input {
+    Key(Key),
+    /// Ctrl+C was pressed to leave the terminal.
+    Quit,
+}
+
+/// Read keys on their own thread, since reading them blocks.
+///
+/// Keys are read raw, so Ctrl+C arrives as a key instead of killing
+/// tockloader with the port left open. The thread stops reading as soon as
+/// Ctrl+C quits, so the keyboard is left alone from then on. It is never
+/// joined: if the terminal closes for another reason, it ends with the next
+/// key read, which is lost, or else along with the process.
+fn spawn_key_reader(ctrl_c: CtrlC) -> mpsc::UnboundedReceiver<Input> {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    let term = Term::stdout();
+    if term.features().is_attended() {
+        std::thread::spawn(move || {
+            let mut previous_ctrl_c = None;
+            while let Ok(key) = term.read_key_raw() {
+                let input = match key {
+                    Key::CtrlC if ctrl_c.quits(previous_ctrl_c, Instant::now()) => Input::Quit,
+                    Key::CtrlC => {
+                        previous_ctrl_c = Some(Instant::now());
+                        Input::Key(key)
+                    }
+                    _ => Input::Key(key),
+                };
+                let quit = matches!(input, Input::Quit);
+                if sender.send(input).is_err() || quit {
+                    break;
+                }
+            }
+        });
+    }
+    receiver
+}
+
+/// Puts the time at the start of each line of the text received from the
+/// board, counted from when the terminal was opened.
+///
+/// Text is passed on as soon as it arrives rather than a line at a time, so
+/// that a prompt without a newline still shows up. A line is stamped with the
+/// time its first character arrived.
+pub struct LineTimestamps {
+    start: Instant,
+    at_line_start: bool,
+}
+
+impl LineTimestamps {
+    /// Count from `start`, which the first line is expected to follow.
+    pub fn new(start: Instant) -> Self {
+        LineTimestamps {
+            start,
+            at_line_start: true,
+        }
+    }
+
+    /// Stamp each line of `text` that starts in it, `now` being when it was
+    /// received.
+    pub fn stamp(&mut self, text: &str, now: Instant) -> String {
+        let elapsed = now.saturating_duration_since(self.start).as_secs_f64();
+        let stamp = format!("[{elapsed:10.3}] ");
+        let mut stamped = String::with_capacity(text.len() + stamp.len());
+        for line in text.split_inclusive('\n') {
+            if self.at_line_start {
+                stamped.push_str(&stamp);
+            }
+            stamped.push_str(line);
+            self.at_line_start = line.ends_with('\n');
+        }
+        stamped
+    }
+
+    /// Start a new line with the next text, as something else was printed.
+    pub fn line_ended(&mut self) {
+        self.at_line_start = true;
+    }
+}
+
+/// A file that the output of the board is copied to.
+///
+/// The file is not buffered, so everything is written out as soon as it is
+/// received, as it should be for a session that may never end cleanly.
+pub struct TerminalLog {
+    file: File,
+    stripper: Option<AnsiStripper>,
+}
+
+impl TerminalLog {
+    /// Log to `file`, removing ANSI escape sequences first if `strip_ansi`
+    /// is set.
+    pub fn new(file: File, strip_ansi: bool) -> Self {
+        TerminalLog {
+            file,
+            stripper: strip_ansi.then(AnsiStripper::default),
+        }
+    }
+
+    /// Copy `bytes` exactly as they were received, even if they are not
+    /// valid UTF-8. Stripping ANSI sequences works on `text`, which was
+    /// decoded from them, instead.
+    fn write(&mut self, text: &str, bytes: &[u8]) -> io::Result<()> {
+        match &mut self.stripper {
+            Some(stripper) => self.file.write_all(stripper.strip(text).as_bytes()),
+            None => self.file.write_all(bytes),
+        }
+    }
+}
+
+/// Removes ANSI escape sequences (CSI sequences such as colors and cursor
+/// movements, and two character escapes) from text.
+///
+/// The state is kept between calls, so a sequence split across two chunks of
+/// text is still removed.
+#[derive(Default)]
+pub struct AnsiStripper {
+    state: AnsiState,
+}
+
+#[derive(Default)]
+enum AnsiState {
+    #[default]
+    Text,
+    /// After `ESC`.
+    Escape,
+    /// After `ESC [`, until the final byte.
+    Csi,
+}
+
+impl AnsiStripper {
+    pub fn strip(&mut self, text: &str) -> String {
+        let mut stripped = String::with_capacity(text.len());
+        for c in text.chars() {
+            self.state = match self.state {
+                AnsiState::Text if c == '\u{1B}' => AnsiState::Escape,
+                AnsiState::Text => {
+                    stripped.push(c);
+                    AnsiState::Text
+                }
+                AnsiState::Escape if c == '[' => AnsiState::Csi,
+                AnsiState::Escape => AnsiState::Text,
+                // Parameter and intermediate bytes.
+                AnsiState::Csi if ('\u{20}'..='\u{3F}').contains(&c) => AnsiState::Csi,
+                // The final byte, or anything which can not be part of the
+                // sequence and ends it.
+                AnsiState::Csi => AnsiState::Text,
+            };
+        }
+        stripped
+    }
+}
+
+/// Decodes the bytes received from the board into text.
+///
+/// Whatever is buffered is emitted right away rather than waiting for a full
+/// line, since the prompt is not followed by a newline. Bytes that are not
+/// valid UTF-8, e.g. noise at the wrong baud rate, show up as U+FFFD rather
+/// than ending the terminal.
+pub struct TerminalCodec;
+
+impl TerminalCodec {
+    /// Take the text at the start of `source`, along with the bytes it was
+    /// decoded from. A character cut off at the end of `source` is kept for
+    /// later, unless `eof` says that the rest of it will never arrive.
+    pub fn decode_bytes(&mut self, source: &mut BytesMut, eof: bool) -> Option<(String, Vec<u8>)> {
+        if source.is_empty() {
+            return None;
+        }
+        let len = match std::str::from_utf8(source) {
+            Ok(_) => source.len(),
+            Err(error) => match error.error_len() {
+                Some(invalid) => error.valid_up_to() + invalid,
+                None if eof => source.len(),
+                // The buffer ends halfway through a character, hold on to it
+                // until the rest arrives.
+                None if error.valid_up_to() == 0 => return None,
+                None => error.valid_up_to(),
+            },
+        };
+        let bytes = source.split_to(len);
+        Some((String::from_utf8_lossy(&bytes).into_owned(), bytes.to_vec()))
+    }
+}
+
+impl Decoder for TerminalCodec {
+    type Item = String;
+    type Error = io::Error;
+
+    fn decode(&mut self, source: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        Ok(self.decode_bytes(source, false).map(|(text, _)| text))
+    }
+
+    fn decode_eof(&mut self, source: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        Ok(self.decode_bytes(source, true).map(|(text, _)| text))
+    }
+}
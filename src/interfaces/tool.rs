@@ -0,0 +1,90 @@
+//! Running the programs that debug probe backends drive the probe with.
+
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::errors::TockloaderError;
+
+/// Run `command` of `tool` to completion, with `input` on its standard input.
+///
+/// A missing program is reported as [`TockloaderError::ToolNotFound`]; the
+/// caller decides whether the output means the command worked.
+pub(crate) fn run_tool(
+    tool: &'static str,
+    command: &mut Command,
+    input: &[u8],
+) -> Result<Output, TockloaderError> {
+    let not_found = |command: &Command, error: io::Error| match error.kind() {
+        io::ErrorKind::NotFound => TockloaderError::ToolNotFound {
+            tool,
+            command: command.get_program().to_string_lossy().into_owned(),
+            hint: install_hint(tool),
+        },
+        _ => TockloaderError::IOError(error),
+    };
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|error| not_found(command, error))?;
+    // The program may exit without reading its input, e.g. after failing to
+    // find the probe, so a broken pipe is left to show up in its output.
+    if let Some(mut stdin) = child.stdin.take() {
+        match stdin.write_all(input) {
+            Err(error) if error.kind() != io::ErrorKind::BrokenPipe => return Err(error.into()),
+            _ => {}
+        }
+    }
+    Ok(child.wait_with_output()?)
+}
+
+/// How to get `tool` installed, or tell tockloader where it is.
+fn install_hint(tool: &str) -> &'static str {
+    match tool {
+        "openocd" => {
+            "Install OpenOCD, e.g. with your package manager, or pass its path with --openocd-cmd."
+        }
+        "JLinkExe" => {
+            "Install the J-Link Software and Documentation Pack from SEGGER, or pass the path \
+             to JLinkExe with --jlink-cmd."
+        }
+        _ => "Install it, or check that it is on the PATH.",
+    }
+}
+
+/// Everything `output` printed, stdout then stderr, for error messages.
+pub(crate) fn printed(output: &Output) -> String {
+    let mut printed = String::from_utf8_lossy(&output.stdout).into_owned();
+    printed.push_str(&String::from_utf8_lossy(&output.stderr));
+    printed.trim().to_string()
+}
+
+/// A path in the temporary directory for a tool to dump flash to, removed
+/// when dropped.
+pub(crate) struct DumpFile(PathBuf);
+
+impl DumpFile {
+    pub(crate) fn new(tool: &str) -> Self {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+        let name = format!(
+            "tockloader-{tool}-{}-{}.bin",
+            std::process::id(),
+            COUNT.fetch_add(1, Ordering::Relaxed)
+        );
+        DumpFile(std::env::temp_dir().join(name))
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for DumpFile {
+    fn drop(&mut self) {
+        // The file is not there if the tool failed before writing it.
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
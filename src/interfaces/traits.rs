@@ -0,0 +1,269 @@
+use std::future::Future;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+use crate::board::BoardAttributes;
+use crate::bootloader::attribute::Attribute;
+use crate::bootloader::commands::COMMAND_INFO;
+use crate::bootloader::crc::tock_crc32;
+use crate::bootloader::info::{BootloaderInfo, BootloaderVersion};
+use crate::errors::TockloaderError;
+
+/// How long to wait for each part of a response from a bootloader, unless
+/// set otherwise.
+pub const DEFAULT_RESPONSE_TIMEOUT: Duration = Duration::from_millis(1000);
+
+/// Wait at most `duration` for `future` to complete, failing with
+/// `TockloaderError::Timeout` otherwise.
+// Only the serial backend talks to a bootloader so far.
+#[cfg_attr(not(feature = "serial"), allow(dead_code))]
+pub(crate) async fn timeout<F: Future>(
+    duration: Duration,
+    future: F,
+) -> Result<F::Output, TockloaderError> {
+    tokio::time::timeout(duration, future)
+        .await
+        .map_err(|_| TockloaderError::Timeout)
+}
+
+/// A connection to a board, which must be opened before anything else.
+pub trait BoardInterface {
+    /// Open the connection to the board.
+    fn open(&mut self) -> Result<(), TockloaderError>;
+}
+
+/// How many bytes [`BytesReader::read_range_to()`] reads before passing them
+/// on to the writer.
+pub const STREAM_CHUNK_LEN: usize = 4096;
+
+/// Read access to the flash of a board.
+#[async_trait]
+pub trait BytesReader {
+    /// Read `len` bytes of flash starting at address `start`.
+    async fn read_range(&mut self, start: usize, len: usize) -> Result<Vec<u8>, TockloaderError>;
+
+    /// Read `len` bytes of flash starting at address `start` into `writer`.
+    ///
+    /// Bytes are written as soon as each chunk arrives, so only one chunk is
+    /// held in memory however large the range is.
+    async fn read_range_to<W>(
+        &mut self,
+        start: usize,
+        len: usize,
+        writer: &mut W,
+    ) -> Result<(), TockloaderError>
+    where
+        W: AsyncWrite + Unpin + Send + ?Sized,
+    {
+        let mut offset = 0;
+        while offset < len {
+            let chunk_len = (len - offset).min(STREAM_CHUNK_LEN);
+            let chunk = self.read_range(start + offset, chunk_len).await?;
+            writer.write_all(&chunk).await?;
+            offset += chunk_len;
+        }
+        writer.flush().await?;
+        Ok(())
+    }
+
+    /// Compute the CRC of `len` bytes of flash starting at address `start`,
+    /// as [`tock_crc32()`] would. Unless the board can compute it itself, the
+    /// bytes are read.
+    async fn crc_range(&mut self, start: usize, len: usize) -> Result<u32, TockloaderError> {
+        let data = self.read_range(start, len).await?;
+        Ok(tock_crc32(&data))
+    }
+}
+
+/// Value of a flash byte that has been erased.
+pub const ERASED_BYTE: u8 = 0xFF;
+
+/// Check that a write of `data` at `address` covers exactly one page of
+/// `page_size` bytes, as [`FlashWriter::write_page()`] requires.
+pub(crate) fn check_page_write(
+    address: u32,
+    data: &[u8],
+    page_size: usize,
+) -> Result<(), TockloaderError> {
+    if !(address as usize).is_multiple_of(page_size) {
+        return Err(TockloaderError::UnalignedPage { address, page_size });
+    }
+    if data.len() != page_size {
+        return Err(TockloaderError::PartialPageWrite {
+            address,
+            length: data.len(),
+            page_size,
+        });
+    }
+    Ok(())
+}
+
+/// Write access to the flash of a board.
+///
+/// Flash can only be written a whole page at a time, so callers are expected
+/// to merge partial writes with the existing contents of the page themselves.
+#[async_trait]
+pub trait FlashWriter {
+    /// Size in bytes of a single flash page.
+    fn page_size(&self) -> usize;
+
+    /// Write one page of flash. The `address` must be page aligned and `data`
+    /// must be exactly one page long.
+    async fn write_page(&mut self, address: u32, data: &[u8]) -> Result<(), TockloaderError>;
+
+    /// Erase the page at `address`, which must be page aligned, so that it
+    /// reads as [`ERASED_BYTE`]. Unless the board can erase pages itself,
+    /// the page is written with erased bytes.
+    async fn erase_page(&mut self, address: u32) -> Result<(), TockloaderError> {
+        let erased = vec![ERASED_BYTE; self.page_size()];
+        self.write_page(address, &erased).await
+    }
+
+    /// Say that flash from `start` up to `end` is about to be written, so
+    /// that progress can be reported. Does nothing unless the board is
+    /// wrapped in a [`ProgressInterface`](crate::interfaces::progress::ProgressInterface).
+    async fn plan_writes(&mut self, _start: u32, _end: u32) {}
+}
+
+/// Number of attribute slots in the bootloader.
+pub const ATTRIBUTE_COUNT: u8 = 16;
+
+/// Commands understood by the Tock bootloader.
+///
+/// The bootloader parses its input with a small state machine, which can be
+/// left halfway through a command by a previous session or a failed one.
+/// Before the first command after entering the bootloader, and after any
+/// command that failed, the state machine is reset with a [`sync`]. Commands
+/// perform this sync themselves, so callers never need to.
+///
+/// [`sync`]: BootloaderInterface::sync
+#[async_trait]
+pub trait BootloaderInterface: Send {
+    /// Reset the command parser of the bootloader by sending
+    /// `COMMAND_RESET`. The bootloader does not respond to it.
+    async fn sync(&mut self) -> Result<(), TockloaderError>;
+
+    /// Whether a [`sync`](BootloaderInterface::sync) will be sent before the
+    /// next command.
+    fn needs_sync(&self) -> bool;
+
+    /// Check that the bootloader is responding.
+    async fn ping(&mut self) -> Result<(), TockloaderError>;
+
+    /// Read the attribute stored at `index` (below [`ATTRIBUTE_COUNT`]). Unused slots are
+    /// `None`.
+    async fn get_attribute(&mut self, index: u8) -> Result<Option<Attribute>, TockloaderError>;
+
+    /// Store `attribute` in the slot at `index` (below [`ATTRIBUTE_COUNT`]),
+    /// replacing whatever was there.
+    async fn set_attribute(
+        &mut self,
+        index: u8,
+        attribute: &Attribute,
+    ) -> Result<(), TockloaderError>;
+
+    /// Compute the CRC of `len` bytes of internal flash from `address`, with
+    /// the algorithm of [`tock_crc32()`].
+    async fn crc_internal_flash(&mut self, address: u32, len: u32) -> Result<u32, TockloaderError>;
+
+    /// Get the information string of the bootloader, which includes its
+    /// version.
+    async fn get_bootloader_info(&mut self) -> Result<String, TockloaderError>;
+
+    /// Set the address the bootloader jumps to when it is done, which is
+    /// where the kernel starts.
+    async fn set_start_address(&mut self, address: u32) -> Result<(), TockloaderError>;
+
+    /// Get the information of the bootloader, parsed from its information
+    /// string.
+    async fn get_info(&mut self) -> Result<BootloaderInfo, TockloaderError> {
+        BootloaderInfo::parse(&self.get_bootloader_info().await?)
+    }
+
+    /// Like [`get_info()`](BootloaderInterface::get_info), but `None` for
+    /// bootloaders which do not understand `COMMAND_INFO`.
+    async fn try_get_info(&mut self) -> Result<Option<BootloaderInfo>, TockloaderError> {
+        match self.get_info().await {
+            Ok(info) => Ok(Some(info)),
+            Err(TockloaderError::CommandRejected {
+                command: COMMAND_INFO,
+                ..
+            }) => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Get the version of the bootloader from its information string, or
+    /// else from the [`VERSION_ATTRIBUTE`](crate::bootloader::info::VERSION_ATTRIBUTE) attribute. Bootloaders which
+    /// report neither give `None`.
+    async fn get_bootloader_version(
+        &mut self,
+    ) -> Result<Option<BootloaderVersion>, TockloaderError> {
+        let info = self.try_get_info().await?;
+        let mut attributes = Vec::new();
+        if info
+            .as_ref()
+            .and_then(BootloaderInfo::parsed_version)
+            .is_none()
+        {
+            for index in 0..ATTRIBUTE_COUNT {
+                attributes.push(self.get_attribute(index).await?);
+            }
+        }
+        Ok(BootloaderVersion::find(info.as_ref(), &attributes))
+    }
+
+    /// Get the address the bootloader jumps to, from the `start_address` key
+    /// of its information string. Older bootloaders do not report it, which
+    /// gives `None`.
+    async fn get_start_address(&mut self) -> Result<Option<u32>, TockloaderError> {
+        Ok(self.get_info().await?.start_address)
+    }
+
+    /// Get the value of the attribute named `key`, searching every slot.
+    async fn find_attribute(&mut self, key: &str) -> Result<Option<String>, TockloaderError> {
+        for index in 0..ATTRIBUTE_COUNT {
+            if let Some(attribute) = self.get_attribute(index).await? {
+                if attribute.key == key {
+                    return Ok(Some(attribute.value));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Read the attributes in which the board describes itself: its name, its
+    /// architecture and where its apps start.
+    async fn detect_board(&mut self) -> Result<BoardAttributes, TockloaderError> {
+        let mut attributes = Vec::new();
+        for index in 0..ATTRIBUTE_COUNT {
+            attributes.push(self.get_attribute(index).await?);
+        }
+        Ok(BoardAttributes::from_attributes(&attributes))
+    }
+
+    /// Store `attribute` in the slot already holding its key, or else in the
+    /// first unused slot. Returns the index of the slot.
+    async fn store_attribute(&mut self, attribute: &Attribute) -> Result<u8, TockloaderError> {
+        let mut unused = None;
+        for index in 0..ATTRIBUTE_COUNT {
+            match self.get_attribute(index).await? {
+                Some(existing) if existing.key == attribute.key => {
+                    self.set_attribute(index, attribute).await?;
+                    return Ok(index);
+                }
+                Some(_) => {}
+                None => {
+                    unused.get_or_insert(index);
+                }
+            }
+        }
+        let index = unused.ok_or_else(|| TockloaderError::NoAttributeSlot {
+            key: attribute.key.clone(),
+        })?;
+        self.set_attribute(index, attribute).await?;
+        Ok(index)
+    }
+}
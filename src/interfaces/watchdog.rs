@@ -0,0 +1,115 @@
+use std::future::Future;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use tokio::time::Instant;
+
+use crate::errors::TockloaderError;
+use crate::interfaces::traits::{BytesReader, FlashWriter};
+
+/// Wraps a board so that a whole operation, such as an install, is aborted
+/// once it has run for longer than a deadline.
+///
+/// Each command already times out on its own, but a board that stopped
+/// responding halfway through would still fail every remaining page one
+/// timeout at a time. The deadline is shared by every command sent through
+/// the wrapper, so it bounds the whole operation instead.
+pub struct WatchdogInterface<B> {
+    board: B,
+    timeout: Duration,
+    deadline: Instant,
+    pages_written: usize,
+}
+
+impl<B> WatchdogInterface<B> {
+    /// Start the clock: the operation must be done within `timeout` from now.
+    pub fn new(board: B, timeout: Duration) -> Self {
+        WatchdogInterface {
+            board,
+            timeout,
+            deadline: Instant::now() + timeout,
+            pages_written: 0,
+        }
+    }
+
+    /// Number of pages written or erased so far.
+    pub fn pages_written(&self) -> usize {
+        self.pages_written
+    }
+
+    pub fn into_inner(self) -> B {
+        self.board
+    }
+
+    async fn guard<T>(
+        deadline: Instant,
+        timeout: Duration,
+        pages_written: usize,
+        operation: impl Future<Output = Result<T, TockloaderError>>,
+    ) -> Result<T, TockloaderError> {
+        tokio::time::timeout_at(deadline, operation)
+            .await
+            .map_err(|_| TockloaderError::OperationTimeout {
+                timeout,
+                pages_written,
+            })?
+    }
+}
+
+#[async_trait]
+impl<B: BytesReader + Send> BytesReader for WatchdogInterface<B> {
+    async fn read_range(&mut self, start: usize, len: usize) -> Result<Vec<u8>, TockloaderError> {
+        Self::guard(
+            self.deadline,
+            self.timeout,
+            self.pages_written,
+            self.board.read_range(start, len),
+        )
+        .await
+    }
+
+    async fn crc_range(&mut self, start: usize, len: usize) -> Result<u32, TockloaderError> {
+        Self::guard(
+            self.deadline,
+            self.timeout,
+            self.pages_written,
+            self.board.crc_range(start, len),
+        )
+        .await
+    }
+}
+
+#[async_trait]
+impl<B: FlashWriter + Send> FlashWriter for WatchdogInterface<B> {
+    fn page_size(&self) -> usize {
+        self.board.page_size()
+    }
+
+    async fn write_page(&mut self, address: u32, data: &[u8]) -> Result<(), TockloaderError> {
+        Self::guard(
+            self.deadline,
+            self.timeout,
+            self.pages_written,
+            self.board.write_page(address, data),
+        )
+        .await?;
+        self.pages_written += 1;
+        Ok(())
+    }
+
+    async fn erase_page(&mut self, address: u32) -> Result<(), TockloaderError> {
+        Self::guard(
+            self.deadline,
+            self.timeout,
+            self.pages_written,
+            self.board.erase_page(address),
+        )
+        .await?;
+        self.pages_written += 1;
+        Ok(())
+    }
+
+    async fn plan_writes(&mut self, start: u32, end: u32) {
+        self.board.plan_writes(start, end).await
+    }
+}
@@ -0,0 +1,63 @@
+//! Flashing the kernel, which sits in front of the app region.
+
+use crate::apps::{AppLocation, TbfIterator};
+use crate::errors::TockloaderError;
+use crate::install::write_bytes;
+use crate::interfaces::traits::{BytesReader, FlashWriter};
+
+/// Find the first app in the app region starting at `app_address` which a
+/// kernel of `kernel_len` bytes written at `kernel_address` would overwrite.
+pub async fn overlapping_app<B>(
+    board: &mut B,
+    kernel_address: u32,
+    kernel_len: usize,
+    app_address: u32,
+) -> Result<Option<AppLocation>, TockloaderError>
+where
+    B: BytesReader + Send + ?Sized,
+{
+    let kernel_end = kernel_address as u64 + kernel_len as u64;
+    let mut iterator = TbfIterator::new(app_address);
+    while let Some(app) = iterator.next(board).await? {
+        // Apps are in address order, so none after this one can overlap.
+        if app.address as u64 >= kernel_end {
+            break;
+        }
+        // Overwriting padding loses nothing.
+        if app.header.is_app() && app.address as u64 + app.total_size as u64 > kernel_address as u64
+        {
+            return Ok(Some(app));
+        }
+    }
+    Ok(None)
+}
+
+/// Write the kernel binary `kernel` at `address`.
+///
+/// Unless `force` is set, nothing is written if the kernel would overwrite
+/// one of the apps in the app region starting at `app_address`.
+pub async fn flash_kernel<B>(
+    board: &mut B,
+    address: u32,
+    kernel: &[u8],
+    app_address: u32,
+    force: bool,
+) -> Result<(), TockloaderError>
+where
+    B: BytesReader + FlashWriter + Send,
+{
+    if !force {
+        if let Some(app) = overlapping_app(board, address, kernel.len(), app_address).await? {
+            return Err(TockloaderError::KernelOverlapsApp {
+                name: app
+                    .header
+                    .get_package_name()
+                    .unwrap_or("(none)")
+                    .to_string(),
+                address: app.address,
+                kernel_end: address + kernel.len() as u32,
+            });
+        }
+    }
+    write_bytes(board, address, kernel).await
+}
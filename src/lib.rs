@@ -0,0 +1,15 @@
+pub mod apps;
+pub mod benchmark;
+pub mod board;
+pub mod bootloader;
+pub mod doctor;
+pub mod errors;
+pub mod hexdump;
+pub mod hooks;
+pub mod inspect;
+pub mod install;
+pub mod interfaces;
+pub mod kernel;
+pub mod style;
+pub mod tab;
+pub mod verify;
@@ -1,21 +1,715 @@
 mod cli;
+use std::fs::File;
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "jlink")]
+use clap::parser::ValueSource;
+use clap::ArgMatches;
 use cli::make_cli;
+use serde_json::json;
+use tbf_parser::parse::SUPPORTED_VERSIONS;
+use tbf_parser::types::{TbfFooterV2CredentialsType, TbfHeader};
+use tockloader::apps::{AppInfo, AppLocation, AppReader};
+use tockloader::benchmark::{benchmark, OperationStats};
+use tockloader::board::{
+    bootloader_entry_instructions, find_board, BoardAttributes, BoardSettings, DEFAULT_PAGE_SIZE,
+    KNOWN_ARCHS,
+};
+use tockloader::bootloader::attribute::{parse_assignment, read_attribute_file, Attribute};
+use tockloader::bootloader::flash::{
+    read_attributes as read_flash_attributes, read_version as read_bootloader_version,
+};
+use tockloader::bootloader::info::{BootloaderInfo, BootloaderVersion};
+use tockloader::doctor::{check_ping, check_port_access, check_ports, check_tools, Check};
+use tockloader::errors::TockloaderError;
+use tockloader::hexdump::HexDump;
+use tockloader::hooks::{run_after, run_before};
+use tockloader::inspect::{
+    hash_tbf, header_fields, parse_header, to_hex, HashReport, Integrity, StoredHash,
+    StoredSignature,
+};
+use tockloader::install::{
+    bundle_apps, check_slot_size, erase_apps, install_apps, replace_apps, uninstall_apps, AppImage,
+};
+use tockloader::interfaces::compiled_backends;
+use tockloader::interfaces::dry_run::DryRunInterface;
+use tockloader::interfaces::flash_file::FlashFileInterface;
+#[cfg(feature = "jlink")]
+use tockloader::interfaces::jlink::JLinkInterface;
+use tockloader::interfaces::keep_alive::{KeepAliveInterface, KEEP_ALIVE_INTERVAL};
+#[cfg(feature = "openocd")]
+use tockloader::interfaces::openocd::OpenOcdInterface;
+use tockloader::interfaces::progress::ProgressInterface;
+use tockloader::interfaces::serial::console_protocol::{PacketProtocol, ProtocolKind, RawProtocol};
+use tockloader::interfaces::serial::virtual_terminal::{
+    ConsoleOutput, CtrlC, InvalidUtf8, LineTimestamps, LocalEcho, Substitution, TerminalLog,
+};
+use tockloader::interfaces::serial::{
+    available_ports, describe_port, prompt_for_port, BootloaderEntry, EntryTimeouts,
+    SerialInterface,
+};
+use tockloader::interfaces::traits::{
+    BoardInterface, BootloaderInterface, BytesReader, FlashWriter, ATTRIBUTE_COUNT,
+};
+use tockloader::interfaces::watchdog::WatchdogInterface;
+use tockloader::kernel::flash_kernel;
+use tockloader::style::{OutputFormat, Style};
+use tockloader::tab::{find_tabs, resolve_arch, Tab, TabMetadata};
+use tockloader::verify::{verify_region, TbfCheck};
+
+#[tokio::main]
+async fn main() {
+    env_logger::init();
 
-fn main() {
     let matches = make_cli().get_matches();
 
+    if let Err(error) = run(&matches).await {
+        if output_format(&matches) == OutputFormat::Json {
+            eprintln!(
+                "{}",
+                json!({ "error": error.to_string(), "kind": error.kind() })
+            );
+        } else {
+            eprintln!("{error}");
+        }
+        std::process::exit(error.exit_code());
+    }
+}
+
+/// Run the subcommand, along with its `--before` and `--after` commands if it
+/// has them.
+async fn run(matches: &ArgMatches) -> Result<(), TockloaderError> {
+    let hook = |id| {
+        matches
+            .subcommand()
+            .and_then(|(_, sub_matches)| sub_matches.try_get_one::<String>(id).ok().flatten())
+    };
+    if let Some(before) = hook("before") {
+        run_before(before).await?;
+    }
+    let result = run_subcommand(matches).await;
+    if let Some(after) = hook("after") {
+        let after_result = run_after(after, &result).await;
+        // A failure of the operation itself matters more.
+        if result.is_ok() {
+            return after_result;
+        }
+        if let Err(error) = after_result {
+            eprintln!("{error}");
+        }
+    }
+    result
+}
+
+/// The format picked by `--format`, with `--json` standing for `--format json`.
+fn output_format(matches: &ArgMatches) -> OutputFormat {
+    if matches.get_flag("json") {
+        OutputFormat::Json
+    } else {
+        *matches.get_one::<OutputFormat>("format").unwrap()
+    }
+}
+
+async fn run_subcommand(matches: &ArgMatches) -> Result<(), TockloaderError> {
     if matches.get_flag("debug") {
         println!("Debug mode enabled");
     }
 
+    let style = Style::detect(matches.get_flag("no-color"));
+    let format = output_format(matches);
+
     match matches.subcommand() {
         Some(("listen", sub_matches)) => {
-            println!("Got the listen subcommand");
-            let default_adr = "NONE".to_string();
-            let adr = sub_matches
-                .get_one::<String>("app-address")
-                .unwrap_or(&default_adr);
-            println!("With App Address {adr}");
+            let interface = open_interface(sub_matches)?;
+            run_terminal(sub_matches, interface).await?;
+        }
+        Some(("reset", sub_matches)) => {
+            #[cfg(feature = "jlink")]
+            if sub_matches.get_flag("jlink") {
+                return build_jlink_interface(sub_matches)?.reset();
+            }
+            #[cfg(feature = "openocd")]
+            if sub_matches.get_flag("openocd") {
+                return build_openocd_interface(sub_matches)?.reset();
+            }
+            let mut interface = build_interface(sub_matches).await?;
+            interface.exit_bootloader().await?;
+            if sub_matches.get_flag("listen") {
+                run_terminal(sub_matches, interface).await?;
+            }
+        }
+        Some(("install", sub_matches)) => {
+            if let Some(mut board) = build_flash_file_interface(sub_matches)? {
+                let settings = flash_file_settings(sub_matches, &mut board).await?;
+                let (names, apps) = load_apps(sub_matches, &settings)?;
+                let alignment =
+                    install_alignment(sub_matches, &apps, settings.app_address, board.page_size())?;
+                let installed = if matches.get_flag("dry-run") {
+                    let mut board = DryRunInterface::new(board);
+                    let installed = place_apps(
+                        &mut board,
+                        sub_matches,
+                        settings.app_address,
+                        names,
+                        &apps,
+                        alignment,
+                        settings.app_region_end,
+                    )
+                    .await?;
+                    board.print_plan();
+                    installed
+                } else {
+                    place_apps(
+                        &mut board,
+                        sub_matches,
+                        settings.app_address,
+                        names,
+                        &apps,
+                        alignment,
+                        settings.app_region_end,
+                    )
+                    .await?
+                };
+                for (name, address) in installed {
+                    println!("Installed {name} at {address:#x}");
+                }
+                return Ok(());
+            }
+
+            let mut board = build_interface(sub_matches).await?;
+            let settings = board_settings(sub_matches, &mut board).await?;
+            let app_address = settings.app_address;
+            let region_end = settings.app_region_end;
+            let (names, apps) = load_apps(sub_matches, &settings)?;
+            let alignment = install_alignment(sub_matches, &apps, app_address, board.page_size())?;
+
+            if !sub_matches.get_flag("no-fast") {
+                let fast_baud = *sub_matches.get_one::<u32>("fast-baud").unwrap();
+                if !board.use_fast_baud_rate(fast_baud).await? {
+                    println!(
+                        "The bootloader can not switch to {fast_baud} baud, flashing at {} baud",
+                        board.baud_rate()
+                    );
+                }
+            }
+            let op_timeout = *sub_matches.get_one::<u64>("op-timeout").unwrap();
+            let board = KeepAliveInterface::new(board, keep_alive_interval(sub_matches));
+            let board = WatchdogInterface::new(board, Duration::from_secs(op_timeout));
+            // Checked before anything is written, so that a bad address does
+            // not stop the command half way.
+            let app_address_attribute = sub_matches
+                .get_flag("update-app-address")
+                .then(|| Attribute::new("appaddr", &format!("{app_address:#x}")))
+                .transpose()?;
+            // The board is kept to store the attribute once the apps are in,
+            // and to restore its baud rate.
+            let (installed, mut board) = if matches.get_flag("dry-run") {
+                let mut board = DryRunInterface::new(board);
+                let installed = place_apps(
+                    &mut board,
+                    sub_matches,
+                    app_address,
+                    names,
+                    &apps,
+                    alignment,
+                    region_end,
+                )
+                .await?;
+                board.print_plan();
+                let mut board = board.into_inner().into_inner().into_inner().await;
+                board.restore_baud_rate().await?;
+                (installed, None)
+            } else {
+                let show_progress = matches.get_flag("debug") || std::io::stdout().is_terminal();
+                let mut board = ProgressInterface::new(board, |written, total| {
+                    if show_progress {
+                        print_progress(written, total);
+                    }
+                });
+                let installed = place_apps(
+                    &mut board,
+                    sub_matches,
+                    app_address,
+                    names,
+                    &apps,
+                    alignment,
+                    region_end,
+                )
+                .await?;
+                board.finish();
+                let board = board.into_inner();
+                (installed, Some(board.into_inner().into_inner().await))
+            };
+            for (name, address) in installed {
+                println!("Installed {name} at {address:#x}");
+            }
+            match (&app_address_attribute, &mut board) {
+                (Some(attribute), Some(board)) => {
+                    let index = store_and_verify(board, attribute).await?;
+                    println!(
+                        "[{index:02}] {}: {} ({})",
+                        attribute.key,
+                        attribute.value,
+                        style.good("verified")
+                    );
+                }
+                (Some(attribute), None) => {
+                    println!("Would store {}={}", attribute.key, attribute.value)
+                }
+                (None, _) => {}
+            }
+            if let Some(board) = &mut board {
+                board.restore_baud_rate().await?;
+            }
+        }
+        Some(("erase-apps", sub_matches)) => {
+            let mut board = build_interface(sub_matches).await?;
+            let settings = board_settings(sub_matches, &mut board).await?;
+            let app_address = settings.app_address;
+            let force = sub_matches.get_flag("force");
+            let alignment = Some(board.page_size() as u32);
+            let apps = board.list_apps(app_address).await?;
+            let mut board = KeepAliveInterface::new(board, keep_alive_interval(sub_matches));
+            let dry_run = matches.get_flag("dry-run");
+            let kept = if dry_run {
+                let mut board = DryRunInterface::new(board);
+                let kept = erase_apps(
+                    &mut board,
+                    app_address,
+                    alignment,
+                    settings.app_region_end,
+                    force,
+                )
+                .await?;
+                board.print_plan();
+                kept
+            } else {
+                erase_apps(
+                    &mut board,
+                    app_address,
+                    alignment,
+                    settings.app_region_end,
+                    force,
+                )
+                .await?
+            };
+            let erased = apps.len() - kept.len();
+            if dry_run {
+                println!("Would erase {erased} apps");
+            } else {
+                println!("Erased {erased} apps");
+            }
+            for app in &kept {
+                println!(
+                    "Kept sticky app {}, use --force to erase it too",
+                    app.header().get_package_name().unwrap_or("(unnamed)")
+                );
+            }
+        }
+        Some(("uninstall", sub_matches)) => {
+            let name = sub_matches.get_one::<String>("NAME").unwrap();
+            let first_only = sub_matches.get_flag("first");
+            let force = sub_matches.get_flag("force");
+            let dry_run = matches.get_flag("dry-run");
+            let (removed, kept) = match build_flash_file_interface(sub_matches)? {
+                Some(mut board) => {
+                    let settings = flash_file_settings(sub_matches, &mut board).await?;
+                    uninstall(
+                        board,
+                        settings.app_address,
+                        name,
+                        first_only,
+                        force,
+                        dry_run,
+                    )
+                    .await?
+                }
+                None => {
+                    let mut board = build_interface(sub_matches).await?;
+                    let settings = board_settings(sub_matches, &mut board).await?;
+                    let board = KeepAliveInterface::new(board, keep_alive_interval(sub_matches));
+                    uninstall(
+                        board,
+                        settings.app_address,
+                        name,
+                        first_only,
+                        force,
+                        dry_run,
+                    )
+                    .await?
+                }
+            };
+            for app in &removed {
+                println!("Removed {name} at {:#x}", app.address);
+            }
+            for app in &kept {
+                println!(
+                    "Kept sticky app {name} at {:#x}, use --force to remove it too",
+                    app.address
+                );
+            }
+        }
+        Some(("list-ports", _)) => {
+            let ports = available_ports()?;
+            if ports.is_empty() {
+                println!("No serial ports found");
+            }
+            for port in &ports {
+                println!("{}", describe_port(port));
+            }
+            // Only serial ports are enumerated, finding J-Link probes is not
+            // implemented.
+            if cfg!(feature = "jlink") {
+                println!("jlink: listing probes is not supported yet");
+            }
+        }
+        Some(("flash-kernel", sub_matches)) => {
+            let kernel = std::fs::read(sub_matches.get_one::<String>("FILE").unwrap())?;
+            let address = *sub_matches.get_one::<u32>("address").unwrap();
+
+            let mut board = build_interface(sub_matches).await?;
+            let settings = board_settings(sub_matches, &mut board).await?;
+            let force = sub_matches.get_flag("force");
+            let mut board = KeepAliveInterface::new(board, keep_alive_interval(sub_matches));
+            if matches.get_flag("dry-run") {
+                let mut board = DryRunInterface::new(board);
+                flash_kernel(&mut board, address, &kernel, settings.app_address, force).await?;
+                board.print_plan();
+            } else {
+                flash_kernel(&mut board, address, &kernel, settings.app_address, force).await?;
+                println!("Flashed {} bytes of kernel at {address:#x}", kernel.len());
+            }
+        }
+        Some(("info", sub_matches)) => {
+            #[cfg(feature = "jlink")]
+            if sub_matches.get_flag("jlink") {
+                let mut board = build_jlink_interface(sub_matches)?;
+                return print_info_from_flash(sub_matches, &mut board, style, format).await;
+            }
+            #[cfg(feature = "openocd")]
+            if sub_matches.get_flag("openocd") {
+                let mut board = build_openocd_interface(sub_matches)?;
+                return print_info_from_flash(sub_matches, &mut board, style, format).await;
+            }
+            if let Some(mut board) = build_flash_file_interface(sub_matches)? {
+                return print_info_from_flash(sub_matches, &mut board, style, format).await;
+            }
+
+            let mut board = build_interface(sub_matches).await?;
+            let settings = board_settings(sub_matches, &mut board).await?;
+
+            let mut attributes = Vec::new();
+            for index in 0..ATTRIBUTE_COUNT {
+                attributes.push(board.get_attribute(index).await?);
+            }
+            // Only printed with the rest of the board information.
+            let bootloader = if sub_matches.get_flag("attributes-only") {
+                None
+            } else {
+                board.try_get_info().await?
+            };
+            print_info(
+                sub_matches,
+                &mut board,
+                &settings,
+                bootloader.as_ref(),
+                &attributes,
+                style,
+                format,
+            )
+            .await?;
+        }
+        Some(("set-attributes", sub_matches)) => {
+            // Check every attribute before writing any of them.
+            let mut attributes = Vec::new();
+            if let Some(path) = sub_matches.get_one::<String>("file") {
+                attributes.extend(read_attribute_file(Path::new(path))?);
+            }
+            for assignment in sub_matches
+                .get_many::<String>("ATTRIBUTES")
+                .into_iter()
+                .flatten()
+            {
+                attributes.push(parse_assignment(assignment)?);
+            }
+
+            let mut board = build_interface(sub_matches).await?;
+            let mut failed = 0;
+            for attribute in &attributes {
+                match store_and_verify(&mut board, attribute).await {
+                    Ok(index) => println!(
+                        "[{index:02}] {}: {} ({})",
+                        attribute.key,
+                        attribute.value,
+                        style.good("verified")
+                    ),
+                    Err(error) => {
+                        failed += 1;
+                        println!("{}: {}, {error}", attribute.key, style.bad("FAILED"));
+                    }
+                }
+            }
+            if failed > 0 {
+                return Err(TockloaderError::AttributesNotSet {
+                    failed,
+                    total: attributes.len(),
+                });
+            }
+        }
+        Some(("get-start-address", sub_matches)) => {
+            let mut board = build_interface(sub_matches).await?;
+            match board.get_start_address().await? {
+                Some(address) => println!("Start address: {address:#x}"),
+                None => println!("The bootloader does not report its start address"),
+            }
+        }
+        Some(("set-start-address", sub_matches)) => {
+            let address = *sub_matches.get_one::<u32>("ADDRESS").unwrap();
+            let mut board = build_interface(sub_matches).await?;
+            let settings = board_settings(sub_matches, &mut board).await?;
+            settings.check_start_address(address)?;
+
+            board.set_start_address(address).await?;
+            match board.get_start_address().await? {
+                Some(reported) if reported != address => {
+                    return Err(TockloaderError::StartAddressNotSet {
+                        requested: address,
+                        reported,
+                    })
+                }
+                Some(_) => println!("Start address set to {address:#x} (verified)"),
+                None => println!(
+                    "Start address set to {address:#x}, the bootloader does not report it back"
+                ),
+            }
+        }
+        Some(("list", sub_matches)) => {
+            let min_version = sub_matches.get_one::<u32>("min-binary-version");
+            let fixed_only = sub_matches.get_flag("fixed-only");
+            let pic_only = sub_matches.get_flag("pic-only");
+            let verbose = sub_matches.get_flag("verbose");
+            let verify = sub_matches.get_flag("verify");
+
+            // Checking the credentials reads the apps in full, so it is only
+            // done when asked for, or for the details.
+            let credentials = verify || (verbose && format == OutputFormat::Text);
+            let apps = list_board_apps(sub_matches, credentials).await?;
+            let mut listed = Vec::new();
+            for (index, (app, report)) in apps.iter().enumerate() {
+                // Apps without a version can not be compared, so they are
+                // left out as soon as a minimum is set.
+                if let Some(min_version) = min_version {
+                    if app
+                        .binary_version()
+                        .is_none_or(|version| version < *min_version)
+                    {
+                        continue;
+                    }
+                }
+                let position_independent = is_position_independent(&app.header);
+                if (fixed_only && position_independent) || (pic_only && !position_independent) {
+                    continue;
+                }
+                match format {
+                    OutputFormat::Text => {
+                        print_app(index, app, style);
+                        if let Some(report) = report.as_ref().filter(|_| verify) {
+                            print_integrity(report, style);
+                        }
+                        if verbose {
+                            print_app_details(app, report.as_ref(), style);
+                        }
+                    }
+                    OutputFormat::Json => listed.push(AppInfo {
+                        integrity: report.as_ref().map(integrity),
+                        ..AppInfo::from(app)
+                    }),
+                }
+            }
+            if format == OutputFormat::Json {
+                println!("{:#}", json!(listed));
+            }
+        }
+        Some(("inspect", sub_matches)) => {
+            let path = PathBuf::from(sub_matches.get_one::<String>("FILE").unwrap());
+            let hash = sub_matches.get_flag("hash");
+            let print = |tbf: &[u8]| {
+                if sub_matches.get_flag("raw") {
+                    print_header_fields(tbf, style)
+                } else {
+                    print_tbf(tbf, hash, style)
+                }
+            };
+            if path.extension().is_some_and(|extension| extension == "tab") {
+                let tab = Tab::open(&path)?;
+                if let Some(metadata) = tab.metadata() {
+                    print_tab_metadata(metadata);
+                }
+                for arch in tab.architectures() {
+                    println!("\n[{arch}]");
+                    print(&tab.extract_binary(&arch)?)?;
+                }
+            } else {
+                print(&std::fs::read(&path)?)?;
+            }
+        }
+        Some(("tab-extract", sub_matches)) => {
+            let tab = Tab::open(Path::new(sub_matches.get_one::<String>("TAB").unwrap()))?;
+            match sub_matches.get_one::<String>("arch") {
+                Some(arch) => {
+                    let tbf = tab.extract_binary(arch)?;
+                    // Refuse to write out something no board would take.
+                    AppImage::from_tbf(&tbf)?;
+                    let output = sub_matches.get_one::<String>("output").unwrap();
+                    std::fs::write(output, &tbf)?;
+                    println!("Wrote the {arch} TBF ({} bytes) to {output}", tbf.len());
+                }
+                None => {
+                    for arch in tab.architectures() {
+                        println!("{arch}");
+                    }
+                }
+            }
+        }
+        Some(("read", sub_matches)) => {
+            let address = *sub_matches.get_one::<u32>("ADDRESS").unwrap();
+            let length = *sub_matches.get_one::<u32>("LENGTH").unwrap();
+            // Checked before connecting, which may take a while.
+            if length == 0 || address.checked_add(length - 1).is_none() {
+                return Err(TockloaderError::InvalidReadRange { address, length });
+            }
+            let (address, length) = (address as usize, length as usize);
+
+            #[cfg(feature = "jlink")]
+            if sub_matches.get_flag("jlink") {
+                let mut board = build_jlink_interface(sub_matches)?;
+                return read_to_output(sub_matches, &mut board, address, length).await;
+            }
+            #[cfg(feature = "openocd")]
+            if sub_matches.get_flag("openocd") {
+                let mut board = build_openocd_interface(sub_matches)?;
+                return read_to_output(sub_matches, &mut board, address, length).await;
+            }
+            match build_flash_file_interface(sub_matches)? {
+                Some(mut board) => read_to_output(sub_matches, &mut board, address, length).await?,
+                None => {
+                    let mut board = build_interface(sub_matches).await?;
+                    read_to_output(sub_matches, &mut board, address, length).await?
+                }
+            }
+        }
+        Some(("benchmark", sub_matches)) => {
+            let scratch_address = *sub_matches.get_one::<u32>("scratch-address").unwrap();
+            let size = *sub_matches.get_one::<u32>("size").unwrap() as usize;
+
+            let mut board = build_interface(sub_matches).await?;
+            board_settings(sub_matches, &mut board).await?;
+            let report = benchmark(&mut board, scratch_address, size).await?;
+            print_stats("write_page", &report.writes);
+            print_stats("read_range", &report.reads);
+        }
+        Some(("doctor", sub_matches)) => {
+            let baud_rate = *sub_matches.get_one::<u32>("baud-rate").unwrap();
+            let mut checks = Vec::new();
+            let mut report = |check: Check| {
+                print_doctor_check(&check, style);
+                checks.push(check);
+            };
+
+            let (ports_check, ports) = check_ports();
+            report(ports_check);
+            for port in &ports {
+                println!("       {}", describe_port(port));
+            }
+            let candidates = match sub_matches.get_one::<String>("port") {
+                Some(port) => vec![port.clone()],
+                None => ports.into_iter().map(|port| port.port_name).collect(),
+            };
+            let mut openable = None;
+            for path in candidates {
+                let check = check_port_access(&path, baud_rate);
+                if check.passed && openable.is_none() {
+                    openable = Some(path);
+                }
+                report(check);
+            }
+
+            for check in check_tools() {
+                report(check);
+            }
+
+            match openable {
+                Some(port) => {
+                    let mut board = SerialInterface::new(Some(port.clone()), baud_rate);
+                    board.set_retries(*sub_matches.get_one::<u32>("retries").unwrap());
+                    let check = match board.open() {
+                        Ok(()) => check_ping(&mut board, &port).await,
+                        Err(error) => {
+                            Check::fail(format!("Bootloader on {port}"), error.to_string())
+                        }
+                    };
+                    report(check);
+                }
+                None => report(Check::fail(
+                    "Bootloader",
+                    "No serial port could be opened to ping the board",
+                )),
+            }
+
+            let failed = checks.iter().filter(|check| !check.passed).count();
+            if failed > 0 {
+                return Err(TockloaderError::DoctorChecksFailed {
+                    failed,
+                    total: checks.len(),
+                });
+            }
+            println!("{}", style.good("Everything looks fine"));
+        }
+        Some(("verify-region", sub_matches)) => {
+            let mut board = build_interface(sub_matches).await?;
+            let settings = board_settings(sub_matches, &mut board).await?;
+            let checks =
+                verify_region(&mut board, settings.app_address, settings.app_region_end).await?;
+
+            println!(
+                "{:<12}{:>10}  {:<16}{:<10}{:<6}Credentials",
+                "Address", "Size", "Name", "Header", "Fits"
+            );
+            for check in &checks {
+                print_check(check, style);
+            }
+
+            let failed = checks.iter().filter(|check| !check.passed()).count();
+            let summary = format!("{} TBFs checked, {failed} failed", checks.len());
+            if failed > 0 {
+                println!("{}", style.bad(&summary));
+            } else {
+                println!("{}", style.good(&summary));
+            }
+            if failed > 0 {
+                return Err(TockloaderError::RegionCheckFailed {
+                    failed,
+                    checked: checks.len(),
+                });
+            }
+        }
+        Some(("version", sub_matches)) => {
+            println!("tockloader {}", env!("CARGO_PKG_VERSION"));
+            if sub_matches.get_flag("verbose") {
+                let tbf_versions: Vec<String> = SUPPORTED_VERSIONS
+                    .iter()
+                    .map(|version| format!("v{version}"))
+                    .collect();
+                println!("TBF header versions: {}", tbf_versions.join(", "));
+                println!("Bootloader protocol: Tock serial bootloader");
+                println!("Backends: {}", compiled_backends().join(", "));
+                println!(
+                    "Git commit: {}",
+                    option_env!("TOCKLOADER_GIT_COMMIT").unwrap_or("unknown")
+                );
+            }
         }
         // If only the "--debug" flag is set, then this branch is executed
         // Or, more likely at this stage, a subcommand hasn't been implemented yet.
@@ -24,4 +718,1065 @@ fn main() {
             _ = make_cli().print_help();
         }
     }
+
+    Ok(())
+}
+
+/// Print what `info` found on the board: its settings, the bootloader and
+/// its attributes, then the apps.
+async fn print_info<B: AppReader>(
+    sub_matches: &ArgMatches,
+    board: &mut B,
+    settings: &BoardSettings,
+    bootloader: Option<&BootloaderInfo>,
+    attributes: &[Option<Attribute>],
+    style: Style,
+    format: OutputFormat,
+) -> Result<(), TockloaderError> {
+    if format == OutputFormat::Json {
+        return print_info_json(sub_matches, board, settings, bootloader, attributes).await;
+    }
+    if !sub_matches.get_flag("attributes-only") {
+        match BootloaderVersion::find(bootloader, attributes) {
+            Some(version) => println!("Bootloader version: {version}"),
+            None => println!("Bootloader version: unknown"),
+        }
+        println!(
+            "Board: {}",
+            settings.board.as_deref().unwrap_or("(unknown)")
+        );
+        println!(
+            "Architecture: {}",
+            settings.arch.as_deref().unwrap_or("(unknown)")
+        );
+        println!("Page size: {} bytes", settings.page_size);
+        match bootloader {
+            Some(bootloader) => println!("Bootloader: {bootloader}"),
+            None => println!("Bootloader: (none found)"),
+        }
+        println!();
+    }
+
+    println!("Attributes:");
+    for (index, attribute) in attributes.iter().enumerate() {
+        match attribute {
+            Some(attribute) => {
+                println!("  [{index:02}] {}: {}", attribute.key, attribute.value)
+            }
+            None => println!("  [{index:02}] (empty)"),
+        }
+    }
+    if sub_matches.get_flag("attributes-only") {
+        return Ok(());
+    }
+
+    let app_address = settings.app_address;
+    let apps = board.list_apps(app_address).await?;
+    let apps_end = board.apps_end(app_address).await?;
+    println!();
+    match settings.app_region_end {
+        Some(end) => println!(
+            "App region: {app_address:#x}-{end:#x}, apps end at {apps_end:#x} ({} bytes free)",
+            end.saturating_sub(apps_end)
+        ),
+        None => println!(
+            "App region starts at {app_address:#x} (end unknown), apps end at {apps_end:#x}"
+        ),
+    }
+    for (index, app) in apps.iter().enumerate() {
+        print_app(index, app, style);
+    }
+    println!(
+        "{} apps using {} bytes of flash",
+        apps.len(),
+        apps_end - app_address
+    );
+    Ok(())
+}
+
+/// Open a terminal to the board on `interface`, as set up by the terminal
+/// arguments.
+async fn run_terminal(
+    sub_matches: &ArgMatches,
+    mut interface: SerialInterface,
+) -> Result<(), TockloaderError> {
+    let log = match sub_matches.get_one::<String>("log") {
+        Some(path) => Some(TerminalLog::new(
+            File::options().create(true).append(true).open(path)?,
+            sub_matches.get_flag("strip-ansi"),
+        )),
+        None => None,
+    };
+    let local_echo = *sub_matches.get_one::<LocalEcho>("local-echo").unwrap();
+    let ctrl_c = *sub_matches.get_one::<CtrlC>("ctrl-c").unwrap();
+    let timestamps = sub_matches
+        .get_flag("timestamp")
+        .then(|| LineTimestamps::new(Instant::now()));
+    interface.set_console_output(if sub_matches.get_flag("raw") {
+        ConsoleOutput::Raw
+    } else {
+        ConsoleOutput::Text(
+            sub_matches
+                .get_many::<Substitution>("substitute")
+                .map(|substitutions| substitutions.copied().collect())
+                .unwrap_or_default(),
+        )
+    });
+    if sub_matches.get_flag("strict-utf8") {
+        interface.set_invalid_utf8(InvalidUtf8::Fail);
+    }
+    match *sub_matches.get_one::<ProtocolKind>("protocol").unwrap() {
+        ProtocolKind::Raw => {
+            interface
+                .run_terminal(log, timestamps, local_echo, ctrl_c, RawProtocol)
+                .await
+        }
+        ProtocolKind::Packet => {
+            interface
+                .run_terminal(
+                    log,
+                    timestamps,
+                    local_echo,
+                    ctrl_c,
+                    PacketProtocol::default(),
+                )
+                .await
+        }
+    }
+}
+
+/// The credentials of a listed app, if they were checked, or why they could
+/// not be.
+type CheckedCredentials = Option<Result<HashReport, TockloaderError>>;
+
+/// List the apps on the board selected by the interface arguments, checking
+/// their credentials if `credentials` is set. Boards whose flash is read
+/// directly take the app address from the attributes in their flash, unless
+/// it is given.
+async fn list_board_apps(
+    sub_matches: &ArgMatches,
+    credentials: bool,
+) -> Result<Vec<(AppLocation, CheckedCredentials)>, TockloaderError> {
+    #[cfg(feature = "jlink")]
+    if sub_matches.get_flag("jlink") {
+        let mut board = build_jlink_interface(sub_matches)?;
+        let attributes = read_flash_attributes(&mut board).await?;
+        let settings = settings_from_flash(sub_matches, &attributes)?;
+        return list_apps_with_credentials(&mut board, settings.app_address, credentials).await;
+    }
+    #[cfg(feature = "openocd")]
+    if sub_matches.get_flag("openocd") {
+        let mut board = build_openocd_interface(sub_matches)?;
+        let attributes = read_flash_attributes(&mut board).await?;
+        let settings = settings_from_flash(sub_matches, &attributes)?;
+        return list_apps_with_credentials(&mut board, settings.app_address, credentials).await;
+    }
+    if let Some(mut board) = build_flash_file_interface(sub_matches)? {
+        let settings = flash_file_settings(sub_matches, &mut board).await?;
+        return list_apps_with_credentials(&mut board, settings.app_address, credentials).await;
+    }
+    let mut board = build_interface(sub_matches).await?;
+    let settings = board_settings(sub_matches, &mut board).await?;
+    list_apps_with_credentials(&mut board, settings.app_address, credentials).await
+}
+
+/// List the apps from `app_address` and, if `credentials` is set, read each
+/// of them to check the credentials in its footers.
+async fn list_apps_with_credentials<B: AppReader>(
+    board: &mut B,
+    app_address: u32,
+    credentials: bool,
+) -> Result<Vec<(AppLocation, CheckedCredentials)>, TockloaderError> {
+    let mut listed = Vec::new();
+    for app in board.list_apps(app_address).await? {
+        let report = if credentials {
+            let tbf = board
+                .read_range(app.address as usize, app.total_size as usize)
+                .await?;
+            Some(hash_tbf(&tbf))
+        } else {
+            None
+        };
+        listed.push((app, report));
+    }
+    Ok(listed)
+}
+
+/// Print what `info` found as one JSON object. Unused attribute slots are
+/// `null`, and only the attributes are given with `--attributes-only`.
+async fn print_info_json<B: AppReader>(
+    sub_matches: &ArgMatches,
+    board: &mut B,
+    settings: &BoardSettings,
+    bootloader: Option<&BootloaderInfo>,
+    attributes: &[Option<Attribute>],
+) -> Result<(), TockloaderError> {
+    if sub_matches.get_flag("attributes-only") {
+        println!("{:#}", json!({ "attributes": attributes }));
+        return Ok(());
+    }
+    let app_address = settings.app_address;
+    let apps = board.list_apps(app_address).await?;
+    let apps_end = board.apps_end(app_address).await?;
+    let info = json!({
+        "board": settings.board,
+        "arch": settings.arch,
+        "page_size": settings.page_size,
+        "bootloader": bootloader,
+        "bootloader_version": BootloaderVersion::find(bootloader, attributes),
+        "attributes": attributes,
+        "app_region": { "start": app_address, "end": settings.app_region_end },
+        "apps_end": apps_end,
+        "apps": apps.iter().map(AppInfo::from).collect::<Vec<_>>(),
+    });
+    println!("{info:#}");
+    Ok(())
+}
+
+/// Print what `info` finds on a board whose flash is read directly, where the
+/// bootloader keeps its attributes and version, rather than through the
+/// bootloader.
+async fn print_info_from_flash<B: AppReader>(
+    sub_matches: &ArgMatches,
+    board: &mut B,
+    style: Style,
+    format: OutputFormat,
+) -> Result<(), TockloaderError> {
+    let attributes = read_flash_attributes(board).await?;
+    let settings = settings_from_flash(sub_matches, &attributes)?;
+    // Only the version is kept in flash.
+    let bootloader = read_bootloader_version(board)
+        .await?
+        .map(|version| BootloaderInfo {
+            version: Some(version),
+            ..Default::default()
+        });
+    print_info(
+        sub_matches,
+        board,
+        &settings,
+        bootloader.as_ref(),
+        &attributes,
+        style,
+        format,
+    )
+    .await
+}
+
+/// How often to ping an idle bootloader, if `--keep-bootloader-alive` was given.
+fn keep_alive_interval(sub_matches: &ArgMatches) -> Option<Duration> {
+    sub_matches
+        .get_flag("keep-bootloader-alive")
+        .then_some(KEEP_ALIVE_INTERVAL)
+}
+
+/// Open the connection to the board selected by the interface arguments, and
+/// make sure its bootloader is running unless `--no-bootloader-entry` says it
+/// is.
+async fn build_interface(sub_matches: &ArgMatches) -> Result<SerialInterface, TockloaderError> {
+    let mut interface = open_interface(sub_matches)?;
+    if sub_matches.get_flag("no-bootloader-entry") {
+        return Ok(interface);
+    }
+    let wait = |id| Duration::from_millis(*sub_matches.get_one::<u64>(id).unwrap());
+    let timeouts = EntryTimeouts {
+        dtr_rts: wait("dtr-rts-wait"),
+        baud_touch: wait("baud-touch-wait"),
+    };
+    let tried = match interface.enter_bootloader(timeouts).await? {
+        BootloaderEntry::Entered => return Ok(interface),
+        BootloaderEntry::NotEntered => true,
+        BootloaderEntry::Unsupported => false,
+    };
+    Err(TockloaderError::BootloaderNotEntered {
+        tried,
+        instructions: bootloader_entry_instructions(
+            sub_matches.get_one::<String>("board").map(String::as_str),
+        ),
+    })
+}
+
+/// Open the connection to the board selected by the interface arguments, as it
+/// is, e.g. to talk to the app running on it.
+///
+/// Only serial ports can be used for now. Other backends, and flash
+/// files where the subcommand does not handle them, are refused with an
+/// explanation of whether they are missing from this build or not implemented
+/// at all.
+fn open_interface(sub_matches: &ArgMatches) -> Result<SerialInterface, TockloaderError> {
+    let backends = [
+        ("jlink", cfg!(feature = "jlink")),
+        ("openocd", cfg!(feature = "openocd")),
+    ];
+    for (backend, compiled) in backends {
+        if sub_matches.get_flag(backend) {
+            return Err(TockloaderError::UnsupportedBackend { backend, compiled });
+        }
+    }
+    // Subcommands which work on a flash file check for it themselves.
+    if sub_matches.contains_id("flash-file") {
+        return Err(TockloaderError::UnsupportedBackend {
+            backend: "flash-file",
+            compiled: true,
+        });
+    }
+
+    let port = match sub_matches.get_one::<String>("port") {
+        Some(port) => Some(port.clone()),
+        None => choose_port(sub_matches.get_flag("no-interactive"))?,
+    };
+    let mut interface =
+        SerialInterface::new(port, *sub_matches.get_one::<u32>("baud-rate").unwrap());
+    interface.set_retries(*sub_matches.get_one::<u32>("retries").unwrap());
+    interface.set_timeout(Duration::from_millis(
+        *sub_matches.get_one::<u64>("timeout").unwrap(),
+    ));
+    interface.open()?;
+    Ok(interface)
+}
+
+/// Open the flash file given with `--flash-file`, creating it if need be, or
+/// `None` if the board itself is to be used.
+fn build_flash_file_interface(
+    sub_matches: &ArgMatches,
+) -> Result<Option<FlashFileInterface>, TockloaderError> {
+    let Some(path) = sub_matches.get_one::<String>("flash-file") else {
+        return Ok(None);
+    };
+    let size = *sub_matches.get_one::<u32>("flash-file-size").unwrap();
+    // The page size is set once the settings are known.
+    FlashFileInterface::with_size(Path::new(path), DEFAULT_PAGE_SIZE, size.into()).map(Some)
+}
+
+/// Pick the serial port to use when `--port` was not given. With several
+/// ports the user is asked, unless `no_interactive` is set or stdin is not a
+/// terminal. `None` leaves it to the interface to open the first port it can.
+fn choose_port(no_interactive: bool) -> Result<Option<String>, TockloaderError> {
+    let ports = available_ports()?;
+    if ports.len() < 2 {
+        return Ok(None);
+    }
+    if no_interactive {
+        return Err(TockloaderError::MultiplePortsAvailable {
+            ports: ports.iter().map(describe_port).collect(),
+        });
+    }
+    if !std::io::stdin().is_terminal() {
+        return Ok(None);
+    }
+    // The prompt goes to stderr so that it does not mix with the output of
+    // e.g. `read`.
+    prompt_for_port(&ports, &mut std::io::stdin().lock(), &mut std::io::stderr()).map(Some)
+}
+
+/// Attach to the board through a J-Link probe, as set up by the interface
+/// arguments.
+#[cfg(feature = "jlink")]
+fn build_jlink_interface(sub_matches: &ArgMatches) -> Result<JLinkInterface, TockloaderError> {
+    let mut device = sub_matches
+        .get_one::<String>("jlink-device")
+        .unwrap()
+        .as_str();
+    // The default device is only a guess, the board knows better.
+    if sub_matches.value_source("jlink-device") == Some(ValueSource::DefaultValue) {
+        if let Some(board_device) = sub_matches
+            .get_one::<String>("board")
+            .and_then(|board| find_board(board))
+            .and_then(|profile| profile.jlink_device)
+        {
+            device = board_device;
+        }
+    }
+    let mut interface = JLinkInterface::new(device);
+    if let Some(command) = sub_matches.get_one::<String>("jlink-cmd") {
+        interface.set_command(command);
+    }
+    if let Some(&speed) = sub_matches.get_one::<u32>("jlink-speed") {
+        interface.set_speed(speed);
+    }
+    if let Some(interface_type) = sub_matches.get_one::<String>("jlink-if") {
+        interface.set_interface(interface_type);
+    }
+    interface.open()?;
+    Ok(interface)
+}
+
+/// Attach to the board through OpenOCD, as set up by the interface arguments.
+#[cfg(feature = "openocd")]
+fn build_openocd_interface(sub_matches: &ArgMatches) -> Result<OpenOcdInterface, TockloaderError> {
+    // `--openocd` requires `--openocd-board`.
+    let mut interface =
+        OpenOcdInterface::new(sub_matches.get_one::<String>("openocd-board").unwrap());
+    interface.set_command(sub_matches.get_one::<String>("openocd-cmd").unwrap());
+    for (action, commands) in sub_matches
+        .get_many::<(String, String)>("openocd-commands")
+        .into_iter()
+        .flatten()
+    {
+        interface.set_action_command(action, commands);
+    }
+    interface.open()?;
+    Ok(interface)
+}
+
+/// Work out the settings for the board, asking it for whatever was not given
+/// on the command line.
+async fn board_settings(
+    sub_matches: &ArgMatches,
+    board: &mut SerialInterface,
+) -> Result<BoardSettings, TockloaderError> {
+    let detected = board.detect_board().await?;
+    let settings = resolve_settings(sub_matches, &detected)?;
+    board.set_page_size(settings.page_size);
+    Ok(settings)
+}
+
+/// Work out the settings for the board a flash file stands in for, from the
+/// command line and the attributes in the file.
+async fn flash_file_settings(
+    sub_matches: &ArgMatches,
+    board: &mut FlashFileInterface,
+) -> Result<BoardSettings, TockloaderError> {
+    let attributes = read_flash_attributes(board).await?;
+    let settings = settings_from_flash(sub_matches, &attributes)?;
+    board.set_page_size(settings.page_size);
+    Ok(settings)
+}
+
+/// Work out the settings for a board whose flash is read directly, taking
+/// what was not given on the command line from the `attributes` found in its
+/// flash.
+fn settings_from_flash(
+    sub_matches: &ArgMatches,
+    attributes: &[Option<Attribute>],
+) -> Result<BoardSettings, TockloaderError> {
+    resolve_settings(sub_matches, &BoardAttributes::from_attributes(attributes))
+}
+
+/// Work out the settings for the board from the command line, falling back
+/// on what the board said about itself in its attributes. A board named
+/// with `--board` wins over the attributes, once they have been checked
+/// against it.
+fn resolve_settings(
+    sub_matches: &ArgMatches,
+    detected: &BoardAttributes,
+) -> Result<BoardSettings, TockloaderError> {
+    let unchecked = BoardAttributes::default();
+    let detected = match sub_matches.get_one::<String>("board") {
+        Some(board) => {
+            check_board_attributes(sub_matches, board, detected)?;
+            if find_board(board).is_some() {
+                &unchecked
+            } else {
+                detected
+            }
+        }
+        None => detected,
+    };
+    let board_name = sub_matches
+        .get_one::<String>("board")
+        .map(String::as_str)
+        .or(detected.board.as_deref());
+    // Only the arch given by the user has to be known, an arch the board
+    // reports is kept as it is.
+    let detected_arch = detected.arch.as_deref();
+    let known_arch = detected_arch.filter(|arch| KNOWN_ARCHS.contains(arch));
+    // A page size of 0 means none was given.
+    let page_size = sub_matches
+        .get_one::<usize>("page-size")
+        .copied()
+        .filter(|&page_size| page_size != 0);
+    let region = sub_matches.get_one::<(u32, u32)>("app-region").copied();
+    let user_arch = sub_matches.get_one::<String>("arch").map(String::as_str);
+    let mut settings = BoardSettings::resolve(
+        board_name,
+        user_arch.or(known_arch),
+        page_size,
+        region
+            .map(|(start, _)| start)
+            .or(sub_matches.get_one::<u32>("kernel-end").copied())
+            .or(sub_matches.get_one::<u32>("app-address").copied())
+            .or(detected.app_address),
+        region
+            .map(|(_, end)| end)
+            .or(sub_matches.get_one::<u32>("app-region-end").copied()),
+    )?;
+    settings.arch = settings.arch.or(detected_arch.map(String::from));
+    Ok(settings)
+}
+
+/// Make sure the attributes of the board agree with the board given with
+/// `--board`. Commands that take `--force` refuse to go on without it,
+/// others only warn.
+fn check_board_attributes(
+    sub_matches: &ArgMatches,
+    board: &str,
+    detected: &BoardAttributes,
+) -> Result<(), TockloaderError> {
+    let differences = detected.differences_from(board);
+    if differences.is_empty() {
+        return Ok(());
+    }
+    let force = sub_matches.try_get_one::<bool>("force").ok().flatten();
+    if force == Some(&false) {
+        return Err(TockloaderError::BoardMismatch {
+            board: board.to_string(),
+            differences,
+        });
+    }
+    eprintln!(
+        "Warning: the board does not look like a '{board}': {}.",
+        differences.join(", ")
+    );
+    Ok(())
+}
+
+/// Read the apps to install from the paths given to `install`, with their
+/// names. Tabs in a directory which have nothing for the board are skipped.
+fn load_apps(
+    sub_matches: &ArgMatches,
+    settings: &BoardSettings,
+) -> Result<(Vec<String>, Vec<AppImage>), TockloaderError> {
+    let paths: Vec<PathBuf> = sub_matches
+        .get_many::<String>("PATH")
+        .unwrap()
+        .map(PathBuf::from)
+        .collect();
+    let board_name = &settings.board;
+    let force = sub_matches.get_flag("force");
+
+    // Whether each file was found in a directory, and may be skipped.
+    let mut files = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            files.extend(find_tabs(&path)?.into_iter().map(|tab| (tab, true)));
+        } else {
+            files.push((path, false));
+        }
+    }
+    // Only tabs need the architecture, TBF files are installed as
+    // they are.
+    let mut arch = None;
+    let mut names = Vec::new();
+    let mut apps = Vec::new();
+    for (path, in_dir) in files {
+        if path.extension().is_some_and(|extension| extension == "tbf") {
+            apps.push(AppImage::from_tbf(&std::fs::read(&path)?)?);
+            names.push(path.display().to_string());
+            continue;
+        }
+        let arch = match &arch {
+            Some(arch) => arch,
+            None => arch.insert(resolve_arch(
+                sub_matches
+                    .get_one::<String>("force-arch")
+                    .map(String::as_str),
+                settings.arch.as_deref(),
+            )?),
+        };
+        let tab = Tab::open(&path)?;
+        let tbf = match &board_name {
+            Some(board_name) if !force => tab.check_board(board_name),
+            _ => Ok(()),
+        }
+        .and_then(|()| tab.extract_binary(arch));
+        match tbf {
+            Ok(tbf) => {
+                apps.push(AppImage::from_tbf(&tbf)?);
+                names.push(path.display().to_string());
+            }
+            // Tabs built for other boards are expected in a project
+            // directory, skip them rather than failing.
+            Err(
+                error @ (TockloaderError::NoBinaryForArch { .. }
+                | TockloaderError::IncompatibleBoard { .. }),
+            ) if in_dir => {
+                println!("Skipping {}: {error}", path.display())
+            }
+            Err(error) => return Err(error),
+        }
+    }
+    Ok((names, apps))
+}
+
+/// Work out where `install` starts each app, warning when the app address is
+/// not on such a boundary.
+fn install_alignment(
+    sub_matches: &ArgMatches,
+    apps: &[AppImage],
+    app_address: u32,
+    page_size: usize,
+) -> Result<u32, TockloaderError> {
+    // Aligning apps to the slot size pads each one to a whole slot.
+    let slot_size = sub_matches.get_one::<u32>("slot-size").copied();
+    if let Some(slot_size) = slot_size {
+        check_slot_size(apps, slot_size)?;
+    }
+    let alignment = sub_matches
+        .get_one::<u32>("alignment")
+        .or(slot_size.as_ref())
+        .copied()
+        .unwrap_or(page_size as u32);
+    if (sub_matches.contains_id("app-address")
+        || sub_matches.contains_id("kernel-end")
+        || sub_matches.contains_id("app-region"))
+        && !app_address.is_multiple_of(alignment)
+        && !sub_matches.get_flag("force")
+    {
+        eprintln!(
+            "Warning: the app address {app_address:#x} is not a multiple of {alignment} bytes, \
+             apps will be placed at the next multiple."
+        );
+    }
+    Ok(alignment)
+}
+
+/// Read `length` bytes of flash from `address` into the file given with
+/// `--output`, or else print them as a hex dump.
+async fn read_to_output<B: BytesReader + Send>(
+    sub_matches: &ArgMatches,
+    board: &mut B,
+    address: usize,
+    length: usize,
+) -> Result<(), TockloaderError> {
+    match sub_matches.get_one::<String>("output") {
+        Some(path) => {
+            let mut file = tokio::fs::File::create(path).await?;
+            board.read_range_to(address, length, &mut file).await?;
+        }
+        None => {
+            let mut dump = HexDump::new(std::io::stdout(), address);
+            board.read_range_to(address, length, &mut dump).await?;
+            dump.finish()?;
+        }
+    }
+    Ok(())
+}
+
+/// Remove the apps named `name` from `board`, or only print what would be
+/// written if `dry_run` is set. Returns the apps removed and the sticky apps
+/// kept.
+async fn uninstall<B>(
+    board: B,
+    app_address: u32,
+    name: &str,
+    first_only: bool,
+    force: bool,
+    dry_run: bool,
+) -> Result<(Vec<AppLocation>, Vec<AppLocation>), TockloaderError>
+where
+    B: BytesReader + FlashWriter + Send,
+{
+    if dry_run {
+        let mut board = DryRunInterface::new(board);
+        let result = uninstall_apps(&mut board, app_address, name, first_only, force).await?;
+        board.print_plan();
+        Ok(result)
+    } else {
+        let mut board = board;
+        uninstall_apps(&mut board, app_address, name, first_only, force).await
+    }
+}
+
+/// Install `apps` after the apps already on the board or, with `--erase`, in
+/// their place (also erasing sticky apps with `--force`). With
+/// `--bundle-apps`, the apps kept are written again along with `apps` in one
+/// pass. Returns the name and address of every app written.
+async fn place_apps<B>(
+    board: &mut B,
+    sub_matches: &ArgMatches,
+    app_address: u32,
+    mut names: Vec<String>,
+    apps: &[AppImage],
+    alignment: u32,
+    region_end: Option<u32>,
+) -> Result<Vec<(String, u32)>, TockloaderError>
+where
+    B: BytesReader + FlashWriter + Send,
+{
+    let erase = sub_matches
+        .get_flag("erase")
+        .then_some(sub_matches.get_flag("force"));
+    if sub_matches.get_flag("bundle-apps") {
+        let (kept, addresses) =
+            bundle_apps(board, app_address, apps, Some(alignment), region_end, erase).await?;
+        let kept_names = kept.iter().map(|app| {
+            let name = app.header().get_package_name().unwrap_or("unnamed app");
+            if erase.is_some() {
+                format!("sticky app {name}")
+            } else {
+                name.to_string()
+            }
+        });
+        names.splice(0..0, kept_names);
+        return Ok(names.into_iter().zip(addresses).collect());
+    }
+    let addresses = match erase {
+        Some(force) => {
+            let (kept, addresses) =
+                replace_apps(board, app_address, apps, Some(alignment), region_end, force).await?;
+            let kept_names = kept.iter().map(|app| {
+                let name = app.header().get_package_name().unwrap_or("unnamed app");
+                format!("sticky app {name}")
+            });
+            names.splice(0..0, kept_names);
+            addresses
+        }
+        None => {
+            let start = board.apps_end(app_address).await?;
+            install_apps(board, start, apps, Some(alignment), region_end).await?
+        }
+    };
+    Ok(names.into_iter().zip(addresses).collect())
+}
+
+/// Show how far a write has got on a single line, which is ended once it is
+/// done.
+fn print_progress(written: usize, total: usize) {
+    // Nothing to write is as done as it gets.
+    let percent = (written * 100).checked_div(total).unwrap_or(100);
+    print!("\rWriting: {percent:3}% ({written}/{total} bytes)");
+    if written == total {
+        println!();
+    }
+    // The line is only for show, it does not matter if it is not flushed.
+    let _ = std::io::stdout().flush();
+}
+
+/// Store `attribute` and read it back to check that the bootloader kept it.
+async fn store_and_verify(
+    board: &mut SerialInterface,
+    attribute: &Attribute,
+) -> Result<u8, TockloaderError> {
+    let index = board.store_attribute(attribute).await?;
+    match board.get_attribute(index).await? {
+        Some(stored) if stored == *attribute => Ok(index),
+        stored => Err(TockloaderError::MalformedResponse(format!(
+            "slot {index} holds {stored:?} after writing"
+        ))),
+    }
+}
+
+/// Apps with only a Main Header have no version, which must not be confused
+/// with version 0.
+fn format_binary_version(version: Option<u32>) -> String {
+    version.map_or("n/a".to_string(), |version| version.to_string())
+}
+
+fn print_app(index: usize, app: &AppLocation, style: Style) {
+    println!("{}", style.heading(&format!("[App {index}]")));
+    println!(
+        "  Name: {}",
+        app.header.get_package_name().unwrap_or("(none)")
+    );
+    println!("  Enabled: {}", app.header.enabled());
+    println!(
+        "  Binary version: {}",
+        format_binary_version(app.binary_version())
+    );
+    println!("  Address in flash: {:#x}", app.address);
+    println!("  Size in flash: {} bytes", app.total_size);
+    println!("  Fixed addresses: {}", format_fixed_addresses(&app.header));
+    println!(
+        "  Minimum RAM: {} bytes",
+        app.header.get_minimum_app_ram_size()
+    );
+}
+
+/// Print what `list --verbose` adds to [`print_app()`].
+fn print_app_details(
+    app: &AppLocation,
+    credentials: Option<&Result<HashReport, TockloaderError>>,
+    style: Style,
+) {
+    let regions = app.header.get_writeable_flash_regions();
+    if regions.is_empty() {
+        println!("  Writeable flash regions: none");
+    } else {
+        println!("  Writeable flash regions:");
+        for (offset, size) in regions {
+            let start = app.address.saturating_add(offset);
+            println!(
+                "    {start:#x}-{:#x} ({size} bytes)",
+                start.saturating_add(size)
+            );
+        }
+    }
+    match app.header.get_all_permissions() {
+        None => println!("  Permissions: any command"),
+        Some(permissions) => {
+            println!("  Permissions:");
+            for (driver_number, offset, allowed_commands) in permissions {
+                // Each entry covers 64 commands, from `offset * 64`.
+                let first = u64::from(offset) * 64;
+                println!(
+                    "    driver {driver_number:#x}, commands {first}-{}: {allowed_commands:#018x}",
+                    first + 63
+                );
+            }
+        }
+    }
+    match app.header.get_kernel_version() {
+        Some((major, minor)) => println!("  Kernel version: {major}.{minor} or later"),
+        None => println!("  Kernel version: any"),
+    }
+    match credentials {
+        Some(Ok(report)) => {
+            let credentials =
+                format_credentials(&report.stored, &report.signatures, &report.unchecked, style);
+            if credentials.is_empty() {
+                println!("  Credentials: none");
+            } else {
+                println!("  Credentials: {}", credentials.join(", "));
+            }
+        }
+        Some(Err(error)) => println!("  Credentials: {}", style.bad(&error.to_string())),
+        None => {}
+    }
+}
+
+/// Whether the credentials of an app match it, footers which can not be parsed
+/// failing the check.
+fn integrity(report: &Result<HashReport, TockloaderError>) -> Integrity {
+    report
+        .as_ref()
+        .map_or(Integrity::Failed, HashReport::integrity)
+}
+
+/// Print what `list --verify` adds to [`print_app()`].
+fn print_integrity(report: &Result<HashReport, TockloaderError>, style: Style) {
+    let result = integrity(report);
+    let text = result.to_string();
+    let styled = match result {
+        Integrity::Ok => style.good(&text),
+        Integrity::Failed => style.bad(&text),
+        Integrity::Unchecked => style.warning(&text),
+    };
+    println!("  Integrity: {styled}");
+}
+
+/// Describe each of the credentials in the footers of an app, with whether it
+/// matches the app.
+fn format_credentials(
+    hashes: &[StoredHash],
+    signatures: &[StoredSignature],
+    unchecked: &[TbfFooterV2CredentialsType],
+    style: Style,
+) -> Vec<String> {
+    let hashes = hashes.iter().map(|hash| {
+        let result = if hash.matches() {
+            style.good("ok")
+        } else {
+            style.bad("MISMATCH")
+        };
+        format!("{} {result}", hash.algorithm.name())
+    });
+    let signatures = signatures.iter().map(|signature| {
+        let result = if signature.valid {
+            style.good("valid")
+        } else {
+            style.bad("INVALID")
+        };
+        format!("{:?} {result}", signature.format)
+    });
+    let unchecked = unchecked
+        .iter()
+        .map(|format| format!("{format:?} {}", style.warning("unchecked")));
+    hashes.chain(signatures).chain(unchecked).collect()
+}
+
+/// Whether the app can be placed anywhere, rather than being built for fixed
+/// addresses in flash or RAM.
+fn is_position_independent(header: &TbfHeader) -> bool {
+    header.get_fixed_address_flash().is_none() && header.get_fixed_address_ram().is_none()
+}
+
+/// Where the app was built to be placed, which has to match where it is
+/// installed for it to load.
+fn format_fixed_addresses(header: &TbfHeader) -> String {
+    if is_position_independent(header) {
+        return "none (position independent)".to_string();
+    }
+    let format = |address: Option<u32>| address.map_or("any".to_string(), |a| format!("{a:#x}"));
+    format!(
+        "flash {}, RAM {}",
+        format(header.get_fixed_address_flash()),
+        format(header.get_fixed_address_ram())
+    )
+}
+
+/// Print one line of the `doctor` checklist, followed by its detail.
+fn print_doctor_check(check: &Check, style: Style) {
+    let status = if check.passed {
+        style.good("[ ok ]")
+    } else {
+        style.bad("[FAIL]")
+    };
+    println!("{status} {}: {}", check.name, check.detail);
+}
+
+fn print_check(check: &TbfCheck, style: Style) {
+    let name = match (&check.name, check.is_app) {
+        (_, false) => "(padding)",
+        (Some(name), true) => name.as_str(),
+        (None, true) => "(none)",
+    };
+    // Pad before styling, the escape codes would count towards the width.
+    let header = if check.header_error.is_some() {
+        style.bad(&format!("{:<10}", "BAD"))
+    } else {
+        style.good(&format!("{:<10}", "ok"))
+    };
+    let fits = if check.fits {
+        style.good(&format!("{:<6}", "yes"))
+    } else {
+        style.bad(&format!("{:<6}", "NO"))
+    };
+
+    let mut credentials =
+        format_credentials(&check.hashes, &check.signatures, &check.unchecked, style);
+    if credentials.is_empty() {
+        credentials.push("-".to_string());
+    }
+
+    println!(
+        "{:<12}{:>10}  {:<16}{header}{fits}{}",
+        format!("{:#x}", check.address),
+        check.total_size,
+        name,
+        credentials.join(", ")
+    );
+    for error in check.header_error.iter().chain(&check.footer_error) {
+        println!("    {error}");
+    }
+}
+
+fn print_stats(command: &str, stats: &OperationStats) {
+    let percentile = |percent| {
+        stats
+            .percentile(percent)
+            .map_or("-".to_string(), |latency| format!("{latency:.1?}"))
+    };
+    println!(
+        "{command}: {} commands, {} bytes, {:.1} KB/s, latency p50 {} p90 {} p99 {}",
+        stats.latencies.len(),
+        stats.bytes,
+        stats.kib_per_second(),
+        percentile(50),
+        percentile(90),
+        percentile(99)
+    );
+}
+
+fn print_tab_metadata(metadata: &TabMetadata) {
+    let or_none = |value: Option<String>| value.unwrap_or_else(|| "(none)".to_string());
+    println!(
+        "Tab version: {}",
+        or_none(metadata.tab_version.map(|v| v.to_string()))
+    );
+    println!("Tab name: {}", or_none(metadata.name.clone()));
+    let boards = metadata.supported_boards();
+    if boards.is_empty() {
+        println!("Only for boards: (any)");
+    } else {
+        println!("Only for boards: {}", boards.join(", "));
+    }
+    println!(
+        "Build date: {}",
+        or_none(metadata.build_date.map(|date| date.to_string()))
+    );
+    println!(
+        "Minimum kernel version: {}",
+        or_none(metadata.minimum_tock_kernel_version.clone())
+    );
+}
+
+fn print_tbf(tbf: &[u8], hash: bool, style: Style) -> Result<(), TockloaderError> {
+    let report = hash_tbf(tbf)?;
+    let header = &report.header;
+    println!("Name: {}", header.get_package_name().unwrap_or("(none)"));
+    println!(
+        "Binary version: {}",
+        format_binary_version(
+            header
+                .has_program_header()
+                .then(|| header.get_binary_version())
+        )
+    );
+    println!("Enabled: {}", header.enabled());
+    println!("Total size: {} bytes", header.get_total_size());
+    println!("Binary end: {:#x}", header.get_binary_end());
+    println!("Fixed addresses: {}", format_fixed_addresses(header));
+
+    if hash {
+        for (algorithm, hash) in &report.computed {
+            println!("Computed {}: {}", algorithm.name(), to_hex(hash));
+        }
+        if report.stored.is_empty() {
+            println!("No hash credentials stored");
+        }
+        for stored in &report.stored {
+            println!(
+                "Stored {}: {} ({})",
+                stored.algorithm.name(),
+                to_hex(&stored.stored),
+                if stored.matches() {
+                    style.good("matches")
+                } else {
+                    style.bad("MISMATCH")
+                }
+            );
+        }
+        for signature in &report.signatures {
+            println!(
+                "Stored {:?}: {}",
+                signature.format,
+                if signature.valid {
+                    style.good("valid signature")
+                } else {
+                    style.bad("INVALID signature")
+                }
+            );
+        }
+        for format in &report.unchecked {
+            println!(
+                "Stored {format:?}: {}",
+                style.warning("not checked, needs the key")
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Print every field of the header of `tbf` on its own line, followed by
+/// whether the header as a whole parses.
+fn print_header_fields(tbf: &[u8], style: Style) -> Result<(), TockloaderError> {
+    let fields = header_fields(tbf)?;
+    println!("{:<8}{:<40}{:>6}  Bytes", "Offset", "Field", "Length");
+    for field in &fields {
+        let bytes: Vec<String> = field
+            .bytes
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect();
+        println!(
+            "{:<8}{:<40}{:>6}  {}",
+            format!("{:#06x}", field.offset),
+            field.name,
+            field.bytes.len(),
+            bytes.join(" ")
+        );
+        if let Some(problem) = &field.problem {
+            println!("{:<8}{}", "", style.bad(problem));
+        }
+    }
+
+    match parse_header(tbf) {
+        Ok(_) => println!("{}", style.good("The header parses")),
+        Err(error) => println!(
+            "{}",
+            style.bad(&format!("The header does not parse: {error}"))
+        ),
+    }
+    Ok(())
 }
@@ -0,0 +1,90 @@
+//! Coloring and format of the output.
+//!
+//! Color is only used when it will be seen: it is turned off by `--no-color`,
+//! by the `NO_COLOR` environment variable (<https://no-color.org>), and when
+//! stdout is not a terminal, e.g. when piped to a file.
+
+use std::ffi::OsStr;
+use std::io::IsTerminal;
+
+use console::style;
+
+/// How subcommands with results to report, such as `info` and `list`, print
+/// them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// For people, colored according to the [`Style`].
+    #[default]
+    Text,
+    /// A JSON document on stdout, e.g. to be read with `jq`.
+    Json,
+}
+
+impl std::str::FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            _ => Err(format!("'{value}' is not one of text or json")),
+        }
+    }
+}
+
+/// Whether, and how, output is colored. Text passes through unchanged when
+/// color is off.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Style {
+    color: bool,
+}
+
+impl Style {
+    pub fn new(color: bool) -> Self {
+        Style { color }
+    }
+
+    /// Pick the style for stdout, given whether `--no-color` was passed.
+    pub fn detect(no_color_flag: bool) -> Self {
+        Style::new(should_color(
+            no_color_flag,
+            std::env::var_os("NO_COLOR").as_deref(),
+            std::io::stdout().is_terminal(),
+        ))
+    }
+
+    pub fn color(&self) -> bool {
+        self.color
+    }
+
+    /// Something that worked or passed a check.
+    pub fn good(&self, text: &str) -> String {
+        style(text).green().force_styling(self.color).to_string()
+    }
+
+    /// Something that failed.
+    pub fn bad(&self, text: &str) -> String {
+        style(text)
+            .red()
+            .bold()
+            .force_styling(self.color)
+            .to_string()
+    }
+
+    /// Something that works, but deserves attention.
+    pub fn warning(&self, text: &str) -> String {
+        style(text).yellow().force_styling(self.color).to_string()
+    }
+
+    /// The title of a group of lines, such as an app.
+    pub fn heading(&self, text: &str) -> String {
+        style(text).bold().force_styling(self.color).to_string()
+    }
+}
+
+/// Decide whether to color output. `NO_COLOR` only counts when it is set to
+/// something other than an empty string.
+pub fn should_color(no_color_flag: bool, no_color_env: Option<&OsStr>, is_terminal: bool) -> bool {
+    let no_color_env = no_color_env.is_some_and(|value| !value.is_empty());
+    !no_color_flag && !no_color_env && is_terminal
+}
@@ -0,0 +1,190 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::errors::TockloaderError;
+
+/// A TBF found inside of a tab, along with the name it was stored under.
+struct TbfFile {
+    filename: String,
+    data: Vec<u8>,
+}
+
+/// A Tock Application Bundle: a tar archive holding one TBF per architecture
+/// the app was compiled for.
+///
+/// TBFs are named after their architecture, optionally followed by fixed
+/// addresses, e.g. `cortex-m4.tbf` or `rv32imac.0x20040000.0x80002800.tbf`.
+pub struct Tab {
+    metadata: Option<TabMetadata>,
+    tbf_files: Vec<TbfFile>,
+}
+
+/// The contents of a tab's `metadata.toml`.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct TabMetadata {
+    pub tab_version: Option<u32>,
+    pub name: Option<String>,
+    /// Comma separated names of the boards the app may be installed on. Any
+    /// board is fine if this is missing or empty.
+    pub only_for_boards: Option<String>,
+    pub build_date: Option<toml::value::Datetime>,
+    pub minimum_tock_kernel_version: Option<String>,
+}
+
+impl TabMetadata {
+    pub fn parse(text: &str) -> Result<TabMetadata, TockloaderError> {
+        toml::from_str(text).map_err(|error| TockloaderError::InvalidTabMetadata(error.to_string()))
+    }
+
+    /// The boards listed in `only-for-boards`, empty if the app is not
+    /// restricted to any.
+    pub fn supported_boards(&self) -> Vec<String> {
+        self.only_for_boards
+            .iter()
+            .flat_map(|boards| boards.split(','))
+            .map(str::trim)
+            .filter(|board| !board.is_empty())
+            .map(String::from)
+            .collect()
+    }
+
+    /// Whether the app may be installed on `board`.
+    pub fn supports_board(&self, board: &str) -> bool {
+        let supported = self.supported_boards();
+        supported.is_empty() || supported.iter().any(|name| name == board)
+    }
+}
+
+impl Tab {
+    /// Read every TBF out of the tab at `path`.
+    pub fn open(path: &Path) -> Result<Self, TockloaderError> {
+        Self::from_reader(File::open(path)?)
+    }
+
+    /// Read every TBF out of a tab archive.
+    pub fn from_reader<R: Read>(reader: R) -> Result<Self, TockloaderError> {
+        let mut archive = tar::Archive::new(reader);
+        let mut metadata = None;
+        let mut tbf_files = Vec::new();
+
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            let path = entry.path()?;
+            let Some(filename) = path.file_name().and_then(|name| name.to_str()) else {
+                continue;
+            };
+            if filename == "metadata.toml" {
+                let mut text = String::new();
+                entry.read_to_string(&mut text)?;
+                metadata = Some(TabMetadata::parse(&text)?);
+                continue;
+            }
+            if !filename.ends_with(".tbf") {
+                continue;
+            }
+            let filename = filename.to_string();
+
+            let mut data = Vec::new();
+            entry.read_to_end(&mut data)?;
+            tbf_files.push(TbfFile { filename, data });
+        }
+
+        Ok(Tab {
+            metadata,
+            tbf_files,
+        })
+    }
+
+    /// The tab's `metadata.toml`, if it has one.
+    pub fn metadata(&self) -> Option<&TabMetadata> {
+        self.metadata.as_ref()
+    }
+
+    /// Check that the app may be installed on `board`, as declared by
+    /// `only-for-boards`.
+    pub fn check_board(&self, board: &str) -> Result<(), TockloaderError> {
+        match &self.metadata {
+            Some(metadata) if !metadata.supports_board(board) => {
+                Err(TockloaderError::IncompatibleBoard {
+                    board: board.to_string(),
+                    supported: metadata.supported_boards(),
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// All of the architectures this tab has a TBF for, sorted and without
+    /// duplicates.
+    pub fn architectures(&self) -> Vec<String> {
+        let mut architectures: Vec<String> = self
+            .tbf_files
+            .iter()
+            .map(|tbf| arch_of(&tbf.filename).to_string())
+            .collect();
+        architectures.sort();
+        architectures.dedup();
+        architectures
+    }
+
+    /// Get the TBF compiled for `arch`.
+    ///
+    /// If the tab was not compiled for that architecture, the error lists the
+    /// architectures that it does support.
+    pub fn extract_binary(&self, arch: &str) -> Result<Vec<u8>, TockloaderError> {
+        self.tbf_files
+            .iter()
+            .find(|tbf| arch_of(&tbf.filename) == arch)
+            .map(|tbf| tbf.data.clone())
+            .ok_or_else(|| TockloaderError::NoBinaryForArch {
+                arch: arch.to_string(),
+                available: self.architectures(),
+            })
+    }
+}
+
+/// Find the tabs directly inside of `dir`, sorted by path.
+pub fn find_tabs(dir: &Path) -> Result<Vec<PathBuf>, TockloaderError> {
+    let mut tabs = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_file() && path.extension().is_some_and(|extension| extension == "tab") {
+            tabs.push(path);
+        }
+    }
+    tabs.sort();
+    Ok(tabs)
+}
+
+/// The architecture a TBF was compiled for, taken from its file name.
+fn arch_of(filename: &str) -> &str {
+    filename.split('.').next().unwrap_or(filename)
+}
+
+/// Decide which architecture's TBF to install.
+///
+/// An architecture passed with `--force-arch` always wins. Otherwise the
+/// architecture reported by the board is used, normalized to the names used
+/// for TBFs inside of tabs (e.g. `cortex-m4`).
+pub fn resolve_arch(
+    force_arch: Option<&str>,
+    board_arch: Option<&str>,
+) -> Result<String, TockloaderError> {
+    match (force_arch, board_arch) {
+        (Some(arch), _) => Ok(arch.to_string()),
+        (None, Some(arch)) => Ok(normalize_arch(arch)),
+        (None, None) => Err(TockloaderError::UnknownArch),
+    }
+}
+
+/// Convert an architecture as stored in a board attribute to a tab key.
+///
+/// Attribute values are null padded, and some boards report the architecture
+/// in upper case.
+fn normalize_arch(arch: &str) -> String {
+    arch.trim_end_matches('\0').trim().to_lowercase()
+}
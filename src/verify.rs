@@ -0,0 +1,108 @@
+//! Checking the health of the apps in flash.
+
+use tbf_parser::types::TbfFooterV2CredentialsType;
+
+use crate::apps::TbfIterator;
+use crate::errors::TockloaderError;
+use crate::inspect::{hash_tbf, StoredHash, StoredSignature};
+use crate::interfaces::traits::BytesReader;
+
+/// What was found when checking one TBF of the app region.
+#[derive(Clone, Debug)]
+pub struct TbfCheck {
+    pub address: u32,
+    pub total_size: u32,
+    pub name: Option<String>,
+    pub is_app: bool,
+    /// Why the header could not be parsed, which includes a bad checksum.
+    pub header_error: Option<String>,
+    /// Whether `total_size` covers the header and binary, and stays inside
+    /// the app region.
+    pub fits: bool,
+    /// Why the footers could not be parsed.
+    pub footer_error: Option<String>,
+    pub hashes: Vec<StoredHash>,
+    pub signatures: Vec<StoredSignature>,
+    /// Credentials which can not be checked.
+    pub unchecked: Vec<TbfFooterV2CredentialsType>,
+}
+
+impl TbfCheck {
+    pub fn passed(&self) -> bool {
+        self.header_error.is_none()
+            && self.fits
+            && self.footer_error.is_none()
+            && self.hashes.iter().all(StoredHash::matches)
+            && self.signatures.iter().all(|signature| signature.valid)
+    }
+}
+
+/// Walk the app region starting at `app_address` and check every TBF in it.
+///
+/// The walk stops at the end of the list, or at the first TBF whose size does
+/// not fit, since the next TBF can not be found after it. If `region_end` is
+/// `None`, only the sizes declared in the headers are checked.
+pub async fn verify_region<B>(
+    board: &mut B,
+    app_address: u32,
+    region_end: Option<u32>,
+) -> Result<Vec<TbfCheck>, TockloaderError>
+where
+    B: BytesReader + Send + ?Sized,
+{
+    let mut iterator = TbfIterator::new(app_address);
+    let mut checks = Vec::new();
+    while let Some(tbf) = iterator.next_raw(board).await? {
+        let end = tbf.address.checked_add(tbf.total_size);
+        // A TBF shorter than the base header would keep the walk in place.
+        let in_region = tbf.total_size >= 16
+            && match (end, region_end) {
+                (None, _) => false,
+                (Some(end), Some(region_end)) => end <= region_end,
+                (Some(_), None) => true,
+            };
+
+        let mut check = TbfCheck {
+            address: tbf.address,
+            total_size: tbf.total_size,
+            name: None,
+            is_app: true,
+            header_error: None,
+            fits: in_region,
+            footer_error: None,
+            hashes: Vec::new(),
+            signatures: Vec::new(),
+            unchecked: Vec::new(),
+        };
+        match tbf.header {
+            Ok(header) => {
+                check.name = header.get_package_name().map(str::to_string);
+                check.is_app = header.is_app();
+                check.fits &= header.get_binary_end() <= tbf.total_size;
+            }
+            Err(error) => check.header_error = Some(error),
+        }
+
+        // Only read the whole TBF once its size can be trusted.
+        if check.header_error.is_none() && check.fits && check.is_app {
+            let tbf = board
+                .read_range(tbf.address as usize, tbf.total_size as usize)
+                .await?;
+            match hash_tbf(&tbf) {
+                Ok(report) => {
+                    check.hashes = report.stored;
+                    check.signatures = report.signatures;
+                    check.unchecked = report.unchecked;
+                }
+                Err(error) => check.footer_error = Some(error.to_string()),
+            }
+        }
+
+        let fits = check.fits;
+        checks.push(check);
+        if !fits {
+            break;
+        }
+    }
+    Ok(checks)
+}
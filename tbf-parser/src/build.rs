@@ -0,0 +1,275 @@
+//! Building v2 TBF headers from scratch.
+//!
+//! Where [`edit`](crate::edit) changes a header that already exists, this
+//! writes a new one, for padding or for an app whose header must be emitted
+//! again, e.g. with a new total size after it was moved.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::edit::compute_checksum;
+use crate::types;
+
+/// Flag bit of an app the kernel should start.
+const FLAG_ENABLED: u32 = 1 << 0;
+/// Flag bit of an app tockloader should not remove without `--force`.
+const FLAG_STICKY: u32 = 1 << 1;
+
+/// Builds a v2 TBF header, filling in the header size, the total size and the
+/// checksum.
+///
+/// The TBF is laid out as the header, the protected trailer of the Main or
+/// Program header, the binary and the footers, and its total size is the sum
+/// of these unless set with [`total_size()`]. Without a Main or Program header
+/// the TBF is padding.
+///
+/// ```
+/// use tbf_parser::build::TbfHeaderBuilder;
+///
+/// let header = TbfHeaderBuilder::new()
+///     .program(0, 0, 4096, 1)
+///     .package_name("blink")
+///     .binary_size(2048)
+///     .build()
+///     .unwrap();
+/// ```
+///
+/// [`total_size()`]: TbfHeaderBuilder::total_size
+#[derive(Clone, Debug, Default)]
+pub struct TbfHeaderBuilder {
+    flags: u32,
+    app: Option<AppHeader>,
+    package_name: Option<String>,
+    writeable_regions: Vec<(u32, u32)>,
+    fixed_addresses: Option<(u32, u32)>,
+    other_tlvs: Vec<(u16, Vec<u8>)>,
+    binary_size: u32,
+    footer_size: u32,
+    total_size: Option<u32>,
+}
+
+/// The Main or Program header, without the binary end offset of the latter
+/// which is only known once the header is built.
+#[derive(Clone, Copy, Debug)]
+struct AppHeader {
+    init_fn_offset: u32,
+    protected_trailer_size: u32,
+    minimum_ram_size: u32,
+    /// The binary version, if this is a Program header.
+    version: Option<u32>,
+}
+
+impl TbfHeaderBuilder {
+    /// Start an enabled TBF with no TLVs, no binary and no footers.
+    pub fn new() -> Self {
+        TbfHeaderBuilder {
+            flags: FLAG_ENABLED,
+            ..Default::default()
+        }
+    }
+
+    /// Start a padding TBF which spans `total_size` bytes, header included.
+    /// Padding is never enabled.
+    pub fn padding(total_size: u32) -> Self {
+        TbfHeaderBuilder::new()
+            .enabled(false)
+            .total_size(total_size)
+    }
+
+    /// Set whether the kernel starts the app.
+    pub fn enabled(mut self, enabled: bool) -> Self {
+        self.set_flag(FLAG_ENABLED, enabled);
+        self
+    }
+
+    /// Set whether the app is kept unless its removal is forced.
+    pub fn sticky(mut self, sticky: bool) -> Self {
+        self.set_flag(FLAG_STICKY, sticky);
+        self
+    }
+
+    /// Add a Main header. `init_fn_offset` is counted from the end of the
+    /// header. Replaces any Main or Program header added before.
+    pub fn main(
+        mut self,
+        init_fn_offset: u32,
+        protected_trailer_size: u32,
+        minimum_ram_size: u32,
+    ) -> Self {
+        self.app = Some(AppHeader {
+            init_fn_offset,
+            protected_trailer_size,
+            minimum_ram_size,
+            version: None,
+        });
+        self
+    }
+
+    /// Add a Program header, whose binary end offset is filled in from the
+    /// sizes. `init_fn_offset` is counted from the end of the header. Replaces
+    /// any Main or Program header added before.
+    pub fn program(
+        mut self,
+        init_fn_offset: u32,
+        protected_trailer_size: u32,
+        minimum_ram_size: u32,
+        version: u32,
+    ) -> Self {
+        self.app = Some(AppHeader {
+            init_fn_offset,
+            protected_trailer_size,
+            minimum_ram_size,
+            version: Some(version),
+        });
+        self
+    }
+
+    /// Add a Package Name TLV.
+    pub fn package_name(mut self, name: &str) -> Self {
+        self.package_name = Some(name.into());
+        self
+    }
+
+    /// Add a Writeable Flash Region TLV, `offset` being counted from the start
+    /// of the TBF. Each call adds another region.
+    pub fn writeable_flash_region(mut self, offset: u32, size: u32) -> Self {
+        self.writeable_regions.push((offset, size));
+        self
+    }
+
+    /// Add a Fixed Addresses TLV. `0xFFFFFFFF` means the app can be placed
+    /// anywhere in RAM or flash.
+    pub fn fixed_addresses(mut self, start_process_ram: u32, start_process_flash: u32) -> Self {
+        self.fixed_addresses = Some((start_process_ram, start_process_flash));
+        self
+    }
+
+    /// Add a TLV of any type with `value` as it is, e.g. one this crate does
+    /// not know. These come after every other TLV, in the order they were
+    /// added.
+    pub fn tlv(mut self, tipe: u16, value: &[u8]) -> Self {
+        self.other_tlvs.push((tipe, value.into()));
+        self
+    }
+
+    /// Set the size of the binary, which follows the protected trailer.
+    pub fn binary_size(mut self, size: u32) -> Self {
+        self.binary_size = size;
+        self
+    }
+
+    /// Set the size of the footers, which follow the binary. Only TBFs with a
+    /// Program header can have footers.
+    pub fn footer_size(mut self, size: u32) -> Self {
+        self.footer_size = size;
+        self
+    }
+
+    /// Set the total size outright, rather than from the sizes of the parts,
+    /// e.g. to keep the size of an existing TBF whose header is emitted again.
+    pub fn total_size(mut self, size: u32) -> Self {
+        self.total_size = Some(size);
+        self
+    }
+
+    /// Build the header.
+    ///
+    /// ## Return
+    ///
+    /// The bytes of the header alone, or `BadTlvEntry` with the type of the
+    /// first TLV that does not fit in the 64 KiB a header can span.
+    pub fn build(&self) -> Result<Vec<u8>, types::TbfParseError> {
+        let mut header = Vec::new();
+        header.extend_from_slice(&2u16.to_le_bytes());
+        // The header size, total size and checksum are filled in at the end.
+        header.extend_from_slice(&[0; 6]);
+        header.extend_from_slice(&self.flags.to_le_bytes());
+        header.extend_from_slice(&[0; 4]);
+
+        // The binary end offset depends on the header size, which depends on
+        // every TLV, so it is filled in at the end too.
+        let mut binary_end_at = None;
+        if let Some(app) = self.app {
+            let mut value = Vec::new();
+            value.extend_from_slice(&app.init_fn_offset.to_le_bytes());
+            value.extend_from_slice(&app.protected_trailer_size.to_le_bytes());
+            value.extend_from_slice(&app.minimum_ram_size.to_le_bytes());
+            let tipe = match app.version {
+                Some(version) => {
+                    binary_end_at = Some(header.len() + 4 + value.len());
+                    value.extend_from_slice(&0u32.to_le_bytes());
+                    value.extend_from_slice(&version.to_le_bytes());
+                    types::TbfHeaderTypes::TbfHeaderProgram
+                }
+                None => types::TbfHeaderTypes::TbfHeaderMain,
+            };
+            push_tlv(&mut header, tipe as u16, &value)?;
+        }
+        if let Some(name) = &self.package_name {
+            push_tlv(
+                &mut header,
+                types::TbfHeaderTypes::TbfHeaderPackageName as u16,
+                name.as_bytes(),
+            )?;
+        }
+        for (offset, size) in &self.writeable_regions {
+            let mut value = Vec::new();
+            value.extend_from_slice(&offset.to_le_bytes());
+            value.extend_from_slice(&size.to_le_bytes());
+            push_tlv(
+                &mut header,
+                types::TbfHeaderTypes::TbfHeaderWriteableFlashRegions as u16,
+                &value,
+            )?;
+        }
+        if let Some((ram, flash)) = self.fixed_addresses {
+            let mut value = Vec::new();
+            value.extend_from_slice(&ram.to_le_bytes());
+            value.extend_from_slice(&flash.to_le_bytes());
+            push_tlv(
+                &mut header,
+                types::TbfHeaderTypes::TbfHeaderFixedAddresses as u16,
+                &value,
+            )?;
+        }
+        for (tipe, value) in &self.other_tlvs {
+            push_tlv(&mut header, *tipe, value)?;
+        }
+
+        let header_size = header.len() as u32;
+        let protected_trailer_size = self.app.map_or(0, |app| app.protected_trailer_size);
+        let binary_end = header_size + protected_trailer_size + self.binary_size;
+        let total_size = self.total_size.unwrap_or(binary_end + self.footer_size);
+
+        header[2..4].copy_from_slice(&(header_size as u16).to_le_bytes());
+        header[4..8].copy_from_slice(&total_size.to_le_bytes());
+        if let Some(at) = binary_end_at {
+            header[at..at + 4].copy_from_slice(&binary_end.to_le_bytes());
+        }
+        let checksum = compute_checksum(&header);
+        header[12..16].copy_from_slice(&checksum.to_le_bytes());
+        Ok(header)
+    }
+
+    fn set_flag(&mut self, flag: u32, set: bool) {
+        if set {
+            self.flags |= flag;
+        } else {
+            self.flags &= !flag;
+        }
+    }
+}
+
+/// Append a TLV to `header`, with its value padded to 4 bytes.
+fn push_tlv(header: &mut Vec<u8>, tipe: u16, value: &[u8]) -> Result<(), types::TbfParseError> {
+    let too_large = types::TbfParseError::BadTlvEntry(tipe as usize);
+    let length = u16::try_from(value.len()).map_err(|_| too_large)?;
+    header.extend_from_slice(&tipe.to_le_bytes());
+    header.extend_from_slice(&length.to_le_bytes());
+    header.extend_from_slice(value);
+    header.resize(header.len().next_multiple_of(4), 0);
+    if header.len() > u16::MAX as usize {
+        return Err(too_large);
+    }
+    Ok(())
+}
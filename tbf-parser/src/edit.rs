@@ -0,0 +1,56 @@
+//! Functions for editing TBF headers in place.
+//!
+//! Edits are made directly in the original bytes of the header, rather than by
+//! serializing a parsed header. Every TLV, including ones this crate does not
+//! understand, is kept exactly as it was, along with its order and padding.
+
+use crate::types;
+
+/// Replace the flags of the TBF header in `header_bytes` with `flags`, and
+/// update its checksum to match.
+///
+/// Only the flags and checksum words are written, every other byte is left
+/// untouched. `header_bytes` must hold at least the whole header.
+pub fn set_flags_in_raw(header_bytes: &mut [u8], flags: u32) -> Result<(), types::TbfParseError> {
+    header_size(header_bytes)?;
+    header_bytes[8..12].copy_from_slice(&flags.to_le_bytes());
+    update_checksum_in_raw(header_bytes)
+}
+
+/// Recompute the checksum of the v2 TBF header in `header_bytes`, after some
+/// of its other bytes were changed in place.
+///
+/// Only the checksum word is written. `header_bytes` must hold at least the
+/// whole header.
+pub fn update_checksum_in_raw(header_bytes: &mut [u8]) -> Result<(), types::TbfParseError> {
+    let header_size = header_size(header_bytes)?;
+    let checksum = compute_checksum(&header_bytes[..header_size]);
+    header_bytes[12..16].copy_from_slice(&checksum.to_le_bytes());
+    Ok(())
+}
+
+/// The size of the header in `header_bytes`, checking that they hold all of
+/// it.
+fn header_size(header_bytes: &[u8]) -> Result<usize, types::TbfParseError> {
+    let base: types::TbfHeaderV2Base = header_bytes.try_into()?;
+    let header_size = base.header_size as usize;
+    if header_size < 16 || header_bytes.len() < header_size {
+        return Err(types::TbfParseError::NotEnoughFlash);
+    }
+    Ok(header_size)
+}
+
+/// The checksum of a header: the XOR of each 4 byte word, excluding the
+/// checksum word itself. A partial last word is treated as zero padded.
+pub(crate) fn compute_checksum(header: &[u8]) -> u32 {
+    let mut checksum: u32 = 0;
+    for (i, chunk) in header.chunks(4).enumerate() {
+        if i == 3 {
+            continue;
+        }
+        let mut word = [0u8; 4];
+        word[..chunk.len()].copy_from_slice(chunk);
+        checksum ^= u32::from_le_bytes(word);
+    }
+    checksum
+}
@@ -0,0 +1,18 @@
+//! Parser for the Tock Binary Format (TBF) used to package Tock applications.
+//!
+//! This crate is adapted from the `tock-tbf` library in the Tock kernel
+//! repository. Unlike the kernel version, parsed headers own their data so
+//! that they can outlive the buffer read from a board or a file.
+
+#![no_std]
+
+extern crate alloc;
+
+pub mod build;
+pub mod edit;
+pub mod parse;
+#[cfg(feature = "testing")]
+pub mod testing;
+pub mod types;
+#[cfg(feature = "verify")]
+pub mod verify;
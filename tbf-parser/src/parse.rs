@@ -0,0 +1,400 @@
+//! Functions for parsing TBF headers out of a buffer of bytes.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::types;
+
+/// The versions of the TBF header that can be parsed.
+pub const SUPPORTED_VERSIONS: &[u16] = &[1, 2];
+
+/// Parse the TBF header length and the entire length of the TBF binary.
+///
+/// ## Return
+///
+/// If all parsing is successful:
+/// - Ok((Version, TBF header length, entire TBF length))
+///
+/// If we cannot parse the header because we have run out of flash, or the
+/// values are entirely wrong we return `UnableToParse`. This means we have hit
+/// the end of apps in flash.
+/// - Err(InitialTbfParseError::UnableToParse)
+///
+/// Any other error we return an error and the length of the entire app so that
+/// we can skip over it and check for the next app.
+/// - Err(InitialTbfParseError::InvalidHeader(app_length))
+pub fn parse_tbf_header_lengths(
+    app: &[u8; 8],
+) -> Result<(u16, u16, u32), types::InitialTbfParseError> {
+    // Version is the first 16 bits of the app TBF contents. We need this to
+    // correctly parse the other lengths.
+    //
+    // ## Safety
+    // We trust that the version number has been checked prior to running this
+    // parsing code. That is, whatever loaded this application has verified
+    // that the version is valid and therefore we can trust it.
+    let version = u16::from_le_bytes([app[0], app[1]]);
+
+    match version {
+        // Version 1 has a 32 bit version field, and a header of fixed size.
+        1 if app[2..4] == [0, 0] => {
+            let tbf_size = u32::from_le_bytes([app[4], app[5], app[6], app[7]]);
+            if (types::TBF_HEADER_V1_SIZE as u32) > tbf_size {
+                Err(types::InitialTbfParseError::InvalidHeader(tbf_size))
+            } else {
+                Ok((version, types::TBF_HEADER_V1_SIZE, tbf_size))
+            }
+        }
+        2 => {
+            // In version 2, the next 16 bits after the version represent
+            // the size of the TBF header in bytes.
+            let tbf_header_size = u16::from_le_bytes([app[2], app[3]]);
+
+            // The next 4 bytes are the size of the entire app's TBF space
+            // including the header. This also must be checked before parsing
+            // this header and we trust the value in flash.
+            let tbf_size = u32::from_le_bytes([app[4], app[5], app[6], app[7]]);
+
+            // Check that the header length isn't greater than the entire app,
+            // and that it is at least long enough for the fixed v2 fields. If
+            // that at least looks good then return the sizes.
+            if tbf_header_size as u32 > tbf_size || tbf_header_size < 16 {
+                Err(types::InitialTbfParseError::InvalidHeader(tbf_size))
+            } else {
+                Ok((version, tbf_header_size, tbf_size))
+            }
+        }
+
+        // Since we have to use the length field, if we don't know what version
+        // this is we can't find the total size.
+        _ => Err(types::InitialTbfParseError::UnableToParse),
+    }
+}
+
+/// Parse a TBF header stored in flash.
+///
+/// The `header` must be a slice that only contains the TBF header. The caller
+/// should use the `parse_tbf_header_lengths()` function to determine this
+/// length to create the correct sized slice.
+pub fn parse_tbf_header(
+    header: &[u8],
+    version: u16,
+) -> Result<types::TbfHeader, types::TbfParseError> {
+    match version {
+        1 => {
+            if header.len() != types::TBF_HEADER_V1_SIZE as usize {
+                return Err(types::TbfParseError::NotEnoughFlash);
+            }
+            let tbf_header: types::TbfHeaderV1 = header.try_into()?;
+
+            // The checksum is the XOR of every other word, all of which come
+            // before it.
+            let mut checksum: u32 = 0;
+            for chunk in header[..header.len() - 4].chunks_exact(4) {
+                checksum ^= u32::from_le_bytes(chunk.try_into()?);
+            }
+            if checksum != tbf_header.checksum {
+                return Err(types::TbfParseError::ChecksumMismatch(
+                    tbf_header.checksum,
+                    checksum,
+                ));
+            }
+
+            Ok(types::TbfHeader::TbfHeaderV1(tbf_header))
+        }
+        2 => {
+            // Get the required base. This will succeed because we parsed the
+            // first bit of the header already in `parse_tbf_header_lengths()`.
+            let tbf_header_base: types::TbfHeaderV2Base = header.try_into()?;
+
+            // Make sure we were handed exactly the header, otherwise the
+            // checksum below would cover the wrong bytes.
+            if header.len() != tbf_header_base.header_size as usize {
+                return Err(types::TbfParseError::NotEnoughFlash);
+            }
+
+            // Calculate checksum. The checksum is the XOR of each 4 byte word
+            // in the header, excluding the checksum word itself.
+            let mut checksum: u32 = 0;
+            let mut chunks = header.chunks_exact(4);
+            for (i, chunk) in chunks.by_ref().enumerate() {
+                let word = u32::from_le_bytes(chunk.try_into()?);
+                if i == 3 {
+                    // Skip the checksum field.
+                } else {
+                    checksum ^= word;
+                }
+            }
+
+            // Handle any remaining bytes as if they were zero padded.
+            let rem = chunks.remainder();
+            if !rem.is_empty() {
+                let mut last = [0u8; 4];
+                last[..rem.len()].copy_from_slice(rem);
+                checksum ^= u32::from_le_bytes(last);
+            }
+
+            if checksum != tbf_header_base.checksum {
+                return Err(types::TbfParseError::ChecksumMismatch(
+                    tbf_header_base.checksum,
+                    checksum,
+                ));
+            }
+
+            // If there is nothing after the base header then this is just a
+            // padding "app" between two other apps.
+            if header.len() == 16 {
+                return Ok(types::TbfHeader::Padding(tbf_header_base));
+            }
+
+            // Otherwise, we have an app, so parse the optional TLVs.
+            let mut main_pointer: Option<types::TbfHeaderV2Main> = None;
+            let mut program_pointer: Option<types::TbfHeaderV2Program> = None;
+            let mut package_name: Option<String> = None;
+            let mut fixed_address_pointer: Option<types::TbfHeaderV2FixedAddresses> = None;
+            let mut writeable_regions: Vec<types::TbfHeaderV2WriteableFlashRegion> = Vec::new();
+            let mut permissions: Option<Vec<types::TbfHeaderDriverPermission>> = None;
+            let mut kernel_version: Option<types::TbfHeaderV2KernelVersion> = None;
+
+            for tlv in header_tlvs(header) {
+                let tlv = tlv?;
+                let tipe = types::TbfHeaderTypes::from(tlv.tipe);
+                // Main, Program, Fixed Addresses and Kernel Version headers
+                // have a fixed size.
+                let fixed_size_value = || {
+                    if tipe.fixed_len() == Some(tlv.value.len()) {
+                        Ok(tlv.value)
+                    } else {
+                        Err(types::TbfParseError::BadTlvEntry(tipe as usize))
+                    }
+                };
+
+                match tipe {
+                    // If there is already a header do nothing: if this is a
+                    // second Main keep the first one, if it's a Program we
+                    // ignore the Main
+                    types::TbfHeaderTypes::TbfHeaderMain if main_pointer.is_none() => {
+                        main_pointer = Some(fixed_size_value()?.try_into()?);
+                    }
+                    types::TbfHeaderTypes::TbfHeaderProgram if program_pointer.is_none() => {
+                        program_pointer = Some(fixed_size_value()?.try_into()?);
+                    }
+                    types::TbfHeaderTypes::TbfHeaderPackageName => {
+                        let name = core::str::from_utf8(tlv.value)
+                            .or(Err(types::TbfParseError::BadTlvEntry(tipe as usize)))?;
+                        package_name = Some(String::from(name));
+                    }
+                    types::TbfHeaderTypes::TbfHeaderFixedAddresses => {
+                        fixed_address_pointer = Some(fixed_size_value()?.try_into()?);
+                    }
+                    // One TLV can list several regions.
+                    types::TbfHeaderTypes::TbfHeaderWriteableFlashRegions => {
+                        if tlv.value.len() % 8 != 0 {
+                            return Err(types::TbfParseError::BadTlvEntry(tipe as usize));
+                        }
+                        for region in tlv.value.chunks_exact(8) {
+                            writeable_regions.push(region.try_into()?);
+                        }
+                    }
+                    // The number of entries, then 16 bytes per entry.
+                    types::TbfHeaderTypes::TbfHeaderPermissions => {
+                        let bad_entry = types::TbfParseError::BadTlvEntry(tipe as usize);
+                        let (count, entries) = tlv.value.split_at_checked(2).ok_or(bad_entry)?;
+                        let count = u16::from_le_bytes(count.try_into()?) as usize;
+                        if entries.len() != count * 16 {
+                            return Err(bad_entry);
+                        }
+                        permissions = Some(
+                            entries
+                                .chunks_exact(16)
+                                .map(types::TbfHeaderDriverPermission::try_from)
+                                .collect::<Result<_, _>>()?,
+                        );
+                    }
+                    types::TbfHeaderTypes::TbfHeaderKernelVersion => {
+                        kernel_version = Some(fixed_size_value()?.try_into()?);
+                    }
+                    // Any TLV we do not interpret yet is skipped using its
+                    // length, like an unknown one.
+                    _ => {}
+                }
+            }
+
+            let tbf_header = types::TbfHeaderV2 {
+                base: tbf_header_base,
+                main: main_pointer,
+                program: program_pointer,
+                package_name,
+                fixed_addresses: fixed_address_pointer,
+                writeable_regions,
+                permissions,
+                kernel_version,
+            };
+
+            Ok(types::TbfHeader::TbfHeaderV2(tbf_header))
+        }
+        _ => Err(types::TbfParseError::UnsupportedVersion(version)),
+    }
+}
+
+/// Walk the TBFs in `flash`, a dump of the app region, returning the offset
+/// and header of each.
+///
+/// Each TBF starts right after the previous one ends, and the walk ends where
+/// no TBF header can be parsed (usually erased flash), at a TBF with a size of
+/// 0, or at the end of `flash`. Padding TBFs are returned as well. TBFs whose
+/// header is invalid but whose length is known are skipped.
+pub fn iter_apps(flash: &[u8]) -> AppIterator<'_> {
+    AppIterator { flash, offset: 0 }
+}
+
+/// The iterator returned by [`iter_apps()`].
+pub struct AppIterator<'a> {
+    flash: &'a [u8],
+    offset: usize,
+}
+
+impl AppIterator<'_> {
+    /// Where the next TBF starts. Once the walk has ended, this is where the
+    /// TBFs end unless it stopped at an unparseable header.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+impl Iterator for AppIterator<'_> {
+    type Item = (usize, types::TbfHeader);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let offset = self.offset;
+            let tbf = self.flash.get(offset..)?;
+            let lengths = tbf.get(0..8)?.try_into().ok()?;
+            let (total_size, header) = match parse_tbf_header_lengths(lengths) {
+                Ok((version, header_size, total_size)) => (
+                    total_size,
+                    tbf.get(..header_size as usize)
+                        .and_then(|header| parse_tbf_header(header, version).ok()),
+                ),
+                Err(types::InitialTbfParseError::InvalidHeader(total_size)) => (total_size, None),
+                Err(types::InitialTbfParseError::UnableToParse) => return None,
+            };
+            if total_size == 0 {
+                return None;
+            }
+            // Past the end of `flash` the walk ends on the next call.
+            self.offset = offset.saturating_add(total_size as usize);
+            if let Some(header) = header {
+                return Some((offset, header));
+            }
+        }
+    }
+}
+
+/// One TLV of a TBF header, as found in its bytes.
+#[derive(Clone, Copy, Debug)]
+pub struct HeaderTlv<'a> {
+    /// Where the TLV starts, from the start of the header.
+    pub offset: usize,
+    /// The type as stored, which [`types::TbfHeaderTypes::from()`] names.
+    pub tipe: u16,
+    /// The value, without the padding up to 4 bytes.
+    pub value: &'a [u8],
+}
+
+/// Walk the TLVs of a v2 `header`, which starts with the 16 byte base header.
+///
+/// The TLVs are not interpreted, so unknown ones are returned too. A TLV that
+/// runs past the end of the header is returned as an error, after which the
+/// walk ends.
+pub fn header_tlvs(header: &[u8]) -> HeaderTlvs<'_> {
+    HeaderTlvs {
+        header,
+        offset: 16,
+        failed: false,
+    }
+}
+
+/// The iterator returned by [`header_tlvs()`].
+pub struct HeaderTlvs<'a> {
+    header: &'a [u8],
+    offset: usize,
+    failed: bool,
+}
+
+impl<'a> HeaderTlvs<'a> {
+    /// Where the next TLV starts, or where the one that failed to be read
+    /// starts, from the start of the header.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    fn read_tlv(&mut self) -> Result<HeaderTlv<'a>, types::TbfParseError> {
+        let offset = self.offset;
+        let remaining = &self.header[offset..];
+        let tlv_header = remaining
+            .get(0..4)
+            .ok_or(types::TbfParseError::NotEnoughFlash)?;
+        let tipe = u16::from_le_bytes([tlv_header[0], tlv_header[1]]);
+        let length = u16::from_le_bytes([tlv_header[2], tlv_header[3]]) as usize;
+        let value = remaining
+            .get(4..4 + length)
+            .ok_or(types::TbfParseError::NotEnoughFlash)?;
+
+        // All TLV blocks are padded to 4 bytes, so we need to skip more if
+        // the length is not a multiple of 4.
+        let skip_len = 4 + length.next_multiple_of(4);
+        if skip_len > remaining.len() {
+            return Err(types::TbfParseError::NotEnoughFlash);
+        }
+        self.offset += skip_len;
+        Ok(HeaderTlv {
+            offset,
+            tipe,
+            value,
+        })
+    }
+}
+
+impl<'a> Iterator for HeaderTlvs<'a> {
+    type Item = Result<HeaderTlv<'a>, types::TbfParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed || self.offset >= self.header.len() {
+            return None;
+        }
+        let tlv = self.read_tlv();
+        self.failed = tlv.is_err();
+        Some(tlv)
+    }
+}
+
+/// Parse one TBF footer.
+///
+/// `footers` must start at a footer, e.g. at the binary end offset of the app
+/// for the first one. Footers can only be Credentials, anything else is an
+/// error.
+///
+/// ## Return
+///
+/// The footer, and the number of bytes it takes up (including its TLV header)
+/// so that the caller can find the next one.
+pub fn parse_tbf_footer(
+    footers: &[u8],
+) -> Result<(types::TbfFooterV2Credentials, u32), types::TbfParseError> {
+    let tlv_header: types::TbfTlv = footers
+        .get(0..4)
+        .ok_or(types::TbfParseError::NotEnoughFlash)?
+        .try_into()?;
+
+    match tlv_header.tipe {
+        types::TbfHeaderTypes::TbfFooterCredentials => {
+            let credentials: types::TbfFooterV2Credentials = footers
+                .get(4..4 + tlv_header.length as usize)
+                .ok_or(types::TbfParseError::NotEnoughFlash)?
+                .try_into()?;
+            Ok((credentials, 4 + tlv_header.length as u32))
+        }
+        _ => Err(types::TbfParseError::BadTlvEntry(tlv_header.tipe as usize)),
+    }
+}
@@ -0,0 +1,131 @@
+//! Building TBF fixtures for tests, so that they do not need checked-in
+//! binaries.
+//!
+//! Only compiled with the `testing` feature, which the tests of this crate and
+//! of tockloader turn on, so that both lay out and checksum headers the same
+//! way, with [`TbfHeaderBuilder`].
+
+use alloc::vec::Vec;
+
+use crate::build::TbfHeaderBuilder;
+use crate::edit::update_checksum_in_raw;
+
+/// Which header tells the kernel how to run the app.
+#[derive(Clone, Copy, Debug)]
+pub enum AppHeader {
+    /// No Main or Program header, and no other TLVs: a padding TBF.
+    None,
+    Main,
+    Program {
+        version: u32,
+    },
+}
+
+/// What [`make_tbf()`] puts in the TBF.
+#[derive(Clone, Debug)]
+pub struct TbfOptions<'a> {
+    /// The version written in the header, which is otherwise laid out as a v2
+    /// header whatever it is. Use [`make_v1_tbf()`] for a real v1 header.
+    pub version: u16,
+    pub enabled: bool,
+    pub header: AppHeader,
+    pub name: Option<&'a str>,
+    /// `(offset, size)` of each writeable flash region.
+    pub writeable_regions: Vec<(u32, u32)>,
+    /// Any other TLVs, as `(type, value)`. Values are padded to 4 bytes.
+    pub extra_tlvs: Vec<(u16, Vec<u8>)>,
+    pub protected_trailer_size: u32,
+    pub binary: Vec<u8>,
+    /// Credentials footers, as `(format, data)`. Only apps with a Program
+    /// header can have footers.
+    pub footers: Vec<(u32, Vec<u8>)>,
+}
+
+impl Default for TbfOptions<'_> {
+    fn default() -> Self {
+        TbfOptions {
+            version: 2,
+            enabled: true,
+            header: AppHeader::Program { version: 0 },
+            name: None,
+            writeable_regions: Vec::new(),
+            extra_tlvs: Vec::new(),
+            protected_trailer_size: 0,
+            binary: Vec::new(),
+            footers: Vec::new(),
+        }
+    }
+}
+
+/// Build a TBF as described by `options`, with a valid checksum. The app
+/// starts executing at the start of its binary, and its protected trailer is
+/// filled with `0xAA`.
+pub fn make_tbf(options: &TbfOptions) -> Vec<u8> {
+    let footers: Vec<u8> = options
+        .footers
+        .iter()
+        .flat_map(|(format, data)| {
+            // Unlike header TLVs, footers are not padded.
+            let mut footer = Vec::new();
+            footer.extend_from_slice(&128u16.to_le_bytes());
+            footer.extend_from_slice(&(4 + data.len() as u16).to_le_bytes());
+            footer.extend_from_slice(&format.to_le_bytes());
+            footer.extend_from_slice(data);
+            footer
+        })
+        .collect();
+
+    let trailer = options.protected_trailer_size;
+    let mut builder = TbfHeaderBuilder::new()
+        .enabled(options.enabled)
+        .binary_size(options.binary.len() as u32)
+        .footer_size(footers.len() as u32);
+    builder = match options.header {
+        AppHeader::None => builder,
+        AppHeader::Main => builder.main(trailer, trailer, 4096),
+        AppHeader::Program { version } => builder.program(trailer, trailer, 4096, version),
+    };
+    if let Some(name) = options.name {
+        builder = builder.package_name(name);
+    }
+    for (offset, size) in &options.writeable_regions {
+        builder = builder.writeable_flash_region(*offset, *size);
+    }
+    for (tipe, value) in &options.extra_tlvs {
+        builder = builder.tlv(*tipe, value);
+    }
+
+    let mut tbf = builder.build().unwrap();
+    if options.version != 2 {
+        tbf[0..2].copy_from_slice(&options.version.to_le_bytes());
+        update_checksum_in_raw(&mut tbf).unwrap();
+    }
+    tbf.extend(core::iter::repeat_n(0xAA, trailer as usize));
+    tbf.extend_from_slice(&options.binary);
+    tbf.extend(footers);
+    tbf
+}
+
+/// Build a TBF with a v1 header in front of `binary`, which starts executing
+/// at its first byte. Every section is empty except for `bss_size` bytes of
+/// BSS, and the stack and heaps are given small sizes.
+pub fn make_v1_tbf(binary: &[u8], bss_size: u32) -> Vec<u8> {
+    let total_size = 76 + binary.len() as u32;
+    let mut words = [0u32; 18];
+    words[0] = 1;
+    words[1] = total_size;
+    words[2] = 76;
+    words[12] = bss_size;
+    words[13] = 1024;
+    words[14] = 2048;
+    words[15] = 512;
+    let checksum = words.iter().fold(0, |acc, word| acc ^ word);
+
+    let mut tbf: Vec<u8> = words
+        .iter()
+        .chain([checksum].iter())
+        .flat_map(|word| word.to_le_bytes())
+        .collect();
+    tbf.extend_from_slice(binary);
+    tbf
+}
@@ -0,0 +1,769 @@
+//! Types and data structures for TBF headers.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+/// Error when parsing just the beginning of the TBF header. This is only used
+/// when establishing the linked list structure of apps installed in flash.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InitialTbfParseError {
+    /// We were unable to parse the beginning of the header. This either means
+    /// we ran out of flash, or the trusted values are invalid meaning this is
+    /// just empty flash after the end of the last app. This error is fine, as
+    /// it just means we have found the end of the linked list of apps.
+    UnableToParse,
+
+    /// Some length or value in the header is invalid. The header parsing has
+    /// failed at this point. However, the total app length field is known to
+    /// be valid. We return the app length so the caller can skip over it.
+    InvalidHeader(u32),
+}
+
+impl From<core::array::TryFromSliceError> for InitialTbfParseError {
+    // Convert a slice to a parsed type. Since we control how long we make our
+    // slices, this conversion should never fail. If it does, then this is a
+    // bug in this library that must be fixed.
+    fn from(_error: core::array::TryFromSliceError) -> Self {
+        InitialTbfParseError::UnableToParse
+    }
+}
+
+/// Error when parsing an app's TBF header.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TbfParseError {
+    /// Not enough bytes in the buffer to parse the expected field.
+    NotEnoughFlash,
+
+    /// Unknown version of the TBF header.
+    UnsupportedVersion(u16),
+
+    /// Checksum calculation did not match what is stored in the TBF header.
+    /// First value is the checksum provided, second value is the checksum we
+    /// calculated.
+    ChecksumMismatch(u32, u32),
+
+    /// One of the TLV entries did not parse correctly. This could happen if the
+    /// TLV.length does not match the size of a fixed-length entry. The `usize`
+    /// is the value of the "tipe" field.
+    BadTlvEntry(usize),
+
+    /// Internal kernel error. This is a bug inside of this library. Likely this
+    /// means that for some reason a slice was not sized properly for parsing a
+    /// certain type, which is something completely controlled by this library.
+    /// If the slice passed in is not long enough, then a `get()` call will
+    /// fail and that will trigger a different error.
+    InternalError,
+
+    /// The credentials can not be checked by this library, such as hashes or
+    /// signatures of a format it does not implement.
+    UnsupportedCredentials(TbfFooterV2CredentialsType),
+}
+
+impl From<core::array::TryFromSliceError> for TbfParseError {
+    // Convert a slice to a parsed type. Since we control how long we make our
+    // slices, this conversion should never fail. If it does, then this is a
+    // bug in this library that must be fixed.
+    fn from(_error: core::array::TryFromSliceError) -> Self {
+        TbfParseError::InternalError
+    }
+}
+
+impl fmt::Display for TbfParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TbfParseError::NotEnoughFlash => write!(f, "Buffer too short to parse TBF header"),
+            TbfParseError::UnsupportedVersion(version) => {
+                write!(f, "TBF version {version} unsupported")
+            }
+            TbfParseError::ChecksumMismatch(app, calc) => write!(
+                f,
+                "Checksum verification failed: app:{app:#x}, calc:{calc:#x}"
+            ),
+            TbfParseError::BadTlvEntry(tipe) => write!(f, "TLV entry type {tipe} is invalid"),
+            TbfParseError::InternalError => write!(f, "Internal error in the TBF parser"),
+            TbfParseError::UnsupportedCredentials(format) => {
+                write!(f, "{format:?} credentials can not be verified")
+            }
+        }
+    }
+}
+
+/// TBF fields that must be present in all v2 headers.
+#[derive(Clone, Copy, Debug)]
+pub struct TbfHeaderV2Base {
+    pub(crate) header_size: u16,
+    pub(crate) total_size: u32,
+    pub(crate) flags: u32,
+    pub(crate) checksum: u32,
+}
+
+/// Types in TLV structures for each optional block of the header.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TbfHeaderTypes {
+    TbfHeaderMain = 1,
+    TbfHeaderWriteableFlashRegions = 2,
+    TbfHeaderPackageName = 3,
+    TbfHeaderFixedAddresses = 5,
+    TbfHeaderPermissions = 6,
+    TbfHeaderStoragePermissions = 7,
+    TbfHeaderKernelVersion = 8,
+    TbfHeaderProgram = 9,
+    TbfFooterCredentials = 128,
+
+    /// Some field in the header that we do not understand. Since the TLV format
+    /// specifies the length of each section, if we get a field we do not
+    /// understand we just skip it, rather than throwing an error.
+    Unknown,
+}
+
+/// The TLV header (T and L).
+#[derive(Clone, Copy, Debug)]
+pub struct TbfTlv {
+    pub(crate) tipe: TbfHeaderTypes,
+    pub(crate) length: u16,
+}
+
+/// The v2 Main Header for apps.
+///
+/// All apps must have either a Main Header or a Program Header. Without
+/// either, the TBF object is considered padding. Main and Program Headers
+/// differ in whether they specify the endpoint of the process binary; Main
+/// Headers do not, while Program Headers do. A TBF with a Main Header cannot
+/// have any Credentials Footers, while a TBF with a Program Header can.
+#[derive(Clone, Copy, Debug)]
+pub struct TbfHeaderV2Main {
+    pub(crate) init_fn_offset: u32,
+    pub(crate) protected_trailer_size: u32,
+    pub(crate) minimum_ram_size: u32,
+}
+
+/// The v2 Program Header for apps.
+///
+/// Like the Main Header, but it also specifies where the process binary ends,
+/// which allows Credentials Footers to be placed after it. It also carries a
+/// version number for the userspace binary.
+#[derive(Clone, Copy, Debug)]
+pub struct TbfHeaderV2Program {
+    pub(crate) init_fn_offset: u32,
+    pub(crate) protected_trailer_size: u32,
+    pub(crate) minimum_ram_size: u32,
+    pub(crate) binary_end_offset: u32,
+    pub(crate) version: u32,
+}
+
+/// The v2 Fixed Addresses Header for apps that are not position independent.
+///
+/// Each address is where the app must be placed, or `0xFFFFFFFF` if it can be
+/// placed anywhere.
+#[derive(Clone, Copy, Debug)]
+pub struct TbfHeaderV2FixedAddresses {
+    pub(crate) start_process_ram: u32,
+    pub(crate) start_process_flash: u32,
+}
+
+/// A region of flash the app may write to, from a v2 Writeable Flash Regions
+/// Header. The offset is counted from the start of the TBF.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TbfHeaderV2WriteableFlashRegion {
+    pub(crate) writeable_flash_region_offset: u32,
+    pub(crate) writeable_flash_region_size: u32,
+}
+
+/// One entry of a v2 Permissions Header: which of 64 commands of a driver
+/// the app may call.
+///
+/// `offset` selects the block of commands, so that bit `n` of
+/// `allowed_commands` is command `offset * 64 + n`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TbfHeaderDriverPermission {
+    pub(crate) driver_number: u32,
+    pub(crate) offset: u32,
+    pub(crate) allowed_commands: u64,
+}
+
+/// What an app may call in one block of 64 commands of a driver, as the
+/// kernel decides from the Permissions Header.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CommandPermissions {
+    /// The app has no Permissions Header, so the kernel does not restrict
+    /// its commands.
+    NoPermsAtAll,
+    /// The Permissions Header does not mention the driver.
+    NoPermsThisDriver,
+    /// The commands of the block that may be called, as a bitmask. The
+    /// driver is listed but not for this block when the mask is 0.
+    Mask(u64),
+}
+
+/// The v2 Kernel Version Header, the oldest kernel version the app works
+/// with.
+#[derive(Clone, Copy, Debug)]
+pub struct TbfHeaderV2KernelVersion {
+    pub(crate) major: u16,
+    pub(crate) minor: u16,
+}
+
+/// Size of a v1 header, which has no optional parts.
+pub const TBF_HEADER_V1_SIZE: u16 = 76;
+
+/// The v1 header, used by Tock apps before TLVs were introduced.
+///
+/// It is a fixed list of 19 words: the version, the total size, the entry
+/// point, then the offsets and sizes of the relocation data, text, GOT, data
+/// and BSS sections, the minimum stack and heap sizes, the offset and size of
+/// the package name, and a checksum of the other words. Only the words that
+/// tell where the app starts and how much memory it needs are kept. The
+/// package name is stored in the app binary rather than in the header.
+#[derive(Clone, Copy, Debug)]
+pub struct TbfHeaderV1 {
+    pub(crate) total_size: u32,
+    pub(crate) entry_offset: u32,
+    pub(crate) got_size: u32,
+    pub(crate) data_size: u32,
+    pub(crate) bss_size: u32,
+    pub(crate) min_stack_len: u32,
+    pub(crate) min_app_heap_len: u32,
+    pub(crate) min_kernel_heap_len: u32,
+    pub(crate) checksum: u32,
+}
+
+/// Single header that can contain all parts of a v2 header.
+#[derive(Clone, Debug)]
+pub struct TbfHeaderV2 {
+    pub(crate) base: TbfHeaderV2Base,
+    pub(crate) main: Option<TbfHeaderV2Main>,
+    pub(crate) program: Option<TbfHeaderV2Program>,
+    pub(crate) package_name: Option<String>,
+    pub(crate) fixed_addresses: Option<TbfHeaderV2FixedAddresses>,
+    pub(crate) writeable_regions: Vec<TbfHeaderV2WriteableFlashRegion>,
+    /// `None` without a Permissions Header, which is not the same as an
+    /// empty one.
+    pub(crate) permissions: Option<Vec<TbfHeaderDriverPermission>>,
+    pub(crate) kernel_version: Option<TbfHeaderV2KernelVersion>,
+}
+
+/// Type that represents the fields of the Tock Binary Format header.
+///
+/// This specifies the locations of the different code and memory sections
+/// in the tock binary, as well as other information about the application.
+/// The kernel can also use this header to keep persistent state about
+/// the application.
+#[derive(Clone, Debug)]
+pub enum TbfHeader {
+    TbfHeaderV1(TbfHeaderV1),
+    TbfHeaderV2(TbfHeaderV2),
+    Padding(TbfHeaderV2Base),
+}
+
+/// The type of a Credentials Footer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TbfFooterV2CredentialsType {
+    Reserved = 0,
+    Rsa3072Key = 1,
+    Rsa4096Key = 2,
+    SHA256 = 3,
+    SHA384 = 4,
+    SHA512 = 5,
+    EcdsaNistP256 = 6,
+}
+
+/// A Credentials Footer, which lets the kernel check the integrity or the
+/// origin of the app. The credentials cover the app from the start of its
+/// header up to the end of its binary.
+#[derive(Clone, Debug)]
+pub struct TbfFooterV2Credentials {
+    pub(crate) format: TbfFooterV2CredentialsType,
+    pub(crate) data: Vec<u8>,
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Conversions from raw bytes
+////////////////////////////////////////////////////////////////////////////////
+
+impl TryFrom<&[u8]> for TbfHeaderV2Base {
+    type Error = TbfParseError;
+
+    fn try_from(b: &[u8]) -> Result<TbfHeaderV2Base, Self::Error> {
+        if b.len() < 16 {
+            return Err(TbfParseError::NotEnoughFlash);
+        }
+        Ok(TbfHeaderV2Base {
+            header_size: u16::from_le_bytes(b[2..4].try_into()?),
+            total_size: u32::from_le_bytes(b[4..8].try_into()?),
+            flags: u32::from_le_bytes(b[8..12].try_into()?),
+            checksum: u32::from_le_bytes(b[12..16].try_into()?),
+        })
+    }
+}
+
+impl TbfHeaderTypes {
+    /// The length of the value of TLVs of this type, for the types whose
+    /// length is fixed.
+    pub fn fixed_len(&self) -> Option<usize> {
+        match self {
+            TbfHeaderTypes::TbfHeaderMain => Some(12),
+            TbfHeaderTypes::TbfHeaderFixedAddresses => Some(8),
+            TbfHeaderTypes::TbfHeaderKernelVersion => Some(4),
+            TbfHeaderTypes::TbfHeaderProgram => Some(20),
+            _ => None,
+        }
+    }
+}
+
+impl From<u16> for TbfHeaderTypes {
+    fn from(h: u16) -> Self {
+        match h {
+            1 => TbfHeaderTypes::TbfHeaderMain,
+            2 => TbfHeaderTypes::TbfHeaderWriteableFlashRegions,
+            3 => TbfHeaderTypes::TbfHeaderPackageName,
+            5 => TbfHeaderTypes::TbfHeaderFixedAddresses,
+            6 => TbfHeaderTypes::TbfHeaderPermissions,
+            7 => TbfHeaderTypes::TbfHeaderStoragePermissions,
+            8 => TbfHeaderTypes::TbfHeaderKernelVersion,
+            9 => TbfHeaderTypes::TbfHeaderProgram,
+            128 => TbfHeaderTypes::TbfFooterCredentials,
+            _ => TbfHeaderTypes::Unknown,
+        }
+    }
+}
+
+impl TryFrom<&[u8]> for TbfTlv {
+    type Error = TbfParseError;
+
+    fn try_from(b: &[u8]) -> Result<TbfTlv, Self::Error> {
+        if b.len() < 4 {
+            return Err(TbfParseError::NotEnoughFlash);
+        }
+        Ok(TbfTlv {
+            tipe: u16::from_le_bytes(b[0..2].try_into()?).into(),
+            length: u16::from_le_bytes(b[2..4].try_into()?),
+        })
+    }
+}
+
+impl TryFrom<u32> for TbfFooterV2CredentialsType {
+    type Error = TbfParseError;
+
+    fn try_from(value: u32) -> Result<TbfFooterV2CredentialsType, Self::Error> {
+        match value {
+            0 => Ok(TbfFooterV2CredentialsType::Reserved),
+            1 => Ok(TbfFooterV2CredentialsType::Rsa3072Key),
+            2 => Ok(TbfFooterV2CredentialsType::Rsa4096Key),
+            3 => Ok(TbfFooterV2CredentialsType::SHA256),
+            4 => Ok(TbfFooterV2CredentialsType::SHA384),
+            5 => Ok(TbfFooterV2CredentialsType::SHA512),
+            6 => Ok(TbfFooterV2CredentialsType::EcdsaNistP256),
+            _ => Err(TbfParseError::BadTlvEntry(
+                TbfHeaderTypes::TbfFooterCredentials as usize,
+            )),
+        }
+    }
+}
+
+impl TryFrom<&[u8]> for TbfFooterV2Credentials {
+    type Error = TbfParseError;
+
+    /// Parse the value of a Credentials TLV: the format followed by the
+    /// credentials themselves.
+    fn try_from(b: &[u8]) -> Result<TbfFooterV2Credentials, Self::Error> {
+        if b.len() < 4 {
+            return Err(TbfParseError::NotEnoughFlash);
+        }
+        Ok(TbfFooterV2Credentials {
+            format: u32::from_le_bytes(b[0..4].try_into()?).try_into()?,
+            data: b[4..].to_vec(),
+        })
+    }
+}
+
+impl TryFrom<&[u8]> for TbfHeaderV2Main {
+    type Error = TbfParseError;
+
+    fn try_from(b: &[u8]) -> Result<TbfHeaderV2Main, Self::Error> {
+        // For 3 or more fields, this shortcut check reduces code size.
+        if b.len() < 12 {
+            return Err(TbfParseError::NotEnoughFlash);
+        }
+        Ok(TbfHeaderV2Main {
+            init_fn_offset: u32::from_le_bytes(b[0..4].try_into()?),
+            protected_trailer_size: u32::from_le_bytes(b[4..8].try_into()?),
+            minimum_ram_size: u32::from_le_bytes(b[8..12].try_into()?),
+        })
+    }
+}
+
+impl TryFrom<&[u8]> for TbfHeaderV2Program {
+    type Error = TbfParseError;
+
+    fn try_from(b: &[u8]) -> Result<TbfHeaderV2Program, Self::Error> {
+        // For 3 or more fields, this shortcut check reduces code size.
+        if b.len() < 20 {
+            return Err(TbfParseError::NotEnoughFlash);
+        }
+        Ok(TbfHeaderV2Program {
+            init_fn_offset: u32::from_le_bytes(b[0..4].try_into()?),
+            protected_trailer_size: u32::from_le_bytes(b[4..8].try_into()?),
+            minimum_ram_size: u32::from_le_bytes(b[8..12].try_into()?),
+            binary_end_offset: u32::from_le_bytes(b[12..16].try_into()?),
+            version: u32::from_le_bytes(b[16..20].try_into()?),
+        })
+    }
+}
+
+impl TryFrom<&[u8]> for TbfHeaderV1 {
+    type Error = TbfParseError;
+
+    fn try_from(b: &[u8]) -> Result<TbfHeaderV1, Self::Error> {
+        if b.len() < TBF_HEADER_V1_SIZE as usize {
+            return Err(TbfParseError::NotEnoughFlash);
+        }
+        let word = |index: usize| -> Result<u32, TbfParseError> {
+            Ok(u32::from_le_bytes(b[index * 4..index * 4 + 4].try_into()?))
+        };
+        Ok(TbfHeaderV1 {
+            total_size: word(1)?,
+            entry_offset: word(2)?,
+            got_size: word(8)?,
+            data_size: word(10)?,
+            bss_size: word(12)?,
+            min_stack_len: word(13)?,
+            min_app_heap_len: word(14)?,
+            min_kernel_heap_len: word(15)?,
+            checksum: word(18)?,
+        })
+    }
+}
+
+impl TryFrom<&[u8]> for TbfHeaderV2FixedAddresses {
+    type Error = TbfParseError;
+
+    fn try_from(b: &[u8]) -> Result<TbfHeaderV2FixedAddresses, Self::Error> {
+        if b.len() < 8 {
+            return Err(TbfParseError::NotEnoughFlash);
+        }
+        Ok(TbfHeaderV2FixedAddresses {
+            start_process_ram: u32::from_le_bytes(b[0..4].try_into()?),
+            start_process_flash: u32::from_le_bytes(b[4..8].try_into()?),
+        })
+    }
+}
+
+impl TryFrom<&[u8]> for TbfHeaderV2WriteableFlashRegion {
+    type Error = TbfParseError;
+
+    fn try_from(b: &[u8]) -> Result<TbfHeaderV2WriteableFlashRegion, Self::Error> {
+        if b.len() < 8 {
+            return Err(TbfParseError::NotEnoughFlash);
+        }
+        Ok(TbfHeaderV2WriteableFlashRegion {
+            writeable_flash_region_offset: u32::from_le_bytes(b[0..4].try_into()?),
+            writeable_flash_region_size: u32::from_le_bytes(b[4..8].try_into()?),
+        })
+    }
+}
+
+impl TryFrom<&[u8]> for TbfHeaderDriverPermission {
+    type Error = TbfParseError;
+
+    fn try_from(b: &[u8]) -> Result<TbfHeaderDriverPermission, Self::Error> {
+        if b.len() < 16 {
+            return Err(TbfParseError::NotEnoughFlash);
+        }
+        Ok(TbfHeaderDriverPermission {
+            driver_number: u32::from_le_bytes(b[0..4].try_into()?),
+            offset: u32::from_le_bytes(b[4..8].try_into()?),
+            allowed_commands: u64::from_le_bytes(b[8..16].try_into()?),
+        })
+    }
+}
+
+impl TryFrom<&[u8]> for TbfHeaderV2KernelVersion {
+    type Error = TbfParseError;
+
+    fn try_from(b: &[u8]) -> Result<TbfHeaderV2KernelVersion, Self::Error> {
+        if b.len() < 4 {
+            return Err(TbfParseError::NotEnoughFlash);
+        }
+        Ok(TbfHeaderV2KernelVersion {
+            major: u16::from_le_bytes(b[0..2].try_into()?),
+            minor: u16::from_le_bytes(b[2..4].try_into()?),
+        })
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Accessors
+////////////////////////////////////////////////////////////////////////////////
+
+impl TbfHeader {
+    /// Return whether this is an app or just padding between apps. Use
+    /// [`as_app()`](TbfHeader::as_app) or
+    /// [`as_padding()`](TbfHeader::as_padding) to also get at the header.
+    pub fn is_app(&self) -> bool {
+        match self {
+            TbfHeader::TbfHeaderV1(_) | TbfHeader::TbfHeaderV2(_) => true,
+            TbfHeader::Padding(_) => false,
+        }
+    }
+
+    /// Return the header of the app, or `None` for padding and apps with a
+    /// v1 header.
+    pub fn as_app(&self) -> Option<&TbfHeaderV2> {
+        match self {
+            TbfHeader::TbfHeaderV2(hd) => Some(hd),
+            TbfHeader::TbfHeaderV1(_) | TbfHeader::Padding(_) => None,
+        }
+    }
+
+    /// Return the base header of padding, or `None` for an app.
+    pub fn as_padding(&self) -> Option<&TbfHeaderV2Base> {
+        match self {
+            TbfHeader::TbfHeaderV1(_) | TbfHeader::TbfHeaderV2(_) => None,
+            TbfHeader::Padding(base) => Some(base),
+        }
+    }
+
+    /// Return whether the application is enabled or not.
+    /// Disabled applications are not started by the kernel.
+    pub fn enabled(&self) -> bool {
+        match self {
+            TbfHeader::TbfHeaderV2(hd) => {
+                // Bit 1 of flags is the enable/disable bit.
+                hd.base.flags & 0x00000001 == 1
+            }
+            // There were no flags yet, every app was started.
+            TbfHeader::TbfHeaderV1(_) => true,
+            TbfHeader::Padding(_) => false,
+        }
+    }
+
+    /// Return whether the application is sticky. Sticky apps are meant to
+    /// stay installed when the other apps are erased.
+    pub fn sticky(&self) -> bool {
+        match self {
+            // Bit 2 of flags is the sticky bit.
+            TbfHeader::TbfHeaderV2(hd) => hd.base.flags & 0x00000002 != 0,
+            TbfHeader::TbfHeaderV1(_) | TbfHeader::Padding(_) => false,
+        }
+    }
+
+    /// Get the size in bytes of the TBF header, including all TLVs.
+    pub fn get_header_size(&self) -> u16 {
+        match self {
+            TbfHeader::TbfHeaderV1(_) => TBF_HEADER_V1_SIZE,
+            TbfHeader::TbfHeaderV2(hd) => hd.base.header_size,
+            TbfHeader::Padding(base) => base.header_size,
+        }
+    }
+
+    /// Get the total size in flash of this app or padding, including the
+    /// header and any footers.
+    pub fn get_total_size(&self) -> u32 {
+        match self {
+            TbfHeader::TbfHeaderV1(hd) => hd.total_size,
+            TbfHeader::TbfHeaderV2(hd) => hd.base.total_size,
+            TbfHeader::Padding(base) => base.total_size,
+        }
+    }
+
+    /// Add up all of the relevant fields in header version 1, or just used the
+    /// app provided value in version 2 to get the total amount of RAM that is
+    /// needed for this app.
+    pub fn get_minimum_app_ram_size(&self) -> u32 {
+        match self {
+            TbfHeader::TbfHeaderV1(hd) => {
+                // Read from flash, so they may add up past what a u32 holds.
+                [
+                    hd.data_size,
+                    hd.bss_size,
+                    hd.min_stack_len,
+                    hd.min_app_heap_len,
+                    hd.min_kernel_heap_len,
+                ]
+                .into_iter()
+                .fold(hd.got_size, u32::saturating_add)
+            }
+            TbfHeader::TbfHeaderV2(hd) => match (hd.program, hd.main) {
+                (Some(program), _) => program.minimum_ram_size,
+                (None, Some(main)) => main.minimum_ram_size,
+                (None, None) => 0,
+            },
+            TbfHeader::Padding(_) => 0,
+        }
+    }
+
+    /// Get the number of bytes from the start of the app's region in flash that
+    /// is for kernel use only. The app cannot write this region. This is the
+    /// TBF header plus the protected trailer that follows it.
+    pub fn get_protected_size(&self) -> u32 {
+        match self {
+            // There is no protected trailer in v1.
+            TbfHeader::TbfHeaderV1(_) => TBF_HEADER_V1_SIZE as u32,
+            TbfHeader::TbfHeaderV2(hd) => {
+                let protected_trailer_size = match (hd.program, hd.main) {
+                    (Some(program), _) => program.protected_trailer_size,
+                    (None, Some(main)) => main.protected_trailer_size,
+                    (None, None) => return 0,
+                };
+                // Saturated so that a corrupt size is too large for any app,
+                // rather than wrapping around to one that looks valid.
+                protected_trailer_size.saturating_add(hd.base.header_size as u32)
+            }
+            TbfHeader::Padding(_) => 0,
+        }
+    }
+
+    /// Get the offset from the beginning of the app's flash region where the
+    /// app binary starts. The binary always immediately follows the protected
+    /// region.
+    pub fn get_app_start_offset(&self) -> u32 {
+        self.get_protected_size()
+    }
+
+    /// Get the offset from the beginning of the app's flash region where the
+    /// app should start executing.
+    pub fn get_init_function_offset(&self) -> u32 {
+        match self {
+            TbfHeader::TbfHeaderV1(hd) => hd.entry_offset,
+            TbfHeader::TbfHeaderV2(hd) => {
+                let init_fn_offset = match (hd.program, hd.main) {
+                    (Some(program), _) => program.init_fn_offset,
+                    (None, Some(main)) => main.init_fn_offset,
+                    (None, None) => return 0,
+                };
+                init_fn_offset.saturating_add(hd.base.header_size as u32)
+            }
+            TbfHeader::Padding(_) => 0,
+        }
+    }
+
+    /// Get the offset from the beginning of the app's flash region where the
+    /// app binary ends and any footers begin. Apps with only a Main Header have
+    /// no footers, so their binary extends to the end of the app.
+    pub fn get_binary_end(&self) -> u32 {
+        match self {
+            TbfHeader::TbfHeaderV1(hd) => hd.total_size,
+            TbfHeader::TbfHeaderV2(hd) => hd
+                .program
+                .map_or(hd.base.total_size, |program| program.binary_end_offset),
+            TbfHeader::Padding(base) => base.total_size,
+        }
+    }
+
+    /// Return whether the app has a Program Header. Apps with only a Main
+    /// Header have no binary version and no footers.
+    pub fn has_program_header(&self) -> bool {
+        match self {
+            TbfHeader::TbfHeaderV2(hd) => hd.program.is_some(),
+            TbfHeader::TbfHeaderV1(_) | TbfHeader::Padding(_) => false,
+        }
+    }
+
+    /// Get the version of the userspace binary, as set in the Program Header.
+    /// Apps without a Program Header report version 0.
+    pub fn get_binary_version(&self) -> u32 {
+        match self {
+            TbfHeader::TbfHeaderV2(hd) => hd.program.map_or(0, |program| program.version),
+            TbfHeader::TbfHeaderV1(_) | TbfHeader::Padding(_) => 0,
+        }
+    }
+
+    /// Get the name of the app. The name of v1 apps is not in their header,
+    /// so it is not known.
+    pub fn get_package_name(&self) -> Option<&str> {
+        match self {
+            TbfHeader::TbfHeaderV2(hd) => hd.package_name.as_deref(),
+            TbfHeader::TbfHeaderV1(_) | TbfHeader::Padding(_) => None,
+        }
+    }
+
+    /// Get the address in RAM the app was built to run from, or `None` if
+    /// it can run from anywhere.
+    pub fn get_fixed_address_ram(&self) -> Option<u32> {
+        let fixed_addresses = self.as_app()?.fixed_addresses?;
+        match fixed_addresses.start_process_ram {
+            0xFFFFFFFF => None,
+            start => Some(start),
+        }
+    }
+
+    /// Get the address in flash the app was built to be placed at, or `None`
+    /// if it can be placed anywhere.
+    pub fn get_fixed_address_flash(&self) -> Option<u32> {
+        let fixed_addresses = self.as_app()?.fixed_addresses?;
+        match fixed_addresses.start_process_flash {
+            0xFFFFFFFF => None,
+            start => Some(start),
+        }
+    }
+
+    /// Get the regions of flash the app may write to, as `(offset, size)`
+    /// with the offset counted from the start of the TBF.
+    pub fn get_writeable_flash_regions(&self) -> Vec<(u32, u32)> {
+        self.as_app().map_or(Vec::new(), |hd| {
+            hd.writeable_regions
+                .iter()
+                .map(|region| {
+                    (
+                        region.writeable_flash_region_offset,
+                        region.writeable_flash_region_size,
+                    )
+                })
+                .collect()
+        })
+    }
+
+    /// Get which commands of `driver_number` the app may call, in the block of
+    /// 64 commands selected by `offset`, the way the kernel checks them.
+    pub fn get_command_permissions(&self, driver_number: u32, offset: u32) -> CommandPermissions {
+        let Some(permissions) = self.as_app().and_then(|hd| hd.permissions.as_ref()) else {
+            return CommandPermissions::NoPermsAtAll;
+        };
+        let mut found_driver = false;
+        for permission in permissions {
+            if permission.driver_number == driver_number {
+                found_driver = true;
+                if permission.offset == offset {
+                    return CommandPermissions::Mask(permission.allowed_commands);
+                }
+            }
+        }
+        if found_driver {
+            CommandPermissions::Mask(0)
+        } else {
+            CommandPermissions::NoPermsThisDriver
+        }
+    }
+
+    /// Get every entry of the Permissions Header, as
+    /// `(driver_number, offset, allowed_commands)` in header order. `None` if
+    /// the app has no Permissions Header, and so may call any command.
+    pub fn get_all_permissions(&self) -> Option<impl Iterator<Item = (u32, u32, u64)> + '_> {
+        let permissions = self.as_app()?.permissions.as_ref()?;
+        Some(permissions.iter().map(|permission| {
+            (
+                permission.driver_number,
+                permission.offset,
+                permission.allowed_commands,
+            )
+        }))
+    }
+
+    /// Get the oldest kernel version the app works with, as
+    /// `(major, minor)`, if the app says.
+    pub fn get_kernel_version(&self) -> Option<(u16, u16)> {
+        let kernel_version = self.as_app()?.kernel_version?;
+        Some((kernel_version.major, kernel_version.minor))
+    }
+}
+
+impl TbfFooterV2Credentials {
+    pub fn get_format(&self) -> TbfFooterV2CredentialsType {
+        self.format
+    }
+
+    pub fn get_credentials(&self) -> &[u8] {
+        &self.data
+    }
+}
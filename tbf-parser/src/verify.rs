@@ -0,0 +1,72 @@
+//! Checking the hashes and signatures in Credentials Footers.
+//!
+//! This is behind the `verify` feature, so that users who only parse headers
+//! do not build the crypto.
+
+use rsa::{BigUint, Pkcs1v15Sign, RsaPublicKey};
+use sha2::{Digest, Sha256, Sha384, Sha512};
+
+use crate::types::{
+    TbfFooterV2Credentials, TbfFooterV2CredentialsType, TbfHeaderTypes, TbfParseError,
+};
+
+/// The public exponent of RSA keys in Credentials Footers, which only hold
+/// the modulus.
+const RSA_PUBLIC_EXPONENT: u32 = 65537;
+
+impl TbfFooterV2Credentials {
+    /// Check the credentials against `covered_bytes`.
+    ///
+    /// The credentials cover the app from the start of its header up to the
+    /// end of its binary, `binary_end_offset` bytes in, which is what
+    /// `covered_bytes` must hold. The footers themselves are not covered.
+    ///
+    /// SHA credentials are the hash of the covered bytes.
+    ///
+    /// RSA credentials hold the big-endian modulus of the public key, whose
+    /// exponent is 65537, followed by a PKCS#1 v1.5 signature of the SHA-512
+    /// hash of the covered bytes, each 384 bytes for `Rsa3072Key` and 512
+    /// for `Rsa4096Key`.
+    ///
+    /// ## Return
+    ///
+    /// Whether the hash matches or the signature is valid, `BadTlvEntry` if
+    /// RSA credentials are not a key and a signature, or
+    /// `UnsupportedCredentials` for other formats.
+    pub fn verify(&self, covered_bytes: &[u8]) -> Result<bool, TbfParseError> {
+        match self.format {
+            TbfFooterV2CredentialsType::SHA256 => {
+                Ok(Sha256::digest(covered_bytes).as_slice() == self.data)
+            }
+            TbfFooterV2CredentialsType::SHA384 => {
+                Ok(Sha384::digest(covered_bytes).as_slice() == self.data)
+            }
+            TbfFooterV2CredentialsType::SHA512 => {
+                Ok(Sha512::digest(covered_bytes).as_slice() == self.data)
+            }
+            TbfFooterV2CredentialsType::Rsa3072Key => self.verify_rsa(covered_bytes, 384),
+            TbfFooterV2CredentialsType::Rsa4096Key => self.verify_rsa(covered_bytes, 512),
+            format => Err(TbfParseError::UnsupportedCredentials(format)),
+        }
+    }
+
+    /// Check the signature of RSA credentials whose key and signature are
+    /// `key_size` bytes each.
+    fn verify_rsa(&self, covered_bytes: &[u8], key_size: usize) -> Result<bool, TbfParseError> {
+        let bad_credentials =
+            TbfParseError::BadTlvEntry(TbfHeaderTypes::TbfFooterCredentials as usize);
+        if self.data.len() != 2 * key_size {
+            return Err(bad_credentials);
+        }
+        let (modulus, signature) = self.data.split_at(key_size);
+        let key = RsaPublicKey::new(
+            BigUint::from_bytes_be(modulus),
+            BigUint::from(RSA_PUBLIC_EXPONENT),
+        )
+        .map_err(|_| bad_credentials)?;
+        let hash = Sha512::digest(covered_bytes);
+        Ok(key
+            .verify(Pkcs1v15Sign::new::<Sha512>(), &hash, signature)
+            .is_ok())
+    }
+}
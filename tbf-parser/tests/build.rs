@@ -0,0 +1,102 @@
+use tbf_parser::build::TbfHeaderBuilder;
+use tbf_parser::parse::{header_tlvs, parse_tbf_header, parse_tbf_header_lengths};
+use tbf_parser::types::{TbfHeader, TbfParseError};
+
+/// Parse a header built on its own, checking its lengths first.
+fn parse(header: &[u8]) -> (TbfHeader, u32) {
+    let (version, header_size, total_size) =
+        parse_tbf_header_lengths(header[0..8].try_into().unwrap()).unwrap();
+    assert_eq!(header_size as usize, header.len());
+    (parse_tbf_header(header, version).unwrap(), total_size)
+}
+
+#[test]
+fn padding_spans_the_given_size() {
+    let header = TbfHeaderBuilder::padding(0x400).build().unwrap();
+    assert_eq!(header.len(), 16);
+    let (header, total_size) = parse(&header);
+    assert!(!header.is_app());
+    assert!(!header.enabled());
+    assert_eq!(total_size, 0x400);
+}
+
+#[test]
+fn program_header_sizes_are_computed() {
+    let header = TbfHeaderBuilder::new()
+        .program(0x20, 0x40, 4096, 3)
+        .package_name("blink")
+        .writeable_flash_region(0x800, 0x200)
+        .fixed_addresses(0x2000_0000, 0xFFFF_FFFF)
+        .binary_size(0x1000)
+        .footer_size(40)
+        .build()
+        .unwrap();
+    // Base, Program, name padded to 8, writeable region and fixed addresses.
+    assert_eq!(header.len(), 16 + 24 + 12 + 12 + 12);
+
+    let (header, total_size) = parse(&header);
+    assert!(header.enabled());
+    assert!(!header.sticky());
+    assert_eq!(header.get_package_name(), Some("blink"));
+    assert_eq!(header.get_binary_version(), 3);
+    assert_eq!(header.get_minimum_app_ram_size(), 4096);
+    assert_eq!(header.get_init_function_offset(), 76 + 0x20);
+    assert_eq!(header.get_protected_size(), 76 + 0x40);
+    assert_eq!(header.get_binary_end(), 76 + 0x40 + 0x1000);
+    assert_eq!(total_size, 76 + 0x40 + 0x1000 + 40);
+    assert_eq!(header.get_fixed_address_ram(), Some(0x2000_0000));
+    assert_eq!(header.get_fixed_address_flash(), None);
+}
+
+#[test]
+fn main_header_has_no_binary_end() {
+    let header = TbfHeaderBuilder::new()
+        .main(0, 0, 2048)
+        .sticky(true)
+        .binary_size(100)
+        .build()
+        .unwrap();
+    let (header, total_size) = parse(&header);
+    assert!(header.sticky());
+    assert!(!header.has_program_header());
+    assert_eq!(header.get_binary_end(), total_size);
+    assert_eq!(total_size, 32 + 100);
+}
+
+#[test]
+fn total_size_can_be_kept() {
+    let header = TbfHeaderBuilder::new()
+        .program(0, 0, 2048, 1)
+        .binary_size(100)
+        .total_size(0x800)
+        .build()
+        .unwrap();
+    let (header, total_size) = parse(&header);
+    assert_eq!(total_size, 0x800);
+    assert_eq!(header.get_binary_end(), 40 + 100);
+}
+
+#[test]
+fn name_too_long_for_a_tlv_is_rejected() {
+    let name = "a".repeat(0x10000);
+    assert_eq!(
+        TbfHeaderBuilder::new().package_name(&name).build(),
+        Err(TbfParseError::BadTlvEntry(3))
+    );
+}
+
+#[test]
+fn other_tlvs_come_last_as_given() {
+    let header = TbfHeaderBuilder::new()
+        .tlv(42, &[1, 2, 3])
+        .main(0, 0, 2048)
+        .build()
+        .unwrap();
+    let tlvs: Vec<_> = header_tlvs(&header)
+        .map(|tlv| tlv.unwrap())
+        .map(|tlv| (tlv.offset, tlv.tipe, tlv.value.len()))
+        .collect();
+    assert_eq!(tlvs, [(16, 1, 12), (32, 42, 3)]);
+    assert_eq!(&header[36..40], [1, 2, 3, 0]);
+    parse(&header);
+}
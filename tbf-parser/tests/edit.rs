@@ -0,0 +1,68 @@
+use tbf_parser::edit::{set_flags_in_raw, update_checksum_in_raw};
+use tbf_parser::parse::parse_tbf_header;
+use tbf_parser::testing::{make_tbf, AppHeader, TbfOptions};
+use tbf_parser::types::TbfParseError;
+
+/// A header with a Main TLV followed by a TLV unknown to the parser, whose
+/// length is not a multiple of 4.
+fn make_header() -> Vec<u8> {
+    make_tbf(&TbfOptions {
+        header: AppHeader::Main,
+        extra_tlvs: vec![(0x2A, vec![0xDE, 0xAD, 0xBE, 0xEF, 0x42])],
+        ..Default::default()
+    })
+}
+
+#[test]
+fn only_flags_and_checksum_change() {
+    let original = make_header();
+    assert!(parse_tbf_header(&original, 2).unwrap().enabled());
+
+    let mut edited = original.clone();
+    set_flags_in_raw(&mut edited, 0).unwrap();
+
+    let header = parse_tbf_header(&edited, 2).unwrap();
+    assert!(!header.enabled());
+    for (index, (before, after)) in original.iter().zip(&edited).enumerate() {
+        if !(8..16).contains(&index) {
+            assert_eq!(before, after, "byte {index} changed");
+        }
+    }
+
+    // Setting the flags back restores the exact original bytes.
+    set_flags_in_raw(&mut edited, 1).unwrap();
+    assert_eq!(edited, original);
+}
+
+#[test]
+fn bytes_after_the_header_are_untouched() {
+    let mut tbf = make_header();
+    tbf.extend_from_slice(&[0x55; 16]);
+    set_flags_in_raw(&mut tbf, 0).unwrap();
+    assert_eq!(&tbf[44..], &[0x55; 16]);
+}
+
+#[test]
+fn short_buffer_is_rejected() {
+    let mut header = make_header();
+    header.truncate(20);
+    assert_eq!(
+        set_flags_in_raw(&mut header, 0),
+        Err(TbfParseError::NotEnoughFlash)
+    );
+}
+
+#[test]
+fn checksum_is_updated_after_an_edit() {
+    let mut header = make_header();
+    // The minimum RAM size of the Main header.
+    header[28..32].copy_from_slice(&8192u32.to_le_bytes());
+    assert!(matches!(
+        parse_tbf_header(&header, 2),
+        Err(TbfParseError::ChecksumMismatch(_, _))
+    ));
+
+    update_checksum_in_raw(&mut header).unwrap();
+    let header = parse_tbf_header(&header, 2).unwrap();
+    assert_eq!(header.get_minimum_app_ram_size(), 8192);
+}
@@ -0,0 +1,438 @@
+use tbf_parser::parse::{
+    header_tlvs, iter_apps, parse_tbf_footer, parse_tbf_header, parse_tbf_header_lengths,
+};
+use tbf_parser::testing::{make_tbf, make_v1_tbf, AppHeader, TbfOptions};
+use tbf_parser::types::{
+    CommandPermissions, InitialTbfParseError, TbfFooterV2CredentialsType, TbfHeader, TbfParseError,
+};
+
+/// Parse the header at the start of `tbf`, checking its lengths first like
+/// the kernel does.
+fn parse(tbf: &[u8]) -> TbfHeader {
+    let (version, header_size, total_size) =
+        parse_tbf_header_lengths(tbf[0..8].try_into().unwrap()).unwrap();
+    assert_eq!(total_size as usize, tbf.len());
+    parse_tbf_header(&tbf[..header_size as usize], version).unwrap()
+}
+
+#[test]
+fn padding_has_only_the_base_header() {
+    let tbf = make_tbf(&TbfOptions {
+        header: AppHeader::None,
+        binary: vec![0; 100],
+        ..Default::default()
+    });
+    let header = parse(&tbf);
+    assert!(!header.is_app());
+    assert!(header.as_app().is_none());
+    assert!(header.as_padding().is_some());
+    assert_eq!(header.get_total_size(), 116);
+}
+
+#[test]
+fn program_header_and_footers() {
+    let tbf = make_tbf(&TbfOptions {
+        name: Some("blink"),
+        header: AppHeader::Program { version: 7 },
+        binary: vec![0x42; 64],
+        footers: vec![(3, vec![0x11; 32]), (0, vec![0; 12])],
+        ..Default::default()
+    });
+    let header = parse(&tbf);
+    assert!(header.as_app().is_some());
+    assert!(header.as_padding().is_none());
+    assert_eq!(header.get_package_name(), Some("blink"));
+    assert_eq!(header.get_binary_version(), 7);
+
+    let binary_end = header.get_binary_end() as usize;
+    let (footer, size) = parse_tbf_footer(&tbf[binary_end..]).unwrap();
+    assert_eq!(footer.get_format(), TbfFooterV2CredentialsType::SHA256);
+    assert_eq!(footer.get_credentials(), &[0x11; 32]);
+    let (footer, _) = parse_tbf_footer(&tbf[binary_end + size as usize..]).unwrap();
+    assert_eq!(footer.get_format(), TbfFooterV2CredentialsType::Reserved);
+}
+
+/// Parse every footer of `tbf`, from the binary end to its total size.
+fn footers(tbf: &[u8]) -> Vec<(TbfFooterV2CredentialsType, usize, u32)> {
+    let header = parse(tbf);
+    let mut offset = header.get_binary_end() as usize;
+    let mut footers = Vec::new();
+    while offset < header.get_total_size() as usize {
+        let (footer, size) = parse_tbf_footer(&tbf[offset..]).unwrap();
+        footers.push((footer.get_format(), footer.get_credentials().len(), size));
+        offset += size as usize;
+    }
+    assert_eq!(offset, tbf.len());
+    footers
+}
+
+#[test]
+fn footer_sha256() {
+    let tbf = make_tbf(&TbfOptions {
+        header: AppHeader::Program { version: 1 },
+        binary: vec![0x42; 64],
+        footers: vec![(3, vec![0x11; 32]), (0, vec![0; 200])],
+        ..Default::default()
+    });
+    assert_eq!(
+        footers(&tbf),
+        [
+            (TbfFooterV2CredentialsType::SHA256, 32, 40),
+            (TbfFooterV2CredentialsType::Reserved, 200, 208),
+        ]
+    );
+}
+
+#[test]
+fn footer_rsa4096() {
+    let tbf = make_tbf(&TbfOptions {
+        header: AppHeader::Program { version: 1 },
+        binary: vec![0x42; 64],
+        footers: vec![(2, vec![0x22; 1024]), (0, vec![0; 60])],
+        ..Default::default()
+    });
+    assert_eq!(
+        footers(&tbf),
+        [
+            (TbfFooterV2CredentialsType::Rsa4096Key, 1024, 1032),
+            (TbfFooterV2CredentialsType::Reserved, 60, 68),
+        ]
+    );
+}
+
+#[test]
+fn truncated_footer_is_not_enough_flash() {
+    let tbf = make_tbf(&TbfOptions {
+        header: AppHeader::Program { version: 1 },
+        binary: vec![0x42; 64],
+        footers: vec![(3, vec![0x11; 32])],
+        ..Default::default()
+    });
+    let binary_end = parse(&tbf).get_binary_end() as usize;
+    assert!(matches!(
+        parse_tbf_footer(&tbf[binary_end..tbf.len() - 1]),
+        Err(TbfParseError::NotEnoughFlash)
+    ));
+}
+
+#[test]
+fn writeable_regions_are_read() {
+    let tbf = make_tbf(&TbfOptions {
+        name: Some("store"),
+        header: AppHeader::Main,
+        writeable_regions: vec![(0x100, 0x200), (0x400, 0x200)],
+        binary: vec![0; 0x600],
+        ..Default::default()
+    });
+    let header = parse(&tbf);
+    assert_eq!(header.get_package_name(), Some("store"));
+    assert!(!header.has_program_header());
+    assert_eq!(
+        header.get_writeable_flash_regions(),
+        [(0x100, 0x200), (0x400, 0x200)]
+    );
+}
+
+#[test]
+fn one_tlv_can_hold_several_writeable_regions() {
+    let regions = [0x100u32, 0x200, 0x400, 0x80]
+        .map(u32::to_le_bytes)
+        .concat();
+    let header = parse(&make_tbf(&TbfOptions {
+        extra_tlvs: vec![(2, regions)],
+        binary: vec![0; 0x600],
+        ..Default::default()
+    }));
+    assert_eq!(
+        header.get_writeable_flash_regions(),
+        [(0x100, 0x200), (0x400, 0x80)]
+    );
+
+    // A region cut short is not read as one.
+    let tbf = make_tbf(&TbfOptions {
+        extra_tlvs: vec![(2, [0x100u32, 0x200, 0x400].map(u32::to_le_bytes).concat())],
+        ..Default::default()
+    });
+    let (version, header_size, _) =
+        parse_tbf_header_lengths(tbf[0..8].try_into().unwrap()).unwrap();
+    assert!(matches!(
+        parse_tbf_header(&tbf[..header_size as usize], version),
+        Err(TbfParseError::BadTlvEntry(2))
+    ));
+}
+
+#[test]
+fn kernel_version_is_read_from_its_tlv() {
+    let header = parse(&make_tbf(&TbfOptions {
+        extra_tlvs: vec![(8, [2u16, 1].map(u16::to_le_bytes).concat())],
+        ..Default::default()
+    }));
+    assert_eq!(header.get_kernel_version(), Some((2, 1)));
+    assert_eq!(
+        parse(&make_tbf(&TbfOptions::default())).get_kernel_version(),
+        None
+    );
+}
+
+/// The value of a Permissions TLV holding `entries`, as
+/// `(driver_number, offset, allowed_commands)`.
+fn permissions_tlv(entries: &[(u32, u32, u64)]) -> Vec<u8> {
+    let mut value = (entries.len() as u16).to_le_bytes().to_vec();
+    for (driver_number, offset, allowed_commands) in entries {
+        value.extend_from_slice(&driver_number.to_le_bytes());
+        value.extend_from_slice(&offset.to_le_bytes());
+        value.extend_from_slice(&allowed_commands.to_le_bytes());
+    }
+    value
+}
+
+#[test]
+fn permissions_are_read_from_their_tlv() {
+    let entries = [(0x1, 0, 0b1011), (0x1, 1, 1 << 63), (0x50003, 0, u64::MAX)];
+    let header = parse(&make_tbf(&TbfOptions {
+        extra_tlvs: vec![(6, permissions_tlv(&entries))],
+        ..Default::default()
+    }));
+    assert_eq!(
+        header.get_all_permissions().unwrap().collect::<Vec<_>>(),
+        entries
+    );
+
+    assert_eq!(
+        header.get_command_permissions(0x1, 0),
+        CommandPermissions::Mask(0b1011)
+    );
+    // Command 127 of the driver is the last bit of the block at offset 1.
+    assert_eq!(
+        header.get_command_permissions(0x1, 1),
+        CommandPermissions::Mask(1 << 63)
+    );
+    // A listed driver allows nothing in the blocks it does not list.
+    assert_eq!(
+        header.get_command_permissions(0x1, 2),
+        CommandPermissions::Mask(0)
+    );
+    assert_eq!(
+        header.get_command_permissions(0x2, 0),
+        CommandPermissions::NoPermsThisDriver
+    );
+}
+
+#[test]
+fn missing_permissions_allow_everything() {
+    let header = parse(&make_tbf(&TbfOptions::default()));
+    assert!(header.get_all_permissions().is_none());
+    assert_eq!(
+        header.get_command_permissions(0x1, 0),
+        CommandPermissions::NoPermsAtAll
+    );
+
+    // An empty Permissions header allows nothing instead.
+    let header = parse(&make_tbf(&TbfOptions {
+        extra_tlvs: vec![(6, permissions_tlv(&[]))],
+        ..Default::default()
+    }));
+    assert_eq!(header.get_all_permissions().unwrap().count(), 0);
+    assert_eq!(
+        header.get_command_permissions(0x1, 0),
+        CommandPermissions::NoPermsThisDriver
+    );
+}
+
+#[test]
+fn permissions_count_must_match_the_entries() {
+    let mut value = permissions_tlv(&[(0x1, 0, 1)]);
+    value[0] = 2;
+    let tbf = make_tbf(&TbfOptions {
+        extra_tlvs: vec![(6, value)],
+        ..Default::default()
+    });
+    let (version, header_size, _) =
+        parse_tbf_header_lengths(tbf[0..8].try_into().unwrap()).unwrap();
+    assert!(matches!(
+        parse_tbf_header(&tbf[..header_size as usize], version),
+        Err(TbfParseError::BadTlvEntry(6))
+    ));
+}
+
+#[test]
+fn v2_layout_with_version_1_is_rejected() {
+    // A v1 header has a 32 bit version, so its bytes 2..4 are always 0. Here
+    // they hold the size of the v2 header, which is not a v1 header.
+    let tbf = make_tbf(&TbfOptions {
+        version: 1,
+        ..Default::default()
+    });
+    assert!(matches!(
+        parse_tbf_header_lengths(tbf[0..8].try_into().unwrap()),
+        Err(InitialTbfParseError::UnableToParse)
+    ));
+}
+
+#[test]
+fn version_1_headers_are_parsed() {
+    let tbf = make_v1_tbf(&[0x42; 100], 256);
+    let header = parse(&tbf);
+    assert!(header.is_app());
+    assert!(header.enabled());
+    assert_eq!(header.get_header_size(), 76);
+    assert_eq!(header.get_total_size(), 176);
+    assert_eq!(header.get_init_function_offset(), 76);
+    assert_eq!(header.get_app_start_offset(), 76);
+    assert_eq!(header.get_binary_end(), 176);
+    assert_eq!(header.get_minimum_app_ram_size(), 256 + 1024 + 2048 + 512);
+    assert_eq!(header.get_package_name(), None);
+    assert!(header.as_app().is_none());
+}
+
+#[test]
+fn version_1_ram_size_saturates() {
+    // The BSS size alone is u32::MAX, before the stack and heaps.
+    let tbf = make_v1_tbf(&[0x42; 100], u32::MAX);
+    assert_eq!(parse(&tbf).get_minimum_app_ram_size(), u32::MAX);
+}
+
+#[test]
+fn version_1_checksum_is_checked() {
+    let mut tbf = make_v1_tbf(&[0x42; 100], 256);
+    tbf[8] ^= 0x01;
+    assert!(matches!(
+        parse_tbf_header(&tbf[..76], 1),
+        Err(TbfParseError::ChecksumMismatch(_, _))
+    ));
+}
+
+#[test]
+fn header_larger_than_the_tbf_is_invalid() {
+    let mut tbf = make_tbf(&TbfOptions::default());
+    let total_size = tbf.len() as u32;
+    tbf[2..4].copy_from_slice(&(total_size as u16 + 4).to_le_bytes());
+    assert!(matches!(
+        parse_tbf_header_lengths(tbf[0..8].try_into().unwrap()),
+        Err(InitialTbfParseError::InvalidHeader(size)) if size == total_size
+    ));
+}
+
+#[test]
+fn checksum_covers_every_header_word() {
+    let mut tbf = make_tbf(&TbfOptions {
+        name: Some("blink"),
+        ..Default::default()
+    });
+    let (version, header_size, _) =
+        parse_tbf_header_lengths(tbf[0..8].try_into().unwrap()).unwrap();
+    let stored = u32::from_le_bytes(tbf[12..16].try_into().unwrap());
+    // Flip a bit in the last word of the header, past the fixed fields.
+    tbf[header_size as usize - 1] ^= 0x01;
+    match parse_tbf_header(&tbf[..header_size as usize], version) {
+        Err(TbfParseError::ChecksumMismatch(expected, actual)) => {
+            assert_eq!(expected, stored);
+            assert_ne!(actual, stored);
+        }
+        other => panic!("Unexpected result: {other:?}"),
+    }
+}
+
+#[test]
+fn header_tlvs_include_unknown_ones() {
+    let tbf = make_tbf(&TbfOptions {
+        header: AppHeader::Main,
+        extra_tlvs: vec![(42, vec![1, 2, 3])],
+        ..Default::default()
+    });
+    let header_size = u16::from_le_bytes([tbf[2], tbf[3]]) as usize;
+    let tlvs: Vec<_> = header_tlvs(&tbf[..header_size])
+        .map(|tlv| tlv.unwrap())
+        .map(|tlv| (tlv.offset, tlv.tipe, tlv.value.len()))
+        .collect();
+    assert_eq!(tlvs, [(16, 1, 12), (32, 42, 3)]);
+}
+
+#[test]
+fn header_tlvs_stop_at_a_truncated_tlv() {
+    let mut tbf = make_tbf(&TbfOptions {
+        header: AppHeader::Main,
+        ..Default::default()
+    });
+    // The Main header claims more bytes than the header holds.
+    tbf[18..20].copy_from_slice(&64u16.to_le_bytes());
+    let mut tlvs = header_tlvs(&tbf[..32]);
+    assert!(matches!(
+        tlvs.next(),
+        Some(Err(TbfParseError::NotEnoughFlash))
+    ));
+    assert_eq!(tlvs.offset(), 16);
+    assert!(tlvs.next().is_none());
+}
+
+#[test]
+fn fixed_addresses_are_read_from_their_tlv() {
+    let pinned = parse(&make_tbf(&TbfOptions {
+        extra_tlvs: vec![(
+            5,
+            [0x2000_8000u32, 0x0004_0000].map(u32::to_le_bytes).concat(),
+        )],
+        ..Default::default()
+    }));
+    assert_eq!(pinned.get_fixed_address_ram(), Some(0x2000_8000));
+    assert_eq!(pinned.get_fixed_address_flash(), Some(0x0004_0000));
+
+    // An address of all ones means the app is not pinned there.
+    let flash_only = parse(&make_tbf(&TbfOptions {
+        extra_tlvs: vec![(
+            5,
+            [0xFFFF_FFFFu32, 0x0004_0000].map(u32::to_le_bytes).concat(),
+        )],
+        ..Default::default()
+    }));
+    assert_eq!(flash_only.get_fixed_address_ram(), None);
+    assert_eq!(flash_only.get_fixed_address_flash(), Some(0x0004_0000));
+
+    let position_independent = parse(&make_tbf(&TbfOptions::default()));
+    assert_eq!(position_independent.get_fixed_address_ram(), None);
+    assert_eq!(position_independent.get_fixed_address_flash(), None);
+}
+
+#[test]
+fn iter_apps_walks_a_flash_image() {
+    let app = make_tbf(&TbfOptions {
+        name: Some("blink"),
+        binary: vec![0x42; 64],
+        ..Default::default()
+    });
+    let padding = make_tbf(&TbfOptions {
+        header: AppHeader::None,
+        binary: vec![0; 100],
+        ..Default::default()
+    });
+    let mut flash = [app.clone(), padding.clone()].concat();
+    flash.extend_from_slice(&[0xFF; 64]);
+
+    let mut tbfs = iter_apps(&flash);
+    let (offset, header) = tbfs.next().unwrap();
+    assert_eq!(offset, 0);
+    assert_eq!(header.get_package_name(), Some("blink"));
+    let (offset, header) = tbfs.next().unwrap();
+    assert_eq!(offset, app.len());
+    assert!(!header.is_app());
+    // Erased flash ends the walk.
+    assert!(tbfs.next().is_none());
+    assert_eq!(tbfs.offset(), app.len() + padding.len());
+}
+
+#[test]
+fn iter_apps_skips_invalid_headers() {
+    let mut broken = make_tbf(&TbfOptions::default());
+    let total_size = broken.len() as u16;
+    broken[2..4].copy_from_slice(&(total_size + 4).to_le_bytes());
+    let app = make_tbf(&TbfOptions {
+        name: Some("hello"),
+        ..Default::default()
+    });
+    let flash = [broken.clone(), app].concat();
+
+    let tbfs: Vec<_> = iter_apps(&flash).collect();
+    assert_eq!(tbfs.len(), 1);
+    assert_eq!(tbfs[0].0, broken.len());
+    assert_eq!(tbfs[0].1.get_package_name(), Some("hello"));
+}
@@ -0,0 +1,185 @@
+#![cfg(feature = "verify")]
+
+use rsa::traits::PublicKeyParts;
+use rsa::{BigUint, Pkcs1v15Sign, RsaPrivateKey};
+use sha2::{Digest, Sha256, Sha512};
+use tbf_parser::parse::{parse_tbf_footer, parse_tbf_header, parse_tbf_header_lengths};
+use tbf_parser::testing::{make_tbf, AppHeader, TbfOptions};
+use tbf_parser::types::{TbfFooterV2Credentials, TbfFooterV2CredentialsType, TbfParseError};
+
+/// The primes of the keys signing the apps, made once with a seeded RNG as
+/// making them for each run takes minutes.
+const P_3072: &str = "e2b413818691c8479c9dfe443c291c06a9f912d24d29a63399c885eb1c6788c3\
+    5322a0453ed937d476fefa69258aa1ff318f425c7ea7c92a0f82958ebd99ecd5\
+    d096fd0c1adb0af1e599cf2b959371df331c9d3bbef5fa8e62149072e11bd5a3\
+    af0277ba917b0320a5d50408cd2e5fd2d5da2f1c77b4e59a979f1e55f6382e63\
+    3db0194921cd49300ae4f4614c2d49427a07e00146e4f5a03ab3f1f72c5814da\
+    cca4832548114d0575784555e7b796b29cdfbed5a538bc8eb655ba33465e914b";
+const Q_3072: &str = "de877c4c917ed2b882481fb55ce4d7467ed62a86c0ef3140c5484e1c2c83ee49\
+    b2903bb8543c7b9a3c5065d1422bf804fc968fce54de86fd7b149b560ad5365e\
+    09eccc80e257c0e3255994eae6a5866156018a430debae704fa3f3b5b826a466\
+    9162594b889eeb1e0e9e2854f0f849c9331b7437782ba5ed67bba88b0426b85b\
+    e95c1badc5863cfe832ed4a30d3bbfae74e4735a2b724c65d522a4c4fbe22240\
+    77cf3b7ff70cdb5a2300814b33bb1885debec13c13fef2c30bb5a34b19be75c1";
+const P_4096: &str = "e206263225476feccc35da86a890755fb5abab0e1b32057e6ee5ce0734e15299\
+    afaa3bf894dfc4ed29b10757cf9e07b26206d6e1da814e493ebede4c132119dc\
+    b650bab84d30f35e40d94f56b57c82813949673ce5822b8909cacfe66d746a67\
+    ed12ffdbd4b38646f6bd585fd63b2f7819de728a44183890b73b14fef9df6897\
+    aab9763c638de8ca488b30afc7458d00f494a8d06fa69766ba26082d0c6402c8\
+    526247953179303b35e78688f566c681f944897343981d6820ca7151dbdac88c\
+    27cf1bb228bbafb938c394395c7372f582c017037e96c13120e9e8ada7855988\
+    816231f39d3861fd6241528a9c5a6206f6cb101b148a630e18c3cd54f24791d7";
+const Q_4096: &str = "ddbffd2c715611a6a8c9f33be2911c5ea9ee456d8e79d2808f46d789c608bae9\
+    070f19e0be3a819c3b94cf0edf5a35e00a8396f968dd3ab1038ae5a6f9c4ca47\
+    63f3256a6cb2e849fa55ea8d3ab0ced74340ec747a990d8814a0511c1db8ee71\
+    a4eb5f1d60d4f651c57f62e1dabaf7130d067f46dce2c01286a1fea827455194\
+    a00642e2f4226663aa415e9d6586f7a5a8a100abb04aba624cff7adc731435c0\
+    bc1c15b341baed7b7baae45b3fa2276f288db1db961600f74a2f7cd4b072cdd8\
+    5bcf3833edb0742a43d5a57ce66a93768c1c7ed673814b0df8e1468abdf0f28d\
+    0896dc84052e9fcd452c0740a23d98458e0c2d497e084c0c077825b177449f27";
+
+/// Build an app with one footer of `format`, signed with a key of `bits`, and
+/// return it with where its binary ends.
+fn signed_tbf(format: u32, bits: usize) -> (Vec<u8>, usize) {
+    let key_size = bits / 8;
+    let mut tbf = make_tbf(&TbfOptions {
+        header: AppHeader::Program { version: 1 },
+        binary: vec![0x42; 64],
+        footers: vec![(format, vec![0; 2 * key_size])],
+        ..Default::default()
+    });
+    let (version, header_size, _) =
+        parse_tbf_header_lengths(tbf[0..8].try_into().unwrap()).unwrap();
+    let header = parse_tbf_header(&tbf[..header_size as usize], version).unwrap();
+    let binary_end = header.get_binary_end() as usize;
+
+    let (p, q) = match bits {
+        3072 => (P_3072, Q_3072),
+        _ => (P_4096, Q_4096),
+    };
+    let prime = |hex: &str| BigUint::parse_bytes(hex.as_bytes(), 16).unwrap();
+    let key = RsaPrivateKey::from_p_q(prime(p), prime(q), BigUint::from(65537u32)).unwrap();
+    let hash = Sha512::digest(&tbf[..binary_end]);
+    let signature = key.sign(Pkcs1v15Sign::new::<Sha512>(), &hash).unwrap();
+    let modulus = key.n().to_bytes_be();
+
+    // The footer TLV and its format come before the credentials.
+    let data = binary_end + 8;
+    tbf[data..data + key_size].copy_from_slice(&modulus);
+    tbf[data + key_size..data + 2 * key_size].copy_from_slice(&signature);
+    (tbf, binary_end)
+}
+
+fn footer(tbf: &[u8], binary_end: usize) -> TbfFooterV2Credentials {
+    parse_tbf_footer(&tbf[binary_end..]).unwrap().0
+}
+
+#[test]
+fn rsa3072_signature_is_valid() {
+    let (tbf, binary_end) = signed_tbf(1, 3072);
+    let credentials = footer(&tbf, binary_end);
+    assert_eq!(
+        credentials.get_format(),
+        TbfFooterV2CredentialsType::Rsa3072Key
+    );
+    assert_eq!(credentials.verify(&tbf[..binary_end]), Ok(true));
+}
+
+#[test]
+fn rsa4096_signature_covers_header_and_binary() {
+    let (mut tbf, binary_end) = signed_tbf(2, 4096);
+    let credentials = footer(&tbf, binary_end);
+    assert_eq!(credentials.verify(&tbf[..binary_end]), Ok(true));
+
+    // Changing the binary breaks the signature.
+    tbf[binary_end - 1] ^= 1;
+    assert_eq!(credentials.verify(&tbf[..binary_end]), Ok(false));
+}
+
+#[test]
+fn wrong_signature_is_invalid() {
+    let (mut tbf, binary_end) = signed_tbf(1, 3072);
+    let last = tbf.len() - 1;
+    tbf[last] ^= 1;
+    let credentials = footer(&tbf, binary_end);
+    assert_eq!(credentials.verify(&tbf[..binary_end]), Ok(false));
+}
+
+#[test]
+fn credentials_of_the_wrong_size_are_bad() {
+    let tbf = make_tbf(&TbfOptions {
+        header: AppHeader::Program { version: 1 },
+        binary: vec![0x42; 64],
+        footers: vec![(1, vec![0x22; 512])],
+        ..Default::default()
+    });
+    let binary_end = tbf.len() - 520;
+    assert_eq!(
+        footer(&tbf, binary_end).verify(&tbf[..binary_end]),
+        Err(TbfParseError::BadTlvEntry(128))
+    );
+}
+
+/// Build an app with a SHA-256 footer holding the hash of its header and
+/// binary, and return it with where its binary ends.
+fn sha256_tbf() -> (Vec<u8>, usize) {
+    let mut tbf = make_tbf(&TbfOptions {
+        header: AppHeader::Program { version: 1 },
+        binary: vec![0x42; 64],
+        footers: vec![(3, vec![0; 32])],
+        ..Default::default()
+    });
+    let binary_end = tbf.len() - 40;
+    let hash = Sha256::digest(&tbf[..binary_end]);
+    tbf[binary_end + 8..].copy_from_slice(&hash);
+    (tbf, binary_end)
+}
+
+#[test]
+fn sha256_hash_matches() {
+    let (tbf, binary_end) = sha256_tbf();
+    let credentials = footer(&tbf, binary_end);
+    assert_eq!(credentials.get_format(), TbfFooterV2CredentialsType::SHA256);
+    assert_eq!(credentials.verify(&tbf[..binary_end]), Ok(true));
+}
+
+#[test]
+fn sha_hash_covers_header_and_binary() {
+    let (mut tbf, binary_end) = sha256_tbf();
+    let credentials = footer(&tbf, binary_end);
+    // The header holds the total size, so it is covered too.
+    tbf[4] ^= 1;
+    assert_eq!(credentials.verify(&tbf[..binary_end]), Ok(false));
+}
+
+#[test]
+fn sha512_hash_of_other_bytes_does_not_match() {
+    let tbf = make_tbf(&TbfOptions {
+        header: AppHeader::Program { version: 1 },
+        binary: vec![0x42; 64],
+        footers: vec![(5, Sha512::digest(b"another app").to_vec())],
+        ..Default::default()
+    });
+    let binary_end = tbf.len() - 72;
+    assert_eq!(
+        footer(&tbf, binary_end).verify(&tbf[..binary_end]),
+        Ok(false)
+    );
+}
+
+#[test]
+fn ecdsa_is_not_verified() {
+    let tbf = make_tbf(&TbfOptions {
+        header: AppHeader::Program { version: 1 },
+        binary: vec![0x42; 64],
+        footers: vec![(6, vec![0x11; 64])],
+        ..Default::default()
+    });
+    let binary_end = tbf.len() - 72;
+    assert_eq!(
+        footer(&tbf, binary_end).verify(&tbf[..binary_end]),
+        Err(TbfParseError::UnsupportedCredentials(
+            TbfFooterV2CredentialsType::EcdsaNistP256
+        ))
+    );
+}
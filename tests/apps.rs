@@ -0,0 +1,162 @@
+use tockloader::apps::{AppInfo, AppReader, TbfIterator, DEFAULT_MAX_TBFS};
+use tockloader::errors::TockloaderError;
+use tockloader::install::{create_padding_tbf, install_app, AppImage};
+use tockloader::interfaces::flash_file::FlashFileInterface;
+
+mod support;
+use support::{make_main_tbf, make_tbf};
+
+#[tokio::test]
+async fn find_app_returns_every_match() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut board = FlashFileInterface::new(&dir.path().join("flash.bin"), 512).unwrap();
+
+    let mut address = 0x1000;
+    let mut addresses = Vec::new();
+    for name in ["blink", "hello", "blink"] {
+        let app = AppImage::from_tbf(&make_tbf(name, 0, &[0; 500])).unwrap();
+        addresses.push(address);
+        address = install_app(&mut board, address, &app, None).await.unwrap();
+    }
+
+    let blinks = board.find_app(0x1000, "blink").await.unwrap();
+    assert_eq!(
+        blinks.iter().map(|app| app.address).collect::<Vec<_>>(),
+        vec![addresses[0], addresses[2]]
+    );
+    assert!(blinks
+        .iter()
+        .all(|app| app.total_size == app.header.get_total_size()));
+
+    let hello = board.find_app(0x1000, "hello").await.unwrap();
+    assert_eq!(hello.len(), 1);
+    assert_eq!(hello[0].address, addresses[1]);
+
+    assert!(board.find_app(0x1000, "missing").await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn iterator_stops_at_erased_flash() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut board = FlashFileInterface::new(&dir.path().join("flash.bin"), 512).unwrap();
+
+    let mut iterator = TbfIterator::new(0);
+    assert!(iterator.next(&mut board).await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn list_apps_skips_padding_and_reports_versions() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut board = FlashFileInterface::new(&dir.path().join("flash.bin"), 512).unwrap();
+
+    let blink = AppImage::from_tbf(&make_tbf("blink", 0, &[0; 500])).unwrap();
+    let legacy = AppImage::from_tbf(&make_main_tbf(&[0; 500])).unwrap();
+    let next = install_app(&mut board, 0x1000, &blink, Some(1024))
+        .await
+        .unwrap();
+    install_app(&mut board, next, &legacy, Some(1024))
+        .await
+        .unwrap();
+
+    let apps = board.list_apps(0x1000).await.unwrap();
+    assert_eq!(apps.len(), 2);
+    assert_eq!(apps[0].header.get_package_name(), Some("blink"));
+    assert_eq!(apps[0].binary_version(), Some(0));
+    assert_eq!(apps[1].address, next);
+    // Version 0 and no version at all are different things.
+    assert_eq!(apps[1].header.get_binary_version(), 0);
+    assert_eq!(apps[1].binary_version(), None);
+}
+
+#[tokio::test]
+async fn zero_size_tbf_ends_the_list() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("flash.bin");
+    let mut tbf = make_tbf("blink", 0, &[0; 100]);
+    tbf.extend_from_slice(&create_padding_tbf(0));
+    std::fs::write(&path, &tbf).unwrap();
+    let mut board = FlashFileInterface::new(&path, 512).unwrap();
+
+    let apps = board.list_apps(0).await.unwrap();
+    assert_eq!(apps.len(), 1);
+    assert_eq!(board.apps_end(0).await.unwrap(), tbf.len() as u32 - 16);
+}
+
+#[tokio::test]
+async fn endless_list_is_cut_off() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("flash.bin");
+    std::fs::write(&path, create_padding_tbf(16).repeat(DEFAULT_MAX_TBFS + 1)).unwrap();
+    let mut board = FlashFileInterface::new(&path, 512).unwrap();
+
+    assert!(matches!(
+        board.list_apps(0).await,
+        Err(TockloaderError::TbfListTooLong(_))
+    ));
+
+    let mut iterator = TbfIterator::new(0).max_tbfs(2 * DEFAULT_MAX_TBFS);
+    let mut count = 0;
+    while iterator.next(&mut board).await.unwrap().is_some() {
+        count += 1;
+    }
+    assert_eq!(count, DEFAULT_MAX_TBFS + 1);
+
+    let mut iterator = TbfIterator::new(0).region_end(16 * 4);
+    for _ in 0..4 {
+        assert!(iterator.next(&mut board).await.unwrap().is_some());
+    }
+    assert!(iterator.next(&mut board).await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn tbf_past_the_address_space_ends_the_list() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("flash.bin");
+    let mut flash = vec![0xFF; 0x1000];
+    flash.extend_from_slice(&create_padding_tbf(u32::MAX - 8));
+    std::fs::write(&path, &flash).unwrap();
+    let mut board = FlashFileInterface::new(&path, 512).unwrap();
+
+    assert!(matches!(
+        board.list_apps(0x1000).await,
+        Err(TockloaderError::TbfListTooLong(reason)) if reason.contains("address space")
+    ));
+}
+
+#[tokio::test]
+async fn read_app_at_returns_the_whole_tbf() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut board = FlashFileInterface::new(&dir.path().join("flash.bin"), 512).unwrap();
+
+    let blink = AppImage::from_tbf(&make_tbf("blink", 0, &[0x42; 500])).unwrap();
+    let end = install_app(&mut board, 0x1000, &blink, Some(1024))
+        .await
+        .unwrap();
+
+    let (header, bytes) = board.read_app_at(0x1000).await.unwrap().unwrap();
+    assert_eq!(header.get_package_name(), Some("blink"));
+    assert_eq!(bytes, blink.as_bytes());
+
+    // The padding after the app, and the erased flash after that.
+    let padding = 0x1000 + blink.as_bytes().len() as u32;
+    assert!(board.read_app_at(padding).await.unwrap().is_none());
+    assert!(board.read_app_at(end).await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn app_info_serializes_to_json() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut board = FlashFileInterface::new(&dir.path().join("flash.bin"), 512).unwrap();
+    let app = AppImage::from_tbf(&make_tbf("blink", 0, &[0; 500])).unwrap();
+    install_app(&mut board, 0x1000, &app, None).await.unwrap();
+
+    let apps = board.list_apps(0x1000).await.unwrap();
+    let info = serde_json::to_value(AppInfo::from(&apps[0])).unwrap();
+    assert_eq!(info["name"], "blink");
+    assert_eq!(info["enabled"], true);
+    assert_eq!(info["sticky"], false);
+    assert_eq!(info["address"], 0x1000);
+    assert_eq!(info["size"], apps[0].total_size);
+    assert_eq!(info["fixed_flash_address"], serde_json::Value::Null);
+    assert_eq!(info["writeable_flash_regions"], serde_json::json!([]));
+}
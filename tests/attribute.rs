@@ -0,0 +1,138 @@
+use tockloader::bootloader::attribute::{
+    parse_assignment, read_attribute_file, Attribute, ATTRIBUTE_LEN,
+};
+use tockloader::bootloader::flash::{
+    read_attributes, read_version, ATTRIBUTES_ADDRESS, VERSION_ADDRESS,
+};
+use tockloader::errors::TockloaderError;
+use tockloader::interfaces::flash_file::FlashFileInterface;
+use tockloader::interfaces::traits::{ATTRIBUTE_COUNT, ERASED_BYTE};
+
+#[test]
+fn raw_encoding_round_trips() {
+    let attribute = Attribute::new("appaddr", "0x40000").unwrap();
+    assert_eq!(Attribute::parse_raw(&attribute.to_raw()), Some(attribute));
+}
+
+/// A slot as the bootloader stores it: `key` padded to 8 bytes, the length
+/// byte, then `rest`, with whatever `fill` is left in the remaining bytes.
+fn raw_slot(key: &[u8], rest: &[u8], fill: u8) -> Vec<u8> {
+    let mut raw = key.to_vec();
+    raw.resize(8, 0);
+    raw.extend_from_slice(rest);
+    raw.resize(ATTRIBUTE_LEN, fill);
+    raw
+}
+
+#[test]
+fn raw_slots_are_parsed_by_their_length_byte() {
+    let slot = raw_slot(b"board", b"\x04hail", 0);
+    assert_eq!(
+        Attribute::parse_raw(&slot),
+        Some(Attribute::new("board", "hail").unwrap())
+    );
+
+    // A shorter value written over a longer one leaves the end of the old one
+    // behind, which is not part of the value.
+    let slot = raw_slot(b"appaddr", b"\x070x30000000", 0);
+    assert_eq!(Attribute::parse_raw(&slot).unwrap().value, "0x30000");
+
+    // The key fills all 8 bytes, with no null after it.
+    let slot = raw_slot(b"jldevice", b"\x0bnrf52840_xx", 0);
+    let attribute = Attribute::parse_raw(&slot).unwrap();
+    assert_eq!(attribute.key, "jldevice");
+    assert_eq!(attribute.value, "nrf52840_xx");
+}
+
+#[test]
+fn unused_slots_have_no_attribute() {
+    // Erased flash, and a slot that was cleared.
+    assert_eq!(Attribute::parse_raw(&[0xFF; ATTRIBUTE_LEN]), None);
+    assert_eq!(Attribute::parse_raw(&[0; ATTRIBUTE_LEN]), None);
+    // The length can not be more than the 55 bytes left in the slot.
+    assert_eq!(Attribute::parse_raw(&raw_slot(b"board", b"\x38", 0)), None);
+}
+
+#[test]
+fn values_keep_nulls_and_non_utf8_bytes() {
+    let slot = raw_slot(b"id", b"\x04a\0b\xFF", 0);
+    let attribute = Attribute::parse_raw(&slot).unwrap();
+    assert_eq!(attribute.value, "a\0b\u{FFFD}");
+}
+
+#[test]
+fn lengths_are_checked() {
+    assert!(Attribute::new("boardname", "hail").is_err());
+    assert!(Attribute::new("", "hail").is_err());
+    assert!(Attribute::new("board", "").is_err());
+    assert!(Attribute::new("board", &"x".repeat(56)).is_err());
+    assert!(Attribute::new("board", &"x".repeat(55)).is_ok());
+}
+
+#[test]
+fn assignments_need_an_equals_sign() {
+    assert_eq!(
+        parse_assignment("arch=cortex-m4").unwrap(),
+        Attribute::new("arch", "cortex-m4").unwrap()
+    );
+    assert!(matches!(
+        parse_assignment("arch"),
+        Err(TockloaderError::InvalidAttribute(_))
+    ));
+}
+
+#[test]
+fn files_are_read_as_toml_or_json() {
+    let dir = tempfile::tempdir().unwrap();
+    let toml = dir.path().join("board.toml");
+    std::fs::write(&toml, "board = \"hail\"\nappaddr = \"0x30000\"\n").unwrap();
+    let json = dir.path().join("board.json");
+    std::fs::write(&json, r#"{"board": "hail", "appaddr": "0x30000"}"#).unwrap();
+
+    let expected = vec![
+        Attribute::new("appaddr", "0x30000").unwrap(),
+        Attribute::new("board", "hail").unwrap(),
+    ];
+    assert_eq!(read_attribute_file(&toml).unwrap(), expected);
+    assert_eq!(read_attribute_file(&json).unwrap(), expected);
+
+    // Numbers would lose the hex format the kernel expects.
+    std::fs::write(&toml, "appaddr = 0x30000\n").unwrap();
+    assert!(matches!(
+        read_attribute_file(&toml),
+        Err(TockloaderError::InvalidAttribute(_))
+    ));
+}
+
+#[tokio::test]
+async fn attributes_are_read_from_flash() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("flash.bin");
+    let mut flash = vec![ERASED_BYTE; ATTRIBUTES_ADDRESS];
+    flash[VERSION_ADDRESS..VERSION_ADDRESS + 5].copy_from_slice(b"1.1.3");
+    flash[VERSION_ADDRESS + 5..VERSION_ADDRESS + 8].fill(0);
+    flash.extend_from_slice(&Attribute::new("board", "nrf52dk").unwrap().to_raw());
+    // The second slot was never written, and the rest is past the file.
+    flash.extend_from_slice(&[ERASED_BYTE; ATTRIBUTE_LEN]);
+    std::fs::write(&path, flash).unwrap();
+    let mut board = FlashFileInterface::new(&path, 512).unwrap();
+
+    let attributes = read_attributes(&mut board).await.unwrap();
+    assert_eq!(attributes.len(), ATTRIBUTE_COUNT as usize);
+    assert_eq!(
+        attributes[0],
+        Some(Attribute::new("board", "nrf52dk").unwrap())
+    );
+    assert!(attributes[1..].iter().all(Option::is_none));
+    assert_eq!(
+        read_version(&mut board).await.unwrap().as_deref(),
+        Some("1.1.3")
+    );
+}
+
+#[tokio::test]
+async fn erased_flash_has_no_bootloader_version() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut board = FlashFileInterface::new(&dir.path().join("flash.bin"), 512).unwrap();
+    assert_eq!(read_version(&mut board).await.unwrap(), None);
+}
@@ -0,0 +1,49 @@
+use std::time::Duration;
+
+use tockloader::benchmark::{benchmark, OperationStats};
+use tockloader::errors::TockloaderError;
+use tockloader::interfaces::flash_file::FlashFileInterface;
+use tockloader::interfaces::traits::BytesReader;
+
+#[tokio::test]
+async fn every_page_is_timed_and_written() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut board = FlashFileInterface::new(&dir.path().join("flash.bin"), 512).unwrap();
+
+    let report = benchmark(&mut board, 0x1000, 1500).await.unwrap();
+    assert_eq!(report.writes.latencies.len(), 3);
+    assert_eq!(report.reads.latencies.len(), 3);
+    assert_eq!(report.writes.bytes, 1536);
+
+    let written = board.read_range(0x1000, 1536).await.unwrap();
+    assert!(written.iter().any(|&byte| byte != 0xFF));
+}
+
+#[tokio::test]
+async fn scratch_address_must_be_page_aligned() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut board = FlashFileInterface::new(&dir.path().join("flash.bin"), 512).unwrap();
+
+    assert!(matches!(
+        benchmark(&mut board, 0x1100, 512).await,
+        Err(TockloaderError::InvalidScratchAddress {
+            address: 0x1100,
+            page_size: 512
+        })
+    ));
+}
+
+#[test]
+fn percentiles_use_the_nearest_rank() {
+    let stats = OperationStats {
+        bytes: 10 * 1024,
+        latencies: (1..=10).rev().map(Duration::from_millis).collect(),
+    };
+    assert_eq!(stats.percentile(50), Some(Duration::from_millis(5)));
+    assert_eq!(stats.percentile(90), Some(Duration::from_millis(9)));
+    assert_eq!(stats.percentile(99), Some(Duration::from_millis(10)));
+    assert_eq!(stats.percentile(0), Some(Duration::from_millis(1)));
+    assert!((stats.kib_per_second() - 10.0 / 0.055).abs() < 1e-6);
+
+    assert_eq!(OperationStats::default().percentile(50), None);
+}
@@ -0,0 +1,163 @@
+use tockloader::board::{
+    bootloader_entry_instructions, find_arch_defaults, BoardAttributes, BoardSettings,
+    DEFAULT_PAGE_SIZE, KNOWN_ARCHS, KNOWN_BOARDS, MANUAL_BOOTLOADER_ENTRY,
+};
+use tockloader::bootloader::attribute::Attribute;
+use tockloader::errors::TockloaderError;
+
+#[test]
+fn known_board_sets_page_size_and_app_address() {
+    let settings = BoardSettings::resolve(Some("nrf52840dk"), None, None, None, None).unwrap();
+    assert_eq!(settings.arch.as_deref(), Some("cortex-m4"));
+    assert_eq!(settings.page_size, 4096);
+    assert_eq!(settings.app_address, 0x40000);
+    assert_eq!(settings.app_region_end, Some(0x100000));
+}
+
+#[test]
+fn explicit_values_win_over_the_profile() {
+    let settings = BoardSettings::resolve(
+        Some("imix"),
+        Some("cortex-m0"),
+        Some(1024),
+        Some(0x50000),
+        None,
+    )
+    .unwrap();
+    assert_eq!(
+        settings,
+        BoardSettings {
+            board: Some("imix".into()),
+            arch: Some("cortex-m0".into()),
+            page_size: 1024,
+            app_address: 0x50000,
+            app_region_end: Some(0x80000),
+        }
+    );
+}
+
+#[test]
+fn unknown_board_uses_the_arch_defaults() {
+    let settings =
+        BoardSettings::resolve(Some("my-board"), Some("rv32imac"), None, None, None).unwrap();
+    assert_eq!(settings.arch.as_deref(), Some("rv32imac"));
+    assert_eq!(settings.page_size, 4096);
+    assert_eq!(settings.app_address, 0x20040000);
+    assert_eq!(settings.app_region_end, None);
+}
+
+#[test]
+fn nothing_known_uses_defaults() {
+    let settings = BoardSettings::resolve(Some("my-board"), None, None, None, None).unwrap();
+    assert_eq!(settings.arch, None);
+    assert_eq!(settings.page_size, DEFAULT_PAGE_SIZE);
+    assert_eq!(settings.app_address, 0x30000);
+}
+
+#[test]
+fn board_attributes_are_picked_from_the_slots() {
+    let attribute = |key, value| Some(Attribute::new(key, value).unwrap());
+    let slots = [
+        attribute("board", "hail"),
+        None,
+        attribute("appaddr", "0x40000"),
+        attribute("arch", "cortex-m4"),
+        attribute("jldevice", "ATSAM4LC8C"),
+    ];
+    assert_eq!(
+        BoardAttributes::from_attributes(&slots),
+        BoardAttributes {
+            board: Some("hail".into()),
+            arch: Some("cortex-m4".into()),
+            app_address: Some(0x40000),
+        }
+    );
+
+    let decimal = BoardAttributes::from_attributes(&[attribute("appaddr", "262144")]);
+    assert_eq!(decimal.app_address, Some(0x40000));
+    let malformed = BoardAttributes::from_attributes(&[attribute("appaddr", "apps")]);
+    assert_eq!(malformed, BoardAttributes::default());
+}
+
+#[test]
+fn attributes_are_checked_against_the_board() {
+    let hail = BoardAttributes {
+        board: Some("hail".into()),
+        arch: Some("cortex-m4".into()),
+        app_address: Some(0x30000),
+    };
+    assert!(hail.differences_from("hail").is_empty());
+    let differences = hail.differences_from("nrf52840dk");
+    assert_eq!(differences.len(), 2, "{differences:?}");
+    assert!(differences[0].contains("'hail'"), "{differences:?}");
+    assert!(differences[1].contains("0x30000"), "{differences:?}");
+    // Nothing is known about an unknown board but its name.
+    assert_eq!(hail.differences_from("myboard").len(), 1);
+    // A board without attributes could be anything.
+    assert!(BoardAttributes::default()
+        .differences_from("nrf52dk")
+        .is_empty());
+}
+
+#[test]
+fn every_known_board_has_a_jlink_device() {
+    for profile in KNOWN_BOARDS {
+        assert!(profile.jlink_device.is_some(), "{}", profile.name);
+    }
+}
+
+#[test]
+fn every_known_arch_has_defaults() {
+    for arch in KNOWN_ARCHS {
+        assert!(find_arch_defaults(arch).is_some(), "{arch}");
+    }
+}
+
+#[test]
+fn explicit_app_region_end_wins_and_is_checked() {
+    let settings = BoardSettings::resolve(Some("hail"), None, None, None, Some(0x60000)).unwrap();
+    assert_eq!(settings.app_region_end, Some(0x60000));
+
+    assert!(matches!(
+        BoardSettings::resolve(Some("hail"), None, None, Some(0x90000), None),
+        Err(TockloaderError::InvalidAppRegion {
+            start: 0x90000,
+            end: 0x80000
+        })
+    ));
+}
+
+#[test]
+fn arch_is_validated() {
+    assert!(matches!(
+        BoardSettings::resolve(None, Some("cortex-m5"), None, None, None),
+        Err(TockloaderError::InvalidArch(arch)) if arch == "cortex-m5"
+    ));
+}
+
+#[test]
+fn start_address_must_be_aligned_and_in_flash() {
+    let settings = BoardSettings::resolve(Some("nrf52dk"), None, None, None, None).unwrap();
+    assert!(settings.check_start_address(0x1000).is_ok());
+    assert!(matches!(
+        settings.check_start_address(0x1200),
+        Err(TockloaderError::InvalidStartAddress(_))
+    ));
+    assert!(matches!(
+        settings.check_start_address(0x80000),
+        Err(TockloaderError::InvalidStartAddress(_))
+    ));
+}
+
+#[test]
+fn bootloader_entry_is_explained_for_any_board() {
+    assert_eq!(
+        bootloader_entry_instructions(Some("hail")),
+        "Hold the user button while pressing reset"
+    );
+    assert_eq!(
+        bootloader_entry_instructions(Some("nrf52dk")),
+        MANUAL_BOOTLOADER_ENTRY
+    );
+    assert_eq!(bootloader_entry_instructions(None), MANUAL_BOOTLOADER_ENTRY);
+}
@@ -0,0 +1,672 @@
+#![cfg(feature = "serial")]
+
+use std::time::{Duration, Instant};
+
+use tockloader::board::BoardAttributes;
+use tockloader::bootloader::attribute::Attribute;
+use tockloader::bootloader::commands::*;
+use tockloader::bootloader::crc::tock_crc32;
+use tockloader::bootloader::info::BootloaderVersion;
+use tockloader::errors::TockloaderError;
+use tockloader::interfaces::serial::{BootloaderEntry, EntryTimeouts, SerialInterface};
+use tockloader::interfaces::traits::{
+    BoardInterface, BootloaderInterface, BytesReader, FlashWriter, DEFAULT_RESPONSE_TIMEOUT,
+};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::sync::mpsc;
+use tokio_serial::{SerialPort, SerialStream};
+
+/// Pretend to be a bootloader on one end of a pseudo terminal, reporting
+/// every command it receives.
+async fn fake_bootloader(stream: SerialStream, commands: mpsc::UnboundedSender<u8>) {
+    fake_bootloader_with_flash(stream, commands, Vec::new(), 0).await
+}
+
+/// Like [`fake_bootloader`], with reads returning bytes from `flash`. The
+/// first `garbled` responses are replaced by noise.
+async fn fake_bootloader_with_flash(
+    mut stream: SerialStream,
+    commands: mpsc::UnboundedSender<u8>,
+    flash: Vec<u8>,
+    mut garbled: usize,
+) {
+    let mut attributes = [[0u8; 64]; 16];
+    attributes[0][0..5].copy_from_slice(b"board");
+    attributes[0][8] = 4;
+    attributes[0][9..13].copy_from_slice(b"imix");
+
+    // Only reported once set, like an older bootloader would.
+    let mut start_address = None;
+
+    let mut args = Vec::new();
+    let mut previous_was_escape = false;
+    loop {
+        let Ok(byte) = stream.read_u8().await else {
+            return;
+        };
+        if byte == ESCAPE_CHAR && !previous_was_escape {
+            previous_was_escape = true;
+            continue;
+        }
+        if !previous_was_escape || byte == ESCAPE_CHAR {
+            // Command argument, possibly escaped.
+            previous_was_escape = false;
+            args.push(byte);
+            continue;
+        }
+        previous_was_escape = false;
+
+        commands.send(byte).unwrap();
+        let response: Vec<u8> = match byte {
+            COMMAND_PING => vec![ESCAPE_CHAR, RESPONSE_PONG],
+            COMMAND_GET_ATTRIBUTE => {
+                let mut response = vec![ESCAPE_CHAR, RESPONSE_GET_ATTRIBUTE];
+                response.extend_from_slice(&escape(&attributes[args[0] as usize]));
+                response
+            }
+            COMMAND_SET_START_ADDRESS => {
+                start_address = Some(u32::from_le_bytes(args[0..4].try_into().unwrap()));
+                vec![ESCAPE_CHAR, RESPONSE_OK]
+            }
+            // The rate of a pseudo terminal makes no difference, so the
+            // change only has to be acknowledged, up to the rate of a
+            // typical USB to serial adapter.
+            COMMAND_CHANGE_BAUD_RATE => {
+                if u32::from_le_bytes(args[1..5].try_into().unwrap()) > 1_000_000 {
+                    vec![ESCAPE_CHAR, RESPONSE_CHANGE_BAUD_FAIL]
+                } else {
+                    vec![ESCAPE_CHAR, RESPONSE_OK]
+                }
+            }
+            COMMAND_SET_ATTRIBUTE => {
+                attributes[args[0] as usize].copy_from_slice(&args[1..65]);
+                vec![ESCAPE_CHAR, RESPONSE_OK]
+            }
+            COMMAND_INFO => {
+                let info = match start_address {
+                    Some(address) => format!(
+                        r#"{{"version":"1.1.3","name":"Tock Bootloader","start_address":"{address:#x}"}}"#
+                    ),
+                    None => r#"{"version":"1.1.3","name":"Tock Bootloader"}"#.to_string(),
+                };
+                let mut data = vec![info.len() as u8];
+                data.extend_from_slice(info.as_bytes());
+                data.resize(193, 0);
+                let mut response = vec![ESCAPE_CHAR, RESPONSE_INFO];
+                response.extend_from_slice(&data);
+                response
+            }
+            COMMAND_READ_RANGE => {
+                let address = u32::from_le_bytes(args[0..4].try_into().unwrap()) as usize;
+                let len = u16::from_le_bytes(args[4..6].try_into().unwrap()) as usize;
+                let mut response = vec![ESCAPE_CHAR, RESPONSE_READ_RANGE];
+                response.extend_from_slice(&escape(&flash[address..address + len]));
+                response
+            }
+            COMMAND_CRC_INTERNAL_FLASH => {
+                let address = u32::from_le_bytes(args[0..4].try_into().unwrap()) as usize;
+                let len = u32::from_le_bytes(args[4..8].try_into().unwrap()) as usize;
+                let crc = tock_crc32(&flash[address..address + len]);
+                let mut response = vec![ESCAPE_CHAR, RESPONSE_CRC_INTERNAL_FLASH];
+                response.extend_from_slice(&escape(&crc.to_le_bytes()));
+                response
+            }
+            COMMAND_ERASE_PAGE => {
+                let address = u32::from_le_bytes(args[0..4].try_into().unwrap());
+                if address >= 0x80000 || address % 512 != 0 {
+                    vec![ESCAPE_CHAR, RESPONSE_BADADDR]
+                } else {
+                    vec![ESCAPE_CHAR, RESPONSE_OK]
+                }
+            }
+            // Only a flash of 512 byte pages up to 0x80000.
+            COMMAND_WRITE_PAGE => {
+                let address = u32::from_le_bytes(args[0..4].try_into().unwrap());
+                if address >= 0x80000 {
+                    vec![ESCAPE_CHAR, RESPONSE_BADADDR]
+                } else if args.len() != 4 + 512 {
+                    vec![ESCAPE_CHAR, RESPONSE_BADARGS]
+                } else {
+                    vec![ESCAPE_CHAR, RESPONSE_OK]
+                }
+            }
+            _ => vec![],
+        };
+        args.clear();
+        if garbled > 0 && !response.is_empty() {
+            garbled -= 1;
+            stream.write_all(&[0x5A, 0xA5]).await.unwrap();
+            continue;
+        }
+        stream.write_all(&response).await.unwrap();
+    }
+}
+
+#[tokio::test]
+async fn sync_is_sent_once_before_first_command() {
+    let (host, board) = SerialStream::pair().unwrap();
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+    tokio::spawn(fake_bootloader(board, sender));
+
+    let mut interface = SerialInterface::with_stream(host, 115200);
+    assert!(interface.needs_sync());
+
+    let attribute = interface.get_attribute(0).await.unwrap().unwrap();
+    assert_eq!(attribute.key, "board");
+    assert_eq!(attribute.value, "imix");
+    assert!(!interface.needs_sync());
+
+    interface.get_attribute(1).await.unwrap();
+    interface.ping().await.unwrap();
+
+    let mut commands = Vec::new();
+    for _ in 0..4 {
+        commands.push(receiver.recv().await.unwrap());
+    }
+    assert_eq!(
+        commands,
+        vec![
+            COMMAND_RESET,
+            COMMAND_GET_ATTRIBUTE,
+            COMMAND_GET_ATTRIBUTE,
+            COMMAND_PING
+        ]
+    );
+}
+
+#[tokio::test]
+async fn exit_bootloader_sends_exit_after_a_sync() {
+    let (host, board) = SerialStream::pair().unwrap();
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+    tokio::spawn(fake_bootloader(board, sender));
+
+    let mut interface = SerialInterface::with_stream(host, 115200);
+    // A pseudo terminal has no modem lines, so only the command is sent.
+    interface.exit_bootloader().await.unwrap();
+    assert_eq!(receiver.recv().await, Some(COMMAND_RESET));
+    assert_eq!(receiver.recv().await, Some(COMMAND_EXIT));
+    // Entering the bootloader again starts from a clean parser.
+    assert!(interface.needs_sync());
+}
+
+#[tokio::test]
+async fn open_populates_the_stream() {
+    let (host, board) = SerialStream::pair().unwrap();
+    // Only the second end of the pair has a path that can be opened again.
+    let path = board.name().or(host.name()).unwrap();
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+    tokio::spawn(fake_bootloader(host, sender));
+
+    let mut interface = SerialInterface::new(Some(path), 115200);
+    assert!(matches!(
+        interface.ping().await,
+        Err(TockloaderError::BootloaderNotOpen)
+    ));
+
+    interface.open().unwrap();
+    interface.ping().await.unwrap();
+    assert_eq!(receiver.recv().await, Some(COMMAND_RESET));
+    assert_eq!(receiver.recv().await, Some(COMMAND_PING));
+}
+
+#[tokio::test]
+async fn read_range_splits_large_reads() {
+    let (host, board) = SerialStream::pair().unwrap();
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+    // Plenty of escape characters, which must come back de-escaped.
+    let flash: Vec<u8> = (0..2048u32).map(|i| (i % 253) as u8 | 0xF0).collect();
+    tokio::spawn(fake_bootloader_with_flash(board, sender, flash.clone(), 0));
+
+    let mut interface = SerialInterface::with_stream(host, 115200);
+    let data = interface.read_range(100, 1200).await.unwrap();
+    assert_eq!(data, flash[100..1300]);
+
+    let mut commands = Vec::new();
+    while let Ok(command) = receiver.try_recv() {
+        commands.push(command);
+    }
+    assert_eq!(
+        commands,
+        vec![
+            COMMAND_RESET,
+            COMMAND_READ_RANGE,
+            COMMAND_READ_RANGE,
+            COMMAND_READ_RANGE
+        ]
+    );
+}
+
+#[tokio::test]
+async fn crc_is_computed_by_the_bootloader() {
+    let (host, board) = SerialStream::pair().unwrap();
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+    let flash: Vec<u8> = (0..2048u32).map(|i| (i % 251) as u8).collect();
+    tokio::spawn(fake_bootloader_with_flash(board, sender, flash.clone(), 0));
+
+    let mut interface = SerialInterface::with_stream(host, 115200);
+    let crc = interface.crc_range(100, 1200).await.unwrap();
+    assert_eq!(crc, tock_crc32(&flash[100..1300]));
+
+    // Nothing is read back to compute it.
+    let mut commands = Vec::new();
+    while let Ok(command) = receiver.try_recv() {
+        commands.push(command);
+    }
+    assert_eq!(commands, vec![COMMAND_RESET, COMMAND_CRC_INTERNAL_FLASH]);
+}
+
+#[tokio::test]
+async fn reads_past_the_address_space_are_not_sent() {
+    let (host, board) = SerialStream::pair().unwrap();
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+    tokio::spawn(fake_bootloader(board, sender));
+
+    let mut interface = SerialInterface::with_stream(host, 115200);
+    assert!(matches!(
+        interface.read_range(0xFFFF_FF00, 0x200).await,
+        Err(TockloaderError::InvalidReadRange {
+            address: 0xFFFF_FF00,
+            length: 0x200
+        })
+    ));
+    drop(interface);
+    assert_eq!(receiver.recv().await, None);
+}
+
+#[tokio::test]
+async fn malformed_response_is_retried_after_sync() {
+    let (host, board) = SerialStream::pair().unwrap();
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+    tokio::spawn(fake_bootloader_with_flash(board, sender, Vec::new(), 1));
+
+    let mut interface = SerialInterface::with_stream(host, 115200);
+    let attribute = interface.get_attribute(0).await.unwrap().unwrap();
+    assert_eq!(attribute.key, "board");
+
+    let mut commands = Vec::new();
+    while let Ok(command) = receiver.try_recv() {
+        commands.push(command);
+    }
+    assert_eq!(
+        commands,
+        vec![
+            COMMAND_RESET,
+            COMMAND_GET_ATTRIBUTE,
+            COMMAND_RESET,
+            COMMAND_GET_ATTRIBUTE
+        ]
+    );
+}
+
+#[tokio::test]
+async fn retries_are_bounded() {
+    let (host, board) = SerialStream::pair().unwrap();
+    let (sender, _receiver) = mpsc::unbounded_channel();
+    tokio::spawn(fake_bootloader_with_flash(board, sender, Vec::new(), 3));
+
+    let mut interface = SerialInterface::with_stream(host, 115200);
+    interface.set_retries(2);
+    assert!(matches!(
+        interface.ping().await,
+        Err(TockloaderError::MalformedResponse(_))
+    ));
+    assert!(interface.needs_sync());
+}
+
+#[tokio::test]
+async fn refused_page_writes_are_not_retried() {
+    let (host, board) = SerialStream::pair().unwrap();
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+    tokio::spawn(fake_bootloader(board, sender));
+
+    let mut interface = SerialInterface::with_stream(host, 115200);
+    interface.write_page(0x40000, &[0xAA; 512]).await.unwrap();
+    assert!(matches!(
+        interface.write_page(0x80000, &[0xAA; 512]).await,
+        Err(TockloaderError::CommandRejected {
+            command: COMMAND_WRITE_PAGE,
+            response: RESPONSE_BADADDR,
+        })
+    ));
+    // Pages of another size than the bootloader's are only refused by it.
+    interface.set_page_size(256);
+    assert!(matches!(
+        interface.write_page(0x40000, &[0xAA; 256]).await,
+        Err(TockloaderError::CommandRejected {
+            response: RESPONSE_BADARGS,
+            ..
+        })
+    ));
+
+    drop(interface);
+    let mut commands = Vec::new();
+    while let Some(command) = receiver.recv().await {
+        commands.push(command);
+    }
+    let writes = commands
+        .iter()
+        .filter(|&&c| c == COMMAND_WRITE_PAGE)
+        .count();
+    assert_eq!(writes, 3);
+}
+
+#[tokio::test]
+async fn unaligned_erase_names_the_page_size() {
+    let (host, board) = SerialStream::pair().unwrap();
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+    tokio::spawn(fake_bootloader(board, sender));
+
+    let mut interface = SerialInterface::with_stream(host, 115200);
+    interface.erase_page(0x40000).await.unwrap();
+    // Refused here, without asking the bootloader.
+    let error = interface.erase_page(0x40010).await.unwrap_err();
+    assert!(matches!(
+        error,
+        TockloaderError::UnalignedPage {
+            address: 0x40010,
+            page_size: 512
+        }
+    ));
+    assert!(error.to_string().contains("multiple of 512 bytes"));
+    // Aligned to the pages assumed here, but not to the bootloader's.
+    interface.set_page_size(256);
+    let error = interface.erase_page(0x40100).await.unwrap_err();
+    assert!(matches!(
+        error,
+        TockloaderError::ErasePageRefused {
+            address: 0x40100,
+            page_size: 256
+        }
+    ));
+    assert!(error.to_string().contains("refused"), "{error}");
+    // Aligned, but past the end of flash.
+    interface.set_page_size(512);
+    assert!(matches!(
+        interface.erase_page(0x80000).await,
+        Err(TockloaderError::ErasePageRefused {
+            address: 0x80000,
+            ..
+        })
+    ));
+
+    drop(interface);
+    let mut commands = Vec::new();
+    while let Some(command) = receiver.recv().await {
+        commands.push(command);
+    }
+    let erases = commands
+        .iter()
+        .filter(|&&c| c == COMMAND_ERASE_PAGE)
+        .count();
+    assert_eq!(erases, 3);
+}
+
+#[tokio::test]
+async fn partial_pages_are_not_sent() {
+    let (host, board) = SerialStream::pair().unwrap();
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+    tokio::spawn(fake_bootloader(board, sender));
+
+    let mut interface = SerialInterface::with_stream(host, 115200);
+    assert!(matches!(
+        interface.write_page(0x40000, &[0xAA; 100]).await,
+        Err(TockloaderError::PartialPageWrite {
+            address: 0x40000,
+            length: 100,
+            page_size: 512
+        })
+    ));
+    assert!(matches!(
+        interface.write_page(0x40100, &[0xAA; 512]).await,
+        Err(TockloaderError::UnalignedPage {
+            address: 0x40100,
+            page_size: 512
+        })
+    ));
+    drop(interface);
+    assert_eq!(receiver.recv().await, None);
+}
+
+#[tokio::test]
+async fn bootloader_info_is_trimmed_to_its_length() {
+    let (host, board) = SerialStream::pair().unwrap();
+    let (sender, _receiver) = mpsc::unbounded_channel();
+    tokio::spawn(fake_bootloader(board, sender));
+
+    let mut interface = SerialInterface::with_stream(host, 115200);
+    assert_eq!(
+        interface.get_bootloader_info().await.unwrap(),
+        r#"{"version":"1.1.3","name":"Tock Bootloader"}"#
+    );
+    assert_eq!(
+        interface.find_attribute("board").await.unwrap().as_deref(),
+        Some("imix")
+    );
+}
+
+#[tokio::test]
+async fn store_attribute_reuses_the_slot_of_its_key() {
+    let (host, board) = SerialStream::pair().unwrap();
+    let (sender, _receiver) = mpsc::unbounded_channel();
+    tokio::spawn(fake_bootloader(board, sender));
+    let mut interface = SerialInterface::with_stream(host, 115200);
+
+    let board = Attribute::new("board", "hail").unwrap();
+    assert_eq!(interface.store_attribute(&board).await.unwrap(), 0);
+    let arch = Attribute::new("arch", "cortex-m4").unwrap();
+    assert_eq!(interface.store_attribute(&arch).await.unwrap(), 1);
+
+    assert_eq!(interface.get_attribute(0).await.unwrap(), Some(board));
+    assert_eq!(interface.get_attribute(1).await.unwrap(), Some(arch));
+    assert_eq!(interface.get_attribute(2).await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn board_is_detected_from_its_attributes() {
+    let (host, board) = SerialStream::pair().unwrap();
+    let (sender, _receiver) = mpsc::unbounded_channel();
+    tokio::spawn(fake_bootloader(board, sender));
+    let mut interface = SerialInterface::with_stream(host, 115200);
+
+    let appaddr = Attribute::new("appaddr", "0x50000").unwrap();
+    interface.set_attribute(7, &appaddr).await.unwrap();
+    assert_eq!(
+        interface.detect_board().await.unwrap(),
+        BoardAttributes {
+            board: Some("imix".into()),
+            arch: None,
+            app_address: Some(0x50000),
+        }
+    );
+}
+
+#[tokio::test]
+async fn attributes_past_the_last_slot_are_not_sent() {
+    let (host, board) = SerialStream::pair().unwrap();
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+    tokio::spawn(fake_bootloader(board, sender));
+
+    let mut interface = SerialInterface::with_stream(host, 115200);
+    let arch = Attribute::new("arch", "cortex-m4").unwrap();
+    interface.set_attribute(15, &arch).await.unwrap();
+    assert_eq!(
+        interface.get_attribute(15).await.unwrap(),
+        Some(arch.clone())
+    );
+    assert!(matches!(
+        interface.set_attribute(16, &arch).await,
+        Err(TockloaderError::InvalidAttribute(_))
+    ));
+
+    drop(interface);
+    let mut commands = Vec::new();
+    while let Some(command) = receiver.recv().await {
+        commands.push(command);
+    }
+    let sets = commands
+        .iter()
+        .filter(|&&c| c == COMMAND_SET_ATTRIBUTE)
+        .count();
+    assert_eq!(sets, 1);
+}
+
+#[tokio::test]
+async fn start_address_is_read_back_from_the_info() {
+    let (host, board) = SerialStream::pair().unwrap();
+    let (sender, _receiver) = mpsc::unbounded_channel();
+    tokio::spawn(fake_bootloader(board, sender));
+    let mut interface = SerialInterface::with_stream(host, 115200);
+
+    assert_eq!(interface.get_start_address().await.unwrap(), None);
+    interface.set_start_address(0x10000).await.unwrap();
+    assert_eq!(interface.get_start_address().await.unwrap(), Some(0x10000));
+}
+
+#[tokio::test]
+async fn fast_baud_rate_is_restored() {
+    let (host, board) = SerialStream::pair().unwrap();
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+    tokio::spawn(fake_bootloader(board, sender));
+    let mut interface = SerialInterface::with_stream(host, 115200);
+
+    assert!(interface.use_fast_baud_rate(921600).await.unwrap());
+    assert_eq!(interface.baud_rate(), 921600);
+    interface.restore_baud_rate().await.unwrap();
+    assert_eq!(interface.baud_rate(), 115200);
+
+    let mut commands = Vec::new();
+    while let Ok(command) = receiver.try_recv() {
+        commands.push(command);
+    }
+    // Set then confirm, once for each rate.
+    assert_eq!(
+        commands,
+        vec![
+            COMMAND_RESET,
+            COMMAND_CHANGE_BAUD_RATE,
+            COMMAND_CHANGE_BAUD_RATE,
+            COMMAND_CHANGE_BAUD_RATE,
+            COMMAND_CHANGE_BAUD_RATE
+        ]
+    );
+}
+
+#[tokio::test]
+async fn refused_baud_rate_is_not_retried() {
+    let (host, board) = SerialStream::pair().unwrap();
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+    tokio::spawn(fake_bootloader(board, sender));
+    let mut interface = SerialInterface::with_stream(host, 115200);
+
+    assert!(!interface.use_fast_baud_rate(2_000_000).await.unwrap());
+    assert_eq!(interface.baud_rate(), 115200);
+    // Nothing to go back to.
+    interface.restore_baud_rate().await.unwrap();
+    interface.ping().await.unwrap();
+
+    let mut commands = Vec::new();
+    while let Ok(command) = receiver.try_recv() {
+        commands.push(command);
+    }
+    assert_eq!(
+        commands,
+        vec![
+            COMMAND_RESET,
+            COMMAND_CHANGE_BAUD_RATE,
+            COMMAND_RESET,
+            COMMAND_PING
+        ]
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn fast_baud_rate_is_restored_when_dropped() {
+    let (host, board) = SerialStream::pair().unwrap();
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+    tokio::spawn(fake_bootloader(board, sender));
+    let mut interface = SerialInterface::with_stream(host, 115200);
+
+    interface.use_fast_baud_rate(921600).await.unwrap();
+    drop(interface);
+
+    let mut changes = 0;
+    while let Ok(command) = receiver.try_recv() {
+        if command == COMMAND_CHANGE_BAUD_RATE {
+            changes += 1;
+        }
+    }
+    assert_eq!(changes, 4);
+}
+
+#[tokio::test]
+async fn running_bootloader_is_not_reset() {
+    let (host, board) = SerialStream::pair().unwrap();
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+    tokio::spawn(fake_bootloader(board, sender));
+
+    let mut interface = SerialInterface::with_stream(host, 115200);
+    let entry = interface
+        .enter_bootloader(EntryTimeouts::default())
+        .await
+        .unwrap();
+    assert_eq!(entry, BootloaderEntry::Entered);
+    assert_eq!(receiver.recv().await, Some(COMMAND_RESET));
+    assert_eq!(receiver.recv().await, Some(COMMAND_PING));
+}
+
+#[tokio::test]
+async fn silent_port_without_modem_lines_can_not_enter_the_bootloader() {
+    // A pseudo terminal has no DTR or RTS, and was not opened by path.
+    let (host, _board) = SerialStream::pair().unwrap();
+    let mut interface = SerialInterface::with_stream(host, 115200);
+    interface.set_timeout(Duration::from_millis(50));
+    let timeouts = EntryTimeouts {
+        dtr_rts: Duration::ZERO,
+        baud_touch: Duration::ZERO,
+    };
+    let entry = interface.enter_bootloader(timeouts).await.unwrap();
+    assert_eq!(entry, BootloaderEntry::Unsupported);
+}
+
+#[tokio::test]
+async fn silent_board_times_out_after_the_configured_time() {
+    let (host, _board) = SerialStream::pair().unwrap();
+    let mut interface = SerialInterface::with_stream(host, 115200);
+    interface.set_timeout(Duration::from_millis(50));
+
+    let start = Instant::now();
+    assert!(matches!(
+        interface.ping().await,
+        Err(TockloaderError::Timeout)
+    ));
+    let elapsed = start.elapsed();
+    assert!(elapsed >= Duration::from_millis(50), "{elapsed:?}");
+    assert!(elapsed < DEFAULT_RESPONSE_TIMEOUT, "{elapsed:?}");
+}
+
+#[tokio::test]
+async fn bootloader_version_comes_from_the_info() {
+    let (host, board) = SerialStream::pair().unwrap();
+    let (sender, _receiver) = mpsc::unbounded_channel();
+    tokio::spawn(fake_bootloader(board, sender));
+
+    let mut interface = SerialInterface::with_stream(host, 115200);
+    assert_eq!(
+        interface.get_bootloader_version().await.unwrap(),
+        Some(BootloaderVersion::new(1, 1, 3))
+    );
+}
+
+#[tokio::test]
+async fn info_is_parsed() {
+    let (host, board) = SerialStream::pair().unwrap();
+    let (sender, _receiver) = mpsc::unbounded_channel();
+    tokio::spawn(fake_bootloader(board, sender));
+
+    let mut interface = SerialInterface::with_stream(host, 115200);
+    let info = interface.get_info().await.unwrap();
+    assert_eq!(info.version.as_deref(), Some("1.1.3"));
+    assert_eq!(info.name.as_deref(), Some("Tock Bootloader"));
+    assert_eq!(info.start_address, None);
+    assert_eq!(info.to_string(), "Tock Bootloader version 1.1.3");
+}
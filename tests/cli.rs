@@ -0,0 +1,50 @@
+#![cfg(feature = "serial")]
+
+use std::process::Command;
+
+mod support;
+use support::make_tbf;
+
+fn tockloader() -> Command {
+    Command::new(env!("CARGO_BIN_EXE_tockloader"))
+}
+
+#[test]
+fn dry_run_is_accepted_after_the_subcommand() {
+    let dir = tempfile::tempdir().unwrap();
+    let tbf = dir.path().join("blink.tbf");
+    std::fs::write(&tbf, make_tbf("blink", 0, &[0; 100])).unwrap();
+    let flash = dir.path().join("flash.bin");
+
+    let output = tockloader()
+        .args(["install", "--dry-run", "--no-color", "--flash-file"])
+        .arg(&flash)
+        .arg(&tbf)
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{output:?}");
+    assert!(stdout.contains("Dry run"), "{stdout}");
+    // Only planned, nothing reached the flash file.
+    let flash = std::fs::read(&flash).unwrap_or_default();
+    assert!(flash.iter().all(|&byte| byte == 0xFF));
+}
+
+#[test]
+fn format_json_also_reports_errors_as_json() {
+    let dir = tempfile::tempdir().unwrap();
+    let missing = dir.path().join("missing").join("flash.bin");
+
+    for format in [&["--format", "json"][..], &["--json"]] {
+        let output = tockloader()
+            .arg("list")
+            .args(format)
+            .arg("--flash-file")
+            .arg(&missing)
+            .output()
+            .unwrap();
+        assert!(!output.status.success());
+        let error: serde_json::Value = serde_json::from_slice(&output.stderr).unwrap();
+        assert_eq!(error["kind"], "IOError");
+    }
+}
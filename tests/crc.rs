@@ -0,0 +1,15 @@
+use tockloader::bootloader::crc::tock_crc32;
+
+#[test]
+fn matches_bootloader_crc() {
+    // Check values for CRC-32/ISO-HDLC, which the bootloader implements.
+    assert_eq!(tock_crc32(b""), 0x0000_0000);
+    assert_eq!(tock_crc32(b"123456789"), 0xCBF4_3926);
+    assert_eq!(
+        tock_crc32(b"The quick brown fox jumps over the lazy dog"),
+        0x414F_A339
+    );
+    // Erased flash, as the bootloader would see it past the end of an app.
+    assert_eq!(tock_crc32(&[0xFF; 4]), 0xFFFF_FFFF);
+    assert_eq!(tock_crc32(&[0x00; 4]), 0x2144_DF1C);
+}
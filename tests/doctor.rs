@@ -0,0 +1,18 @@
+use tockloader::doctor::{check_tool, find_program};
+
+#[cfg(unix)]
+#[test]
+fn programs_are_found_on_the_path() {
+    let sh = find_program("sh").unwrap();
+    assert!(sh.is_absolute());
+    assert_eq!(find_program(sh.to_str().unwrap()), Some(sh));
+    assert_eq!(find_program("tockloader-no-such-program"), None);
+}
+
+#[test]
+fn missing_tool_fails_with_a_hint() {
+    let check = check_tool("openocd", "tockloader-no-such-openocd");
+    assert_eq!(check.name, "openocd backend");
+    assert!(!check.passed);
+    assert!(check.detail.contains("not on the PATH"), "{}", check.detail);
+}
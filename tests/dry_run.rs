@@ -0,0 +1,39 @@
+use tockloader::interfaces::dry_run::{DryRunInterface, FlashOperation};
+use tockloader::interfaces::flash_file::FlashFileInterface;
+use tockloader::interfaces::traits::{BytesReader, FlashWriter};
+
+#[tokio::test]
+async fn dry_run_records_writes_without_modifying_flash() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("flash.bin");
+    let board = FlashFileInterface::new(&path, 512).unwrap();
+    let mut dry_run = DryRunInterface::new(board);
+
+    dry_run.write_page(0x200, &[0xAB; 512]).await.unwrap();
+    dry_run.write_page(0x600, &[0xCD; 512]).await.unwrap();
+
+    assert_eq!(
+        dry_run.operations(),
+        &[
+            FlashOperation::WritePage {
+                address: 0x200,
+                len: 512
+            },
+            FlashOperation::WritePage {
+                address: 0x600,
+                len: 512
+            },
+        ]
+    );
+
+    // Reads during the same command see the planned writes.
+    let data = dry_run.read_range(0x3F0, 0x20).await.unwrap();
+    assert_eq!(&data[..0x10], &[0xAB; 0x10]);
+    assert_eq!(&data[0x10..], &[0xFF; 0x10]);
+
+    // But nothing reached the flash file.
+    let mut board = dry_run.into_inner();
+    let data = board.read_range(0, 0x1000).await.unwrap();
+    assert!(data.iter().all(|&b| b == 0xFF));
+    assert_eq!(std::fs::metadata(&path).unwrap().len(), 0);
+}
@@ -0,0 +1,97 @@
+use tockloader::errors::TockloaderError;
+
+#[test]
+fn exit_codes_group_errors_by_category() {
+    let port = TockloaderError::PermissionDenied {
+        paths: vec!["/dev/ttyACM0".into()],
+    };
+    assert_eq!(
+        port.exit_code(),
+        TockloaderError::NoPortAvailable.exit_code()
+    );
+    assert_eq!(port.kind(), "PermissionDenied");
+    // A device the probe tool does not know is a tool setup problem, not a
+    // broken app.
+    let device = TockloaderError::UnknownDevice {
+        tool: "JLinkExe",
+        device: "cortex-m9".into(),
+    };
+    assert_eq!(device.exit_code(), port.exit_code());
+
+    let board = TockloaderError::MalformedResponse("bad header".into());
+    assert_eq!(board.exit_code(), TockloaderError::Timeout.exit_code());
+    assert_eq!(
+        TockloaderError::InvalidTbf("bad".into()).exit_code(),
+        TockloaderError::UnknownArch.exit_code()
+    );
+
+    let codes = [
+        TockloaderError::IOError(std::io::Error::other("oops")).exit_code(),
+        port.exit_code(),
+        board.exit_code(),
+        TockloaderError::UnknownArch.exit_code(),
+    ];
+    for (index, code) in codes.iter().enumerate() {
+        assert_ne!(*code, 0);
+        // clap exits with 2 on usage errors.
+        assert_ne!(*code, 2);
+        assert!(!codes[index + 1..].contains(code));
+    }
+}
+
+#[test]
+fn bootloader_errors_say_what_to_do() {
+    let timeout = TockloaderError::Timeout.to_string();
+    assert!(timeout.contains("--timeout"), "{timeout}");
+    let malformed = TockloaderError::MalformedResponse("expected response 0x11, got 0x20".into());
+    let malformed = malformed.to_string();
+    assert!(malformed.contains("got 0x20"), "{malformed}");
+    assert!(malformed.contains("other program"), "{malformed}");
+}
+
+#[test]
+fn invalid_read_ranges_say_why() {
+    let empty = TockloaderError::InvalidReadRange {
+        address: 0x1000,
+        length: 0,
+    };
+    assert!(empty.to_string().contains("length is 0"), "{empty}");
+    let wrapping = TockloaderError::InvalidReadRange {
+        address: 0xFFFF_F000,
+        length: 0x2000,
+    };
+    assert!(wrapping.to_string().contains("address space"), "{wrapping}");
+    assert_eq!(
+        wrapping.exit_code(),
+        TockloaderError::UnknownArch.exit_code()
+    );
+}
+
+#[test]
+fn board_mismatch_asks_for_force() {
+    let mismatch = TockloaderError::BoardMismatch {
+        board: "nrf52dk".into(),
+        differences: vec!["it says it is 'hail'".into()],
+    };
+    let message = mismatch.to_string();
+    assert!(message.contains("'hail'"), "{message}");
+    assert!(message.contains("--force"), "{message}");
+    assert_eq!(
+        mismatch.exit_code(),
+        TockloaderError::UnknownArch.exit_code()
+    );
+}
+
+#[cfg(feature = "serial")]
+#[test]
+fn serial_port_errors_are_port_errors() {
+    let serial = TockloaderError::from(tokio_serial::Error::new(
+        tokio_serial::ErrorKind::NoDevice,
+        "gone",
+    ));
+    assert_eq!(serial.kind(), "TokioSerialError");
+    assert_eq!(
+        serial.exit_code(),
+        TockloaderError::NoPortAvailable.exit_code()
+    );
+}
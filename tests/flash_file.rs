@@ -0,0 +1,32 @@
+use tockloader::interfaces::flash_file::FlashFileInterface;
+use tockloader::interfaces::traits::{BytesReader, FlashWriter};
+
+#[tokio::test]
+async fn missing_file_is_created_erased() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("flash.bin");
+    let mut board = FlashFileInterface::with_size(&path, 512, 0x1000).unwrap();
+    board.write_page(0x200, &[0x42; 512]).await.unwrap();
+    drop(board);
+
+    let flash = std::fs::read(&path).unwrap();
+    assert_eq!(flash.len(), 0x1000);
+    assert!(flash[..0x200].iter().all(|&byte| byte == 0xFF));
+    assert!(flash[0x200..0x400].iter().all(|&byte| byte == 0x42));
+    assert!(flash[0x400..].iter().all(|&byte| byte == 0xFF));
+}
+
+#[tokio::test]
+async fn existing_file_is_kept() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("flash.bin");
+    std::fs::write(&path, [1, 2, 3, 4]).unwrap();
+    let mut board = FlashFileInterface::with_size(&path, 512, 0x1000).unwrap();
+    assert_eq!(
+        board.read_range(0, 6).await.unwrap(),
+        [1, 2, 3, 4, 0xFF, 0xFF]
+    );
+    drop(board);
+
+    assert_eq!(std::fs::read(&path).unwrap(), [1, 2, 3, 4]);
+}
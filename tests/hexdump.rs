@@ -0,0 +1,49 @@
+use std::io::Write;
+
+use tockloader::hexdump::HexDump;
+use tockloader::interfaces::flash_file::FlashFileInterface;
+use tockloader::interfaces::traits::{BytesReader, STREAM_CHUNK_LEN};
+
+#[test]
+fn lines_are_the_same_however_bytes_arrive() {
+    let bytes: Vec<u8> = (0..40).collect();
+
+    let mut whole = HexDump::new(Vec::new(), 0x30000);
+    whole.write_all(&bytes).unwrap();
+    let whole = whole.finish().unwrap();
+
+    let mut split = HexDump::new(Vec::new(), 0x30000);
+    for chunk in bytes.chunks(7) {
+        split.write_all(chunk).unwrap();
+    }
+    let split = split.finish().unwrap();
+
+    assert_eq!(whole, split);
+    let text = String::from_utf8(whole).unwrap();
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert_eq!(
+        lines[0],
+        "00030000  00 01 02 03 04 05 06 07  08 09 0a 0b 0c 0d 0e 0f  |................|"
+    );
+    assert!(lines[2].starts_with("00030020  20 21 22 23 24 25 26 27"));
+    assert!(lines[2].ends_with("| !\"#$%&'|"));
+}
+
+#[tokio::test]
+async fn read_range_to_streams_the_whole_range() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("flash.bin");
+    let flash: Vec<u8> = (0..3 * STREAM_CHUNK_LEN + 100)
+        .map(|index| index as u8)
+        .collect();
+    std::fs::write(&path, &flash).unwrap();
+    let mut board = FlashFileInterface::new(&path, 512).unwrap();
+
+    let mut output = Vec::new();
+    board
+        .read_range_to(10, flash.len() - 10, &mut output)
+        .await
+        .unwrap();
+    assert_eq!(output, flash[10..]);
+}
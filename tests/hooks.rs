@@ -0,0 +1,32 @@
+#![cfg(unix)]
+
+use tockloader::errors::TockloaderError;
+use tockloader::hooks::{run_after, run_before};
+
+#[tokio::test]
+async fn failing_before_command_is_an_error() {
+    run_before("true").await.unwrap();
+    assert!(matches!(
+        run_before("exit 3").await,
+        Err(TockloaderError::HookFailed { code: Some(3), .. })
+    ));
+}
+
+#[tokio::test]
+async fn after_command_is_told_the_result() {
+    let ok: Result<(), TockloaderError> = Ok(());
+    run_after(
+        r#"test "$TOCKLOADER_RESULT" = success && test "$TOCKLOADER_EXIT_CODE" = 0"#,
+        &ok,
+    )
+    .await
+    .unwrap();
+
+    let failed: Result<(), TockloaderError> = Err(TockloaderError::Timeout);
+    run_after(
+        r#"test "$TOCKLOADER_RESULT" = failure && test "$TOCKLOADER_EXIT_CODE" = 4"#,
+        &failed,
+    )
+    .await
+    .unwrap();
+}
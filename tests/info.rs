@@ -0,0 +1,68 @@
+use tockloader::bootloader::attribute::Attribute;
+use tockloader::bootloader::info::{BootloaderInfo, BootloaderVersion};
+use tockloader::errors::TockloaderError;
+
+#[test]
+fn unknown_keys_are_kept() {
+    let info = BootloaderInfo::parse(
+        r#"{"version":"1.1.3","start_address":"0x10000","hw":"hail","flags":3}"#,
+    )
+    .unwrap();
+    assert_eq!(info.version.as_deref(), Some("1.1.3"));
+    assert_eq!(info.name, None);
+    assert_eq!(info.start_address, Some(0x10000));
+    assert_eq!(info.other["hw"], "hail");
+    assert_eq!(info.other["flags"], "3");
+    assert_eq!(
+        info.to_string(),
+        "version 1.1.3, starts the kernel at 0x10000, flags: 3, hw: hail"
+    );
+}
+
+#[test]
+fn info_must_be_an_object() {
+    assert!(matches!(
+        BootloaderInfo::parse("1.1.3"),
+        Err(TockloaderError::MalformedResponse(_))
+    ));
+}
+
+#[test]
+fn versions_are_parsed_leniently() {
+    assert_eq!(
+        BootloaderVersion::parse("1.1.3"),
+        Some(BootloaderVersion::new(1, 1, 3))
+    );
+    assert_eq!(
+        BootloaderVersion::parse("v1.2"),
+        Some(BootloaderVersion::new(1, 2, 0))
+    );
+    assert_eq!(
+        BootloaderVersion::parse("1.2.0-dev"),
+        Some(BootloaderVersion::new(1, 2, 0))
+    );
+    assert_eq!(BootloaderVersion::parse("dev"), None);
+    assert_eq!(BootloaderVersion::parse("1.2.3.4"), None);
+    assert!(BootloaderVersion::new(1, 1, 3) < BootloaderVersion::new(1, 10, 0));
+    assert_eq!(BootloaderVersion::new(1, 1, 3).to_string(), "1.1.3");
+}
+
+#[test]
+fn version_is_found_in_the_info_or_the_attributes() {
+    let attributes = [
+        Some(Attribute::new("board", "hail").unwrap()),
+        None,
+        Some(Attribute::new("bootver", "1.0.1").unwrap()),
+    ];
+    assert_eq!(
+        BootloaderVersion::find(None, &attributes),
+        Some(BootloaderVersion::new(1, 0, 1))
+    );
+    assert_eq!(BootloaderVersion::find(None, &attributes[..2]), None);
+    // What the bootloader reports comes first.
+    let info = BootloaderInfo::parse(r#"{"version":"1.1.3"}"#).unwrap();
+    assert_eq!(
+        BootloaderVersion::find(Some(&info), &attributes),
+        Some(BootloaderVersion::new(1, 1, 3))
+    );
+}
@@ -0,0 +1,146 @@
+use sha2::{Digest, Sha256};
+use tbf_parser::edit::update_checksum_in_raw;
+use tbf_parser::testing::make_v1_tbf;
+use tbf_parser::types::TbfFooterV2CredentialsType;
+use tockloader::inspect::{
+    hash_tbf, header_fields, parse_header, to_hex, HashAlgorithm, Integrity, StoredSignature,
+};
+
+mod support;
+use support::{make_signed_tbf, make_tbf, make_tbf_with_credentials};
+
+#[test]
+fn stored_hash_matches_computed() {
+    let tbf = make_signed_tbf();
+    let report = hash_tbf(&tbf).unwrap();
+
+    let binary_end = report.header.get_binary_end() as usize;
+    let expected = Sha256::digest(&tbf[..binary_end]).to_vec();
+    assert_eq!(
+        report.computed[0],
+        (HashAlgorithm::Sha256, expected.clone())
+    );
+    assert_eq!(report.computed[1].1.len(), 48);
+    assert_eq!(report.computed[2].1.len(), 64);
+
+    assert_eq!(report.stored.len(), 1);
+    assert_eq!(report.stored[0].algorithm, HashAlgorithm::Sha256);
+    assert_eq!(report.stored[0].stored, expected);
+    assert!(report.stored[0].matches());
+}
+
+#[test]
+fn modified_binary_is_a_mismatch() {
+    let mut tbf = make_signed_tbf();
+    let header_size = u16::from_le_bytes([tbf[2], tbf[3]]) as usize;
+    tbf[header_size + 10] ^= 0xFF;
+
+    let report = hash_tbf(&tbf).unwrap();
+    assert!(!report.stored[0].matches());
+}
+
+#[test]
+fn integrity_sums_up_the_credentials() {
+    let mut tbf = make_signed_tbf();
+    assert_eq!(hash_tbf(&tbf).unwrap().integrity(), Integrity::Ok);
+
+    let header_size = u16::from_le_bytes([tbf[2], tbf[3]]) as usize;
+    tbf[header_size + 10] ^= 0xFF;
+    assert_eq!(hash_tbf(&tbf).unwrap().integrity(), Integrity::Failed);
+
+    let unsigned = make_tbf("blink", 0, &[0x42; 100]);
+    assert_eq!(
+        hash_tbf(&unsigned).unwrap().integrity(),
+        Integrity::Unchecked
+    );
+    let ecdsa = make_tbf_with_credentials(6, &[0x5A; 64]);
+    assert_eq!(hash_tbf(&ecdsa).unwrap().integrity(), Integrity::Unchecked);
+}
+
+#[test]
+fn forged_rsa_signature_is_invalid() {
+    let tbf = make_tbf_with_credentials(1, &[0x5A; 768]);
+    let report = hash_tbf(&tbf).unwrap();
+    assert_eq!(
+        report.signatures,
+        [StoredSignature {
+            format: TbfFooterV2CredentialsType::Rsa3072Key,
+            valid: false,
+        }]
+    );
+    assert!(report.unchecked.is_empty());
+}
+
+#[test]
+fn ecdsa_signature_is_unchecked() {
+    let tbf = make_tbf_with_credentials(6, &[0x5A; 64]);
+    let report = hash_tbf(&tbf).unwrap();
+    assert!(report.signatures.is_empty());
+    assert_eq!(
+        report.unchecked,
+        [TbfFooterV2CredentialsType::EcdsaNistP256]
+    );
+}
+
+#[test]
+fn hex_is_lowercase_without_separators() {
+    assert_eq!(to_hex(&[0x00, 0xAB, 0x0F]), "00ab0f");
+}
+
+#[test]
+fn header_fields_list_the_base_header_and_tlvs() {
+    let tbf = make_tbf("blink", 0, &[0; 16]);
+    let fields = header_fields(&tbf).unwrap();
+    let names: Vec<&str> = fields.iter().map(|field| field.name.as_str()).collect();
+    assert_eq!(
+        names,
+        [
+            "version",
+            "header_size",
+            "total_size",
+            "flags",
+            "checksum",
+            "TbfHeaderProgram (9)",
+            "TbfHeaderPackageName (3)"
+        ]
+    );
+    assert_eq!(fields[0].bytes, [2, 0]);
+    assert_eq!((fields[5].offset, fields[5].bytes.len()), (16, 20));
+    assert_eq!((fields[6].offset, fields[6].bytes), (40, &b"blink"[..]));
+    assert!(fields.iter().all(|field| field.problem.is_none()));
+}
+
+#[test]
+fn v1_headers_are_listed_and_parsed() {
+    let tbf = make_v1_tbf(&[0; 16], 0);
+    let fields = header_fields(&tbf).unwrap();
+    assert_eq!(fields.len(), 19);
+    assert_eq!(fields[0].name, "version");
+    assert_eq!(fields[0].bytes, [1, 0, 0, 0]);
+    assert_eq!(fields.last().unwrap().name, "checksum");
+    // The upper half of the v1 version is not a header size.
+    assert!(parse_header(&tbf).unwrap().is_app());
+}
+
+#[test]
+fn header_fields_point_at_the_malformed_tlv() {
+    // A Program header claiming to be 16 bytes long.
+    let mut tbf = make_tbf("blink", 0, &[0; 16]);
+    tbf[18..20].copy_from_slice(&16u16.to_le_bytes());
+    update_checksum_in_raw(&mut tbf).unwrap();
+    let fields = header_fields(&tbf).unwrap();
+    assert_eq!(fields[5].name, "TbfHeaderProgram (9)");
+    assert_eq!(
+        fields[5].problem.as_deref(),
+        Some("should be 20 bytes long")
+    );
+
+    // A package name running past the end of the header.
+    let mut tbf = make_tbf("blink", 0, &[0; 16]);
+    tbf[42..44].copy_from_slice(&200u16.to_le_bytes());
+    update_checksum_in_raw(&mut tbf).unwrap();
+    let fields = header_fields(&tbf).unwrap();
+    let last = fields.last().unwrap();
+    assert_eq!(last.offset, 40);
+    assert!(last.problem.is_some());
+}
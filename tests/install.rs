@@ -0,0 +1,695 @@
+use tbf_parser::build::TbfHeaderBuilder;
+use tbf_parser::edit::{set_flags_in_raw, update_checksum_in_raw};
+use tbf_parser::parse::{parse_tbf_header, parse_tbf_header_lengths};
+use tockloader::apps::{AppReader, TbfIterator};
+use tockloader::errors::TockloaderError;
+use tockloader::install::{
+    bundle_apps, check_slot_size, erase_apps, erase_range, install_app, install_apps, plan_layout,
+    replace_apps, uninstall_apps, AppImage, PADDING_HEADER_SIZE,
+};
+use tockloader::interfaces::dry_run::{DryRunInterface, FlashOperation};
+use tockloader::interfaces::flash_file::FlashFileInterface;
+use tockloader::interfaces::traits::{BytesReader, FlashWriter};
+
+mod support;
+use support::make_tbf;
+
+/// A board whose flash silently stops taking writes after `pages_left` pages,
+/// like a transfer that was cut off.
+struct CutOffBoard {
+    flash: FlashFileInterface,
+    pages_left: usize,
+}
+
+#[async_trait::async_trait]
+impl BytesReader for CutOffBoard {
+    async fn read_range(&mut self, start: usize, len: usize) -> Result<Vec<u8>, TockloaderError> {
+        self.flash.read_range(start, len).await
+    }
+}
+
+#[async_trait::async_trait]
+impl FlashWriter for CutOffBoard {
+    fn page_size(&self) -> usize {
+        self.flash.page_size()
+    }
+
+    async fn write_page(&mut self, address: u32, data: &[u8]) -> Result<(), TockloaderError> {
+        if self.pages_left == 0 {
+            return Ok(());
+        }
+        self.pages_left -= 1;
+        self.flash.write_page(address, data).await
+    }
+}
+
+/// A board which computes CRCs itself, and gets them wrong.
+struct WrongCrcBoard {
+    flash: FlashFileInterface,
+}
+
+#[async_trait::async_trait]
+impl BytesReader for WrongCrcBoard {
+    async fn read_range(&mut self, start: usize, len: usize) -> Result<Vec<u8>, TockloaderError> {
+        self.flash.read_range(start, len).await
+    }
+
+    async fn crc_range(&mut self, _start: usize, _len: usize) -> Result<u32, TockloaderError> {
+        Ok(0x1234_5678)
+    }
+}
+
+#[async_trait::async_trait]
+impl FlashWriter for WrongCrcBoard {
+    fn page_size(&self) -> usize {
+        self.flash.page_size()
+    }
+
+    async fn write_page(&mut self, address: u32, data: &[u8]) -> Result<(), TockloaderError> {
+        self.flash.write_page(address, data).await
+    }
+}
+
+#[tokio::test]
+async fn install_preserves_protected_trailer_offsets() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut board = FlashFileInterface::new(&dir.path().join("flash.bin"), 512).unwrap();
+
+    let binary: Vec<u8> = (0..200u8).collect();
+    let tbf = make_tbf("blink", 0x40, &binary);
+    let app = AppImage::from_tbf(&tbf).unwrap();
+    assert_eq!(app.header().get_app_start_offset(), 52 + 0x40);
+
+    let address = 0x400;
+    install_app(&mut board, address, &app, None).await.unwrap();
+
+    let lengths = board.read_range(address as usize, 8).await.unwrap();
+    let (version, header_size, total_size) =
+        parse_tbf_header_lengths(&lengths.try_into().unwrap()).unwrap();
+    assert_eq!(total_size as usize, tbf.len());
+
+    let flash = board
+        .read_range(address as usize, total_size as usize)
+        .await
+        .unwrap();
+    let header = parse_tbf_header(&flash[..header_size as usize], version).unwrap();
+    assert_eq!(
+        header.get_protected_size(),
+        app.header().get_protected_size()
+    );
+    assert_eq!(
+        header.get_app_start_offset(),
+        app.header().get_app_start_offset()
+    );
+    assert_eq!(
+        header.get_init_function_offset(),
+        app.header().get_init_function_offset()
+    );
+    assert_eq!(header.get_package_name(), Some("blink"));
+
+    // The trailer is intact and the binary lands right after it.
+    let app_start = header.get_app_start_offset() as usize;
+    assert!(flash[header_size as usize..app_start]
+        .iter()
+        .all(|&b| b == 0xAA));
+    assert_eq!(&flash[app_start..], &binary[..]);
+
+    // Flash before the app was left erased.
+    let before = board.read_range(0, address as usize).await.unwrap();
+    assert!(before.iter().all(|&b| b == 0xFF));
+}
+
+#[test]
+fn init_function_outside_binary_is_rejected() {
+    let mut tbf = make_tbf("blink", 0x40, &[0; 16]);
+    // Move the entry point into the protected trailer.
+    let init_fn_offset = 0x10u32;
+    tbf[20..24].copy_from_slice(&init_fn_offset.to_le_bytes());
+    update_checksum_in_raw(&mut tbf).unwrap();
+
+    assert!(AppImage::from_tbf(&tbf).is_err());
+}
+
+#[test]
+fn app_without_main_or_program_header_is_rejected() {
+    // Only a Package Name TLV after the base header.
+    let mut tbf = TbfHeaderBuilder::new()
+        .package_name("blink")
+        .binary_size(64)
+        .build()
+        .unwrap();
+    tbf.resize(tbf.len() + 64, 0);
+
+    assert!(matches!(
+        AppImage::from_tbf(&tbf),
+        Err(TockloaderError::InvalidTbf(reason)) if reason.contains("Main")
+    ));
+}
+
+#[test]
+fn overflowing_header_offsets_are_rejected() {
+    let mut tbf = make_tbf("blink", 0, &[0; 16]);
+    // Protected trailer size and init function offset near u32::MAX, which
+    // would wrap around once the header size is added.
+    tbf[20..24].copy_from_slice(&u32::MAX.to_le_bytes());
+    tbf[24..28].copy_from_slice(&(u32::MAX - 8).to_le_bytes());
+    update_checksum_in_raw(&mut tbf).unwrap();
+
+    assert!(matches!(
+        AppImage::from_tbf(&tbf),
+        Err(TockloaderError::InvalidTbf(_))
+    ));
+}
+
+#[tokio::test]
+async fn install_pads_apps_to_alignment() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut board = FlashFileInterface::new(&dir.path().join("flash.bin"), 512).unwrap();
+
+    let blink = AppImage::from_tbf(&make_tbf("blink", 0, &[0; 300])).unwrap();
+    let hello = AppImage::from_tbf(&make_tbf("hello", 0, &[0; 1500])).unwrap();
+    let next = install_app(&mut board, 0x1000, &blink, Some(1024))
+        .await
+        .unwrap();
+    assert_eq!(next, 0x1400);
+    let next = install_app(&mut board, next, &hello, Some(1024))
+        .await
+        .unwrap();
+    assert_eq!(next, 0x1C00);
+
+    let mut iterator = TbfIterator::new(0x1000);
+    let mut found = Vec::new();
+    while let Some(tbf) = iterator.next(&mut board).await.unwrap() {
+        found.push((tbf.address, tbf.header.get_package_name().map(String::from)));
+    }
+    let blink_end = 0x1000 + blink.header().get_total_size();
+    let hello_end = 0x1400 + hello.header().get_total_size();
+    assert_eq!(
+        found,
+        vec![
+            (0x1000, Some("blink".to_string())),
+            (blink_end, None),
+            (0x1400, Some("hello".to_string())),
+            (hello_end, None),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn install_apps_lays_out_contiguously() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut board = FlashFileInterface::new(&dir.path().join("flash.bin"), 512).unwrap();
+
+    let apps = [
+        AppImage::from_tbf(&make_tbf("blink", 0, &[0; 300])).unwrap(),
+        AppImage::from_tbf(&make_tbf("hello", 0, &[0; 1500])).unwrap(),
+    ];
+    let addresses = install_apps(&mut board, 0x1000, &apps, Some(1024), Some(0x2000))
+        .await
+        .unwrap();
+    assert_eq!(addresses, vec![0x1000, 0x1400]);
+    assert_eq!(board.apps_end(0x1000).await.unwrap(), 0x1C00);
+
+    let names: Vec<_> = board
+        .list_apps(0x1000)
+        .await
+        .unwrap()
+        .iter()
+        .map(|app| app.header.get_package_name().unwrap().to_string())
+        .collect();
+    assert_eq!(names, vec!["blink", "hello"]);
+}
+
+#[tokio::test]
+async fn apps_in_fixed_slots_start_at_multiples_of_the_slot() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut board = FlashFileInterface::new(&dir.path().join("flash.bin"), 512).unwrap();
+
+    let apps = [
+        AppImage::from_tbf(&make_tbf("blink", 0, &[0; 300])).unwrap(),
+        AppImage::from_tbf(&make_tbf("hello", 0, &[0; 1500])).unwrap(),
+        AppImage::from_tbf(&make_tbf("sensors", 0, &[0; 100])).unwrap(),
+    ];
+    check_slot_size(&apps, 0x800).unwrap();
+    let addresses = install_apps(&mut board, 0x1000, &apps, Some(0x800), None)
+        .await
+        .unwrap();
+    assert_eq!(addresses, vec![0x1000, 0x1800, 0x2000]);
+    assert_eq!(board.apps_end(0x1000).await.unwrap(), 0x2800);
+}
+
+#[test]
+fn apps_must_fit_their_slot_with_its_padding() {
+    let app = AppImage::from_tbf(&make_tbf("hello", 0, &[0; 1500])).unwrap();
+    let size = app.as_bytes().len() as u32;
+    assert!(check_slot_size(std::slice::from_ref(&app), size).is_ok());
+    assert!(check_slot_size(std::slice::from_ref(&app), size + PADDING_HEADER_SIZE).is_ok());
+
+    match check_slot_size(&[app], size + 1) {
+        Err(TockloaderError::AppTooLargeForSlot {
+            name,
+            size: app_size,
+            slot_size,
+        }) => {
+            assert_eq!(name, "hello");
+            assert_eq!(app_size, size);
+            assert_eq!(slot_size, size + 1);
+        }
+        other => panic!("Unexpected result: {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn install_app_refuses_slots_past_the_address_space() {
+    let dir = tempfile::tempdir().unwrap();
+    let flash = dir.path().join("flash.bin");
+    let mut board = FlashFileInterface::new(&flash, 512).unwrap();
+    let app = AppImage::from_tbf(&make_tbf("blink", 0, &[0; 300])).unwrap();
+    let size = app.as_bytes().len() as u32;
+
+    match install_app(&mut board, 0xFFFF_FF00, &app, Some(512)).await {
+        Err(TockloaderError::AppRegionFull { needed, available }) => {
+            // The app and its padding, up to the next page after the end.
+            assert_eq!(needed, 0x300);
+            assert_eq!(available, 0xFF);
+        }
+        other => panic!("Unexpected result: {other:?}"),
+    }
+    // Only the padding of the slot runs past the end, as the room left after
+    // the app is too small for a padding header.
+    assert!(matches!(
+        install_app(&mut board, 0xFFFF_FFF8 - size, &app, Some(512)).await,
+        Err(TockloaderError::AppRegionFull { .. })
+    ));
+    assert!(matches!(
+        plan_layout(0xFFFF_F000, &[app.clone(), app], Some(0x800)),
+        Err(TockloaderError::AppRegionFull { .. })
+    ));
+    assert_eq!(std::fs::metadata(&flash).unwrap().len(), 0);
+}
+
+#[tokio::test]
+async fn install_apps_refuses_overflow_before_writing() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut board = FlashFileInterface::new(&dir.path().join("flash.bin"), 512).unwrap();
+
+    let apps = [
+        AppImage::from_tbf(&make_tbf("blink", 0, &[0; 300])).unwrap(),
+        AppImage::from_tbf(&make_tbf("hello", 0, &[0; 1500])).unwrap(),
+    ];
+    let (_, end) = plan_layout(0x1000, &apps, Some(1024)).unwrap();
+    assert_eq!(end, 0x1C00);
+
+    match install_apps(&mut board, 0x1000, &apps, Some(1024), Some(0x1800)).await {
+        Err(TockloaderError::AppRegionFull { needed, available }) => {
+            assert_eq!(needed, 0xC00);
+            assert_eq!(available, 0x800);
+        }
+        other => panic!("Unexpected result: {other:?}"),
+    }
+    // Not even the first app was written.
+    assert!(board.list_apps(0x1000).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn install_apps_aligns_the_first_app() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut board = FlashFileInterface::new(&dir.path().join("flash.bin"), 512).unwrap();
+
+    let apps = [AppImage::from_tbf(&make_tbf("blink", 0, &[0; 300])).unwrap()];
+    let addresses = install_apps(&mut board, 0x1010, &apps, Some(512), None)
+        .await
+        .unwrap();
+    assert_eq!(addresses, vec![0x1200]);
+
+    // The kernel starts walking at the requested address, and finds the app
+    // after the padding.
+    let mut iterator = TbfIterator::new(0x1010);
+    let padding = iterator.next(&mut board).await.unwrap().unwrap();
+    assert!(!padding.header.is_app());
+    assert_eq!(padding.total_size, 0x1F0);
+    let app = iterator.next(&mut board).await.unwrap().unwrap();
+    assert_eq!(app.address, 0x1200);
+    assert_eq!(app.header.get_package_name(), Some("blink"));
+}
+
+#[tokio::test]
+async fn partially_written_app_is_detected() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut board = CutOffBoard {
+        flash: FlashFileInterface::new(&dir.path().join("flash.bin"), 512).unwrap(),
+        pages_left: 2,
+    };
+
+    let apps = [AppImage::from_tbf(&make_tbf("blink", 0, &[0x42; 1500])).unwrap()];
+    let result = install_apps(&mut board, 0, &apps, Some(512), None).await;
+    assert!(matches!(
+        result,
+        Err(TockloaderError::InstalledAppMismatch { address: 0, reason })
+            if reason.contains("byte 1024")
+    ));
+}
+
+#[tokio::test]
+async fn crc_mismatch_is_reported() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut board = WrongCrcBoard {
+        flash: FlashFileInterface::new(&dir.path().join("flash.bin"), 512).unwrap(),
+    };
+
+    let apps = [AppImage::from_tbf(&make_tbf("blink", 0, &[0x42; 1500])).unwrap()];
+    let result = install_apps(&mut board, 0, &apps, Some(512), None).await;
+    assert!(matches!(
+        result,
+        Err(TockloaderError::InstalledAppMismatch { address: 0, reason })
+            if reason.starts_with("its CRC is 0x12345678 instead of")
+    ));
+}
+
+#[test]
+fn truncated_tbf_names_both_sizes() {
+    let mut tbf = make_tbf("blink", 0, &[0; 300]);
+    let expected = tbf.len();
+    tbf.truncate(200);
+    let error = AppImage::from_tbf(&tbf).err().unwrap();
+    assert!(matches!(
+        error,
+        TockloaderError::NotEnoughFlash { expected: e, actual: 200 } if e == expected
+    ));
+    assert!(error.to_string().contains(&expected.to_string()));
+}
+
+/// The names of the apps from `address`, in flash order.
+async fn app_names(board: &mut FlashFileInterface, address: u32) -> Vec<String> {
+    board
+        .list_apps(address)
+        .await
+        .unwrap()
+        .iter()
+        .map(|app| app.header.get_package_name().unwrap().to_string())
+        .collect()
+}
+
+fn make_sticky_tbf(name: &str, binary: &[u8]) -> Vec<u8> {
+    let mut tbf = make_tbf(name, 0, binary);
+    set_flags_in_raw(&mut tbf, 3).unwrap();
+    tbf
+}
+
+#[tokio::test]
+async fn replace_apps_removes_the_previous_apps() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut board = FlashFileInterface::new(&dir.path().join("flash.bin"), 512).unwrap();
+    let old = [
+        AppImage::from_tbf(&make_tbf("blink", 0, &[0; 300])).unwrap(),
+        AppImage::from_tbf(&make_tbf("hello", 0, &[0; 1500])).unwrap(),
+    ];
+    install_apps(&mut board, 0x1000, &old, Some(512), None)
+        .await
+        .unwrap();
+
+    let new = [AppImage::from_tbf(&make_tbf("sensors", 0, &[0; 100])).unwrap()];
+    let (kept, addresses) = replace_apps(&mut board, 0x1000, &new, Some(512), None, false)
+        .await
+        .unwrap();
+    assert!(kept.is_empty());
+    assert_eq!(addresses, vec![0x1000]);
+    assert_eq!(app_names(&mut board, 0x1000).await, vec!["sensors"]);
+    assert_eq!(board.apps_end(0x1000).await.unwrap(), 0x1200);
+}
+
+#[tokio::test]
+async fn replace_apps_keeps_sticky_apps_unless_forced() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut board = FlashFileInterface::new(&dir.path().join("flash.bin"), 512).unwrap();
+    let old = [
+        AppImage::from_tbf(&make_tbf("blink", 0, &[0; 300])).unwrap(),
+        AppImage::from_tbf(&make_sticky_tbf("keeper", &[0x42; 300])).unwrap(),
+    ];
+    install_apps(&mut board, 0x1000, &old, Some(512), None)
+        .await
+        .unwrap();
+
+    let new = [AppImage::from_tbf(&make_tbf("sensors", 0, &[0; 100])).unwrap()];
+    let (kept, addresses) = replace_apps(&mut board, 0x1000, &new, Some(512), None, false)
+        .await
+        .unwrap();
+    assert_eq!(kept.len(), 1);
+    assert_eq!(addresses, vec![0x1000, 0x1200]);
+    assert_eq!(
+        app_names(&mut board, 0x1000).await,
+        vec!["keeper", "sensors"]
+    );
+    assert_eq!(
+        board
+            .read_range(0x1000, old[1].as_bytes().len())
+            .await
+            .unwrap(),
+        old[1].as_bytes()
+    );
+
+    let (kept, _) = replace_apps(&mut board, 0x1000, &new, Some(512), None, true)
+        .await
+        .unwrap();
+    assert!(kept.is_empty());
+    assert_eq!(app_names(&mut board, 0x1000).await, vec!["sensors"]);
+}
+
+#[tokio::test]
+async fn bundle_apps_packs_every_app_in_one_pass() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut board = FlashFileInterface::new(&dir.path().join("flash.bin"), 512).unwrap();
+    let old = [
+        AppImage::from_tbf(&make_tbf("blink", 0, &[0; 300])).unwrap(),
+        AppImage::from_tbf(&make_tbf("hello", 0, &[0; 1500])).unwrap(),
+    ];
+    install_apps(&mut board, 0x1000, &old, Some(1024), None)
+        .await
+        .unwrap();
+
+    let new = [AppImage::from_tbf(&make_tbf("sensors", 0, &[0; 100])).unwrap()];
+    let mut board = DryRunInterface::new(board);
+    let (kept, addresses) = bundle_apps(&mut board, 0x1000, &new, Some(512), None, None)
+        .await
+        .unwrap();
+    assert_eq!(kept.len(), 2);
+    assert_eq!(addresses, vec![0x1000, 0x1200, 0x1A00]);
+    // Each page of the region is written once, in order.
+    assert_eq!(
+        board.operations(),
+        (0x1000..0x1C00)
+            .step_by(512)
+            .map(|address| FlashOperation::WritePage { address, len: 512 })
+            .collect::<Vec<_>>()
+    );
+
+    let mut board = board.into_inner();
+    bundle_apps(&mut board, 0x1000, &new, Some(512), None, None)
+        .await
+        .unwrap();
+    assert_eq!(
+        app_names(&mut board, 0x1000).await,
+        vec!["blink", "hello", "sensors"]
+    );
+    assert_eq!(
+        board
+            .read_range(0x1200, old[1].as_bytes().len())
+            .await
+            .unwrap(),
+        old[1].as_bytes()
+    );
+}
+
+#[tokio::test]
+async fn bundle_apps_refuses_overflow_before_writing() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut board = FlashFileInterface::new(&dir.path().join("flash.bin"), 512).unwrap();
+    let old = [AppImage::from_tbf(&make_tbf("hello", 0, &[0; 1500])).unwrap()];
+    install_apps(&mut board, 0x1000, &old, Some(512), None)
+        .await
+        .unwrap();
+
+    let new = [AppImage::from_tbf(&make_tbf("sensors", 0, &[0; 600])).unwrap()];
+    let mut board = DryRunInterface::new(board);
+    let result = bundle_apps(&mut board, 0x1000, &new, Some(512), Some(0x1A00), None).await;
+    match result.err() {
+        Some(TockloaderError::AppRegionFull { needed, available }) => {
+            assert_eq!(needed, 0xC00);
+            assert_eq!(available, 0xA00);
+        }
+        other => panic!("Unexpected error: {other:?}"),
+    }
+    assert!(board.operations().is_empty());
+}
+
+#[tokio::test]
+async fn bundle_apps_with_replace_keeps_only_sticky_apps() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut board = FlashFileInterface::new(&dir.path().join("flash.bin"), 512).unwrap();
+    let old = [
+        AppImage::from_tbf(&make_tbf("blink", 0, &[0; 300])).unwrap(),
+        AppImage::from_tbf(&make_sticky_tbf("keeper", &[0x42; 300])).unwrap(),
+        AppImage::from_tbf(&make_tbf("hello", 0, &[0; 1500])).unwrap(),
+    ];
+    install_apps(&mut board, 0x1000, &old, Some(512), None)
+        .await
+        .unwrap();
+
+    let new = [AppImage::from_tbf(&make_tbf("sensors", 0, &[0; 100])).unwrap()];
+    let (kept, addresses) = bundle_apps(&mut board, 0x1000, &new, Some(512), None, Some(false))
+        .await
+        .unwrap();
+    assert_eq!(kept.len(), 1);
+    assert_eq!(addresses, vec![0x1000, 0x1200]);
+    assert_eq!(
+        app_names(&mut board, 0x1000).await,
+        vec!["keeper", "sensors"]
+    );
+    // What the old apps left past the new ones is erased.
+    assert_eq!(board.apps_end(0x1000).await.unwrap(), 0x1400);
+}
+
+#[tokio::test]
+async fn erasing_all_apps_leaves_an_empty_region() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut board = FlashFileInterface::new(&dir.path().join("flash.bin"), 512).unwrap();
+    let old = [
+        AppImage::from_tbf(&make_tbf("blink", 0, &[0; 300])).unwrap(),
+        AppImage::from_tbf(&make_tbf("hello", 0, &[0; 1500])).unwrap(),
+    ];
+    install_apps(&mut board, 0x1000, &old, Some(512), None)
+        .await
+        .unwrap();
+
+    let mut board = DryRunInterface::new(board);
+    let (kept, addresses) = replace_apps(&mut board, 0x1000, &[], Some(512), None, false)
+        .await
+        .unwrap();
+    assert!(kept.is_empty());
+    assert!(addresses.is_empty());
+    // Whole pages are erased rather than written.
+    assert_eq!(
+        board.operations(),
+        (0x1000..0x1A00)
+            .step_by(512)
+            .map(|address| FlashOperation::ErasePage { address })
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(board.apps_end(0x1000).await.unwrap(), 0x1000);
+}
+
+#[tokio::test]
+async fn uninstall_removes_every_app_with_the_name() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut board = FlashFileInterface::new(&dir.path().join("flash.bin"), 512).unwrap();
+    let apps = [
+        AppImage::from_tbf(&make_tbf("blink", 0, &[0; 300])).unwrap(),
+        AppImage::from_tbf(&make_tbf("hello", 0, &[0; 300])).unwrap(),
+        AppImage::from_tbf(&make_tbf("blink", 0, &[0; 300])).unwrap(),
+    ];
+    install_apps(&mut board, 0x1000, &apps, Some(512), None)
+        .await
+        .unwrap();
+
+    let (removed, kept) = uninstall_apps(&mut board, 0x1000, "blink", true, false)
+        .await
+        .unwrap();
+    assert_eq!(removed.len(), 1);
+    assert_eq!(removed[0].address, 0x1000);
+    assert!(kept.is_empty());
+    assert_eq!(app_names(&mut board, 0x1000).await, vec!["hello", "blink"]);
+
+    let (removed, _) = uninstall_apps(&mut board, 0x1000, "blink", false, false)
+        .await
+        .unwrap();
+    assert_eq!(removed.len(), 1);
+    assert_eq!(removed[0].address, 0x1400);
+    // The apps left stay where they were.
+    let hello = board.list_apps(0x1000).await.unwrap();
+    assert_eq!(hello.len(), 1);
+    assert_eq!(hello[0].address, 0x1200);
+    assert_eq!(board.apps_end(0x1000).await.unwrap(), 0x1600);
+}
+
+#[tokio::test]
+async fn uninstall_keeps_sticky_apps_unless_forced() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut board = FlashFileInterface::new(&dir.path().join("flash.bin"), 512).unwrap();
+    let apps = [AppImage::from_tbf(&make_sticky_tbf("keeper", &[0x42; 300])).unwrap()];
+    install_apps(&mut board, 0x1000, &apps, Some(512), None)
+        .await
+        .unwrap();
+
+    let (removed, kept) = uninstall_apps(&mut board, 0x1000, "keeper", false, false)
+        .await
+        .unwrap();
+    assert!(removed.is_empty());
+    assert_eq!(kept.len(), 1);
+    assert_eq!(app_names(&mut board, 0x1000).await, vec!["keeper"]);
+
+    uninstall_apps(&mut board, 0x1000, "keeper", false, true)
+        .await
+        .unwrap();
+    assert!(app_names(&mut board, 0x1000).await.is_empty());
+}
+
+#[tokio::test]
+async fn uninstalling_a_missing_app_names_the_installed_ones() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut board = FlashFileInterface::new(&dir.path().join("flash.bin"), 512).unwrap();
+    let apps = [AppImage::from_tbf(&make_tbf("blink", 0, &[0; 300])).unwrap()];
+    install_apps(&mut board, 0x1000, &apps, Some(512), None)
+        .await
+        .unwrap();
+
+    let error = uninstall_apps(&mut board, 0x1000, "hello", false, false)
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        &error,
+        TockloaderError::AppNotFound { name, installed } if name == "hello" && installed == &["blink"]
+    ));
+    assert_eq!(app_names(&mut board, 0x1000).await, vec!["blink"]);
+}
+
+#[tokio::test]
+async fn erase_range_keeps_the_rest_of_partial_pages() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut board = FlashFileInterface::new(&dir.path().join("flash.bin"), 512).unwrap();
+    for page in 0..4 {
+        board.write_page(page * 512, &[0x42; 512]).await.unwrap();
+    }
+
+    erase_range(&mut board, 0x100, 0x700).await.unwrap();
+    let flash = board.read_range(0, 0x800).await.unwrap();
+    assert!(flash[..0x100].iter().all(|&byte| byte == 0x42));
+    assert!(flash[0x100..0x700].iter().all(|&byte| byte == 0xFF));
+    assert!(flash[0x700..].iter().all(|&byte| byte == 0x42));
+
+    // Within a single page.
+    erase_range(&mut board, 0x710, 0x720).await.unwrap();
+    let flash = board.read_range(0x700, 0x100).await.unwrap();
+    assert!(flash[..0x10].iter().all(|&byte| byte == 0x42));
+    assert!(flash[0x10..0x20].iter().all(|&byte| byte == 0xFF));
+    assert!(flash[0x20..].iter().all(|&byte| byte == 0x42));
+}
+
+#[tokio::test]
+async fn erase_apps_erases_the_whole_region() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut board = FlashFileInterface::new(&dir.path().join("flash.bin"), 512).unwrap();
+    let app = AppImage::from_tbf(&make_tbf("blink", 0, &[0; 600])).unwrap();
+    install_apps(&mut board, 0x1000, &[app], Some(512), Some(0x3000))
+        .await
+        .unwrap();
+    // Left over past the end of the apps.
+    board.write_page(0x2800, &[0x42; 512]).await.unwrap();
+
+    let kept = erase_apps(&mut board, 0x1000, Some(512), Some(0x3000), false)
+        .await
+        .unwrap();
+    assert!(kept.is_empty());
+    let flash = board.read_range(0x1000, 0x2000).await.unwrap();
+    assert!(flash.iter().all(|&byte| byte == 0xFF));
+    assert!(board.list_apps(0x1000).await.unwrap().is_empty());
+}
@@ -0,0 +1,173 @@
+#![cfg(feature = "jlink")]
+
+#[cfg(unix)]
+use std::os::unix::fs::PermissionsExt;
+
+use tockloader::errors::TockloaderError;
+use tockloader::interfaces::jlink::JLinkInterface;
+use tockloader::interfaces::traits::{BoardInterface, BytesReader};
+
+#[test]
+fn missing_jlink_is_reported() {
+    let mut board = JLinkInterface::new("nRF52840_xxAA");
+    board.set_command("/nonexistent/JLinkExe");
+
+    let error = board.open().unwrap_err();
+    assert!(
+        matches!(
+            &error,
+            TockloaderError::ToolNotFound {
+                tool: "JLinkExe",
+                ..
+            }
+        ),
+        "{error:?}"
+    );
+    assert!(error.to_string().starts_with("JLinkExe not found"));
+    assert!(error.to_string().contains("\nInstall the J-Link"));
+    assert!(error.to_string().ends_with("--jlink-cmd."));
+}
+
+/// A J-Link Commander that prints what an old version does without a target.
+#[cfg(unix)]
+#[test]
+fn missing_target_is_reported_from_the_output() {
+    let dir = tempfile::tempdir().unwrap();
+    let script = dir.path().join("JLinkExe");
+    std::fs::write(
+        &script,
+        "#!/bin/sh\ncat > /dev/null\necho 'Cannot connect to target.'\n",
+    )
+    .unwrap();
+    std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    let mut board = JLinkInterface::new("nRF52840_xxAA");
+    board.set_command(script.to_str().unwrap());
+    match board.open() {
+        Err(TockloaderError::TargetNotResponding { tool, output }) => {
+            assert_eq!(tool, "JLinkExe");
+            assert_eq!(output, "Cannot connect to target.");
+        }
+        other => panic!("Unexpected result: {other:?}"),
+    }
+    assert!(!board.is_connected());
+}
+
+/// Install a J-Link Commander in `dir` which runs `body` as a shell script,
+/// with its commands on standard input.
+#[cfg(unix)]
+fn fake_jlink(dir: &std::path::Path, body: &str) -> String {
+    let script = dir.join("JLinkExe");
+    std::fs::write(&script, format!("#!/bin/sh\n{body}")).unwrap();
+    std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+    script.to_str().unwrap().to_string()
+}
+
+/// Fills the file of a `savebin` command with as many `0x5A` as asked for.
+#[cfg(unix)]
+const SAVEBIN: &str = "set -- $(sed -n 's/^savebin \\(.*\\), \\(.*\\), 0x\\(.*\\)$/\\1 \\3/p')\n\
+                       head -c $((0x$2)) /dev/zero | tr '\\0' 'Z' > \"$1\"\n";
+
+#[cfg(unix)]
+#[tokio::test]
+async fn flash_is_read_through_a_saved_binary() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut board = JLinkInterface::new("nRF52840_xxAA");
+    board.set_command(&fake_jlink(dir.path(), SAVEBIN));
+
+    assert_eq!(board.read_range(0x40E, 8).await.unwrap(), [0x5A; 8]);
+    assert_eq!(board.read_range(0x600, 1024).await.unwrap(), [0x5A; 1024]);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn read_without_a_saved_binary_fails() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut board = JLinkInterface::new("nRF52840_xxAA");
+    board.set_command(&fake_jlink(
+        dir.path(),
+        "cat > /dev/null\necho 'Could not read memory.'\n",
+    ));
+
+    match board.read_range(0x600, 1024).await {
+        Err(TockloaderError::ToolCommandFailed { tool, output }) => {
+            assert_eq!(tool, "JLinkExe");
+            assert_eq!(output, "Could not read memory.");
+        }
+        other => panic!("Unexpected result: {other:?}"),
+    }
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn saved_binary_is_removed_after_reading() {
+    let dir = tempfile::tempdir().unwrap();
+    let noted = dir.path().join("saved");
+    let mut board = JLinkInterface::new("nRF52840_xxAA");
+    board.set_command(&fake_jlink(
+        dir.path(),
+        &format!("{SAVEBIN}echo \"$1\" > {}\n", noted.display()),
+    ));
+
+    board.read_range(0x600, 16).await.unwrap();
+    let saved = std::fs::read_to_string(&noted).unwrap();
+    assert!(!std::path::Path::new(saved.trim()).exists(), "{saved}");
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn failed_exit_fails_the_read_even_with_a_saved_binary() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut board = JLinkInterface::new("nRF52840_xxAA");
+    board.set_command(&fake_jlink(dir.path(), &format!("{SAVEBIN}exit 1\n")));
+
+    assert!(matches!(
+        board.read_range(0x600, 16).await,
+        Err(TockloaderError::ToolCommandFailed {
+            tool: "JLinkExe",
+            ..
+        })
+    ));
+}
+
+#[cfg(unix)]
+#[test]
+fn reset_lets_the_board_run() {
+    let dir = tempfile::tempdir().unwrap();
+    let commands = dir.path().join("commands");
+    let mut board = JLinkInterface::new("nRF52840_xxAA");
+    board.set_command(&fake_jlink(
+        dir.path(),
+        &format!("cat > {}\n", commands.display()),
+    ));
+
+    board.reset().unwrap();
+    assert_eq!(std::fs::read_to_string(&commands).unwrap(), "r\ng\nexit\n");
+
+    board.set_command(&fake_jlink(dir.path(), "cat > /dev/null\nexit 1\n"));
+    assert!(matches!(
+        board.reset(),
+        Err(TockloaderError::ToolCommandFailed {
+            tool: "JLinkExe",
+            ..
+        })
+    ));
+}
+
+#[cfg(unix)]
+#[test]
+fn unknown_device_is_named() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut board = JLinkInterface::new("nRF99");
+    board.set_command(&fake_jlink(
+        dir.path(),
+        "cat > /dev/null\necho 'Unknown device specified.'\nexit 1\n",
+    ));
+
+    let error = board.open().unwrap_err();
+    assert!(
+        matches!(&error, TockloaderError::UnknownDevice { device, .. } if device == "nRF99"),
+        "{error:?}"
+    );
+    assert!(error.to_string().contains("'nRF99'"));
+}
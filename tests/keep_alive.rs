@@ -0,0 +1,104 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use tockloader::bootloader::attribute::Attribute;
+use tockloader::bootloader::crc::tock_crc32;
+use tockloader::errors::TockloaderError;
+use tockloader::interfaces::keep_alive::KeepAliveInterface;
+use tockloader::interfaces::traits::{BootloaderInterface, BytesReader, FlashWriter};
+
+/// A board noting down every command, with writes taking a while.
+#[derive(Default)]
+struct SlowBoard {
+    log: Vec<&'static str>,
+}
+
+#[async_trait]
+impl BytesReader for SlowBoard {
+    async fn read_range(&mut self, _start: usize, len: usize) -> Result<Vec<u8>, TockloaderError> {
+        self.log.push("read");
+        Ok(vec![0xFF; len])
+    }
+}
+
+#[async_trait]
+impl FlashWriter for SlowBoard {
+    fn page_size(&self) -> usize {
+        512
+    }
+
+    async fn write_page(&mut self, _address: u32, _data: &[u8]) -> Result<(), TockloaderError> {
+        self.log.push("write started");
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        self.log.push("write done");
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl BootloaderInterface for SlowBoard {
+    async fn sync(&mut self) -> Result<(), TockloaderError> {
+        Ok(())
+    }
+
+    fn needs_sync(&self) -> bool {
+        false
+    }
+
+    async fn ping(&mut self) -> Result<(), TockloaderError> {
+        self.log.push("ping");
+        Ok(())
+    }
+
+    async fn get_attribute(&mut self, _index: u8) -> Result<Option<Attribute>, TockloaderError> {
+        Ok(None)
+    }
+
+    async fn set_attribute(
+        &mut self,
+        _index: u8,
+        _attribute: &Attribute,
+    ) -> Result<(), TockloaderError> {
+        Ok(())
+    }
+
+    async fn crc_internal_flash(
+        &mut self,
+        _address: u32,
+        len: u32,
+    ) -> Result<u32, TockloaderError> {
+        Ok(tock_crc32(&vec![0xFF; len as usize]))
+    }
+
+    async fn get_bootloader_info(&mut self) -> Result<String, TockloaderError> {
+        Ok("{}".to_string())
+    }
+
+    async fn set_start_address(&mut self, _address: u32) -> Result<(), TockloaderError> {
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn idle_bootloader_is_pinged_between_commands() {
+    let mut board = KeepAliveInterface::new(SlowBoard::default(), Some(Duration::from_millis(20)));
+    board.write_page(0x1000, &[0; 512]).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(150)).await;
+    board.read_range(0x1000, 16).await.unwrap();
+    let log = board.into_inner().await.log;
+
+    // Nothing is pinged while the write is in progress, only once idle.
+    assert_eq!(log[..2], ["write started", "write done"]);
+    assert_eq!(log.last(), Some(&"read"));
+    let pings = &log[2..log.len() - 1];
+    assert!(pings.len() >= 2, "{log:?}");
+    assert!(pings.iter().all(|&command| command == "ping"), "{log:?}");
+}
+
+#[tokio::test]
+async fn nothing_is_pinged_unless_asked() {
+    let mut board = KeepAliveInterface::new(SlowBoard::default(), None);
+    board.read_range(0x1000, 16).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(board.into_inner().await.log, ["read"]);
+}
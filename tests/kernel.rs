@@ -0,0 +1,48 @@
+use tockloader::errors::TockloaderError;
+use tockloader::install::{install_apps, AppImage};
+use tockloader::interfaces::flash_file::FlashFileInterface;
+use tockloader::interfaces::traits::BytesReader;
+use tockloader::kernel::flash_kernel;
+
+mod support;
+use support::make_tbf;
+
+async fn board_with_app(path: &std::path::Path) -> FlashFileInterface {
+    let mut board = FlashFileInterface::new(path, 512).unwrap();
+    let apps = [AppImage::from_tbf(&make_tbf("blink", 0, &[0; 300])).unwrap()];
+    install_apps(&mut board, 0x4000, &apps, Some(512), None)
+        .await
+        .unwrap();
+    board
+}
+
+#[tokio::test]
+async fn kernel_before_the_apps_is_written() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut board = board_with_app(&dir.path().join("flash.bin")).await;
+
+    let kernel = vec![0x5A; 0x4000];
+    flash_kernel(&mut board, 0, &kernel, 0x4000, false)
+        .await
+        .unwrap();
+    assert_eq!(board.read_range(0, 0x4000).await.unwrap(), kernel);
+}
+
+#[tokio::test]
+async fn kernel_overlapping_an_app_needs_force() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut board = board_with_app(&dir.path().join("flash.bin")).await;
+    let app = board.read_range(0x4000, 16).await.unwrap();
+
+    let kernel = vec![0x5A; 0x4100];
+    assert!(matches!(
+        flash_kernel(&mut board, 0, &kernel, 0x4000, false).await,
+        Err(TockloaderError::KernelOverlapsApp { name, address: 0x4000, .. }) if name == "blink"
+    ));
+    assert_eq!(board.read_range(0x4000, 16).await.unwrap(), app);
+
+    flash_kernel(&mut board, 0, &kernel, 0x4000, true)
+        .await
+        .unwrap();
+    assert_eq!(board.read_range(0x4000, 16).await.unwrap(), vec![0x5A; 16]);
+}
@@ -0,0 +1,127 @@
+#![cfg(feature = "openocd")]
+
+use tockloader::errors::TockloaderError;
+use tockloader::interfaces::openocd::OpenOcdInterface;
+use tockloader::interfaces::traits::{BoardInterface, BytesReader};
+
+#[test]
+fn missing_openocd_is_reported() {
+    let mut board = OpenOcdInterface::new("nordic_nrf52_dk.cfg");
+    board.set_command("/nonexistent/openocd");
+
+    let error = board.open().unwrap_err();
+    assert!(
+        matches!(&error, TockloaderError::ToolNotFound { tool: "openocd", command, .. } if command == "/nonexistent/openocd"),
+        "{error:?}"
+    );
+    assert!(error.to_string().starts_with("openocd not found"));
+    assert!(error.to_string().ends_with(
+        "\nInstall OpenOCD, e.g. with your package manager, or pass its path with --openocd-cmd."
+    ));
+    assert!(!board.is_connected());
+}
+
+#[cfg(unix)]
+#[test]
+fn failing_openocd_means_the_target_is_not_responding() {
+    let mut board = OpenOcdInterface::new("nordic_nrf52_dk.cfg");
+    board.set_command("false");
+
+    assert!(matches!(
+        board.open(),
+        Err(TockloaderError::TargetNotResponding {
+            tool: "openocd",
+            ..
+        })
+    ));
+    assert!(!board.is_connected());
+}
+
+/// Install a fake `openocd` in `dir`, which fills the file of a `dump_image`
+/// command with `0x5A`, but with only `written` bytes if that is given.
+#[cfg(unix)]
+fn fake_openocd(dir: &std::path::Path, written: Option<usize>) -> String {
+    use std::os::unix::fs::PermissionsExt;
+
+    let length = written.map_or("$3".to_string(), |written| written.to_string());
+    let script = format!(
+        "#!/bin/sh\n\
+         set -- $(echo \"$2\" | sed -n 's/.*dump_image \\([^ ]*\\) \\([^ ]*\\) \\([0-9]*\\).*/\\1 \\2 \\3/p')\n\
+         head -c {length} /dev/zero | tr '\\0' 'Z' > \"$1\"\n"
+    );
+    let path = dir.join("openocd");
+    std::fs::write(&path, script).unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    path.to_str().unwrap().to_string()
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn flash_is_read_through_a_dumped_image() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut board = OpenOcdInterface::new("nordic_nrf52_dk.cfg");
+    board.set_command(&fake_openocd(dir.path(), None));
+
+    assert_eq!(board.read_range(0x600, 1024).await.unwrap(), [0x5A; 1024]);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn short_dump_is_a_failed_read() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut board = OpenOcdInterface::new("nordic_nrf52_dk.cfg");
+    board.set_command(&fake_openocd(dir.path(), Some(16)));
+
+    assert!(matches!(
+        board.read_range(0x600, 1024).await,
+        Err(TockloaderError::ToolCommandFailed {
+            tool: "openocd",
+            ..
+        })
+    ));
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn read_commands_can_be_replaced() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut board = OpenOcdInterface::new("nordic_nrf52_dk.cfg");
+    board.set_command(&fake_openocd(dir.path(), None));
+    // The fake only knows `dump_image`, so this reads 4 bytes whatever the
+    // length asked for.
+    board.set_action_command("read", "reset init; dump_image {binary} {address} 4");
+
+    assert!(board.read_range(0x600, 1024).await.is_err());
+    assert_eq!(board.read_range(0x600, 4).await.unwrap(), [0x5A; 4]);
+}
+
+#[cfg(unix)]
+#[test]
+fn reset_runs_reset_run() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mut board = OpenOcdInterface::new("nordic_nrf52_dk.cfg");
+    board.set_command("false");
+    assert!(matches!(
+        board.reset(),
+        Err(TockloaderError::ToolCommandFailed {
+            tool: "openocd",
+            ..
+        })
+    ));
+
+    let dir = tempfile::tempdir().unwrap();
+    let script = dir.path().join("script");
+    let path = dir.path().join("openocd");
+    std::fs::write(
+        &path,
+        format!("#!/bin/sh\necho \"$2\" > {}\n", script.display()),
+    )
+    .unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    board.set_command(path.to_str().unwrap());
+    board.reset().unwrap();
+    assert!(std::fs::read_to_string(&script)
+        .unwrap()
+        .contains("init; reset run; exit"));
+}
@@ -0,0 +1,48 @@
+use tockloader::install::{install_apps, AppImage};
+use tockloader::interfaces::flash_file::FlashFileInterface;
+use tockloader::interfaces::progress::ProgressInterface;
+use tockloader::interfaces::traits::FlashWriter;
+
+mod support;
+use support::make_tbf;
+
+#[tokio::test]
+async fn install_reports_progress_up_to_the_whole_layout() {
+    let dir = tempfile::tempdir().unwrap();
+    let board = FlashFileInterface::new(&dir.path().join("flash.bin"), 512).unwrap();
+    let apps = [
+        AppImage::from_tbf(&make_tbf("blink", 0, &[0; 1500])).unwrap(),
+        AppImage::from_tbf(&make_tbf("hello", 0, &[0; 700])).unwrap(),
+    ];
+
+    let mut reports = Vec::new();
+    let mut board = ProgressInterface::new(board, |written, total| reports.push((written, total)));
+    install_apps(&mut board, 0x1000, &apps, Some(512), None)
+        .await
+        .unwrap();
+    board.finish();
+    drop(board);
+
+    let total = reports[0].1;
+    assert!(reports.iter().all(|&(_, t)| t == total), "{reports:?}");
+    assert!(
+        reports.windows(2).all(|pair| pair[0].0 < pair[1].0),
+        "{reports:?}"
+    );
+    assert_eq!(reports.last(), Some(&(total, total)));
+    // Both apps, each rounded up to whole pages.
+    assert!(total >= 1500 + 700, "{total}");
+    assert_eq!(total % 512, 0);
+}
+
+#[tokio::test]
+async fn nothing_is_reported_without_a_plan() {
+    let dir = tempfile::tempdir().unwrap();
+    let board = FlashFileInterface::new(&dir.path().join("flash.bin"), 512).unwrap();
+    let mut calls = 0;
+    let mut board = ProgressInterface::new(board, |_, _| calls += 1);
+    board.write_page(0, &[0; 512]).await.unwrap();
+    board.finish();
+    drop(board);
+    assert_eq!(calls, 0);
+}
@@ -0,0 +1,65 @@
+#![cfg(feature = "serial")]
+
+use tockloader::interfaces::serial::{clean_port_path, describe_port, prompt_for_port};
+use tokio_serial::{SerialPortInfo, SerialPortType};
+
+#[test]
+fn sysfs_paths_become_device_nodes() {
+    if cfg!(target_os = "linux") {
+        assert_eq!(
+            clean_port_path("/sys/class/tty/ttyACM0".to_string()),
+            "/dev/ttyACM0"
+        );
+    }
+    assert_eq!(clean_port_path("/dev/ttyUSB1".to_string()), "/dev/ttyUSB1");
+    assert_eq!(clean_port_path("COM3".to_string()), "COM3");
+}
+
+#[test]
+fn ports_are_described_starting_with_their_path() {
+    let port = SerialPortInfo {
+        port_name: "/dev/ttyS0".to_string(),
+        port_type: SerialPortType::PciPort,
+    };
+    assert_eq!(describe_port(&port), "/dev/ttyS0  PCI");
+}
+
+fn pci_ports(names: &[&str]) -> Vec<SerialPortInfo> {
+    names
+        .iter()
+        .map(|name| SerialPortInfo {
+            port_name: name.to_string(),
+            port_type: SerialPortType::PciPort,
+        })
+        .collect()
+}
+
+#[test]
+fn port_is_picked_by_number() {
+    let ports = pci_ports(&["/dev/ttyS0", "/dev/ttyS1"]);
+    let mut output = Vec::new();
+    let port = prompt_for_port(&ports, &mut "2\n".as_bytes(), &mut output).unwrap();
+    assert_eq!(port, "/dev/ttyS1");
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.contains("[1] /dev/ttyS0  PCI"), "{output}");
+    assert!(output.contains("[2] /dev/ttyS1  PCI"), "{output}");
+}
+
+#[test]
+fn first_port_is_the_default() {
+    let ports = pci_ports(&["/dev/ttyS0", "/dev/ttyS1"]);
+    let port = prompt_for_port(&ports, &mut "\n".as_bytes(), &mut Vec::new()).unwrap();
+    assert_eq!(port, "/dev/ttyS0");
+    let port = prompt_for_port(&ports, &mut "".as_bytes(), &mut Vec::new()).unwrap();
+    assert_eq!(port, "/dev/ttyS0");
+}
+
+#[test]
+fn invalid_answers_ask_again() {
+    let ports = pci_ports(&["/dev/ttyS0", "/dev/ttyS1"]);
+    let mut output = Vec::new();
+    let port = prompt_for_port(&ports, &mut "3\nttyS1\n2\n".as_bytes(), &mut output).unwrap();
+    assert_eq!(port, "/dev/ttyS1");
+    let output = String::from_utf8(output).unwrap();
+    assert_eq!(output.matches("Enter a number from 1 to 2").count(), 2);
+}
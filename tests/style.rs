@@ -0,0 +1,35 @@
+use std::ffi::OsStr;
+
+use tockloader::style::{should_color, OutputFormat, Style};
+
+#[test]
+fn color_needs_a_terminal_and_no_opt_out() {
+    assert!(should_color(false, None, true));
+    assert!(!should_color(false, None, false));
+    assert!(!should_color(true, None, true));
+    assert!(!should_color(false, Some(OsStr::new("1")), true));
+    // An empty NO_COLOR does not count as set.
+    assert!(should_color(false, Some(OsStr::new("")), true));
+}
+
+#[test]
+fn text_is_unchanged_without_color() {
+    let plain = Style::new(false);
+    assert_eq!(plain.bad("MISMATCH"), "MISMATCH");
+    assert_eq!(plain.heading("[App 0]"), "[App 0]");
+
+    let colored = Style::new(true);
+    let bad = colored.bad("MISMATCH");
+    assert!(bad.contains("MISMATCH"));
+    assert!(bad.starts_with('\u{1B}'));
+}
+
+#[test]
+fn output_format_is_text_or_json() {
+    assert_eq!("text".parse(), Ok(OutputFormat::Text));
+    assert_eq!("json".parse(), Ok(OutputFormat::Json));
+    assert!("yaml"
+        .parse::<OutputFormat>()
+        .unwrap_err()
+        .contains("text or json"));
+}
@@ -0,0 +1,49 @@
+//! Helpers shared between the integration tests.
+
+// Not every test uses every helper.
+#![allow(dead_code)]
+
+use sha2::{Digest, Sha256};
+use tbf_parser::testing::{AppHeader, TbfOptions};
+
+/// Build a v2 TBF with a Program header, a package name, and a protected
+/// trailer of `protected_trailer_size` bytes filled with `0xAA`.
+pub fn make_tbf(name: &str, protected_trailer_size: u32, binary: &[u8]) -> Vec<u8> {
+    tbf_parser::testing::make_tbf(&TbfOptions {
+        name: Some(name),
+        protected_trailer_size,
+        binary: binary.to_vec(),
+        ..Default::default()
+    })
+}
+
+/// Build a v2 TBF with only a Main header, as older toolchains do. It has no
+/// binary version and no footers.
+pub fn make_main_tbf(binary: &[u8]) -> Vec<u8> {
+    tbf_parser::testing::make_tbf(&TbfOptions {
+        header: AppHeader::Main,
+        binary: binary.to_vec(),
+        ..Default::default()
+    })
+}
+
+/// Build an app followed by a SHA256 Credentials Footer over its header and
+/// binary.
+pub fn make_signed_tbf() -> Vec<u8> {
+    let mut tbf = make_tbf_with_credentials(3, &[0; 32]);
+    let binary_end = tbf.len() - (4 + 4 + 32);
+    let hash = Sha256::digest(&tbf[..binary_end]);
+    tbf[binary_end + 8..].copy_from_slice(&hash);
+    tbf
+}
+
+/// An app named `blink` with a Credentials Footer of `format` holding
+/// `credentials`, whatever they are.
+pub fn make_tbf_with_credentials(format: u32, credentials: &[u8]) -> Vec<u8> {
+    tbf_parser::testing::make_tbf(&TbfOptions {
+        name: Some("blink"),
+        binary: vec![0x42; 100],
+        footers: vec![(format, credentials.to_vec())],
+        ..Default::default()
+    })
+}
@@ -0,0 +1,118 @@
+use tockloader::errors::TockloaderError;
+use tockloader::tab::{find_tabs, resolve_arch, Tab};
+
+/// Build a tab archive in memory containing the given files.
+fn make_tab(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut builder = tar::Builder::new(Vec::new());
+    for (name, data) in files {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, format!("blink/{name}"), *data)
+            .unwrap();
+    }
+    builder.into_inner().unwrap()
+}
+
+#[test]
+fn selects_binary_for_board_arch() {
+    let tab = make_tab(&[
+        ("metadata.toml", b"tab-version = 1"),
+        ("cortex-m0.tbf", b"m0"),
+        ("cortex-m4.tbf", b"m4"),
+    ]);
+    let tab = Tab::from_reader(&tab[..]).unwrap();
+    assert_eq!(tab.architectures(), vec!["cortex-m0", "cortex-m4"]);
+
+    let arch = resolve_arch(None, Some("Cortex-M4\0\0\0")).unwrap();
+    assert_eq!(tab.extract_binary(&arch).unwrap(), b"m4");
+
+    let arch = resolve_arch(Some("cortex-m0"), Some("cortex-m4")).unwrap();
+    assert_eq!(tab.extract_binary(&arch).unwrap(), b"m0");
+}
+
+#[test]
+fn missing_arch_lists_available() {
+    let tab = make_tab(&[
+        ("cortex-m4.tbf", b"m4"),
+        ("rv32imac.0x20040000.0x80002800.tbf", b"rv"),
+    ]);
+    let tab = Tab::from_reader(&tab[..]).unwrap();
+
+    match tab.extract_binary("cortex-m0") {
+        Err(TockloaderError::NoBinaryForArch { arch, available }) => {
+            assert_eq!(arch, "cortex-m0");
+            assert_eq!(available, vec!["cortex-m4", "rv32imac"]);
+        }
+        other => panic!("Unexpected result: {other:?}"),
+    }
+    assert!(matches!(
+        resolve_arch(None, None),
+        Err(TockloaderError::UnknownArch)
+    ));
+}
+
+#[test]
+fn finds_tabs_in_a_directory() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("hello.tab"), make_tab(&[])).unwrap();
+    std::fs::write(dir.path().join("blink.tab"), make_tab(&[])).unwrap();
+    std::fs::write(dir.path().join("notes.txt"), b"").unwrap();
+    std::fs::create_dir(dir.path().join("old.tab")).unwrap();
+
+    assert_eq!(
+        find_tabs(dir.path()).unwrap(),
+        vec![dir.path().join("blink.tab"), dir.path().join("hello.tab")]
+    );
+}
+
+#[test]
+fn metadata_restricts_boards() {
+    let metadata = b"tab-version = 1\n\
+        name = \"blink\"\n\
+        only-for-boards = \"hail, imix\"\n\
+        build-date = 2023-06-09T21:52:59Z\n\
+        minimum-tock-kernel-version = \"2.1\"\n";
+    let tab = make_tab(&[("metadata.toml", metadata), ("cortex-m4.tbf", b"m4")]);
+    let tab = Tab::from_reader(&tab[..]).unwrap();
+
+    let metadata = tab.metadata().unwrap();
+    assert_eq!(metadata.tab_version, Some(1));
+    assert_eq!(metadata.supported_boards(), vec!["hail", "imix"]);
+    assert_eq!(
+        metadata.build_date.unwrap().to_string(),
+        "2023-06-09T21:52:59Z"
+    );
+
+    assert!(tab.check_board("imix").is_ok());
+    match tab.check_board("nrf52840dk") {
+        Err(TockloaderError::IncompatibleBoard { board, supported }) => {
+            assert_eq!(board, "nrf52840dk");
+            assert_eq!(supported, vec!["hail", "imix"]);
+        }
+        other => panic!("Unexpected result: {other:?}"),
+    }
+}
+
+#[test]
+fn tabs_without_restrictions_fit_any_board() {
+    let tab = make_tab(&[("metadata.toml", b"tab-version = 1\nonly-for-boards = \"\"")]);
+    let tab = Tab::from_reader(&tab[..]).unwrap();
+    assert!(tab.check_board("hail").is_ok());
+
+    let tab = make_tab(&[("cortex-m4.tbf", b"m4")]);
+    let tab = Tab::from_reader(&tab[..]).unwrap();
+    assert!(tab.metadata().is_none());
+    assert!(tab.check_board("hail").is_ok());
+}
+
+#[test]
+fn invalid_metadata_is_an_error() {
+    let tab = make_tab(&[("metadata.toml", b"tab-version = \"one")]);
+    assert!(matches!(
+        Tab::from_reader(&tab[..]),
+        Err(TockloaderError::InvalidTabMetadata(_))
+    ));
+}
@@ -0,0 +1,356 @@
+#![cfg(feature = "serial")]
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use bytes::BytesMut;
+use console::Key;
+use tockloader::errors::TockloaderError;
+use tockloader::interfaces::serial::console_protocol::{
+    ConsoleMessage, ConsoleProtocol, PacketProtocol, ProtocolKind, RawProtocol,
+};
+use tockloader::interfaces::serial::keys::key_to_bytes;
+use tockloader::interfaces::serial::virtual_terminal::{
+    echo_text, AnsiStripper, ConsoleOutput, CtrlC, InvalidUtf8, LineTimestamps, LocalEcho,
+    Substitution, TerminalCodec, TerminalLog, DEFAULT_PROMPT, DOUBLE_CTRL_C,
+};
+use tockloader::interfaces::serial::SerialInterface;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Notify;
+use tokio_serial::SerialStream;
+use tokio_util::codec::Decoder;
+
+#[test]
+fn keys_map_to_terminal_bytes() {
+    let keys = [
+        (Key::Char('a'), Some("a")),
+        (Key::Char('é'), Some("é")),
+        (Key::Enter, Some("\n")),
+        (Key::Backspace, Some("\u{8}")),
+        (Key::Tab, Some("\t")),
+        (Key::ArrowUp, Some("\u{1B}[A")),
+        (Key::ArrowLeft, Some("\u{1B}[D")),
+        (Key::PageDown, Some("\u{1B}[6~")),
+        (Key::Del, Some("\u{1B}[3~")),
+        (Key::Home, Some("\u{1B}[H")),
+        (Key::Escape, Some("\u{1B}")),
+        (Key::CtrlC, Some("\u{3}")),
+        (Key::Unknown, None),
+        (Key::UnknownEscSeq(vec!['[', '2', '4', '~']), None),
+        (Key::Alt, None),
+        (Key::Shift, None),
+    ];
+    for (key, expected) in keys {
+        assert_eq!(key_to_bytes(key).as_deref(), expected);
+    }
+}
+
+#[test]
+fn ansi_sequences_are_stripped_across_chunks() {
+    let mut stripper = AnsiStripper::default();
+    assert_eq!(stripper.strip("\u{1B}[1;32mtock$ \u{1B}[0m"), "tock$ ");
+    // A color split across three reads.
+    assert_eq!(stripper.strip("red: \u{1B}"), "red: ");
+    assert_eq!(stripper.strip("[31"), "");
+    assert_eq!(stripper.strip("mhot\u{1B}[0m\r\n"), "hot\r\n");
+    // Cursor movement and erasing, as sent for arrow keys and line editing.
+    assert_eq!(stripper.strip("ab\u{1B}[D\u{1B}[K\u{1B}[2~c"), "abc");
+    // Two character escapes.
+    assert_eq!(stripper.strip("\u{1B}7saved\u{1B}8"), "saved");
+}
+
+#[test]
+fn character_split_across_reads_is_decoded_once() {
+    let mut codec = TerminalCodec;
+    // "€" is E2 82 AC, cut after its first byte.
+    let mut source = BytesMut::from(&b"caf\xE2"[..]);
+    assert_eq!(codec.decode(&mut source).unwrap().as_deref(), Some("caf"));
+    assert_eq!(&source[..], b"\xE2");
+    // Nothing new has arrived, so there is nothing to emit yet.
+    assert_eq!(codec.decode(&mut source).unwrap(), None);
+
+    source.extend_from_slice(b"\x82\xAC\r\ntock$ ");
+    assert_eq!(
+        codec.decode(&mut source).unwrap().as_deref(),
+        Some("\u{20AC}\r\ntock$ ")
+    );
+    assert!(source.is_empty());
+}
+
+#[test]
+fn character_cut_off_by_the_end_of_the_stream_is_kept() {
+    let mut codec = TerminalCodec;
+    let mut source = BytesMut::from(&b"tock$ \xE2\x82"[..]);
+    assert_eq!(
+        codec.decode(&mut source).unwrap().as_deref(),
+        Some("tock$ ")
+    );
+    assert_eq!(
+        codec.decode_eof(&mut source).unwrap().as_deref(),
+        Some("\u{FFFD}")
+    );
+    assert!(source.is_empty());
+}
+
+#[test]
+fn invalid_bytes_are_kept_alongside_their_text() {
+    let mut codec = TerminalCodec;
+    let mut source = BytesMut::from(&b"ok\xFF\xFEok"[..]);
+    let (text, bytes) = codec.decode_bytes(&mut source, false).unwrap();
+    assert_eq!(text, "ok\u{FFFD}");
+    assert_eq!(bytes, b"ok\xFF");
+    // Each invalid byte ends the text it is in.
+    assert_eq!(
+        codec.decode(&mut source).unwrap().as_deref(),
+        Some("\u{FFFD}")
+    );
+    assert_eq!(codec.decode(&mut source).unwrap().as_deref(), Some("ok"));
+    assert!(source.is_empty());
+}
+
+#[test]
+fn truncated_character_followed_by_ascii_is_replaced() {
+    let mut codec = TerminalCodec;
+    // The first two bytes of "€" (E2 82 AC), then text.
+    let mut source = BytesMut::from(&b"\xE2\x82panic at 0x1000\r\n"[..]);
+    let (text, bytes) = codec.decode_bytes(&mut source, false).unwrap();
+    assert_eq!(text, "\u{FFFD}");
+    assert_eq!(bytes, b"\xE2\x82");
+    assert_eq!(
+        codec.decode(&mut source).unwrap().as_deref(),
+        Some("panic at 0x1000\r\n")
+    );
+    assert!(source.is_empty());
+
+    assert!(InvalidUtf8::default().check(&text, &bytes).is_ok());
+    let error = InvalidUtf8::Fail.check(&text, &bytes).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    assert!(InvalidUtf8::Fail.check("ok", b"ok").is_ok());
+}
+
+#[tokio::test]
+async fn prompt_split_across_writes_is_found() {
+    let (host, mut board) = SerialStream::pair().unwrap();
+    let mut interface = SerialInterface::with_stream(host, 115200);
+
+    tokio::spawn(async move {
+        board
+            .write_all(b"Initialization complete.\r\nto")
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        board.write_all(b"ck$ ").await.unwrap();
+        // Keep the pseudo terminal open until the test is done.
+        tokio::time::sleep(Duration::from_secs(5)).await;
+    });
+
+    interface
+        .wait_for_prompt(DEFAULT_PROMPT, Duration::from_secs(2))
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn missing_prompt_times_out() {
+    let (host, mut board) = SerialStream::pair().unwrap();
+    let mut interface = SerialInterface::with_stream(host, 115200);
+    board.write_all(b"tock> ").await.unwrap();
+
+    let result = interface
+        .wait_for_prompt(DEFAULT_PROMPT, Duration::from_millis(200))
+        .await;
+    assert!(matches!(
+        result,
+        Err(TockloaderError::PromptNotFound { prompt, .. }) if prompt == DEFAULT_PROMPT
+    ));
+}
+
+#[tokio::test]
+async fn terminal_returns_on_shutdown() {
+    let (host, mut board) = SerialStream::pair().unwrap();
+    let mut interface = SerialInterface::with_stream(host, 115200);
+    let shutdown = Arc::new(Notify::new());
+
+    let notifier = shutdown.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        notifier.notify_one();
+    });
+    tokio::time::timeout(
+        Duration::from_secs(2),
+        interface.run_terminal_until(
+            None,
+            None,
+            LocalEcho::Off,
+            CtrlC::Quit,
+            RawProtocol,
+            shutdown.notified(),
+        ),
+    )
+    .await
+    .unwrap()
+    .unwrap();
+
+    // The stream is back, for the board to be talked to again.
+    board.write_all(DEFAULT_PROMPT.as_bytes()).await.unwrap();
+    interface
+        .wait_for_prompt(DEFAULT_PROMPT, Duration::from_secs(2))
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn log_gets_the_bytes_as_received() {
+    let (host, mut board) = SerialStream::pair().unwrap();
+    let mut interface = SerialInterface::with_stream(host, 115200);
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("listen.log");
+    std::fs::write(&path, b"earlier session\n").unwrap();
+    let log = TerminalLog::new(
+        std::fs::File::options().append(true).open(&path).unwrap(),
+        false,
+    );
+
+    board.write_all(b"noise \xFF\xC0 tock$ ").await.unwrap();
+    let shutdown = tokio::time::sleep(Duration::from_millis(200));
+    interface
+        .run_terminal_until(
+            Some(log),
+            None,
+            LocalEcho::Off,
+            CtrlC::Quit,
+            RawProtocol,
+            shutdown,
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(
+        std::fs::read(&path).unwrap(),
+        b"earlier session\nnoise \xFF\xC0 tock$ "
+    );
+}
+
+#[test]
+fn second_ctrl_c_in_a_row_quits_when_forwarded() {
+    let now = Instant::now();
+    assert_eq!("forward".parse::<CtrlC>(), Ok(CtrlC::Forward));
+    assert_eq!(CtrlC::default(), CtrlC::Quit);
+    assert!(CtrlC::Quit.quits(None, now));
+
+    assert!(!CtrlC::Forward.quits(None, now));
+    assert!(CtrlC::Forward.quits(Some(now - Duration::from_millis(200)), now));
+    assert!(!CtrlC::Forward.quits(Some(now - DOUBLE_CTRL_C), now));
+}
+
+#[test]
+fn lines_are_stamped_when_they_start() {
+    let start = Instant::now();
+    let mut timestamps = LineTimestamps::new(start);
+    let at = |millis| start + Duration::from_millis(millis);
+
+    assert_eq!(
+        timestamps.stamp("Initialization complete.\r\nEnter", at(1500)),
+        "[     1.500] Initialization complete.\r\n[     1.500] Enter"
+    );
+    // The rest of a line is not stamped again.
+    assert_eq!(timestamps.stamp(" command\r\n", at(1600)), " command\r\n");
+    // The prompt has no newline, and shows up right away.
+    assert_eq!(timestamps.stamp("tock$ ", at(2000)), "[     2.000] tock$ ");
+
+    timestamps.line_ended();
+    assert_eq!(timestamps.stamp("ok", at(12345)), "[    12.345] ok");
+}
+
+#[test]
+fn text_is_printed_unchanged_by_default() {
+    let text = "one\ntwo\0";
+    assert_eq!(ConsoleOutput::default().substitute(text), text);
+    assert_eq!(ConsoleOutput::Raw.substitute(text), text);
+}
+
+#[test]
+fn substitutions_for_older_kernels() {
+    let substitutions: Vec<Substitution> = "newline,null"
+        .split(',')
+        .map(|value| value.parse().unwrap())
+        .collect();
+    let output = ConsoleOutput::Text(substitutions);
+    assert_eq!(output.substitute("one\ntwo\0"), "one\r\ntwo\u{2400}");
+    assert!("cr".parse::<Substitution>().is_err());
+}
+
+#[test]
+fn local_echo_defaults_to_the_board() {
+    assert_eq!("on".parse::<LocalEcho>(), Ok(LocalEcho::On));
+    assert!("yes".parse::<LocalEcho>().is_err());
+    assert!(!LocalEcho::default().enabled());
+    assert!(!LocalEcho::Off.enabled());
+    assert!(LocalEcho::On.enabled());
+
+    assert_eq!(echo_text("a"), "a");
+    assert_eq!(echo_text("\n"), "\r\n");
+    assert_eq!(echo_text("\u{8}"), "\u{8} \u{8}");
+}
+
+fn message(process: Option<u8>, text: &str) -> ConsoleMessage {
+    ConsoleMessage {
+        process,
+        text: text.to_string(),
+        bytes: text.as_bytes().to_vec(),
+    }
+}
+
+#[test]
+fn raw_protocol_passes_text_through() {
+    let mut protocol = RawProtocol;
+    let mut source = BytesMut::from(&b"tock$ "[..]);
+    assert_eq!(
+        protocol.decode(&mut source).unwrap(),
+        Some(message(None, "tock$ "))
+    );
+    assert_eq!(protocol.encode_input(b"help\n"), b"help\n");
+    assert_eq!("raw".parse::<ProtocolKind>(), Ok(ProtocolKind::default()));
+}
+
+#[test]
+fn packets_are_routed_to_their_process() {
+    let mut protocol = PacketProtocol::default();
+    // A whole packet for process 1, and the first half of one for process 2.
+    let mut source = BytesMut::from(&b"\x01\x03\x00hi\n\x02\x05\x00ab"[..]);
+    assert_eq!(
+        protocol.decode(&mut source).unwrap(),
+        Some(message(Some(1), "hi\n"))
+    );
+    assert_eq!(protocol.decode(&mut source).unwrap(), None);
+
+    source.extend_from_slice(b"cde");
+    assert_eq!(
+        protocol.decode(&mut source).unwrap(),
+        Some(message(Some(2), "abcde"))
+    );
+    assert!(source.is_empty());
+}
+
+#[test]
+fn character_split_across_packets_of_a_process_is_kept_whole() {
+    let mut protocol = PacketProtocol::default();
+    // "\u{20AC}" is E2 82 AC, cut by a packet of another process.
+    let mut source = BytesMut::from(&b"\x03\x01\x00\xE2\x04\x02\x00ok\x03\x02\x00\x82\xAC"[..]);
+    assert_eq!(
+        protocol.decode(&mut source).unwrap(),
+        Some(message(Some(4), "ok"))
+    );
+    assert_eq!(
+        protocol.decode(&mut source).unwrap(),
+        Some(message(Some(3), "\u{20AC}"))
+    );
+}
+
+#[test]
+fn packet_input_goes_to_the_input_process() {
+    assert_eq!(
+        PacketProtocol::new(5).encode_input(b"ls\n"),
+        b"\x05\x03\x00ls\n"
+    );
+}
@@ -0,0 +1,83 @@
+use std::path::Path;
+
+use tockloader::interfaces::flash_file::FlashFileInterface;
+use tockloader::verify::verify_region;
+
+mod support;
+use support::{make_main_tbf, make_signed_tbf, make_tbf, make_tbf_with_credentials};
+
+fn flash_with(path: &Path, tbfs: &[Vec<u8>]) -> FlashFileInterface {
+    std::fs::write(path, tbfs.concat()).unwrap();
+    FlashFileInterface::new(path, 512).unwrap()
+}
+
+#[tokio::test]
+async fn healthy_region_passes() {
+    let dir = tempfile::tempdir().unwrap();
+    let tbfs = [make_signed_tbf(), make_main_tbf(&[0; 64])];
+    let size: usize = tbfs.iter().map(Vec::len).sum();
+    let mut board = flash_with(&dir.path().join("flash.bin"), &tbfs);
+
+    let checks = verify_region(&mut board, 0, Some(size as u32))
+        .await
+        .unwrap();
+    assert_eq!(checks.len(), 2);
+    assert!(checks.iter().all(|check| check.passed()));
+    assert_eq!(checks[0].name.as_deref(), Some("blink"));
+    assert_eq!(checks[0].hashes.len(), 1);
+    assert!(checks[1].hashes.is_empty());
+}
+
+#[tokio::test]
+async fn bad_checksum_and_hash_are_reported_and_walk_continues() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut bad_header = make_tbf("broken", 0, &[0; 64]);
+    bad_header[12] ^= 0xFF;
+    let mut bad_hash = make_signed_tbf();
+    let header_size = u16::from_le_bytes([bad_hash[2], bad_hash[3]]) as usize;
+    bad_hash[header_size] ^= 0xFF;
+    let mut board = flash_with(
+        &dir.path().join("flash.bin"),
+        &[bad_header, bad_hash, make_tbf("fine", 0, &[0; 64])],
+    );
+
+    let checks = verify_region(&mut board, 0, None).await.unwrap();
+    assert_eq!(checks.len(), 3);
+    assert!(checks[0].header_error.is_some());
+    assert!(checks[0].fits);
+    assert!(checks[1].header_error.is_none());
+    assert!(!checks[1].hashes[0].matches());
+    assert!(!checks[1].passed());
+    assert!(checks[2].passed());
+}
+
+#[tokio::test]
+async fn invalid_signature_fails_the_check() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut board = flash_with(
+        &dir.path().join("flash.bin"),
+        &[make_tbf_with_credentials(2, &[0x5A; 1024])],
+    );
+
+    let checks = verify_region(&mut board, 0, None).await.unwrap();
+    assert_eq!(checks.len(), 1);
+    assert!(!checks[0].signatures[0].valid);
+    assert!(!checks[0].passed());
+}
+
+#[tokio::test]
+async fn tbf_past_region_end_stops_the_walk() {
+    let dir = tempfile::tempdir().unwrap();
+    let first = make_tbf("first", 0, &[0; 64]);
+    let end = first.len() as u32 + 16;
+    let mut board = flash_with(
+        &dir.path().join("flash.bin"),
+        &[first, make_tbf("second", 0, &[0; 64])],
+    );
+
+    let checks = verify_region(&mut board, 0, Some(end)).await.unwrap();
+    assert_eq!(checks.len(), 2);
+    assert!(checks[0].passed());
+    assert!(!checks[1].fits);
+    assert!(!checks[1].passed());
+}
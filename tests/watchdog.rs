@@ -0,0 +1,54 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use tockloader::errors::TockloaderError;
+use tockloader::install::{install_app, AppImage};
+use tockloader::interfaces::traits::{BytesReader, FlashWriter};
+use tockloader::interfaces::watchdog::WatchdogInterface;
+
+mod support;
+use support::make_tbf;
+
+/// A board which stops answering after writing `working_pages` pages.
+struct FailingBoard {
+    working_pages: usize,
+}
+
+#[async_trait]
+impl BytesReader for FailingBoard {
+    async fn read_range(&mut self, _start: usize, len: usize) -> Result<Vec<u8>, TockloaderError> {
+        Ok(vec![0xFF; len])
+    }
+}
+
+#[async_trait]
+impl FlashWriter for FailingBoard {
+    fn page_size(&self) -> usize {
+        512
+    }
+
+    async fn write_page(&mut self, _address: u32, _data: &[u8]) -> Result<(), TockloaderError> {
+        if self.working_pages == 0 {
+            std::future::pending::<()>().await;
+        }
+        self.working_pages -= 1;
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn aborts_install_when_board_stops_responding() {
+    let app = AppImage::from_tbf(&make_tbf("blink", 0, &[0; 4000])).unwrap();
+    let mut board = WatchdogInterface::new(
+        FailingBoard { working_pages: 3 },
+        Duration::from_millis(200),
+    );
+
+    match install_app(&mut board, 0x1000, &app, None).await {
+        Err(TockloaderError::OperationTimeout { pages_written, .. }) => {
+            assert_eq!(pages_written, 3)
+        }
+        other => panic!("Unexpected result: {other:?}"),
+    }
+    assert_eq!(board.pages_written(), 3);
+}